@@ -0,0 +1,49 @@
+#ifndef AVDL_H
+#define AVDL_H
+
+/* Generated with cbindgen from crates/avdl-ffi, do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Returned by `avdl_parse_to_json` when the protocol was converted.
+#define AVDL_OK 0
+
+// Returned by `avdl_parse_to_json` when the protocol is invalid, or could
+// not be converted.
+#define AVDL_ERROR 1
+
+// Returned by `avdl_parse_to_json` when one of its arguments is NULL.
+#define AVDL_NULL_ARGUMENT -1
+
+// Parse the AVDL protocol in `idl` and write its named types as a JSON
+// array of schemas.
+//
+// On success, returns `AVDL_OK`, with `*out_json` set to the JSON and
+// `*out_err` to NULL. On failure, returns `AVDL_ERROR`, with `*out_err` set
+// to the error, telling the line and column of the problem when known, and
+// `*out_json` to NULL. Returns `AVDL_NULL_ARGUMENT`, writing nothing, when
+// an argument is NULL.
+//
+// The strings written to `*out_json` and `*out_err` are owned by the
+// caller, who must release them with `avdl_free_string`, and not `free`.
+//
+// # Safety
+//
+// `idl` must point to a NUL-terminated string, and `out_json` and `out_err`
+// to pointers that can be written to.
+int avdl_parse_to_json(const char *idl, char **out_json, char **out_err);
+
+// Release a string returned by `avdl_parse_to_json`. Does nothing if `s` is
+// NULL.
+//
+// # Safety
+//
+// `s` must be NULL or a string returned by `avdl_parse_to_json`, not
+// released yet.
+void avdl_free_string(char *s);
+
+#endif  /* AVDL_H */
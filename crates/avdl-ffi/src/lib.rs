@@ -0,0 +1,101 @@
+//! C bindings, built into `libavdl_ffi` with `cargo build -p avdl-ffi`. The
+//! header is `include/avdl.h`.
+
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use avdl_parser::error::SpannedError;
+use avdl_parser::{parse_spanned, AvdlError, ParserOptions};
+
+/// Returned by `avdl_parse_to_json` when the protocol was converted.
+pub const AVDL_OK: c_int = 0;
+
+/// Returned by `avdl_parse_to_json` when the protocol is invalid, or could
+/// not be converted.
+pub const AVDL_ERROR: c_int = 1;
+
+/// Returned by `avdl_parse_to_json` when one of its arguments is NULL.
+pub const AVDL_NULL_ARGUMENT: c_int = -1;
+
+// The named types of the protocol in `idl`, as a JSON array of schemas
+fn parse_to_json(idl: &CStr) -> Result<String, String> {
+    let input = idl
+        .to_str()
+        .map_err(|err| format!("the protocol is not valid UTF-8: {err}"))?;
+    match parse_spanned(input, &ParserOptions::default()) {
+        Ok((protocol, _warnings, _spans)) => {
+            serde_json::to_string(&protocol.into_schemas()).map_err(|err| err.to_string())
+        }
+        // Parse errors tell their location already
+        Err(SpannedError { error, span }) => match (error.as_ref(), span) {
+            (AvdlError::Parse { .. }, _) | (_, None) => Err(error.to_string()),
+            (_, Some(span)) => Err(format!("error at {}: {error}", span.location(input))),
+        },
+    }
+}
+
+// A C string of `s`, which can't contain NUL bytes since it is JSON or an
+// error message
+fn into_c_string(s: String) -> *mut c_char {
+    CString::new(s.replace('\0', "\\0"))
+        .expect("NUL bytes are escaped")
+        .into_raw()
+}
+
+/// Parse the AVDL protocol in `idl` and write its named types as a JSON
+/// array of schemas.
+///
+/// On success, returns `AVDL_OK`, with `*out_json` set to the JSON and
+/// `*out_err` to NULL. On failure, returns `AVDL_ERROR`, with `*out_err` set
+/// to the error, telling the line and column of the problem when known, and
+/// `*out_json` to NULL. Returns `AVDL_NULL_ARGUMENT`, writing nothing, when
+/// an argument is NULL.
+///
+/// The strings written to `*out_json` and `*out_err` are owned by the
+/// caller, who must release them with `avdl_free_string`, and not `free`.
+///
+/// # Safety
+///
+/// `idl` must point to a NUL-terminated string, and `out_json` and `out_err`
+/// to pointers that can be written to.
+#[no_mangle]
+pub unsafe extern "C" fn avdl_parse_to_json(
+    idl: *const c_char,
+    out_json: *mut *mut c_char,
+    out_err: *mut *mut c_char,
+) -> c_int {
+    if idl.is_null() || out_json.is_null() || out_err.is_null() {
+        return AVDL_NULL_ARGUMENT;
+    }
+    let idl = CStr::from_ptr(idl);
+    // Unwinding across the C boundary is undefined behavior
+    let result = panic::catch_unwind(AssertUnwindSafe(|| parse_to_json(idl)))
+        .unwrap_or_else(|_| Err("internal error while parsing the protocol".to_string()));
+    match result {
+        Ok(json) => {
+            *out_json = into_c_string(json);
+            *out_err = ptr::null_mut();
+            AVDL_OK
+        }
+        Err(err) => {
+            *out_json = ptr::null_mut();
+            *out_err = into_c_string(err);
+            AVDL_ERROR
+        }
+    }
+}
+
+/// Release a string returned by `avdl_parse_to_json`. Does nothing if `s` is
+/// NULL.
+///
+/// # Safety
+///
+/// `s` must be NULL or a string returned by `avdl_parse_to_json`, not
+/// released yet.
+#[no_mangle]
+pub unsafe extern "C" fn avdl_free_string(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
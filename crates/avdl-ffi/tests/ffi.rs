@@ -0,0 +1,71 @@
+//! Calls the library through its C declarations, like a C program would.
+
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::ptr;
+
+// Declared as in include/avdl.h, linked from the rlib of the crate
+extern "C" {
+    fn avdl_parse_to_json(
+        idl: *const c_char,
+        out_json: *mut *mut c_char,
+        out_err: *mut *mut c_char,
+    ) -> c_int;
+    fn avdl_free_string(s: *mut c_char);
+}
+
+// The return code, JSON and error of parsing `idl`, releasing the strings
+fn parse(idl: &str) -> (c_int, Option<String>, Option<String>) {
+    let idl = CString::new(idl).unwrap();
+    let mut json = ptr::null_mut();
+    let mut err = ptr::null_mut();
+    unsafe {
+        let code = avdl_parse_to_json(idl.as_ptr(), &mut json, &mut err);
+        let owned = |s: *mut c_char| {
+            (!s.is_null()).then(|| {
+                let owned = CStr::from_ptr(s).to_str().unwrap().to_string();
+                avdl_free_string(s);
+                owned
+            })
+        };
+        (code, owned(json), owned(err))
+    }
+}
+
+#[test]
+fn test_parse_to_json() {
+    let (code, json, err) = parse(
+        r#"@namespace("org.example")
+protocol Shop {
+    enum Kind { BOOK, FOOD }
+    record Item { string id; union { null, Kind } kind = null; }
+}"#,
+    );
+    assert_eq!(code, avdl_ffi::AVDL_OK);
+    assert_eq!(err, None);
+    let schemas: serde_json::Value = serde_json::from_str(&json.unwrap()).unwrap();
+    assert_eq!(schemas[0]["name"], "Kind");
+    assert_eq!(schemas[1]["name"], "Item");
+}
+
+#[test]
+fn test_parse_to_json_errors() {
+    let (code, json, err) = parse("protocol Shop {\n    record Item { string id }\n}");
+    assert_eq!(code, avdl_ffi::AVDL_ERROR);
+    assert_eq!(json, None);
+    let err = err.unwrap();
+    assert!(err.starts_with("error at 2:"), "{err}");
+
+    let (code, _json, err) = parse("protocol Shop {\n    record Item { Missing kind; }\n}");
+    assert_eq!(code, avdl_ffi::AVDL_ERROR);
+    let err = err.unwrap();
+    assert!(err.starts_with("error at 2:19:"), "{err}");
+}
+
+#[test]
+fn test_null_arguments() {
+    let mut json = ptr::null_mut();
+    let code = unsafe { avdl_parse_to_json(ptr::null(), &mut json, ptr::null_mut()) };
+    assert_eq!(code, avdl_ffi::AVDL_NULL_ARGUMENT);
+    assert!(json.is_null());
+    unsafe { avdl_free_string(ptr::null_mut()) };
+}
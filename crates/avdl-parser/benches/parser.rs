@@ -0,0 +1,107 @@
+//! Parsing protocols of different shapes, and serializing the parsed schemas
+//! to JSON, which is what `avrokit convert` spends its time on.
+//!
+//! Hand written inputs are checked in under `benches/fixtures`, big ones are
+//! generated before measuring.
+
+use apache_avro::Schema;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use avdl_parser::parse;
+
+// A protocol with `count` records, each one using the one before it
+fn protocol_with_records(count: usize) -> String {
+    let mut input = String::from("@namespace(\"org.example.generated\")\nprotocol Generated {\n");
+    for i in 0..count {
+        input.push_str(&format!(
+            "    /** Record {i} */
+    record Record{i} {{
+        string id;
+        long created;
+        array<string> tags = [];
+        union {{ null, string }} note = null;
+"
+        ));
+        if i > 0 {
+            input.push_str(&format!(
+                "        union {{ null, Record{} }} previous = null;\n",
+                i - 1
+            ));
+        }
+        input.push_str("    }\n\n");
+    }
+    input.push_str("}\n");
+    input
+}
+
+// A record with `count` fields, a fifth of them with a default
+fn record_with_fields(count: usize) -> String {
+    let types = ["string", "long", "array<int>", "map<string>"];
+    let mut input = String::from("protocol Fields {\n    record Wide {\n");
+    for i in 0..count {
+        let field = match i % 5 {
+            4 => format!("        union {{ null, string }} field_{i} = null;\n"),
+            kind => format!(
+                "        /** Field {i} */\n        {} field_{i};\n",
+                types[kind]
+            ),
+        };
+        input.push_str(&field);
+    }
+    input.push_str("    }\n}\n");
+    input
+}
+
+// A record with `count` fields of each kind that takes a default
+fn record_with_defaults(count: usize) -> String {
+    let row = format!("[{}]", ["1"; 10].join(", "));
+    let matrix = vec![row; 10].join(", ");
+    let mut input = String::from("protocol Defaults {\n    record Settings {\n");
+    for i in 0..count {
+        input.push_str(&format!(
+            "        int int_{i} = {i};
+        string string_{i} = \"value {i}\";
+        union {{ null, string }} optional_{i} = null;
+        array<array<long>> matrix_{i} = [{matrix}];
+        map<double> weights_{i} = {{\"a\": 1.5, \"b\": 2.5}};
+"
+        ));
+    }
+    input.push_str("    }\n}\n");
+    input
+}
+
+fn inputs() -> Vec<(&'static str, String)> {
+    vec![
+        ("small", include_str!("fixtures/small.avdl").to_string()),
+        ("nested", include_str!("fixtures/nested.avdl").to_string()),
+        ("500 records", protocol_with_records(500)),
+        ("1000 fields", record_with_fields(1000)),
+        ("500 defaults", record_with_defaults(100)),
+    ]
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    for (name, input) in inputs() {
+        group.throughput(Throughput::Bytes(input.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &input, |b, input| {
+            b.iter(|| parse(black_box(input)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_serialize(c: &mut Criterion) {
+    let mut group = c.benchmark_group("serialize");
+    for (name, input) in inputs() {
+        let (_, schemas): (_, Vec<Schema>) = parse(&input).unwrap();
+        group.bench_with_input(BenchmarkId::from_parameter(name), &schemas, |b, schemas| {
+            b.iter(|| serde_json::to_string(black_box(schemas)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_parse, bench_serialize);
+criterion_main!(benches);
@@ -0,0 +1,382 @@
+//! Reading and writing protocols as `.avpr` documents.
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+
+use apache_avro::schema::{Name, Namespace, Schema};
+use serde::ser::SerializeMap;
+use serde::{Serialize, Serializer};
+use serde_json::Value;
+
+use crate::avsc::{
+    invalid, read_doc, read_field, read_schema, read_strings, FieldWriter, SchemaWriter,
+};
+use crate::error::AvdlError;
+use crate::parser::resolve;
+use crate::protocol::{Message, Protocol};
+use crate::validate::collect_names;
+
+const PROTOCOL_KEYS: [&str; 6] = [
+    "protocol",
+    "namespace",
+    "doc",
+    "aliases",
+    "types",
+    "messages",
+];
+
+/// Parse an `.avpr` document.
+///
+/// References to named types are solved the same way the IDL parser does,
+/// so reading back a written protocol gives an equal one. Keys unknown to
+/// Avro are kept in the `properties` of the protocol.
+pub fn from_avpr_str(input: &str) -> Result<Protocol, AvdlError> {
+    let value: Value = serde_json::from_str(input)?;
+    from_avpr_json(&value)
+}
+
+/// Build a protocol out of an already parsed JSON value.
+pub fn from_avpr_json(value: &Value) -> Result<Protocol, AvdlError> {
+    let object = value
+        .as_object()
+        .ok_or_else(|| invalid("", "expected an object"))?;
+    let name = match object.get("protocol") {
+        Some(Value::String(name)) => name.clone(),
+        _ => return Err(invalid("/protocol", "expected the name of the protocol")),
+    };
+    let namespace = match object.get("namespace") {
+        Some(Value::String(namespace)) => Some(namespace.clone()),
+        Some(_) => return Err(invalid("/namespace", "expected a string")),
+        None => None,
+    };
+    let aliases = match object.get("aliases") {
+        Some(aliases) => read_strings(aliases, "/aliases")?,
+        None => Vec::new(),
+    };
+    let types = match object.get("types") {
+        Some(Value::Array(types)) => types
+            .iter()
+            .enumerate()
+            .map(|(i, schema)| read_schema(schema, &format!("/types/{i}")))
+            .collect::<Result<Vec<_>, _>>()?,
+        Some(_) => return Err(invalid("/types", "expected an array")),
+        None => Vec::new(),
+    };
+    let messages = match object.get("messages") {
+        Some(Value::Object(messages)) => messages
+            .iter()
+            .map(|(name, message)| read_message(name, message))
+            .collect::<Result<Vec<_>, _>>()?,
+        Some(_) => return Err(invalid("/messages", "expected an object")),
+        None => Vec::new(),
+    };
+    let properties = object
+        .iter()
+        .filter(|(key, _)| !PROTOCOL_KEYS.contains(&key.as_str()))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    let mut protocol = Protocol {
+        name,
+        namespace,
+        doc: read_doc(object, "")?,
+        aliases,
+        imports: Vec::new(),
+        types,
+        messages,
+        properties,
+    };
+
+    // Types defined inside a previous one are only written by name
+    let mut names = HashMap::new();
+    for schema in &protocol.types {
+        collect_names(schema, &mut names);
+    }
+    let names: HashMap<Name, Schema> = names
+        .into_iter()
+        .flat_map(|(name, schema)| {
+            let fullname = name.fully_qualified_name(&protocol.namespace);
+            [(name, schema.clone()), (fullname, schema.clone())]
+        })
+        .collect();
+    for schema in protocol.types.iter_mut() {
+        if let Schema::Ref { name } = schema {
+            if let Some(defined) = names.get(name) {
+                *schema = defined.clone();
+            }
+        }
+    }
+    resolve(&mut protocol, &names);
+    Ok(protocol)
+}
+
+fn read_message(name: &str, value: &Value) -> Result<Message, AvdlError> {
+    let path = format!("/messages/{name}");
+    let object = value
+        .as_object()
+        .ok_or_else(|| invalid(&path, "expected an object"))?;
+    let request = match object.get("request") {
+        Some(Value::Array(parameters)) => parameters
+            .iter()
+            .enumerate()
+            .map(|(i, parameter)| read_field(parameter, i, &format!("{path}/request/{i}")))
+            .collect::<Result<Vec<_>, _>>()?,
+        _ => return Err(invalid(&format!("{path}/request"), "expected an array")),
+    };
+    let response = match object.get("response") {
+        Some(response) => read_schema(response, &format!("{path}/response"))?,
+        None => return Err(invalid(&format!("{path}/response"), "missing \"response\"")),
+    };
+    let errors = match object.get("errors") {
+        Some(Value::Array(errors)) => errors
+            .iter()
+            .enumerate()
+            .map(|(i, error)| read_schema(error, &format!("{path}/errors/{i}")))
+            .collect::<Result<Vec<_>, _>>()?,
+        Some(_) => return Err(invalid(&format!("{path}/errors"), "expected an array")),
+        None => Vec::new(),
+    };
+    let one_way = match object.get("one-way") {
+        Some(Value::Bool(one_way)) => *one_way,
+        Some(_) => return Err(invalid(&format!("{path}/one-way"), "expected a boolean")),
+        None => false,
+    };
+    Ok(Message {
+        name: name.to_string(),
+        doc: read_doc(object, &path)?,
+        request,
+        response,
+        errors,
+        one_way,
+    })
+}
+
+/// Serializes a protocol the way it is written in an `.avpr` file.
+///
+/// Keys are written in the same order as the Java implementation does. Named
+/// types are defined once, in `types`, and referenced by name afterwards.
+pub struct Avpr<'a>(pub &'a Protocol);
+
+struct MessageWriter<'a> {
+    message: &'a Message,
+    written: &'a RefCell<HashSet<String>>,
+    namespace: &'a Namespace,
+}
+
+impl Serialize for Avpr<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let protocol = self.0;
+        let written = RefCell::new(HashSet::new());
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("protocol", &protocol.name)?;
+        if let Some(namespace) = &protocol.namespace {
+            map.serialize_entry("namespace", namespace)?;
+        }
+        if let Some(doc) = &protocol.doc {
+            map.serialize_entry("doc", doc)?;
+        }
+        if !protocol.aliases.is_empty() {
+            map.serialize_entry("aliases", &protocol.aliases)?;
+        }
+        for (key, value) in &protocol.properties {
+            map.serialize_entry(key, value)?;
+        }
+        let types: Vec<SchemaWriter> = protocol
+            .types
+            .iter()
+            .map(|schema| SchemaWriter::new(schema, &written, &protocol.namespace))
+            .collect();
+        map.serialize_entry("types", &types)?;
+        map.serialize_entry(
+            "messages",
+            &MessagesWriter {
+                messages: &protocol.messages,
+                written: &written,
+                namespace: &protocol.namespace,
+            },
+        )?;
+        map.end()
+    }
+}
+
+// Messages are an object keyed by name, in declaration order
+struct MessagesWriter<'a> {
+    messages: &'a [Message],
+    written: &'a RefCell<HashSet<String>>,
+    namespace: &'a Namespace,
+}
+
+impl Serialize for MessagesWriter<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.messages.iter().map(|message| {
+            (
+                &message.name,
+                MessageWriter {
+                    message,
+                    written: self.written,
+                    namespace: self.namespace,
+                },
+            )
+        }))
+    }
+}
+
+impl Serialize for MessageWriter<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let message = self.message;
+        let mut map = serializer.serialize_map(None)?;
+        if let Some(doc) = &message.doc {
+            map.serialize_entry("doc", doc)?;
+        }
+        let request: Vec<FieldWriter> = message
+            .request
+            .iter()
+            .map(|parameter| FieldWriter::new(parameter, self.written, self.namespace))
+            .collect();
+        map.serialize_entry("request", &request)?;
+        map.serialize_entry(
+            "response",
+            &SchemaWriter::new(&message.response, self.written, self.namespace),
+        )?;
+        if !message.errors.is_empty() {
+            let errors: Vec<SchemaWriter> = message
+                .errors
+                .iter()
+                .map(|schema| SchemaWriter::new(schema, self.written, self.namespace))
+                .collect();
+            map.serialize_entry("errors", &errors)?;
+        }
+        if message.one_way {
+            map.serialize_entry("one-way", &true)?;
+        }
+        map.end()
+    }
+}
+
+/// Write `protocol` as a JSON value.
+pub fn to_avpr_json(protocol: &Protocol) -> Result<Value, AvdlError> {
+    Ok(serde_json::to_value(Avpr(protocol))?)
+}
+
+/// Write `protocol` as a pretty printed `.avpr` document.
+pub fn to_avpr_string(protocol: &Protocol) -> Result<String, AvdlError> {
+    Ok(serde_json::to_string_pretty(&Avpr(protocol))?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::options::ParserOptions;
+    use crate::parser::parse_protocol_with_options;
+    use rstest::rstest;
+
+    fn library() -> Protocol {
+        let input = include_str!("../../../tests/samples/Library.avdl");
+        let (protocol, _warnings) =
+            parse_protocol_with_options(input, &ParserOptions::default()).unwrap();
+        protocol
+    }
+
+    #[test]
+    fn test_to_avpr_json() {
+        let expected: Value =
+            serde_json::from_str(include_str!("../../../tests/golden/Library.avpr")).unwrap();
+        assert_eq!(library().to_avpr_json().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_avpr_key_order() {
+        let written = to_avpr_string(&library()).unwrap();
+        let position = |key: &str| written.find(&format!("\"{key}\"")).unwrap();
+        assert!(position("protocol") < position("doc"));
+        assert!(position("doc") < position("types"));
+        assert!(position("types") < position("messages"));
+    }
+
+    #[test]
+    fn test_avpr_types_load() {
+        let avpr = library().to_avpr_json().unwrap();
+        let types: Vec<String> = avpr["types"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|schema| schema.to_string())
+            .collect();
+        let types: Vec<&str> = types.iter().map(String::as_str).collect();
+        assert_eq!(Schema::parse_list(&types).unwrap().len(), 3);
+    }
+
+    #[rstest]
+    #[case(include_str!("../../../tests/samples/Library.avdl"))]
+    #[case(include_str!("../../../tests/samples/Shop.avdl"))]
+    fn test_avpr_round_trip(#[case] input: &str) {
+        let (protocol, _warnings) =
+            parse_protocol_with_options(input, &ParserOptions::default()).unwrap();
+        let written = to_avpr_string(&protocol).unwrap();
+        assert_eq!(Protocol::from_avpr_str(&written).unwrap(), protocol);
+    }
+
+    #[test]
+    fn test_from_avpr_spec_example() {
+        let input = include_str!("../../../tests/samples/HelloWorld.avpr");
+        let protocol = Protocol::from_avpr_str(input).unwrap();
+        assert_eq!(protocol.fullname(), "com.acme.HelloWorld");
+        assert_eq!(protocol.doc.as_deref(), Some("Protocol Greetings"));
+        assert_eq!(protocol.types.len(), 2);
+        assert_eq!(protocol.messages.len(), 1);
+        let hello = &protocol.messages[0];
+        assert_eq!(hello.name, "hello");
+        assert_eq!(hello.doc.as_deref(), Some("Say hello."));
+        assert_eq!(hello.request[0].name, "greeting");
+        assert_eq!(hello.errors.len(), 1);
+        assert!(!hello.one_way);
+    }
+
+    #[test]
+    fn test_avpr_parameters_are_fields() {
+        let input = include_str!("../../../tests/samples/HelloWorld.avpr").replace(
+            r#"{"name": "greeting", "type": "Greeting" }"#,
+            r#"{"name": "greeting", "type": "Greeting", "doc": "Who to greet", "default": {"message": "hi"}}"#,
+        );
+        let protocol = Protocol::from_avpr_str(&input).unwrap();
+        let greeting = &protocol.messages[0].request[0];
+        assert_eq!(greeting.doc.as_deref(), Some("Who to greet"));
+        assert_eq!(greeting.default, Some(serde_json::json!({"message": "hi"})));
+        let original: Value = serde_json::from_str(&input).unwrap();
+        let written = protocol.to_avpr_json().unwrap();
+        assert_eq!(
+            written["messages"]["hello"]["request"],
+            original["messages"]["hello"]["request"]
+        );
+    }
+
+    #[test]
+    fn test_from_avpr_keeps_unknown_keys() {
+        let input =
+            r#"{"protocol": "P", "aliases": ["Q"], "version": "1.2", "types": [], "messages": {}}"#;
+        let protocol = Protocol::from_avpr_str(input).unwrap();
+        assert_eq!(protocol.properties["version"], Value::from("1.2"));
+        assert_eq!(protocol.aliases, ["Q"]);
+        let written = protocol.to_avpr_json().unwrap();
+        assert_eq!(written, serde_json::from_str::<Value>(input).unwrap());
+    }
+
+    #[test]
+    fn test_avpr_unknown_type_keys_round_trip() {
+        let input = r#"{
+  "protocol": "P",
+  "types": [
+    {"type": "enum", "name": "Status", "symbols": ["ON"], "x-internal": true},
+    {
+      "type": "record",
+      "name": "Device",
+      "fields": [{"name": "status", "type": "Status", "confluent:tags": ["PII"]}],
+      "confluent:version": 2
+    }
+  ],
+  "messages": {}
+}"#;
+        let protocol = Protocol::from_avpr_str(input).unwrap();
+        let original: Value = serde_json::from_str(input).unwrap();
+        assert_eq!(protocol.to_avpr_json().unwrap(), original);
+    }
+}
@@ -0,0 +1,1123 @@
+//! Reading and writing `.avsc` documents.
+//!
+//! Keys that are not part of the Avro specification (Confluent's
+//! `connect.name`, `x-*` properties, ...) are kept in the `attributes` of
+//! named schemas and in the `custom_attributes` of record fields, and are
+//! written back after the standard keys, sorted by name. Writing a schema
+//! fails when one of them is named like a standard key, e.g. `type`.
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashSet};
+
+use apache_avro::schema::{
+    Alias, Name, Namespace, RecordField, RecordFieldOrder, Schema, UnionSchema,
+};
+use serde::ser::{Error as _, SerializeMap};
+use serde::{Serialize, Serializer};
+use serde_json::{Map, Value};
+
+use crate::error::AvdlError;
+
+const NAMED_KEYS: [&str; 5] = ["type", "name", "namespace", "doc", "aliases"];
+const FIELD_KEYS: [&str; 6] = ["name", "type", "doc", "default", "order", "aliases"];
+
+/// Parse an `.avsc` document.
+pub fn from_avsc_str(input: &str) -> Result<Schema, AvdlError> {
+    let value: Value = serde_json::from_str(input)?;
+    from_json(&value)
+}
+
+/// Like [`from_avsc_str`], but fail if writing the schema back would not
+/// give the same JSON document (key order and whitespace are ignored).
+pub fn from_avsc_str_lossless(input: &str) -> Result<Schema, AvdlError> {
+    let original: Value = serde_json::from_str(input)?;
+    let schema = from_json(&original)?;
+    let written = serde_json::to_value(Avsc(&schema))?;
+    let lost = lossy_paths(&original, &written);
+    if lost.is_empty() {
+        Ok(schema)
+    } else {
+        Err(AvdlError::LossyAvsc(lost))
+    }
+}
+
+/// Build a schema out of an already parsed JSON value.
+pub fn from_json(value: &Value) -> Result<Schema, AvdlError> {
+    read_schema(value, "")
+}
+
+/// Write `schema` as a pretty printed `.avsc` document.
+pub fn to_avsc_string(schema: &Schema) -> Result<String, AvdlError> {
+    Ok(serde_json::to_string_pretty(&Avsc(schema))?)
+}
+
+/// JSON pointers to the values of `original` which are missing or different
+/// in `written`.
+pub fn lossy_paths(original: &Value, written: &Value) -> Vec<String> {
+    let mut paths = Vec::new();
+    collect_lossy_paths(original, written, String::new(), &mut paths);
+    paths
+}
+
+fn collect_lossy_paths(original: &Value, written: &Value, path: String, paths: &mut Vec<String>) {
+    match (original, written) {
+        (Value::Object(original), Value::Object(written)) => {
+            for (key, value) in original {
+                let path = format!("{path}/{}", key.replace('~', "~0").replace('/', "~1"));
+                match written.get(key) {
+                    Some(other) => collect_lossy_paths(value, other, path, paths),
+                    None => paths.push(path),
+                }
+            }
+        }
+        (Value::Array(original), Value::Array(written)) if original.len() == written.len() => {
+            for (i, (value, other)) in original.iter().zip(written).enumerate() {
+                collect_lossy_paths(value, other, format!("{path}/{i}"), paths);
+            }
+        }
+        (original, written) if original == written => (),
+        _ => paths.push(path),
+    }
+}
+
+pub(crate) fn invalid(path: &str, message: impl Into<String>) -> AvdlError {
+    AvdlError::InvalidAvsc {
+        path: if path.is_empty() { "/" } else { path }.to_string(),
+        message: message.into(),
+    }
+}
+
+pub(crate) fn read_schema(value: &Value, path: &str) -> Result<Schema, AvdlError> {
+    match value {
+        Value::String(name) => read_type_name(name),
+        Value::Array(variants) => {
+            let variants = variants
+                .iter()
+                .enumerate()
+                .map(|(i, variant)| read_schema(variant, &format!("{path}/{i}")))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(Schema::Union(UnionSchema::new(variants)?))
+        }
+        Value::Object(object) => read_object(object, path),
+        _ => Err(invalid(path, "expected a type name, a union or an object")),
+    }
+}
+
+fn read_type_name(name: &str) -> Result<Schema, AvdlError> {
+    let schema = match name {
+        "null" => Schema::Null,
+        "boolean" => Schema::Boolean,
+        "int" => Schema::Int,
+        "long" => Schema::Long,
+        "float" => Schema::Float,
+        "double" => Schema::Double,
+        "bytes" => Schema::Bytes,
+        "string" => Schema::String,
+        _ => Schema::Ref {
+            name: Name::new(name)?,
+        },
+    };
+    Ok(schema)
+}
+
+fn read_object(object: &Map<String, Value>, path: &str) -> Result<Schema, AvdlError> {
+    let type_name = match object.get("type") {
+        Some(Value::String(type_name)) => type_name.as_str(),
+        Some(value) => return read_schema(value, &format!("{path}/type")),
+        None => return Err(invalid(path, "missing \"type\"")),
+    };
+    let schema = match type_name {
+        "record" | "error" => {
+            let fields_path = format!("{path}/fields");
+            let fields = match object.get("fields") {
+                Some(Value::Array(fields)) => fields
+                    .iter()
+                    .enumerate()
+                    .map(|(i, field)| read_field(field, i, &format!("{fields_path}/{i}")))
+                    .collect::<Result<Vec<_>, _>>()?,
+                _ => return Err(invalid(&fields_path, "expected an array of fields")),
+            };
+            let lookup = fields
+                .iter()
+                .map(|field| (field.name.clone(), field.position))
+                .collect();
+            Ok(Schema::Record {
+                name: read_name(object, path)?,
+                aliases: read_aliases(object, path)?,
+                doc: read_doc(object, path)?,
+                fields,
+                lookup,
+                attributes: read_attributes(object, &["fields"]),
+            })
+        }
+        "enum" => {
+            let symbols = object
+                .get("symbols")
+                .and_then(Value::as_array)
+                .and_then(|symbols| {
+                    symbols
+                        .iter()
+                        .map(|symbol| symbol.as_str().map(String::from))
+                        .collect::<Option<Vec<_>>>()
+                })
+                .ok_or_else(|| {
+                    invalid(&format!("{path}/symbols"), "expected an array of strings")
+                })?;
+            Ok(Schema::Enum {
+                name: read_name(object, path)?,
+                aliases: read_aliases(object, path)?,
+                doc: read_doc(object, path)?,
+                symbols,
+                attributes: read_attributes(object, &["symbols"]),
+            })
+        }
+        "fixed" => {
+            let size = object
+                .get("size")
+                .and_then(Value::as_u64)
+                .ok_or_else(|| invalid(&format!("{path}/size"), "expected a positive integer"))?;
+            Ok(Schema::Fixed {
+                name: read_name(object, path)?,
+                aliases: read_aliases(object, path)?,
+                doc: read_doc(object, path)?,
+                size: size as usize,
+                attributes: read_attributes(object, &["size"]),
+            })
+        }
+        "array" => {
+            let items = object
+                .get("items")
+                .ok_or_else(|| invalid(path, "missing \"items\""))?;
+            Ok(Schema::Array(Box::new(read_schema(
+                items,
+                &format!("{path}/items"),
+            )?)))
+        }
+        "map" => {
+            let values = object
+                .get("values")
+                .ok_or_else(|| invalid(path, "missing \"values\""))?;
+            Ok(Schema::Map(Box::new(read_schema(
+                values,
+                &format!("{path}/values"),
+            )?)))
+        }
+        type_name => read_type_name(type_name),
+    }?;
+    match object.get("logicalType") {
+        Some(Value::String(logical_type)) => Ok(read_logical_type(schema, logical_type, object)),
+        _ => Ok(schema),
+    }
+}
+
+// Logical types which do not apply to the underlying type are ignored, as
+// the specification asks. A duration is left as the fixed it is stored in,
+// since `Schema::Duration` has no room for the name of the fixed
+fn read_logical_type(schema: Schema, logical_type: &str, object: &Map<String, Value>) -> Schema {
+    match (logical_type, schema) {
+        ("decimal", schema @ (Schema::Bytes | Schema::Fixed { .. })) => {
+            let precision = object.get("precision").and_then(Value::as_u64);
+            let scale = object.get("scale").and_then(Value::as_u64).unwrap_or(0);
+            match precision {
+                Some(precision) if precision > 0 && scale <= precision => Schema::Decimal {
+                    precision: precision as usize,
+                    scale: scale as usize,
+                    inner: Box::new(without_logical_keys(schema)),
+                },
+                _ => schema,
+            }
+        }
+        ("uuid", Schema::String) => Schema::Uuid,
+        ("date", Schema::Int) => Schema::Date,
+        ("time-millis", Schema::Int) => Schema::TimeMillis,
+        ("time-micros", Schema::Long) => Schema::TimeMicros,
+        ("timestamp-millis", Schema::Long) => Schema::TimestampMillis,
+        ("timestamp-micros", Schema::Long) => Schema::TimestampMicros,
+        ("local-timestamp-millis", Schema::Long) => Schema::LocalTimestampMillis,
+        ("local-timestamp-micros", Schema::Long) => Schema::LocalTimestampMicros,
+        (_, schema) => schema,
+    }
+}
+
+// The keys of the logical type were read as attributes of the fixed
+fn without_logical_keys(mut schema: Schema) -> Schema {
+    if let Schema::Fixed { attributes, .. } = &mut schema {
+        for key in ["logicalType", "precision", "scale"] {
+            attributes.remove(key);
+        }
+    }
+    schema
+}
+
+pub(crate) fn read_field(
+    value: &Value,
+    position: usize,
+    path: &str,
+) -> Result<RecordField, AvdlError> {
+    let object = value
+        .as_object()
+        .ok_or_else(|| invalid(path, "expected a field object"))?;
+    let name = object
+        .get("name")
+        .and_then(Value::as_str)
+        .ok_or_else(|| invalid(path, "missing field \"name\""))?;
+    let schema = object
+        .get("type")
+        .ok_or_else(|| invalid(path, "missing field \"type\""))?;
+    let order = match object.get("order").and_then(Value::as_str) {
+        None | Some("ascending") => RecordFieldOrder::Ascending,
+        Some("descending") => RecordFieldOrder::Descending,
+        Some("ignore") => RecordFieldOrder::Ignore,
+        Some(order) => {
+            return Err(invalid(
+                &format!("{path}/order"),
+                format!("unknown order \"{order}\""),
+            ))
+        }
+    };
+    let aliases = match object.get("aliases") {
+        Some(aliases) => Some(read_strings(aliases, &format!("{path}/aliases"))?),
+        None => None,
+    };
+    let custom_attributes = object
+        .iter()
+        .filter(|(key, _)| !FIELD_KEYS.contains(&key.as_str()))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    Ok(RecordField {
+        name: name.to_string(),
+        doc: read_doc(object, path)?,
+        aliases,
+        default: object.get("default").cloned(),
+        schema: read_schema(schema, &format!("{path}/type"))?,
+        order,
+        position,
+        custom_attributes,
+    })
+}
+
+fn read_name(object: &Map<String, Value>, path: &str) -> Result<Name, AvdlError> {
+    let name = object
+        .get("name")
+        .and_then(Value::as_str)
+        .ok_or_else(|| invalid(path, "missing \"name\""))?;
+    let mut name = Name::new(name)?;
+    if name.namespace.is_none() {
+        name.namespace = object
+            .get("namespace")
+            .and_then(Value::as_str)
+            .filter(|namespace| !namespace.is_empty())
+            .map(String::from);
+    }
+    Ok(name)
+}
+
+pub(crate) fn read_doc(
+    object: &Map<String, Value>,
+    path: &str,
+) -> Result<Option<String>, AvdlError> {
+    match object.get("doc") {
+        Some(Value::String(doc)) => Ok(Some(doc.clone())),
+        Some(_) => Err(invalid(&format!("{path}/doc"), "expected a string")),
+        None => Ok(None),
+    }
+}
+
+fn read_aliases(object: &Map<String, Value>, path: &str) -> Result<Option<Vec<Alias>>, AvdlError> {
+    match object.get("aliases") {
+        Some(aliases) => {
+            let aliases = read_strings(aliases, &format!("{path}/aliases"))?
+                .iter()
+                .map(|alias| Alias::new(alias))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(Some(aliases))
+        }
+        None => Ok(None),
+    }
+}
+
+pub(crate) fn read_strings(value: &Value, path: &str) -> Result<Vec<String>, AvdlError> {
+    value
+        .as_array()
+        .and_then(|values| {
+            values
+                .iter()
+                .map(|value| value.as_str().map(String::from))
+                .collect::<Option<Vec<_>>>()
+        })
+        .ok_or_else(|| invalid(path, "expected an array of strings"))
+}
+
+/// Keys of a named schema which are not understood by the reader.
+fn read_attributes(object: &Map<String, Value>, known: &[&str]) -> BTreeMap<String, Value> {
+    object
+        .iter()
+        .filter(|(key, _)| !NAMED_KEYS.contains(&key.as_str()) && !known.contains(&key.as_str()))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect()
+}
+
+/// Serializes a schema the way it is written in an `.avsc` file, including
+/// the attributes unknown to Avro.
+///
+/// Keys are written in the same order as the Java implementation does. A
+/// named type is only defined the first time it appears, later occurrences
+/// are written as its name.
+pub struct Avsc<'a>(pub &'a Schema);
+
+impl Serialize for Avsc<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SchemaWriter::new(self.0, &RefCell::new(HashSet::new()), &None).serialize(serializer)
+    }
+}
+
+/// Serializes named types as a JSON array of `.avsc` schemas, the names
+/// without a namespace belonging to the one given.
+///
+/// As in an `.avpr` file, a named type is defined where it is first used
+/// and referenced by name afterwards, so the array loads when every type
+/// comes after the ones it references, the way
+/// [`crate::graph::topological_order`] sorts them.
+pub struct AvscList<'a>(pub &'a [&'a Schema], pub &'a Namespace);
+
+impl Serialize for AvscList<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let AvscList(schemas, namespace) = self;
+        // Each schema of the array is read on its own, with no enclosing
+        // namespace
+        let schemas: Vec<Schema> = schemas
+            .iter()
+            .map(|schema| {
+                let mut schema = (*schema).clone();
+                if let Schema::Record { name, .. }
+                | Schema::Enum { name, .. }
+                | Schema::Fixed { name, .. } = &mut schema
+                {
+                    *name = name.fully_qualified_name(namespace);
+                }
+                schema
+            })
+            .collect();
+        let written = RefCell::new(HashSet::new());
+        serializer.collect_seq(
+            schemas
+                .iter()
+                .map(|schema| SchemaWriter::new(schema, &written, namespace)),
+        )
+    }
+}
+
+/// Writes a schema like [`Avsc`], sharing the names already written with
+/// other schemas of the same document.
+///
+/// `namespace` is the one the schema is written in, which the names without a
+/// namespace belong to.
+pub(crate) struct SchemaWriter<'a> {
+    schema: &'a Schema,
+    written: &'a RefCell<HashSet<String>>,
+    namespace: Namespace,
+}
+
+/// Writes a record field like [`SchemaWriter`], also used for the parameters
+/// of messages.
+pub(crate) struct FieldWriter<'a> {
+    field: &'a RecordField,
+    written: &'a RefCell<HashSet<String>>,
+    namespace: Namespace,
+}
+
+impl<'a> SchemaWriter<'a> {
+    pub(crate) fn new(
+        schema: &'a Schema,
+        written: &'a RefCell<HashSet<String>>,
+        namespace: &Namespace,
+    ) -> Self {
+        SchemaWriter {
+            schema,
+            written,
+            namespace: namespace.clone(),
+        }
+    }
+
+    fn nested(&self, schema: &'a Schema) -> Self {
+        SchemaWriter::new(schema, self.written, &self.namespace)
+    }
+
+    // Whether `name` is written for the first time, remembering it
+    fn first_time(&self, name: &Name) -> bool {
+        let fullname = name.fully_qualified_name(&self.namespace);
+        self.written.borrow_mut().insert(fullname.fullname(None))
+    }
+
+    // A reference to `name` is resolved in the enclosing namespace, so the
+    // namespace is only written when it is another one, as the Java
+    // implementation does
+    fn reference(&self, name: &Name) -> String {
+        let fullname = name.fully_qualified_name(&self.namespace);
+        if fullname.namespace == self.namespace {
+            fullname.name
+        } else {
+            fullname.fullname(None)
+        }
+    }
+}
+
+impl Serialize for SchemaWriter<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let name = match self.schema {
+            Schema::Record { name, .. }
+            | Schema::Enum { name, .. }
+            | Schema::Fixed { name, .. } => Some(name),
+            Schema::Decimal { inner, .. } => match inner.as_ref() {
+                Schema::Fixed { name, .. } => Some(name),
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some(name) = name {
+            if !self.first_time(name) {
+                return serializer.serialize_str(&self.reference(name));
+            }
+        }
+        match self.schema {
+            Schema::Null => serializer.serialize_str("null"),
+            Schema::Boolean => serializer.serialize_str("boolean"),
+            Schema::Int => serializer.serialize_str("int"),
+            Schema::Long => serializer.serialize_str("long"),
+            Schema::Float => serializer.serialize_str("float"),
+            Schema::Double => serializer.serialize_str("double"),
+            Schema::Bytes => serializer.serialize_str("bytes"),
+            Schema::String => serializer.serialize_str("string"),
+            Schema::Ref { name } => serializer.serialize_str(&self.reference(name)),
+            Schema::Union(union) => {
+                serializer.collect_seq(union.variants().iter().map(|variant| self.nested(variant)))
+            }
+            Schema::Array(items) => {
+                let mut map = serializer.serialize_map(None)?;
+                map.serialize_entry("type", "array")?;
+                map.serialize_entry("items", &self.nested(items))?;
+                map.end()
+            }
+            Schema::Map(values) => {
+                let mut map = serializer.serialize_map(None)?;
+                map.serialize_entry("type", "map")?;
+                map.serialize_entry("values", &self.nested(values))?;
+                map.end()
+            }
+            Schema::Record {
+                name,
+                aliases,
+                doc,
+                fields,
+                attributes,
+                ..
+            } => {
+                let mut map = serializer.serialize_map(None)?;
+                map.serialize_entry("type", "record")?;
+                serialize_name(&mut map, name, doc)?;
+                let namespace = name.fully_qualified_name(&self.namespace).namespace;
+                let fields: Vec<FieldWriter> = fields
+                    .iter()
+                    .map(|field| FieldWriter::new(field, self.written, &namespace))
+                    .collect();
+                map.serialize_entry("fields", &fields)?;
+                serialize_attributes(&mut map, attributes, &["fields"])?;
+                serialize_aliases(&mut map, aliases)?;
+                map.end()
+            }
+            Schema::Enum {
+                name,
+                aliases,
+                doc,
+                symbols,
+                attributes,
+            } => {
+                let mut map = serializer.serialize_map(None)?;
+                map.serialize_entry("type", "enum")?;
+                serialize_name(&mut map, name, doc)?;
+                map.serialize_entry("symbols", symbols)?;
+                serialize_attributes(&mut map, attributes, &["symbols"])?;
+                serialize_aliases(&mut map, aliases)?;
+                map.end()
+            }
+            Schema::Fixed {
+                name,
+                aliases,
+                doc,
+                size,
+                attributes,
+            } => {
+                let mut map = serializer.serialize_map(None)?;
+                map.serialize_entry("type", "fixed")?;
+                serialize_name(&mut map, name, doc)?;
+                map.serialize_entry("size", size)?;
+                serialize_attributes(&mut map, attributes, &["size"])?;
+                serialize_aliases(&mut map, aliases)?;
+                map.end()
+            }
+            Schema::Decimal {
+                precision,
+                scale,
+                inner,
+            } => {
+                let mut map = serializer.serialize_map(None)?;
+                match inner.as_ref() {
+                    Schema::Fixed {
+                        name,
+                        aliases,
+                        doc,
+                        size,
+                        attributes,
+                    } => {
+                        map.serialize_entry("type", "fixed")?;
+                        serialize_name(&mut map, name, doc)?;
+                        map.serialize_entry("size", size)?;
+                        serialize_attributes(
+                            &mut map,
+                            attributes,
+                            &["size", "logicalType", "precision", "scale"],
+                        )?;
+                        serialize_aliases(&mut map, aliases)?;
+                    }
+                    _ => map.serialize_entry("type", "bytes")?,
+                }
+                map.serialize_entry("logicalType", "decimal")?;
+                map.serialize_entry("precision", precision)?;
+                map.serialize_entry("scale", scale)?;
+                map.end()
+            }
+            Schema::Uuid => serialize_logical(serializer, "string", "uuid"),
+            Schema::Date => serialize_logical(serializer, "int", "date"),
+            Schema::TimeMillis => serialize_logical(serializer, "int", "time-millis"),
+            Schema::TimeMicros => serialize_logical(serializer, "long", "time-micros"),
+            Schema::TimestampMillis => serialize_logical(serializer, "long", "timestamp-millis"),
+            Schema::TimestampMicros => serialize_logical(serializer, "long", "timestamp-micros"),
+            Schema::LocalTimestampMillis => {
+                serialize_logical(serializer, "long", "local-timestamp-millis")
+            }
+            Schema::LocalTimestampMicros => {
+                serialize_logical(serializer, "long", "local-timestamp-micros")
+            }
+            // Written as a fixed named after it, defined at its first use
+            Schema::Duration => {
+                let name = Name::new("duration").map_err(S::Error::custom)?;
+                if !self.first_time(&name) {
+                    return serializer.serialize_str(&self.reference(&name));
+                }
+                let mut map = serializer.serialize_map(None)?;
+                map.serialize_entry("type", "fixed")?;
+                map.serialize_entry("name", "duration")?;
+                map.serialize_entry("size", &12)?;
+                map.serialize_entry("logicalType", "duration")?;
+                map.end()
+            }
+            schema => Err(S::Error::custom(format!(
+                "cannot write {:?} to avsc",
+                apache_avro::schema::SchemaKind::from(schema)
+            ))),
+        }
+    }
+}
+
+impl<'a> FieldWriter<'a> {
+    pub(crate) fn new(
+        field: &'a RecordField,
+        written: &'a RefCell<HashSet<String>>,
+        namespace: &Namespace,
+    ) -> Self {
+        FieldWriter {
+            field,
+            written,
+            namespace: namespace.clone(),
+        }
+    }
+}
+
+impl Serialize for FieldWriter<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let field = self.field;
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("name", &field.name)?;
+        map.serialize_entry(
+            "type",
+            &SchemaWriter::new(&field.schema, self.written, &self.namespace),
+        )?;
+        if let Some(doc) = &field.doc {
+            map.serialize_entry("doc", doc)?;
+        }
+        if let Some(default) = &field.default {
+            map.serialize_entry("default", default)?;
+        }
+        match field.order {
+            RecordFieldOrder::Ascending => (),
+            RecordFieldOrder::Descending => map.serialize_entry("order", "descending")?,
+            RecordFieldOrder::Ignore => map.serialize_entry("order", "ignore")?,
+        }
+        if let Some(key) = field
+            .custom_attributes
+            .keys()
+            .find(|key| FIELD_KEYS.contains(&key.as_str()))
+        {
+            return Err(S::Error::custom(format!(
+                "attribute `{key}` of field `{}` is a reserved key",
+                field.name
+            )));
+        }
+        for (key, value) in &field.custom_attributes {
+            map.serialize_entry(key, value)?;
+        }
+        if let Some(aliases) = &field.aliases {
+            map.serialize_entry("aliases", aliases)?;
+        }
+        map.end()
+    }
+}
+
+fn serialize_name<M: SerializeMap>(
+    map: &mut M,
+    name: &Name,
+    doc: &Option<String>,
+) -> Result<(), M::Error> {
+    map.serialize_entry("name", &name.name)?;
+    if let Some(namespace) = &name.namespace {
+        map.serialize_entry("namespace", namespace)?;
+    }
+    if let Some(doc) = doc {
+        map.serialize_entry("doc", doc)?;
+    }
+    Ok(())
+}
+
+fn serialize_aliases<M: SerializeMap>(
+    map: &mut M,
+    aliases: &Option<Vec<Alias>>,
+) -> Result<(), M::Error> {
+    if let Some(aliases) = aliases {
+        let aliases: Vec<String> = aliases.iter().map(|alias| alias.fullname(None)).collect();
+        map.serialize_entry("aliases", &aliases)?;
+    }
+    Ok(())
+}
+
+// Attributes are written after the standard keys, failing when one would
+// replace them, which are the keys of every named type and the `reserved`
+// ones of its kind
+fn serialize_attributes<M: SerializeMap>(
+    map: &mut M,
+    attributes: &BTreeMap<String, Value>,
+    reserved: &[&str],
+) -> Result<(), M::Error> {
+    if let Some(key) = attributes
+        .keys()
+        .find(|key| NAMED_KEYS.contains(&key.as_str()) || reserved.contains(&key.as_str()))
+    {
+        return Err(M::Error::custom(format!(
+            "attribute `{key}` is a reserved key"
+        )));
+    }
+    for (key, value) in attributes {
+        map.serialize_entry(key, value)?;
+    }
+    Ok(())
+}
+
+fn serialize_logical<S: Serializer>(
+    serializer: S,
+    type_name: &str,
+    logical_type: &str,
+) -> Result<S::Ok, S::Error> {
+    let mut map = serializer.serialize_map(None)?;
+    map.serialize_entry("type", type_name)?;
+    map.serialize_entry("logicalType", logical_type)?;
+    map.end()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::builder::{EnumBuilder, FieldBuilder, FixedBuilder, RecordBuilder};
+    use crate::parser::parse;
+    use apache_avro::schema::SchemaKind;
+    use rstest::rstest;
+    use serde_json::json;
+
+    #[test]
+    fn test_confluent_keys_are_preserved() {
+        let input = r#"{
+  "type": "record",
+  "name": "Payment",
+  "namespace": "io.confluent.examples",
+  "doc": "A payment",
+  "fields": [
+    {
+      "name": "id",
+      "type": "string"
+    },
+    {
+      "name": "status",
+      "type": {
+        "type": "enum",
+        "name": "Status",
+        "symbols": [
+          "PENDING",
+          "DONE"
+        ],
+        "connect.name": "io.confluent.examples.Status"
+      }
+    }
+  ],
+  "connect.name": "io.confluent.examples.Payment",
+  "connect.version": 2,
+  "x-owner": "payments-team"
+}"#;
+        let schema = from_avsc_str(input).unwrap();
+        match &schema {
+            Schema::Record { attributes, .. } => {
+                assert_eq!(
+                    attributes.keys().collect::<Vec<_>>(),
+                    vec!["connect.name", "connect.version", "x-owner"]
+                );
+            }
+            _ => panic!("expected a record"),
+        }
+        assert_eq!(to_avsc_string(&schema).unwrap(), input);
+        assert!(from_avsc_str_lossless(input).is_ok());
+    }
+
+    #[test]
+    fn test_field_unknown_keys_are_preserved() {
+        let input = r#"{
+  "type": "record",
+  "name": "User",
+  "fields": [
+    {
+      "name": "email",
+      "type": "string",
+      "doc": "Contact address",
+      "x-pii": true,
+      "x-tags": [
+        "contact"
+      ]
+    }
+  ]
+}"#;
+        let schema = from_avsc_str(input).unwrap();
+        match &schema {
+            Schema::Record { fields, .. } => {
+                assert_eq!(fields[0].custom_attributes["x-pii"], Value::Bool(true));
+            }
+            _ => panic!("expected a record"),
+        }
+        assert_eq!(to_avsc_string(&schema).unwrap(), input);
+    }
+
+    #[test]
+    fn test_field_custom_attributes_follow_standard_keys() {
+        let mut schema = from_avsc_str(
+            r#"{"type": "record", "name": "User", "fields": [{"name": "email", "type": "string"}]}"#,
+        )
+        .unwrap();
+        if let Schema::Record { fields, .. } = &mut schema {
+            fields[0].default = Some(Value::from(""));
+            fields[0].aliases = Some(vec!["mail".to_string()]);
+            fields[0]
+                .custom_attributes
+                .insert("x-tags".to_string(), Value::from("contact"));
+            fields[0]
+                .custom_attributes
+                .insert("x-pii".to_string(), Value::Bool(true));
+        }
+        let written = serde_json::to_string(&Avsc(&schema)).unwrap();
+        assert!(written.contains(
+            r#"{"name":"email","type":"string","default":"","x-pii":true,"x-tags":"contact","aliases":["mail"]}"#
+        ));
+    }
+
+    #[test]
+    fn test_built_attributes_are_written() {
+        let status = EnumBuilder::new("Status")
+            .symbols(["ON", "OFF"])
+            .attribute("x-owner", json!("ops"))
+            .build()
+            .unwrap();
+        let hash = FixedBuilder::new("Hash", 16)
+            .attribute("x-algorithm", json!("md5"))
+            .build()
+            .unwrap();
+        let device = RecordBuilder::new("Device")
+            .attribute("x-version", json!(2))
+            .attribute("x-owner", json!("ops"))
+            .field(FieldBuilder::new("status", status).attribute("x-pii", json!(false)))
+            .field(FieldBuilder::new("hash", hash))
+            .build()
+            .unwrap();
+        assert_eq!(
+            serde_json::to_string(&Avsc(&device)).unwrap(),
+            concat!(
+                r#"{"type":"record","name":"Device","fields":["#,
+                r#"{"name":"status","type":{"type":"enum","name":"Status","symbols":["ON","OFF"],"x-owner":"ops"},"x-pii":false},"#,
+                r#"{"name":"hash","type":{"type":"fixed","name":"Hash","size":16,"x-algorithm":"md5"}}"#,
+                r#"],"x-owner":"ops","x-version":2}"#
+            )
+        );
+    }
+
+    #[rstest]
+    #[case(RecordBuilder::new("R").attribute("name", json!("S")).build(), "attribute `name` is a reserved key")]
+    #[case(RecordBuilder::new("R").attribute("fields", json!([])).build(), "attribute `fields` is a reserved key")]
+    #[case(EnumBuilder::new("E").symbol("A").attribute("symbols", json!([])).build(), "attribute `symbols` is a reserved key")]
+    #[case(FixedBuilder::new("F", 4).attribute("type", json!("bytes")).build(), "attribute `type` is a reserved key")]
+    #[case(
+        RecordBuilder::new("R").field(FieldBuilder::new("id", Schema::Int).attribute("default", json!(1))).build(),
+        "attribute `default` of field `id` is a reserved key"
+    )]
+    fn test_reserved_attribute_fails(
+        #[case] schema: Result<Schema, AvdlError>,
+        #[case] message: &str,
+    ) {
+        let err = serde_json::to_string(&Avsc(&schema.unwrap())).unwrap_err();
+        assert_eq!(err.to_string(), message);
+    }
+
+    #[rstest]
+    #[case::record(
+        r#"{"type": "record", "name": "R", "fields": [], "x-internal": true, "confluent:version": 3}"#
+    )]
+    #[case::field(
+        r#"{"type": "record", "name": "R", "fields": [{"name": "id", "type": "long", "x-internal": {"owner": "ops"}}]}"#
+    )]
+    #[case::enum_(r#"{"type": "enum", "name": "E", "symbols": ["A"], "x-internal": ["a", 1]}"#)]
+    #[case::fixed(r#"{"type": "fixed", "name": "F", "size": 4, "x-internal": null}"#)]
+    #[case::decimal(
+        r#"{"type": "fixed", "name": "D", "size": 8, "logicalType": "decimal", "precision": 9, "scale": 2, "x-internal": "yes"}"#
+    )]
+    fn test_unknown_keys_round_trip(#[case] input: &str) {
+        let schema = from_avsc_str_lossless(input).unwrap();
+        let written: Value = serde_json::from_str(&to_avsc_string(&schema).unwrap()).unwrap();
+        let original: Value = serde_json::from_str(input).unwrap();
+        assert_eq!(written, original);
+    }
+
+    #[test]
+    fn test_lossy_avsc() {
+        let input = r#"{
+  "type": "record",
+  "name": "User",
+  "fields": [
+    {
+      "name": "email",
+      "type": ["null", {"type": "string", "x-max-length": 254}]
+    }
+  ]
+}"#;
+        match from_avsc_str_lossless(input) {
+            Err(AvdlError::LossyAvsc(paths)) => {
+                assert_eq!(paths, vec!["/fields/0/type/1"]);
+            }
+            result => panic!("unexpected result {result:?}"),
+        }
+    }
+
+    #[test]
+    fn test_named_type_is_defined_once() {
+        let input = r#"{
+  "type": "record",
+  "name": "Pair",
+  "fields": [
+    {
+      "name": "first",
+      "type": {
+        "type": "fixed",
+        "name": "Hash",
+        "size": 16
+      }
+    },
+    {
+      "name": "second",
+      "type": "Hash"
+    }
+  ]
+}"#;
+        let mut schema = from_avsc_str(input).unwrap();
+        // Solve the reference the way the IDL parser does
+        if let Schema::Record { fields, .. } = &mut schema {
+            fields[1].schema = fields[0].schema.clone();
+        }
+        assert_eq!(to_avsc_string(&schema).unwrap(), input);
+    }
+
+    fn field_types(written: &str) -> Vec<Value> {
+        let value: Value = serde_json::from_str(written).unwrap();
+        value["fields"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|field| field["type"].clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_references_in_the_same_namespace_are_short() {
+        let (_tail, schemas) = parse(
+            r#"@namespace("org.example") protocol P {
+    enum Status { ACTIVE }
+    record User { Status status; union { null, Status } previous = null; }
+}"#,
+        )
+        .unwrap();
+        let written = to_avsc_string(&schemas[1]).unwrap();
+        assert_eq!(
+            field_types(&written)[1],
+            serde_json::json!(["null", "Status"])
+        );
+        assert_eq!(from_avsc_str(&written).unwrap(), schemas[1]);
+    }
+
+    #[test]
+    fn test_references_across_namespaces_are_qualified() {
+        let input = r#"{
+  "type": "record",
+  "name": "User",
+  "namespace": "org.example",
+  "fields": [
+    {"name": "home", "type": "org.geo.Place"},
+    {"name": "work", "type": {"type": "record", "name": "Office", "fields": [
+      {"name": "place", "type": "org.geo.Place"},
+      {"name": "owner", "type": "User"}
+    ]}}
+  ]
+}"#;
+        let schema = from_avsc_str(input).unwrap();
+        let written = to_avsc_string(&schema).unwrap();
+        let types = field_types(&written);
+        assert_eq!(types[0], "org.geo.Place");
+        assert_eq!(types[1]["fields"][0]["type"], "org.geo.Place");
+        assert_eq!(types[1]["fields"][1]["type"], "User");
+    }
+
+    #[rstest]
+    #[case(
+        r#"{"type": "long", "logicalType": "timestamp-millis"}"#,
+        SchemaKind::TimestampMillis
+    )]
+    #[case(
+        r#"{"type": "long", "logicalType": "local-timestamp-micros"}"#,
+        SchemaKind::LocalTimestampMicros
+    )]
+    #[case(r#"{"type": "int", "logicalType": "date"}"#, SchemaKind::Date)]
+    #[case(r#"{"type": "string", "logicalType": "uuid"}"#, SchemaKind::Uuid)]
+    #[case(
+        r#"{"type": "bytes", "logicalType": "decimal", "precision": 4, "scale": 2}"#,
+        SchemaKind::Decimal
+    )]
+    #[case(r#"{"type": "fixed", "name": "Money", "size": 8, "logicalType": "decimal", "precision": 4}"#, SchemaKind::Decimal)]
+    // Kept as the fixed, with its name
+    #[case(
+        r#"{"type": "fixed", "name": "Span", "size": 12, "logicalType": "duration"}"#,
+        SchemaKind::Fixed
+    )]
+    // Not applicable, the underlying type is kept
+    #[case(r#"{"type": "string", "logicalType": "date"}"#, SchemaKind::String)]
+    #[case(
+        r#"{"type": "bytes", "logicalType": "decimal", "precision": 2, "scale": 4}"#,
+        SchemaKind::Bytes
+    )]
+    #[case(r#"{"type": "long", "logicalType": "x-unknown"}"#, SchemaKind::Long)]
+    fn test_logical_types(#[case] input: &str, #[case] expected: SchemaKind) {
+        let schema = from_avsc_str(input).unwrap();
+        assert_eq!(SchemaKind::from(&schema), expected);
+    }
+
+    #[rstest]
+    #[case(r#"{"type": "bytes", "logicalType": "decimal", "precision": 9, "scale": 2}"#)]
+    #[case(r#"{"type": "fixed", "name": "Money", "size": 8, "logicalType": "decimal", "precision": 4, "scale": 0}"#)]
+    #[case(r#"{"type": "fixed", "name": "Span", "size": 12, "logicalType": "duration"}"#)]
+    #[case(
+        r#"{"type": "record", "name": "Trip", "fields": [{"name": "out", "type": {"type": "fixed", "name": "Span", "size": 12, "logicalType": "duration"}}, {"name": "back", "type": "Span"}]}"#
+    )]
+    #[case(r#"["null", {"type": "array", "items": ["int", {"type": "map", "values": ["null", "string"]}]}]"#)]
+    #[case(
+        r#"{"type": "enum", "name": "Kind", "namespace": "org.example", "aliases": ["Sort"], "doc": "What it is", "symbols": ["A", "B"], "default": "A"}"#
+    )]
+    #[case(
+        r#"{"type": "record", "name": "Tagged", "aliases": ["org.old.Labelled"], "doc": "Some tags", "owner": "team", "fields": [{"name": "tags", "type": [{"type": "array", "items": "string"}, "null"], "doc": "The tags", "aliases": ["labels"], "default": []}]}"#
+    )]
+    fn test_avsc_round_trip(#[case] input: &str) {
+        // Schemas read from avsc are the same type the parser returns, so a
+        // round trip is writing and reading them back
+        let schema = from_avsc_str(input).unwrap();
+        let written = to_avsc_string(&schema).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Value>(&written).unwrap(),
+            serde_json::from_str::<Value>(input).unwrap()
+        );
+        let read = from_avsc_str(&written).unwrap();
+        assert_eq!(read.canonical_form(), schema.canonical_form());
+        assert_eq!(to_avsc_string(&read).unwrap(), written);
+    }
+
+    #[test]
+    fn test_parsed_record_round_trip() {
+        let input = r#"protocol P {
+    enum Kind { A, B }
+    fixed Hash(16);
+    record Sample {
+        date day;
+        time_ms at;
+        timestamp_ms created;
+        @logicalType("timestamp-micros") long updated;
+        uuid id;
+        decimal(9, 2) price;
+        Kind kind;
+        union { null, Hash } hash = null;
+        array<string> tags = [];
+        map<int> counts;
+    }
+}"#;
+        let (_tail, schemas) = parse(input).unwrap();
+        let record = &schemas[2];
+        let written = to_avsc_string(record).unwrap();
+        let read = from_avsc_str(&written).unwrap();
+        assert_eq!(read.canonical_form(), record.canonical_form());
+        let kinds = |schema: &Schema| match schema {
+            Schema::Record { fields, .. } => fields
+                .iter()
+                .map(|field| SchemaKind::from(&field.schema))
+                .collect::<Vec<_>>(),
+            _ => panic!("expected a record"),
+        };
+        assert_eq!(kinds(&read), kinds(record));
+    }
+
+    #[test]
+    fn test_durations_defined_once() {
+        let input = r#"protocol P {
+    record Trip {
+        @logicalType("duration") duration out;
+        array<@logicalType("duration") duration> stops;
+    }
+}"#;
+        let (_tail, schemas) = parse(input).unwrap();
+        let written = to_avsc_string(&schemas[0]).unwrap();
+        let types = field_types(&written);
+        assert_eq!(types[0]["name"], "duration");
+        assert_eq!(types[1]["items"], "duration");
+        assert!(from_avsc_str(&written).is_ok());
+    }
+
+    #[test]
+    fn test_avsc_list() {
+        let input = r#"@namespace("org.example")
+protocol P {
+    enum Kind { A, B }
+    record Sample { Kind kind; union { null, Kind } previous = null; }
+}"#;
+        let (_tail, schemas) = parse(input).unwrap();
+        let schemas: Vec<&Schema> = schemas.iter().collect();
+        let namespace = Some("org.example".to_string());
+        let written = serde_json::to_value(AvscList(&schemas, &namespace)).unwrap();
+        assert_eq!(written[0]["namespace"], "org.example");
+        assert_eq!(written[1]["fields"][0]["type"], "Kind");
+        assert_eq!(written[1]["fields"][1]["type"][1], "Kind");
+    }
+}
@@ -0,0 +1,470 @@
+//! Building named types in code, instead of writing the `Schema` variants by
+//! hand.
+
+use std::collections::{BTreeMap, HashSet};
+
+use apache_avro::schema::{Alias, Name, RecordField, RecordFieldOrder, Schema};
+use serde_json::Value;
+
+use crate::error::AvdlError;
+use crate::options::DEFAULT_MAX_DEPTH;
+use crate::validate::{invalid_names, is_valid_name, validate_defaults};
+
+/// Builds a [`Schema::Record`], numbering its fields and filling the lookup
+/// of their names.
+///
+/// ```
+/// use apache_avro::schema::{RecordFieldOrder, Schema};
+/// use avdl_parser::builder::{FieldBuilder, RecordBuilder};
+/// use serde_json::json;
+///
+/// let person = RecordBuilder::new("Person")
+///     .namespace("org.example")
+///     .doc("Someone we know")
+///     .field(FieldBuilder::new("name", Schema::String).default(json!("unknown")))
+///     .field(FieldBuilder::new("age", Schema::Int).order(RecordFieldOrder::Descending))
+///     .build()
+///     .unwrap();
+/// assert_eq!(person.name().unwrap().fullname(None), "org.example.Person");
+/// ```
+#[derive(Debug, Clone)]
+pub struct RecordBuilder {
+    named: NamedBuilder,
+    fields: Vec<FieldBuilder>,
+}
+
+/// Builds a field for [`RecordBuilder::field`].
+#[derive(Debug, Clone)]
+pub struct FieldBuilder {
+    name: String,
+    schema: Schema,
+    doc: Option<String>,
+    default: Option<Value>,
+    order: RecordFieldOrder,
+    aliases: Vec<String>,
+    attributes: BTreeMap<String, Value>,
+}
+
+/// Builds a [`Schema::Enum`].
+///
+/// ```
+/// use avdl_parser::builder::EnumBuilder;
+///
+/// let suit = EnumBuilder::new("Suit")
+///     .symbols(["SPADES", "HEARTS", "DIAMONDS", "CLUBS"])
+///     .build()
+///     .unwrap();
+/// assert!(EnumBuilder::new("Suit").symbols(["A", "A"]).build().is_err());
+/// ```
+#[derive(Debug, Clone)]
+pub struct EnumBuilder {
+    named: NamedBuilder,
+    symbols: Vec<String>,
+}
+
+/// Builds a [`Schema::Fixed`].
+///
+/// ```
+/// use avdl_parser::builder::FixedBuilder;
+///
+/// let md5 = FixedBuilder::new("MD5", 16).namespace("org.example").build().unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct FixedBuilder {
+    named: NamedBuilder,
+    size: usize,
+}
+
+// What all named types have
+#[derive(Debug, Clone, Default)]
+struct NamedBuilder {
+    name: String,
+    namespace: Option<String>,
+    doc: Option<String>,
+    aliases: Vec<String>,
+    attributes: BTreeMap<String, Value>,
+}
+
+impl NamedBuilder {
+    fn new(name: String) -> NamedBuilder {
+        NamedBuilder {
+            name,
+            ..NamedBuilder::default()
+        }
+    }
+
+    fn name(&self) -> Result<Name, AvdlError> {
+        if let Some(namespace) = &self.namespace {
+            check_fullname(namespace)?;
+        }
+        Ok(Name {
+            name: self.name.clone(),
+            namespace: self.namespace.clone(),
+        })
+    }
+
+    fn aliases(&self) -> Result<Option<Vec<Alias>>, AvdlError> {
+        if self.aliases.is_empty() {
+            return Ok(None);
+        }
+        let aliases = self
+            .aliases
+            .iter()
+            .map(|alias| {
+                check_fullname(alias)?;
+                Ok(Alias::new(alias)?)
+            })
+            .collect::<Result<_, AvdlError>>()?;
+        Ok(Some(aliases))
+    }
+}
+
+// Every part of a dotted name must be a valid name on its own
+fn check_fullname(fullname: &str) -> Result<(), AvdlError> {
+    match fullname.split('.').all(is_valid_name) {
+        true => Ok(()),
+        false => Err(AvdlError::InvalidName(fullname.to_string())),
+    }
+}
+
+fn check_unique<'a>(names: impl IntoIterator<Item = &'a String>) -> Result<(), AvdlError> {
+    let mut seen = HashSet::new();
+    match names.into_iter().find(|name| !seen.insert(*name)) {
+        Some(name) => Err(AvdlError::Duplicate(name.clone())),
+        None => Ok(()),
+    }
+}
+
+// Names are checked the same way for every kind of named type
+fn check_names(schema: Schema) -> Result<Schema, AvdlError> {
+    match invalid_names(std::slice::from_ref(&schema))
+        .into_iter()
+        .next()
+    {
+        Some(err) => Err(err),
+        None => Ok(schema),
+    }
+}
+
+impl RecordBuilder {
+    pub fn new(name: impl Into<String>) -> RecordBuilder {
+        RecordBuilder {
+            named: NamedBuilder::new(name.into()),
+            fields: Vec::new(),
+        }
+    }
+
+    pub fn namespace(mut self, namespace: impl Into<String>) -> RecordBuilder {
+        self.named.namespace = Some(namespace.into());
+        self
+    }
+
+    pub fn doc(mut self, doc: impl Into<String>) -> RecordBuilder {
+        self.named.doc = Some(doc.into());
+        self
+    }
+
+    pub fn alias(mut self, alias: impl Into<String>) -> RecordBuilder {
+        self.named.aliases.push(alias.into());
+        self
+    }
+
+    /// Add a property not defined by Avro, e.g. `connect.name`.
+    pub fn attribute(mut self, key: impl Into<String>, value: Value) -> RecordBuilder {
+        self.named.attributes.insert(key.into(), value);
+        self
+    }
+
+    pub fn field(mut self, field: FieldBuilder) -> RecordBuilder {
+        self.fields.push(field);
+        self
+    }
+
+    /// Build the record, failing if a name is not valid, two fields have the
+    /// same name or a default does not match the type of its field.
+    pub fn build(self) -> Result<Schema, AvdlError> {
+        check_unique(self.fields.iter().map(|field| &field.name))?;
+        let fields: Vec<RecordField> = self
+            .fields
+            .into_iter()
+            .enumerate()
+            .map(|(position, field)| RecordField {
+                aliases: match field.aliases.is_empty() {
+                    true => None,
+                    false => Some(field.aliases),
+                },
+                name: field.name,
+                doc: field.doc,
+                default: field.default,
+                schema: field.schema,
+                order: field.order,
+                position,
+                custom_attributes: field.attributes,
+            })
+            .collect();
+        let lookup = fields
+            .iter()
+            .map(|field| (field.name.clone(), field.position))
+            .collect();
+        let schema = Schema::Record {
+            name: self.named.name()?,
+            aliases: self.named.aliases()?,
+            doc: self.named.doc,
+            fields,
+            lookup,
+            attributes: self.named.attributes,
+        };
+        let schema = check_names(schema)?;
+        match validate_defaults(std::slice::from_ref(&schema), DEFAULT_MAX_DEPTH)
+            .into_iter()
+            .next()
+        {
+            Some(err) => Err(err),
+            None => Ok(schema),
+        }
+    }
+}
+
+impl FieldBuilder {
+    pub fn new(name: impl Into<String>, schema: Schema) -> FieldBuilder {
+        FieldBuilder {
+            name: name.into(),
+            schema,
+            doc: None,
+            default: None,
+            order: RecordFieldOrder::Ascending,
+            aliases: Vec::new(),
+            attributes: BTreeMap::new(),
+        }
+    }
+
+    pub fn doc(mut self, doc: impl Into<String>) -> FieldBuilder {
+        self.doc = Some(doc.into());
+        self
+    }
+
+    pub fn default(mut self, default: Value) -> FieldBuilder {
+        self.default = Some(default);
+        self
+    }
+
+    pub fn order(mut self, order: RecordFieldOrder) -> FieldBuilder {
+        self.order = order;
+        self
+    }
+
+    pub fn alias(mut self, alias: impl Into<String>) -> FieldBuilder {
+        self.aliases.push(alias.into());
+        self
+    }
+
+    /// Add a property not defined by Avro, e.g. `x-pii`.
+    pub fn attribute(mut self, key: impl Into<String>, value: Value) -> FieldBuilder {
+        self.attributes.insert(key.into(), value);
+        self
+    }
+}
+
+impl EnumBuilder {
+    pub fn new(name: impl Into<String>) -> EnumBuilder {
+        EnumBuilder {
+            named: NamedBuilder::new(name.into()),
+            symbols: Vec::new(),
+        }
+    }
+
+    pub fn namespace(mut self, namespace: impl Into<String>) -> EnumBuilder {
+        self.named.namespace = Some(namespace.into());
+        self
+    }
+
+    pub fn doc(mut self, doc: impl Into<String>) -> EnumBuilder {
+        self.named.doc = Some(doc.into());
+        self
+    }
+
+    pub fn alias(mut self, alias: impl Into<String>) -> EnumBuilder {
+        self.named.aliases.push(alias.into());
+        self
+    }
+
+    /// Add a property not defined by Avro.
+    pub fn attribute(mut self, key: impl Into<String>, value: Value) -> EnumBuilder {
+        self.named.attributes.insert(key.into(), value);
+        self
+    }
+
+    pub fn symbol(mut self, symbol: impl Into<String>) -> EnumBuilder {
+        self.symbols.push(symbol.into());
+        self
+    }
+
+    pub fn symbols(mut self, symbols: impl IntoIterator<Item = impl Into<String>>) -> EnumBuilder {
+        self.symbols.extend(symbols.into_iter().map(Into::into));
+        self
+    }
+
+    /// Build the enum, failing if a name is not valid or a symbol is
+    /// repeated.
+    pub fn build(self) -> Result<Schema, AvdlError> {
+        check_unique(&self.symbols)?;
+        check_names(Schema::Enum {
+            name: self.named.name()?,
+            aliases: self.named.aliases()?,
+            doc: self.named.doc,
+            symbols: self.symbols,
+            attributes: self.named.attributes,
+        })
+    }
+}
+
+impl FixedBuilder {
+    pub fn new(name: impl Into<String>, size: usize) -> FixedBuilder {
+        FixedBuilder {
+            named: NamedBuilder::new(name.into()),
+            size,
+        }
+    }
+
+    pub fn namespace(mut self, namespace: impl Into<String>) -> FixedBuilder {
+        self.named.namespace = Some(namespace.into());
+        self
+    }
+
+    pub fn doc(mut self, doc: impl Into<String>) -> FixedBuilder {
+        self.named.doc = Some(doc.into());
+        self
+    }
+
+    pub fn alias(mut self, alias: impl Into<String>) -> FixedBuilder {
+        self.named.aliases.push(alias.into());
+        self
+    }
+
+    /// Add a property not defined by Avro.
+    pub fn attribute(mut self, key: impl Into<String>, value: Value) -> FixedBuilder {
+        self.named.attributes.insert(key.into(), value);
+        self
+    }
+
+    /// Build the fixed, failing if a name is not valid.
+    pub fn build(self) -> Result<Schema, AvdlError> {
+        check_names(Schema::Fixed {
+            name: self.named.name()?,
+            aliases: self.named.aliases()?,
+            doc: self.named.doc,
+            size: self.size,
+            attributes: self.named.attributes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_record_builder() {
+        let built = RecordBuilder::new("Person")
+            .namespace("org.example")
+            .doc("Someone")
+            .alias("Human")
+            .field(FieldBuilder::new("name", Schema::String).default(json!("n")))
+            .field(
+                FieldBuilder::new("age", Schema::Int)
+                    .order(RecordFieldOrder::Descending)
+                    .attribute("x-pii", json!(true)),
+            )
+            .build()
+            .unwrap();
+        let expected = Schema::Record {
+            name: Name {
+                name: "Person".to_string(),
+                namespace: Some("org.example".to_string()),
+            },
+            aliases: Some(vec![Alias::new("Human").unwrap()]),
+            doc: Some("Someone".to_string()),
+            fields: vec![
+                RecordField {
+                    name: "name".to_string(),
+                    doc: None,
+                    default: Some(json!("n")),
+                    schema: Schema::String,
+                    order: RecordFieldOrder::Ascending,
+                    aliases: None,
+                    position: 0,
+                    custom_attributes: BTreeMap::new(),
+                },
+                RecordField {
+                    name: "age".to_string(),
+                    doc: None,
+                    default: None,
+                    schema: Schema::Int,
+                    order: RecordFieldOrder::Descending,
+                    aliases: None,
+                    position: 1,
+                    custom_attributes: BTreeMap::from([("x-pii".to_string(), json!(true))]),
+                },
+            ],
+            lookup: BTreeMap::from([("name".to_string(), 0), ("age".to_string(), 1)]),
+            attributes: BTreeMap::new(),
+        };
+        assert_eq!(format!("{built:?}"), format!("{expected:?}"));
+    }
+
+    #[test]
+    fn test_enum_and_fixed_builders() {
+        let built = EnumBuilder::new("Suit")
+            .symbol("SPADES")
+            .symbol("HEARTS")
+            .build()
+            .unwrap();
+        let expected = Schema::Enum {
+            name: Name::new("Suit").unwrap(),
+            aliases: None,
+            doc: None,
+            symbols: vec!["SPADES".to_string(), "HEARTS".to_string()],
+            attributes: BTreeMap::new(),
+        };
+        assert_eq!(format!("{built:?}"), format!("{expected:?}"));
+
+        let built = FixedBuilder::new("MD5", 16).doc("A hash").build().unwrap();
+        let expected = Schema::Fixed {
+            name: Name::new("MD5").unwrap(),
+            aliases: None,
+            doc: Some("A hash".to_string()),
+            size: 16,
+            attributes: BTreeMap::new(),
+        };
+        assert_eq!(format!("{built:?}"), format!("{expected:?}"));
+    }
+
+    #[test]
+    fn test_builders_validate() {
+        let err = RecordBuilder::new("Person")
+            .field(FieldBuilder::new("age", Schema::Int).default(json!("old")))
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, AvdlError::InvalidDefault { .. }), "{err:?}");
+
+        let err = RecordBuilder::new("Person")
+            .field(FieldBuilder::new("name", Schema::String))
+            .field(FieldBuilder::new("name", Schema::String))
+            .build()
+            .unwrap_err();
+        assert_eq!(err.to_string(), "duplicate name `name`");
+
+        let err = RecordBuilder::new("Person")
+            .field(FieldBuilder::new("first-name", Schema::String))
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, AvdlError::InvalidName(name) if name == "first-name"));
+
+        let err = FixedBuilder::new("MD5", 16)
+            .namespace("org.1example")
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, AvdlError::InvalidName(name) if name == "org.1example"));
+    }
+}
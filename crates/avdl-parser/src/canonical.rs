@@ -0,0 +1,258 @@
+//! The Parsing Canonical Form of schemas, written without going through the
+//! JSON representation of `apache_avro`.
+
+use std::fmt::Write;
+use std::hash::{Hash, Hasher};
+
+use apache_avro::schema::{Name, Namespace, Schema};
+
+/// The Parsing Canonical Form of `schema`, as defined by the specification.
+///
+/// Docs, aliases, defaults and any other attribute are dropped, names are
+/// written as fullnames and keys in the order given by the specification.
+/// Logical types are written as their underlying type. Unlike
+/// `Schema::canonical_form`, references are written by their fullname even
+/// when the type they point to is unknown.
+pub fn canonical_form(schema: &Schema) -> String {
+    let mut out = String::new();
+    write_schema(&mut out, schema, &None);
+    out
+}
+
+/// A schema that is equal to another, and hashes the same, when their
+/// Parsing Canonical Forms are the same, e.g. to deduplicate schemas in a
+/// `HashSet` or to key a cache with them.
+///
+/// `Schema` belongs to apache_avro, so it can't implement `Hash` itself. Its
+/// `PartialEq` writes both canonical forms on every comparison, this one
+/// writes it once, when wrapping the schema.
+#[derive(Debug, Clone)]
+pub struct CanonicalSchema {
+    schema: Schema,
+    canonical: String,
+}
+
+impl CanonicalSchema {
+    pub fn new(schema: Schema) -> Self {
+        let canonical = canonical_form(&schema);
+        CanonicalSchema { schema, canonical }
+    }
+
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    /// The canonical form the schema is compared by, as [`canonical_form`]
+    /// writes it.
+    pub fn canonical_form(&self) -> &str {
+        &self.canonical
+    }
+
+    pub fn into_schema(self) -> Schema {
+        self.schema
+    }
+}
+
+impl From<Schema> for CanonicalSchema {
+    fn from(schema: Schema) -> Self {
+        CanonicalSchema::new(schema)
+    }
+}
+
+impl PartialEq for CanonicalSchema {
+    fn eq(&self, other: &Self) -> bool {
+        self.canonical == other.canonical
+    }
+}
+
+impl Eq for CanonicalSchema {}
+
+impl Hash for CanonicalSchema {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.canonical.hash(state);
+    }
+}
+
+fn write_schema(out: &mut String, schema: &Schema, enclosing_namespace: &Namespace) {
+    match schema {
+        Schema::Null => out.push_str("\"null\""),
+        Schema::Boolean => out.push_str("\"boolean\""),
+        Schema::Int | Schema::Date | Schema::TimeMillis => out.push_str("\"int\""),
+        Schema::Long
+        | Schema::TimeMicros
+        | Schema::TimestampMillis
+        | Schema::TimestampMicros
+        | Schema::LocalTimestampMillis
+        | Schema::LocalTimestampMicros => out.push_str("\"long\""),
+        Schema::Float => out.push_str("\"float\""),
+        Schema::Double => out.push_str("\"double\""),
+        Schema::Bytes => out.push_str("\"bytes\""),
+        Schema::String | Schema::Uuid => out.push_str("\"string\""),
+        Schema::Decimal { inner, .. } => write_schema(out, inner, enclosing_namespace),
+        Schema::Duration => out.push_str(r#"{"name":"duration","type":"fixed","size":12}"#),
+        Schema::Array(items) => {
+            out.push_str(r#"{"type":"array","items":"#);
+            write_schema(out, items, enclosing_namespace);
+            out.push('}');
+        }
+        Schema::Map(values) => {
+            out.push_str(r#"{"type":"map","values":"#);
+            write_schema(out, values, enclosing_namespace);
+            out.push('}');
+        }
+        Schema::Union(union) => {
+            out.push('[');
+            for (i, variant) in union.variants().iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_schema(out, variant, enclosing_namespace);
+            }
+            out.push(']');
+        }
+        Schema::Record { name, fields, .. } => {
+            let fullname = write_name(out, name, "record", enclosing_namespace);
+            out.push_str(r#","fields":["#);
+            for (i, field) in fields.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(r#"{"name":"#);
+                write_string(out, &field.name);
+                out.push_str(r#","type":"#);
+                write_schema(out, &field.schema, &fullname.namespace);
+                out.push('}');
+            }
+            out.push_str("]}");
+        }
+        Schema::Enum { name, symbols, .. } => {
+            write_name(out, name, "enum", enclosing_namespace);
+            out.push_str(r#","symbols":["#);
+            for (i, symbol) in symbols.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_string(out, symbol);
+            }
+            out.push_str("]}");
+        }
+        Schema::Fixed { name, size, .. } => {
+            write_name(out, name, "fixed", enclosing_namespace);
+            let _ = write!(out, r#","size":{size}}}"#);
+        }
+        Schema::Ref { name } => {
+            let fullname = name.fully_qualified_name(enclosing_namespace);
+            write_string(out, &fullname.fullname(None));
+        }
+    }
+}
+
+// Opens the object of a named type, up to its type. Types defined inside it
+// take the namespace of the returned fullname.
+fn write_name(out: &mut String, name: &Name, kind: &str, enclosing_namespace: &Namespace) -> Name {
+    let fullname = name.fully_qualified_name(enclosing_namespace);
+    out.push_str(r#"{"name":"#);
+    write_string(out, &fullname.fullname(None));
+    let _ = write!(out, r#","type":"{kind}""#);
+    fullname
+}
+
+fn write_string(out: &mut String, value: &str) {
+    // Only quotes, backslashes and control characters are escaped, the rest
+    // is written as is, like the specification asks
+    out.push_str(&serde_json::to_string(value).expect("strings are always serializable"));
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::avsc::from_avsc_str;
+    use crate::expand::{expand_refs, registry};
+    use crate::parser::parse;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(r#"{"type": "int", "logicalType": "date"}"#, r#""int""#)]
+    #[case(
+        r#"{"type": "enum", "name": "Suit", "namespace": "cards", "doc": "A suit", "symbols": ["SPADES", "HEARTS"]}"#,
+        r#"{"name":"cards.Suit","type":"enum","symbols":["SPADES","HEARTS"]}"#
+    )]
+    #[case(
+        r#"{"type": "record", "name": "Hand", "namespace": "cards", "fields": [
+            {"name": "cards", "type": {"type": "array", "items": {
+                "type": "record", "name": "Card", "fields": [{"name": "rank", "type": "int", "default": 1}]
+            }}},
+            {"name": "best", "type": ["null", "Card"], "default": null}
+        ]}"#,
+        r#"{"name":"cards.Hand","type":"record","fields":[{"name":"cards","type":{"type":"array","items":{"name":"cards.Card","type":"record","fields":[{"name":"rank","type":"int"}]}}},{"name":"best","type":["null","cards.Card"]}]}"#
+    )]
+    fn test_canonical_form(#[case] input: &str, #[case] expected: &str) {
+        let schema = from_avsc_str(input).unwrap();
+        assert_eq!(canonical_form(&schema), expected);
+    }
+
+    #[test]
+    fn test_canonical_form_unknown_ref() {
+        let schema = from_avsc_str(
+            r#"{"type": "record", "name": "r", "namespace": "a", "fields": [{"name": "f", "type": "Missing"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            canonical_form(&schema),
+            r#"{"name":"a.r","type":"record","fields":[{"name":"f","type":"a.Missing"}]}"#
+        );
+    }
+
+    #[test]
+    fn test_canonical_schema_deduplicates() {
+        let schemas = [
+            r#"{"type": "record", "name": "Card", "namespace": "cards", "fields": [{"name": "rank", "type": "int"}]}"#,
+            r#"{"type": "record", "name": "Card", "namespace": "cards", "doc": "A card", "aliases": ["Old"],
+                "fields": [{"name": "rank", "type": "int", "doc": "From 1", "default": 1}]}"#,
+            r#"{"type": "record", "name": "cards.Card", "fields": [{"name": "rank", "type": "int", "aliases": ["value"]}]}"#,
+            r#"{"type": "record", "name": "Card", "namespace": "cards", "fields": [{"name": "rank", "type": "long"}]}"#,
+            r#"{"type": "int", "logicalType": "date"}"#,
+            r#""int""#,
+        ];
+        let set: HashSet<CanonicalSchema> = schemas
+            .iter()
+            .map(|input| CanonicalSchema::new(from_avsc_str(input).unwrap()))
+            .collect();
+        assert_eq!(set.len(), 3);
+        let card = CanonicalSchema::new(from_avsc_str(schemas[1]).unwrap());
+        assert!(set.contains(&card));
+        assert!(set.contains(&CanonicalSchema::from(Schema::Int)));
+    }
+
+    fn has_ref(schema: &Schema) -> bool {
+        match schema {
+            Schema::Ref { .. } => true,
+            Schema::Array(inner) | Schema::Map(inner) => has_ref(inner),
+            Schema::Union(union) => union.variants().iter().any(has_ref),
+            Schema::Record { fields, .. } => fields.iter().any(|field| has_ref(&field.schema)),
+            _ => false,
+        }
+    }
+
+    #[rstest]
+    #[case(include_str!("../../../tests/samples/Library.avdl"))]
+    #[case(include_str!("../../../tests/samples/NativeFields.avdl"))]
+    #[case(include_str!("../../../tests/samples/Order.avdl"))]
+    #[case(include_str!("../../../tests/samples/Shop.avdl"))]
+    fn test_canonical_form_matches_apache_avro(#[case] input: &str) {
+        let (_tail, schemas) = parse(input).unwrap();
+        let registry = registry(&schemas);
+        let mut compared = 0;
+        for schema in &schemas {
+            let schema = expand_refs(schema, &registry);
+            if has_ref(&schema) {
+                continue;
+            }
+            assert_eq!(canonical_form(&schema), schema.canonical_form());
+            compared += 1;
+        }
+        assert!(compared > 0);
+    }
+}
@@ -0,0 +1,602 @@
+//! Generating Rust types from the named types of a protocol.
+//!
+//! Records become structs, enums become Rust enums and fixed types become
+//! newtypes over a byte array, all deriving serde's traits with the field and
+//! symbol names of the schema, so values can be written with `apache_avro`.
+
+use std::collections::HashSet;
+
+use apache_avro::schema::{Name, Names, RecordField, Schema, UnionSchema};
+use serde_json::Value;
+
+use crate::protocol::Protocol;
+use crate::visit::{SchemaVisitor, Walk};
+
+const INDENT: &str = "    ";
+
+// Keywords which can't be written as raw identifiers
+const RESERVED: [&str; 4] = ["crate", "self", "Self", "super"];
+
+const KEYWORDS: [&str; 49] = [
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in",
+    "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "Self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where",
+];
+
+// Largest array serde implements its traits for
+const MAX_ARRAY: usize = 32;
+
+/// Types used for the logical types in the generated code. The logical
+/// types not mapped are written as the type they annotate.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RustOptions {
+    /// Timestamps as `chrono::DateTime<chrono::Utc>`, written with the serde
+    /// helpers of `chrono`, instead of `i64`.
+    pub chrono: bool,
+    /// Uuids as `uuid::Uuid` instead of `String`.
+    pub uuid: bool,
+}
+
+/// Render the named types of `protocol`, including the ones declared inside
+/// others, as a Rust module.
+///
+/// Unions of `null` and another type become an `Option`, other unions an enum
+/// named after the record and field using them. Fields with a default get it
+/// through a function, and records whose fields all have one implement
+/// `Default`.
+pub fn to_rust(protocol: &Protocol, options: &RustOptions) -> String {
+    let mut collector = TypeCollector::default();
+    for schema in &protocol.types {
+        schema.walk(&mut collector);
+    }
+    let mut writer = RustWriter {
+        options,
+        names: &collector.names,
+        uses_map: false,
+        records: Vec::new(),
+        definitions: Vec::new(),
+    };
+    for schema in &collector.types {
+        writer.define(schema);
+    }
+
+    let mut rust = format!(
+        "// Generated from the protocol {}, do not edit.\n\n",
+        protocol.fullname()
+    );
+    if writer.uses_map {
+        rust.push_str("use std::collections::HashMap;\n\n");
+    }
+    rust.push_str("use serde::{Deserialize, Serialize};\n");
+    for definition in &writer.definitions {
+        rust.push('\n');
+        rust.push_str(definition);
+    }
+    rust
+}
+
+// The named types, in the order they are declared, once each
+#[derive(Default)]
+struct TypeCollector {
+    types: Vec<Schema>,
+    names: Names,
+    seen: HashSet<Name>,
+}
+
+impl SchemaVisitor for TypeCollector {
+    fn enter(&mut self, schema: &Schema) {
+        if let Schema::Record { name, .. }
+        | Schema::Enum { name, .. }
+        | Schema::Fixed { name, .. } = schema
+        {
+            if self.seen.insert(name.clone()) {
+                self.types.push(schema.clone());
+                self.names.insert(name.clone(), schema.clone());
+            }
+        }
+    }
+}
+
+struct RustWriter<'a> {
+    options: &'a RustOptions,
+    names: &'a Names,
+    uses_map: bool,
+    // Records being written, which fields can only contain boxed
+    records: Vec<String>,
+    definitions: Vec<String>,
+}
+
+impl RustWriter<'_> {
+    fn define(&mut self, schema: &Schema) {
+        let definition = match schema {
+            Schema::Record {
+                name, doc, fields, ..
+            } => self.record(name, doc, fields),
+            Schema::Enum {
+                name, doc, symbols, ..
+            } => enumeration(name, doc, symbols),
+            Schema::Fixed {
+                name, doc, size, ..
+            } => fixed(name, doc, *size),
+            _ => return,
+        };
+        self.definitions.push(definition);
+    }
+
+    fn record(&mut self, name: &Name, doc: &Option<String>, fields: &[RecordField]) -> String {
+        let type_name = &name.name;
+        self.records.push(type_name.clone());
+        let mut members = String::new();
+        let mut defaults = Vec::new();
+        for field in fields {
+            let hint = format!("{type_name}{}", pascal_case(&field.name));
+            let field_type = self.type_of(&field.schema, &hint, false);
+            let ident = field_ident(&field.name);
+            members.push_str(&doc_comment(&field.doc, INDENT));
+            if ident.trim_start_matches("r#") != field.name {
+                members.push_str(&format!("{INDENT}#[serde(rename = \"{}\")]\n", field.name));
+            }
+            if let Some(with) = self.serde_with(&field.schema) {
+                members.push_str(&format!("{INDENT}#[serde(with = \"{with}\")]\n"));
+            }
+            let default = field
+                .default
+                .as_ref()
+                .and_then(|value| self.default_value(&field.schema, value, &hint));
+            if let Some(default) = default {
+                let function = format!("default_{}", ident.trim_start_matches("r#"));
+                members.push_str(&format!(
+                    "{INDENT}#[serde(default = \"{type_name}::{function}\")]\n"
+                ));
+                defaults.push((ident.clone(), function, field_type.clone(), default));
+            }
+            members.push_str(&format!("{INDENT}pub {ident}: {field_type},\n"));
+        }
+        self.records.pop();
+
+        let mut rust = doc_comment(doc, "");
+        rust.push_str("#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]\n");
+        rust.push_str(&format!("pub struct {type_name} {{\n{members}}}\n"));
+        if defaults.is_empty() {
+            return rust;
+        }
+        rust.push_str(&format!("\nimpl {type_name} {{\n"));
+        let functions: Vec<String> = defaults
+            .iter()
+            .map(|(_, function, field_type, default)| {
+                format!(
+                    "{INDENT}fn {function}() -> {field_type} {{\n{INDENT}{INDENT}{default}\n{INDENT}}}\n"
+                )
+            })
+            .collect();
+        rust.push_str(&functions.join("\n"));
+        rust.push_str("}\n");
+        if defaults.len() == fields.len() {
+            rust.push_str(&format!(
+                "\nimpl Default for {type_name} {{\n{INDENT}fn default() -> {type_name} {{\n{INDENT}{INDENT}{type_name} {{\n"
+            ));
+            for (ident, function, _, _) in &defaults {
+                rust.push_str(&format!(
+                    "{INDENT}{INDENT}{INDENT}{ident}: {type_name}::{function}(),\n"
+                ));
+            }
+            rust.push_str(&format!("{INDENT}{INDENT}}}\n{INDENT}}}\n}}\n"));
+        }
+        rust
+    }
+
+    // Rust type of `schema`, `hint` naming the enum written for a union.
+    // `indirect` tells whether it is already behind a pointer, like the items
+    // of a `Vec`.
+    fn type_of(&mut self, schema: &Schema, hint: &str, indirect: bool) -> String {
+        match schema {
+            Schema::Null => "()".to_string(),
+            Schema::Boolean => "bool".to_string(),
+            Schema::Int | Schema::Date | Schema::TimeMillis => "i32".to_string(),
+            Schema::Long | Schema::TimeMicros => "i64".to_string(),
+            Schema::LocalTimestampMillis | Schema::LocalTimestampMicros => "i64".to_string(),
+            Schema::TimestampMillis | Schema::TimestampMicros if self.options.chrono => {
+                "chrono::DateTime<chrono::Utc>".to_string()
+            }
+            Schema::TimestampMillis | Schema::TimestampMicros => "i64".to_string(),
+            Schema::Float => "f32".to_string(),
+            Schema::Double => "f64".to_string(),
+            Schema::Bytes => "Vec<u8>".to_string(),
+            Schema::String => "String".to_string(),
+            Schema::Uuid if self.options.uuid => "uuid::Uuid".to_string(),
+            Schema::Uuid => "String".to_string(),
+            Schema::Duration => "[u8; 12]".to_string(),
+            Schema::Decimal { inner, .. } => match inner.as_ref() {
+                Schema::Fixed { size, .. } => bytes(*size),
+                _ => "Vec<u8>".to_string(),
+            },
+            Schema::Array(items) => format!("Vec<{}>", self.type_of(items, hint, true)),
+            Schema::Map(values) => {
+                self.uses_map = true;
+                format!("HashMap<String, {}>", self.type_of(values, hint, true))
+            }
+            Schema::Union(union) => self.union(union, hint, indirect),
+            Schema::Record { name, .. }
+            | Schema::Enum { name, .. }
+            | Schema::Fixed { name, .. }
+            | Schema::Ref { name } => {
+                if !indirect && self.records.contains(&name.name) {
+                    format!("Box<{}>", name.name)
+                } else {
+                    name.name.clone()
+                }
+            }
+            _ => "()".to_string(),
+        }
+    }
+
+    fn union(&mut self, union: &UnionSchema, hint: &str, indirect: bool) -> String {
+        let variants = union.variants();
+        let others: Vec<&Schema> = variants
+            .iter()
+            .filter(|variant| **variant != Schema::Null)
+            .collect();
+        let optional = others.len() < variants.len();
+        if let [single] = others.as_slice() {
+            return format!("Option<{}>", self.type_of(single, hint, indirect));
+        }
+        let mut rust = String::from("#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]\n");
+        rust.push_str(&format!("pub enum {hint} {{\n"));
+        for variant in &others {
+            let variant_type = self.type_of(variant, hint, indirect);
+            rust.push_str(&format!(
+                "{INDENT}{}({variant_type}),\n",
+                variant_name(variant)
+            ));
+        }
+        rust.push_str("}\n");
+        self.definitions.push(rust);
+        if optional {
+            format!("Option<{hint}>")
+        } else {
+            hint.to_string()
+        }
+    }
+
+    // Module of the serde helpers used to write fields of `schema`
+    fn serde_with(&self, schema: &Schema) -> Option<&'static str> {
+        if !self.options.chrono {
+            return None;
+        }
+        let (schema, optional) = match schema {
+            Schema::Union(union) => match union.variants() {
+                [Schema::Null, schema] | [schema, Schema::Null] => (schema, true),
+                _ => return None,
+            },
+            schema => (schema, false),
+        };
+        match (schema, optional) {
+            (Schema::TimestampMillis, false) => Some("chrono::serde::ts_milliseconds"),
+            (Schema::TimestampMillis, true) => Some("chrono::serde::ts_milliseconds_option"),
+            (Schema::TimestampMicros, false) => Some("chrono::serde::ts_microseconds"),
+            (Schema::TimestampMicros, true) => Some("chrono::serde::ts_microseconds_option"),
+            _ => None,
+        }
+    }
+
+    // Rust expression of the default `value` of a field of type `schema`,
+    // None when it can't be written. `hint` is the one given to `type_of`.
+    fn default_value(&self, schema: &Schema, value: &Value, hint: &str) -> Option<String> {
+        let rust = match (schema, value) {
+            (Schema::Null, Value::Null) => "()".to_string(),
+            (Schema::Boolean, Value::Bool(b)) => b.to_string(),
+            (Schema::TimestampMillis, Value::Number(n)) if self.options.chrono => format!(
+                "chrono::DateTime::from_timestamp_millis({}).unwrap()",
+                n.as_i64()?
+            ),
+            (Schema::TimestampMicros, Value::Number(n)) if self.options.chrono => format!(
+                "chrono::DateTime::from_timestamp_micros({}).unwrap()",
+                n.as_i64()?
+            ),
+            (
+                Schema::Int
+                | Schema::Long
+                | Schema::Date
+                | Schema::TimeMillis
+                | Schema::TimeMicros
+                | Schema::TimestampMillis
+                | Schema::TimestampMicros
+                | Schema::LocalTimestampMillis
+                | Schema::LocalTimestampMicros,
+                Value::Number(n),
+            ) => n.as_i64()?.to_string(),
+            (Schema::Float | Schema::Double, Value::Number(n)) => format!("{:?}", n.as_f64()?),
+            (Schema::String, Value::String(s)) => format!("{s:?}.to_string()"),
+            (Schema::Uuid, Value::String(s)) if self.options.uuid => format!(
+                "uuid::Uuid::from_u128({:#034x})",
+                uuid::Uuid::parse_str(s).ok()?.as_u128()
+            ),
+            (Schema::Uuid, Value::String(s)) => format!("{s:?}.to_string()"),
+            (Schema::Bytes, value) => format!("vec!{}", byte_list(value)?),
+            (Schema::Decimal { inner, .. }, value) => match inner.as_ref() {
+                Schema::Fixed { .. } => byte_list(value)?,
+                _ => format!("vec!{}", byte_list(value)?),
+            },
+            (Schema::Duration, value) => byte_list(value)?,
+            (Schema::Fixed { name, size, .. }, value) if *size <= MAX_ARRAY => {
+                format!("{}({})", name.name, byte_list(value)?)
+            }
+            (Schema::Fixed { name, .. }, value) => {
+                format!("{}(vec!{})", name.name, byte_list(value)?)
+            }
+            (Schema::Enum { name, .. }, Value::String(symbol)) => {
+                format!("{}::{}", name.name, pascal_case(symbol))
+            }
+            (Schema::Array(items), Value::Array(values)) => {
+                let values = values
+                    .iter()
+                    .map(|value| self.default_value(items, value, hint))
+                    .collect::<Option<Vec<_>>>()?;
+                format!("vec![{}]", values.join(", "))
+            }
+            (Schema::Map(_), Value::Object(entries)) if entries.is_empty() => {
+                "HashMap::new()".to_string()
+            }
+            (Schema::Map(values), Value::Object(entries)) => {
+                let entries = entries
+                    .iter()
+                    .map(|(key, value)| {
+                        let value = self.default_value(values, value, hint)?;
+                        Some(format!("({key:?}.to_string(), {value})"))
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                format!("[{}].into_iter().collect()", entries.join(", "))
+            }
+            // The default of a union is for its first type
+            (Schema::Union(union), value) => {
+                let variants = union.variants();
+                let first = variants.first()?;
+                if *first == Schema::Null {
+                    return Some("None".to_string());
+                }
+                let mut rust = self.default_value(first, value, hint)?;
+                if variants.len() > 2 || !variants.contains(&Schema::Null) {
+                    rust = format!("{hint}::{}({rust})", variant_name(first));
+                }
+                if variants.contains(&Schema::Null) {
+                    rust = format!("Some({rust})");
+                }
+                rust
+            }
+            (Schema::Record { name, fields, .. }, Value::Object(entries)) => {
+                let members = fields
+                    .iter()
+                    .map(|field| {
+                        let value = entries.get(&field.name).or(field.default.as_ref())?;
+                        let hint = format!("{}{}", name.name, pascal_case(&field.name));
+                        let value = self.default_value(&field.schema, value, &hint)?;
+                        Some(format!("{}: {value}", field_ident(&field.name)))
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                format!("{} {{ {} }}", name.name, members.join(", "))
+            }
+            (Schema::Ref { name }, value) => {
+                return self.default_value(self.names.get(name)?, value, hint)
+            }
+            _ => return None,
+        };
+        Some(rust)
+    }
+}
+
+fn enumeration(name: &Name, doc: &Option<String>, symbols: &[String]) -> String {
+    let mut rust = doc_comment(doc, "");
+    rust.push_str("#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]\n");
+    rust.push_str(&format!("pub enum {} {{\n", name.name));
+    for symbol in symbols {
+        let variant = pascal_case(symbol);
+        if variant != *symbol {
+            rust.push_str(&format!("{INDENT}#[serde(rename = \"{symbol}\")]\n"));
+        }
+        rust.push_str(&format!("{INDENT}{variant},\n"));
+    }
+    rust.push_str("}\n");
+    rust
+}
+
+fn fixed(name: &Name, doc: &Option<String>, size: usize) -> String {
+    let mut rust = doc_comment(doc, "");
+    if size <= MAX_ARRAY {
+        rust.push_str(
+            "#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]\n",
+        );
+    } else {
+        rust.push_str("#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]\n");
+    }
+    rust.push_str(&format!("pub struct {}(pub {});\n", name.name, bytes(size)));
+    rust
+}
+
+// Bytes of a fixed size, as an array when serde supports it
+fn bytes(size: usize) -> String {
+    if size <= MAX_ARRAY {
+        format!("[u8; {size}]")
+    } else {
+        "Vec<u8>".to_string()
+    }
+}
+
+// Bytes are written in JSON as a string of code points up to 255
+fn byte_list(value: &Value) -> Option<String> {
+    let bytes = match value {
+        Value::String(s) => s
+            .chars()
+            .map(|c| u8::try_from(u32::from(c)).ok())
+            .collect::<Option<Vec<u8>>>()?,
+        Value::Array(values) => values
+            .iter()
+            .map(|value| value.as_u64().and_then(|n| u8::try_from(n).ok()))
+            .collect::<Option<Vec<u8>>>()?,
+        _ => return None,
+    };
+    let bytes: Vec<String> = bytes.iter().map(u8::to_string).collect();
+    Some(format!("[{}]", bytes.join(", ")))
+}
+
+fn variant_name(schema: &Schema) -> String {
+    match schema {
+        Schema::Record { name, .. }
+        | Schema::Enum { name, .. }
+        | Schema::Fixed { name, .. }
+        | Schema::Ref { name } => name.name.clone(),
+        schema => format!("{:?}", apache_avro::schema::SchemaKind::from(schema)),
+    }
+}
+
+// Identifier of a field, in snake case
+fn field_ident(name: &str) -> String {
+    let mut ident = String::new();
+    let mut previous_lower = false;
+    for c in name.chars() {
+        if c.is_uppercase() && previous_lower {
+            ident.push('_');
+        }
+        previous_lower = c.is_lowercase() || c.is_ascii_digit();
+        ident.extend(c.to_lowercase());
+    }
+    if RESERVED.contains(&ident.as_str()) {
+        format!("{ident}_")
+    } else if KEYWORDS.contains(&ident.as_str()) {
+        format!("r#{ident}")
+    } else {
+        ident
+    }
+}
+
+// Name of a type or variant, in pascal case. Symbols all in upper case, like
+// `IN_STOCK`, are lowered but for the first letter of each word.
+fn pascal_case(name: &str) -> String {
+    let upper = !name.chars().any(char::is_lowercase);
+    name.split('_')
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            let first = chars.next().map(|c| c.to_ascii_uppercase());
+            let rest: String = if upper {
+                chars.as_str().to_lowercase()
+            } else {
+                chars.collect()
+            };
+            first.into_iter().chain(rest.chars()).collect::<String>()
+        })
+        .collect()
+}
+
+fn doc_comment(doc: &Option<String>, indent: &str) -> String {
+    match doc {
+        Some(doc) => doc
+            .lines()
+            .map(|line| match line.trim() {
+                "" => format!("{indent}///\n"),
+                line => format!("{indent}/// {line}\n"),
+            })
+            .collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::options::ParserOptions;
+    use crate::parser::parse_protocol_with_options;
+
+    fn generate(input: &str, options: &RustOptions) -> String {
+        let (protocol, _warnings) =
+            parse_protocol_with_options(input, &ParserOptions::default()).unwrap();
+        to_rust(&protocol, options)
+    }
+
+    // The golden file is also compiled, with the tests of the crate
+    #[allow(dead_code)]
+    mod shop {
+        include!("../../../tests/golden/Shop.rs");
+    }
+
+    #[test]
+    fn test_to_rust_golden() {
+        let input = include_str!("../../../tests/samples/Shop.avdl");
+        assert_eq!(
+            generate(input, &RustOptions::default()),
+            include_str!("../../../tests/golden/Shop.rs")
+        );
+    }
+
+    #[test]
+    fn test_generated_defaults() {
+        let item: shop::Item = serde_json::from_str(r#"{"id": "42", "kind": "FOOD"}"#).unwrap();
+        assert_eq!(item.kind, shop::Kind::Food);
+        assert_eq!(item.tags, Vec::<String>::new());
+        assert_eq!(item.hash, None);
+        assert_eq!(item.price, 1.5);
+    }
+
+    #[test]
+    fn test_to_rust_shapes() {
+        let input = r#"protocol P {
+    enum Status { IN_STOCK, sold_out }
+    record Node {
+        string name = "root";
+        array<Node> children = [];
+        union { null, Node } parent = null;
+        union { string, int, null } label = "none";
+        timestamp_ms created = 0;
+        uuid id = "2d5c3d4e-0000-4000-8000-000000000001";
+        union { null, Status } status = null;
+        map<int> counts = {"a": 1};
+        int type = 1;
+    }
+}"#;
+        let rust = generate(input, &RustOptions::default());
+        assert!(
+            rust.contains("#[serde(rename = \"IN_STOCK\")]\n    InStock,"),
+            "{rust}"
+        );
+        assert!(
+            rust.contains("#[serde(rename = \"sold_out\")]\n    SoldOut,"),
+            "{rust}"
+        );
+        assert!(rust.contains("pub children: Vec<Node>,"), "{rust}");
+        assert!(rust.contains("pub parent: Option<Box<Node>>,"), "{rust}");
+        assert!(rust.contains("pub label: Option<NodeLabel>,"), "{rust}");
+        assert!(
+            rust.contains("Some(NodeLabel::String(\"none\".to_string()))"),
+            "{rust}"
+        );
+        assert!(
+            rust.contains("pub enum NodeLabel {\n    String(String),\n    Int(i32),\n}"),
+            "{rust}"
+        );
+        assert!(rust.contains("pub created: i64,"), "{rust}");
+        assert!(rust.contains("pub id: String,"), "{rust}");
+        assert!(rust.contains("pub r#type: i32,"), "{rust}");
+        assert!(
+            rust.contains("[(\"a\".to_string(), 1)].into_iter().collect()"),
+            "{rust}"
+        );
+        assert!(rust.contains("impl Default for Node {"), "{rust}");
+
+        let rust = generate(
+            input,
+            &RustOptions {
+                chrono: true,
+                uuid: true,
+            },
+        );
+        assert!(
+            rust.contains("    #[serde(with = \"chrono::serde::ts_milliseconds\")]\n    #[serde(default = \"Node::default_created\")]\n    pub created: chrono::DateTime<chrono::Utc>,"),
+            "{rust}"
+        );
+        assert!(
+            rust.contains("uuid::Uuid::from_u128(0x2d5c3d4e000040008000000000000001)"),
+            "{rust}"
+        );
+    }
+}
@@ -0,0 +1,326 @@
+//! Checking that data written with one schema can be read with another, by
+//! the schema resolution rules of the specification.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use apache_avro::schema::{Alias, Name, Schema};
+
+use crate::diff::matching_field;
+use crate::validate::type_name;
+
+/// A reason data written with the writer schema can't be read with the
+/// reader schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Incompatibility {
+    /// Full name of the type, followed by the fields leading to the problem,
+    /// e.g. `org.example.User.email`.
+    pub path: String,
+    pub message: String,
+}
+
+impl Incompatibility {
+    fn new(path: &str, message: impl Into<String>) -> Incompatibility {
+        Incompatibility {
+            path: path.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Incompatibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Everything preventing `reader` from reading data written with `writer`,
+/// empty when they are compatible.
+///
+/// Named types are matched by their unqualified name or the aliases of the
+/// reader, and references are expected to point to types checked elsewhere:
+/// use [`crate::expand::expand_refs`] first to check what they point to.
+pub fn check_reader_writer(reader: &Schema, writer: &Schema) -> Vec<Incompatibility> {
+    let path = match reader {
+        Schema::Record { name, .. } | Schema::Enum { name, .. } | Schema::Fixed { name, .. } => {
+            name.fullname(None)
+        }
+        _ => type_name(reader),
+    };
+    let mut found = Vec::new();
+    check(reader, writer, &path, &mut HashSet::new(), &mut found);
+    found
+}
+
+fn check(
+    reader: &Schema,
+    writer: &Schema,
+    path: &str,
+    checked: &mut HashSet<(Name, Name)>,
+    found: &mut Vec<Incompatibility>,
+) {
+    let (reader, writer) = (underlying(reader), underlying(writer));
+    match (reader, writer) {
+        (_, Schema::Union(union)) => {
+            for variant in union.variants() {
+                if !readable(reader, variant, path, checked) {
+                    found.push(Incompatibility::new(
+                        path,
+                        format!("writer type {} can't be read", type_name(variant)),
+                    ));
+                }
+            }
+        }
+        (Schema::Union(union), _) => {
+            if !union
+                .variants()
+                .iter()
+                .any(|variant| readable(variant, writer, path, checked))
+            {
+                found.push(Incompatibility::new(
+                    path,
+                    format!("no type of the union reads {}", type_name(writer)),
+                ));
+            }
+        }
+        (Schema::Array(reader_items), Schema::Array(writer_items)) => {
+            check(reader_items, writer_items, path, checked, found)
+        }
+        (Schema::Map(reader_values), Schema::Map(writer_values)) => {
+            check(reader_values, writer_values, path, checked, found)
+        }
+        (
+            Schema::Record {
+                name: reader_name,
+                aliases,
+                fields: reader_fields,
+                ..
+            },
+            Schema::Record {
+                name: writer_name,
+                fields: writer_fields,
+                ..
+            },
+        ) => {
+            if !same_name(reader_name, aliases, writer_name, path, found)
+                || !checked.insert((reader_name.clone(), writer_name.clone()))
+            {
+                return;
+            }
+            for field in reader_fields {
+                let field_path = format!("{path}.{}", field.name);
+                match matching_field(field, writer_fields) {
+                    Some(written) => {
+                        check(&field.schema, &written.schema, &field_path, checked, found)
+                    }
+                    None if field.default.is_none() => found.push(Incompatibility::new(
+                        &field_path,
+                        "field is missing from the writer and has no default",
+                    )),
+                    None => (),
+                }
+            }
+        }
+        (
+            Schema::Enum {
+                name: reader_name,
+                aliases,
+                symbols: reader_symbols,
+                ..
+            },
+            Schema::Enum {
+                name: writer_name,
+                symbols: writer_symbols,
+                ..
+            },
+        ) => {
+            if !same_name(reader_name, aliases, writer_name, path, found) {
+                return;
+            }
+            for symbol in writer_symbols
+                .iter()
+                .filter(|symbol| !reader_symbols.contains(symbol))
+            {
+                found.push(Incompatibility::new(
+                    path,
+                    format!("symbol {symbol} is missing from the reader"),
+                ));
+            }
+        }
+        (
+            Schema::Fixed {
+                name: reader_name,
+                aliases,
+                size: reader_size,
+                ..
+            },
+            Schema::Fixed {
+                name: writer_name,
+                size: writer_size,
+                ..
+            },
+        ) => {
+            if same_name(reader_name, aliases, writer_name, path, found)
+                && reader_size != writer_size
+            {
+                found.push(Incompatibility::new(
+                    path,
+                    format!("size {writer_size} is read as {reader_size}"),
+                ));
+            }
+        }
+        (Schema::Ref { name: reader_name }, Schema::Ref { name: writer_name }) => {
+            same_name(reader_name, &None, writer_name, path, found);
+        }
+        _ if promotes(writer, reader) => (),
+        _ => found.push(Incompatibility::new(
+            path,
+            format!(
+                "{} can't be read as {}",
+                type_name(writer),
+                type_name(reader)
+            ),
+        )),
+    }
+}
+
+// Whether `reader` reads `writer` without any problem
+fn readable(reader: &Schema, writer: &Schema, path: &str, checked: &HashSet<(Name, Name)>) -> bool {
+    let mut found = Vec::new();
+    check(reader, writer, path, &mut checked.clone(), &mut found);
+    found.is_empty()
+}
+
+// Logical types are read and written as the type they annotate
+fn underlying(schema: &Schema) -> &Schema {
+    match schema {
+        Schema::Date | Schema::TimeMillis => &Schema::Int,
+        Schema::TimeMicros
+        | Schema::TimestampMillis
+        | Schema::TimestampMicros
+        | Schema::LocalTimestampMillis
+        | Schema::LocalTimestampMicros => &Schema::Long,
+        Schema::Uuid => &Schema::String,
+        Schema::Decimal { inner, .. } => underlying(inner),
+        _ => schema,
+    }
+}
+
+fn promotes(writer: &Schema, reader: &Schema) -> bool {
+    matches!(
+        (writer, reader),
+        (Schema::Null, Schema::Null)
+            | (Schema::Boolean, Schema::Boolean)
+            | (
+                Schema::Int,
+                Schema::Int | Schema::Long | Schema::Float | Schema::Double
+            )
+            | (Schema::Long, Schema::Long | Schema::Float | Schema::Double)
+            | (Schema::Float, Schema::Float | Schema::Double)
+            | (Schema::Double, Schema::Double)
+            | (
+                Schema::Bytes | Schema::String,
+                Schema::Bytes | Schema::String
+            )
+            | (Schema::Duration, Schema::Duration)
+    )
+}
+
+fn same_name(
+    reader: &Name,
+    aliases: &Option<Vec<Alias>>,
+    writer: &Name,
+    path: &str,
+    found: &mut Vec<Incompatibility>,
+) -> bool {
+    let aliased = aliases
+        .iter()
+        .flatten()
+        .any(|alias| alias.name() == writer.name);
+    if reader.name == writer.name || aliased {
+        return true;
+    }
+    found.push(Incompatibility::new(
+        path,
+        format!("{} can't be read as {}", writer.name, reader.name),
+    ));
+    false
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse;
+
+    fn first_type(input: &str) -> Schema {
+        let (_tail, mut schemas) = parse(input).unwrap();
+        schemas.remove(0)
+    }
+
+    fn messages(reader: &str, writer: &str) -> Vec<String> {
+        check_reader_writer(&first_type(reader), &first_type(writer))
+            .iter()
+            .map(Incompatibility::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_field_added_without_default() {
+        let old = "protocol P { record User { string name; } }";
+        let new = "protocol P { record User { string name; string email; } }";
+        assert_eq!(
+            messages(new, old),
+            vec!["User.email: field is missing from the writer and has no default"]
+        );
+        let new = r#"protocol P { record User { string name; string email = ""; } }"#;
+        assert!(messages(new, old).is_empty());
+    }
+
+    #[test]
+    fn test_field_removed() {
+        let old = "protocol P { record User { string name; int age; } }";
+        let new = "protocol P { record User { string name; } }";
+        // New readers skip the field, old readers can't do without it
+        assert!(messages(new, old).is_empty());
+        assert_eq!(
+            messages(old, new),
+            vec!["User.age: field is missing from the writer and has no default"]
+        );
+    }
+
+    #[test]
+    fn test_narrowed_union() {
+        let old = "protocol P { record User { union { null, string, int } id; } }";
+        let new = "protocol P { record User { union { null, string } id; } }";
+        assert_eq!(
+            messages(new, old),
+            vec!["User.id: writer type int can't be read"]
+        );
+        assert!(messages(old, new).is_empty());
+    }
+
+    #[test]
+    fn test_type_promotions() {
+        let old = "protocol P { record Reading { int value; float ratio; string raw; } }";
+        let new = "protocol P { record Reading { long value; double ratio; bytes raw; } }";
+        assert!(messages(new, old).is_empty());
+        assert_eq!(
+            messages(old, new),
+            vec![
+                "Reading.value: long can't be read as int",
+                "Reading.ratio: double can't be read as float",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_enum_symbol_removed() {
+        let old = "protocol P { enum Status { ACTIVE, CLOSED } }";
+        let new = "protocol P { enum Status { ACTIVE } }";
+        assert_eq!(
+            messages(new, old),
+            vec!["Status: symbol CLOSED is missing from the reader"]
+        );
+        assert!(messages(old, new).is_empty());
+    }
+}
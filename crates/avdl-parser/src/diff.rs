@@ -0,0 +1,481 @@
+//! Comparing two versions of the named types of a protocol.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use apache_avro::schema::{Alias, Name, RecordField, Schema};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::validate::type_name;
+
+/// How much a change matters to the readers and writers of the types.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// Data written with one version may not be read with the other.
+    Breaking,
+    /// Worth a look when reviewing, but data stays readable.
+    Info,
+}
+
+/// What a change is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    TypeAdded,
+    TypeRemoved,
+    /// The new type has the old name as an alias.
+    TypeRenamed,
+    /// A record became an enum, for instance.
+    TypeKindChanged,
+    FieldAdded,
+    FieldRemoved,
+    /// The new field has the old name as an alias.
+    FieldRenamed,
+    FieldRetyped,
+    DefaultChanged,
+    SymbolsChanged,
+    SizeChanged,
+    /// Only the documentation changed, which readers and writers ignore.
+    DocChanged,
+    AttributesChanged,
+}
+
+/// A difference between two versions of a named type.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Change {
+    pub severity: Severity,
+    pub kind: ChangeKind,
+    /// Full name of the type, followed by the field when the change is in
+    /// one, e.g. `org.example.User.email`.
+    pub path: String,
+    pub message: String,
+}
+
+impl Change {
+    fn breaking(kind: ChangeKind, path: String, message: impl Into<String>) -> Change {
+        Change {
+            severity: Severity::Breaking,
+            kind,
+            path,
+            message: message.into(),
+        }
+    }
+
+    fn info(kind: ChangeKind, path: String, message: impl Into<String>) -> Change {
+        Change {
+            severity: Severity::Info,
+            kind,
+            path,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Breaking => "breaking",
+            Severity::Info => "info",
+        };
+        write!(f, "{severity}: {}: {}", self.path, self.message)
+    }
+}
+
+/// Changes from the named types `old` to `new`.
+///
+/// Types are matched by full name or by the aliases of the new type, and
+/// fields by name or by the aliases of the new field. Types are reported in
+/// the order of `old`, then the ones added in `new`.
+pub fn diff(old: &[Schema], new: &[Schema]) -> Vec<Change> {
+    let (old, new) = (named(old), named(new));
+    let mut changes = Vec::new();
+    for old_type in &old {
+        let name = old_type.name.fullname(None);
+        match new.iter().find(|new_type| new_type.replaces(old_type)) {
+            Some(new_type) => {
+                let new_name = new_type.name.fullname(None);
+                if new_name != name {
+                    changes.push(Change::info(
+                        ChangeKind::TypeRenamed,
+                        name.clone(),
+                        format!("renamed to {new_name}"),
+                    ));
+                }
+                diff_type(&name, old_type.schema, new_type.schema, &mut changes);
+            }
+            None => changes.push(Change::breaking(
+                ChangeKind::TypeRemoved,
+                name,
+                "type removed",
+            )),
+        }
+    }
+    for new_type in &new {
+        if !old.iter().any(|old_type| new_type.replaces(old_type)) {
+            changes.push(Change::info(
+                ChangeKind::TypeAdded,
+                new_type.name.fullname(None),
+                "type added",
+            ));
+        }
+    }
+    changes
+}
+
+/// The field of `fields` read into `field`, by name or by one of the aliases
+/// of `field`.
+pub fn matching_field<'a>(
+    field: &RecordField,
+    fields: &'a [RecordField],
+) -> Option<&'a RecordField> {
+    fields.iter().find(|other| reads(field, other))
+}
+
+// Whether `field` reads the data of `other`
+fn reads(field: &RecordField, other: &RecordField) -> bool {
+    field.name == other.name
+        || field
+            .aliases
+            .iter()
+            .flatten()
+            .any(|alias| *alias == other.name)
+}
+
+struct Named<'a> {
+    name: &'a Name,
+    aliases: Option<&'a Vec<Alias>>,
+    schema: &'a Schema,
+}
+
+impl Named<'_> {
+    // Whether this type stands for `old`, by full name or by one of its aliases
+    fn replaces(&self, old: &Named) -> bool {
+        let fullname = old.name.fullname(None);
+        self.name.fullname(None) == fullname
+            || self
+                .aliases
+                .into_iter()
+                .flatten()
+                .any(|alias| alias.fullname(self.name.namespace.clone()) == fullname)
+    }
+}
+
+fn named(schemas: &[Schema]) -> Vec<Named<'_>> {
+    schemas
+        .iter()
+        .filter_map(|schema| match schema {
+            Schema::Record { name, aliases, .. }
+            | Schema::Enum { name, aliases, .. }
+            | Schema::Fixed { name, aliases, .. } => Some(Named {
+                name,
+                aliases: aliases.as_ref(),
+                schema,
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+fn diff_type(name: &str, old: &Schema, new: &Schema, changes: &mut Vec<Change>) {
+    match (old, new) {
+        (
+            Schema::Record {
+                fields: old_fields,
+                doc: old_doc,
+                attributes: old_attributes,
+                ..
+            },
+            Schema::Record {
+                fields: new_fields,
+                doc: new_doc,
+                attributes: new_attributes,
+                ..
+            },
+        ) => {
+            diff_doc(name, old_doc, new_doc, changes);
+            diff_attributes(name, old_attributes, new_attributes, changes);
+            for field in old_fields {
+                let path = format!("{name}.{}", field.name);
+                match new_fields.iter().find(|other| reads(other, field)) {
+                    Some(other) => {
+                        if other.name != field.name {
+                            changes.push(Change::info(
+                                ChangeKind::FieldRenamed,
+                                path.clone(),
+                                format!("renamed to {}", other.name),
+                            ));
+                        }
+                        diff_field(path, field, other, changes)
+                    }
+                    None => changes.push(Change::breaking(
+                        ChangeKind::FieldRemoved,
+                        path,
+                        "field removed",
+                    )),
+                }
+            }
+            for field in new_fields {
+                if matching_field(field, old_fields).is_none() {
+                    let path = format!("{name}.{}", field.name);
+                    match field.default {
+                        Some(_) => {
+                            changes.push(Change::info(ChangeKind::FieldAdded, path, "field added"))
+                        }
+                        None => changes.push(Change::breaking(
+                            ChangeKind::FieldAdded,
+                            path,
+                            "field added without a default",
+                        )),
+                    }
+                }
+            }
+        }
+        (
+            Schema::Enum {
+                symbols: old_symbols,
+                doc: old_doc,
+                attributes: old_attributes,
+                ..
+            },
+            Schema::Enum {
+                symbols: new_symbols,
+                doc: new_doc,
+                attributes: new_attributes,
+                ..
+            },
+        ) => {
+            diff_doc(name, old_doc, new_doc, changes);
+            diff_attributes(name, old_attributes, new_attributes, changes);
+            for symbol in old_symbols.iter().filter(|s| !new_symbols.contains(s)) {
+                changes.push(Change::breaking(
+                    ChangeKind::SymbolsChanged,
+                    name.to_string(),
+                    format!("symbol {symbol} removed"),
+                ));
+            }
+            for symbol in new_symbols.iter().filter(|s| !old_symbols.contains(s)) {
+                changes.push(Change::info(
+                    ChangeKind::SymbolsChanged,
+                    name.to_string(),
+                    format!("symbol {symbol} added"),
+                ));
+            }
+        }
+        (
+            Schema::Fixed {
+                size: old_size,
+                doc: old_doc,
+                attributes: old_attributes,
+                ..
+            },
+            Schema::Fixed {
+                size: new_size,
+                doc: new_doc,
+                attributes: new_attributes,
+                ..
+            },
+        ) => {
+            diff_doc(name, old_doc, new_doc, changes);
+            diff_attributes(name, old_attributes, new_attributes, changes);
+            if old_size != new_size {
+                changes.push(Change::breaking(
+                    ChangeKind::SizeChanged,
+                    name.to_string(),
+                    format!("size changed from {old_size} to {new_size}"),
+                ));
+            }
+        }
+        _ => changes.push(Change::breaking(
+            ChangeKind::TypeKindChanged,
+            name.to_string(),
+            format!("changed from {} to {}", kind_name(old), kind_name(new)),
+        )),
+    }
+}
+
+fn kind_name(schema: &Schema) -> &'static str {
+    match schema {
+        Schema::Record { .. } => "record",
+        Schema::Enum { .. } => "enum",
+        _ => "fixed",
+    }
+}
+
+fn diff_field(path: String, old: &RecordField, new: &RecordField, changes: &mut Vec<Change>) {
+    let (old_type, new_type) = (type_name(&old.schema), type_name(&new.schema));
+    if old_type != new_type {
+        changes.push(Change::breaking(
+            ChangeKind::FieldRetyped,
+            path.clone(),
+            format!("type changed from {old_type} to {new_type}"),
+        ));
+    }
+    match (&old.default, &new.default) {
+        (Some(_), None) => changes.push(Change::breaking(
+            ChangeKind::DefaultChanged,
+            path.clone(),
+            "default removed",
+        )),
+        (None, Some(default)) => changes.push(Change::info(
+            ChangeKind::DefaultChanged,
+            path.clone(),
+            format!("default {default} added"),
+        )),
+        (Some(old_default), Some(new_default)) if old_default != new_default => {
+            changes.push(Change::info(
+                ChangeKind::DefaultChanged,
+                path.clone(),
+                format!("default changed from {old_default} to {new_default}"),
+            ))
+        }
+        _ => (),
+    }
+    diff_doc(&path, &old.doc, &new.doc, changes);
+    diff_attributes(
+        &path,
+        &old.custom_attributes,
+        &new.custom_attributes,
+        changes,
+    );
+}
+
+fn diff_doc(path: &str, old: &Option<String>, new: &Option<String>, changes: &mut Vec<Change>) {
+    if old != new {
+        changes.push(Change::info(
+            ChangeKind::DocChanged,
+            path.to_string(),
+            "doc changed",
+        ));
+    }
+}
+
+// Attributes are not part of the data, so their changes are only informational
+fn diff_attributes(
+    path: &str,
+    old: &BTreeMap<String, Value>,
+    new: &BTreeMap<String, Value>,
+    changes: &mut Vec<Change>,
+) {
+    for (key, value) in old {
+        let message = match new.get(key) {
+            Some(other) if other == value => continue,
+            Some(other) => format!("attribute {key} changed from {value} to {other}"),
+            None => format!("attribute {key} removed"),
+        };
+        changes.push(Change::info(
+            ChangeKind::AttributesChanged,
+            path.to_string(),
+            message,
+        ));
+    }
+    for (key, value) in new {
+        if !old.contains_key(key) {
+            changes.push(Change::info(
+                ChangeKind::AttributesChanged,
+                path.to_string(),
+                format!("attribute {key} added with {value}"),
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::avsc::from_avsc_str;
+    use crate::parser::parse;
+
+    fn user(pii: &str) -> Schema {
+        from_avsc_str(&format!(
+            r#"{{
+  "type": "record",
+  "name": "User",
+  "namespace": "org.example",
+  "fields": [
+    {{"name": "email", "type": "string", "x-pii": {pii}, "x-owner": "accounts"}}
+  ]
+}}"#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_diff_field_attribute_changed() {
+        let changes = diff(&[user("true")], &[user("false")]);
+        assert_eq!(
+            changes,
+            vec![Change::info(
+                ChangeKind::AttributesChanged,
+                "org.example.User.email".to_string(),
+                "attribute x-pii changed from true to false"
+            )]
+        );
+        assert_eq!(
+            changes[0].to_string(),
+            "info: org.example.User.email: attribute x-pii changed from true to false"
+        );
+        assert!(diff(&[user("true")], &[user("true")]).is_empty());
+    }
+
+    #[test]
+    fn test_diff_fields_and_types() {
+        let (_tail, old) = parse(
+            "protocol P {
+    enum Status { ACTIVE, CLOSED }
+    record Account { string id; int balance; string owner; }
+}",
+        )
+        .unwrap();
+        let (_tail, new) = parse(
+            "protocol P {
+    enum Status { ACTIVE }
+    record Account { string id; long balance; string currency = \"EUR\"; }
+    record Audit { string id; }
+}",
+        )
+        .unwrap();
+        let changes: Vec<String> = diff(&old, &new).iter().map(Change::to_string).collect();
+        assert_eq!(
+            changes,
+            vec![
+                "breaking: Status: symbol CLOSED removed",
+                "breaking: Account.balance: type changed from int to long",
+                "breaking: Account.owner: field removed",
+                "info: Account.currency: field added",
+                "info: Audit: type added",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_renames() {
+        let (_tail, old) = parse(
+            "protocol P {
+    record Account { string id; string owner; }
+}",
+        )
+        .unwrap();
+        let (_tail, new) = parse(
+            r#"protocol P {
+    @aliases(["Account"]) record Wallet { string id; string @aliases(["owner"]) holder; }
+}"#,
+        )
+        .unwrap();
+        let changes = diff(&old, &new);
+        let kinds: Vec<ChangeKind> = changes.iter().map(|change| change.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![ChangeKind::TypeRenamed, ChangeKind::FieldRenamed]
+        );
+        let changes: Vec<String> = changes.iter().map(Change::to_string).collect();
+        assert_eq!(
+            changes,
+            vec![
+                "info: Account: renamed to Wallet",
+                "info: Account.owner: renamed to holder",
+            ]
+        );
+    }
+}
@@ -0,0 +1,520 @@
+//! Human readable documentation pages generated from parsed schemas.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use apache_avro::schema::{Name, RecordField, Schema};
+
+use crate::protocol::Protocol;
+use crate::validate::type_name;
+
+/// Format of the generated documentation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocFormat {
+    Markdown,
+    Html,
+}
+
+impl DocFormat {
+    /// File extension of the generated pages.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            DocFormat::Markdown => "md",
+            DocFormat::Html => "html",
+        }
+    }
+
+    fn escape(&self, text: &str) -> String {
+        match self {
+            DocFormat::Markdown => escape_markdown(text),
+            DocFormat::Html => escape_html(text),
+        }
+    }
+
+    // A link to the named type `name`, wherever `layout` puts it
+    fn link(&self, name: &str, layout: DocLayout) -> String {
+        let target = match layout {
+            DocLayout::SinglePage => format!("#{name}"),
+            DocLayout::PagePerType => format!("{name}.{}", self.extension()),
+        };
+        match self {
+            DocFormat::Markdown => format!("[{}]({target})", escape_markdown(name)),
+            DocFormat::Html => format!("<a href=\"{target}\">{}</a>", escape_html(name)),
+        }
+    }
+
+    fn code(&self, text: &str) -> String {
+        match self {
+            // Pipes would still split the table cell inside a code span
+            DocFormat::Markdown if text.contains('`') => {
+                format!("`` {} ``", text.replace('|', "\\|"))
+            }
+            DocFormat::Markdown => format!("`{}`", text.replace('|', "\\|")),
+            DocFormat::Html => format!("<code>{}</code>", escape_html(text)),
+        }
+    }
+
+    fn paragraph(&self, text: &str) -> String {
+        match self {
+            DocFormat::Markdown => format!("\n{text}\n"),
+            DocFormat::Html => format!("<p>{text}</p>\n"),
+        }
+    }
+}
+
+/// How the documentation of a protocol is split into pages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocLayout {
+    /// A page for the whole protocol, with a table of contents.
+    SinglePage,
+    /// A page for each named type, named after it.
+    PagePerType,
+}
+
+/// Render the documentation of a protocol on a single page, returning the
+/// path and contents of the page relative to the output folder.
+///
+/// Named types get an anchor with their name, and field types link to them.
+pub fn render_protocol_docs(protocol: &Protocol, format: DocFormat) -> Vec<(PathBuf, String)> {
+    render_docs(protocol, format, DocLayout::SinglePage)
+}
+
+/// Render the documentation of a protocol split into pages by `layout`,
+/// returning the path and contents of each page relative to the output
+/// folder.
+///
+/// Field types link to the named types, and named types to the records using
+/// them.
+pub fn render_docs(
+    protocol: &Protocol,
+    format: DocFormat,
+    layout: DocLayout,
+) -> Vec<(PathBuf, String)> {
+    let used_by = used_by(&protocol.types);
+    match layout {
+        DocLayout::SinglePage => {
+            let path = PathBuf::from(format!("{}.{}", protocol.name, format.extension()));
+            let page = match format {
+                DocFormat::Markdown => render_markdown(protocol, &used_by),
+                DocFormat::Html => render_html(protocol, &used_by),
+            };
+            vec![(path, page)]
+        }
+        DocLayout::PagePerType => protocol
+            .types
+            .iter()
+            .filter_map(|schema| {
+                let name = &named(schema)?.0.name;
+                let path = PathBuf::from(format!("{name}.{}", format.extension()));
+                let body = render_body(schema, format, layout, &used_by);
+                let page = match format {
+                    DocFormat::Markdown => format!("# {}\n{body}", escape_markdown(name)),
+                    DocFormat::Html => html_page(name, &body),
+                };
+                Some((path, page))
+            })
+            .collect(),
+    }
+}
+
+fn render_markdown(protocol: &Protocol, used_by: &HashMap<&str, Vec<&str>>) -> String {
+    let format = DocFormat::Markdown;
+    let layout = DocLayout::SinglePage;
+    let schemas = &protocol.types;
+    let mut page = format!("# {}\n", escape_markdown(&protocol.name));
+    if let Some(doc) = &protocol.doc {
+        page.push_str(&format.paragraph(&escape_markdown(doc)));
+    }
+    let sections = sections(schemas);
+    page.push_str("\n## Contents\n\n");
+    for (_, schemas) in &sections {
+        for (name, _) in schemas.iter().filter_map(|schema| named(schema)) {
+            page.push_str(&format!("- {}\n", format.link(&name.name, layout)));
+        }
+    }
+    for (title, schemas) in sections {
+        page.push_str(&format!("\n## {title}\n"));
+        for schema in schemas {
+            let Some((name, _)) = named(schema) else {
+                continue;
+            };
+            page.push_str(&format!(
+                "\n### <a id=\"{}\"></a>{}\n",
+                name.name,
+                escape_markdown(&name.name)
+            ));
+            page.push_str(&render_body(schema, format, layout, used_by));
+        }
+    }
+    page
+}
+
+fn render_html(protocol: &Protocol, used_by: &HashMap<&str, Vec<&str>>) -> String {
+    let format = DocFormat::Html;
+    let layout = DocLayout::SinglePage;
+    let schemas = &protocol.types;
+    let mut body = String::new();
+    if let Some(doc) = &protocol.doc {
+        body.push_str(&format.paragraph(&escape_html(doc)));
+    }
+    let sections = sections(schemas);
+    body.push_str("<h2>Contents</h2>\n<ul>\n");
+    for (_, schemas) in &sections {
+        for (name, _) in schemas.iter().filter_map(|schema| named(schema)) {
+            body.push_str(&format!("<li>{}</li>\n", format.link(&name.name, layout)));
+        }
+    }
+    body.push_str("</ul>\n");
+    for (title, schemas) in sections {
+        body.push_str(&format!("<h2>{title}</h2>\n"));
+        for schema in schemas {
+            let Some((name, _)) = named(schema) else {
+                continue;
+            };
+            body.push_str(&format!(
+                "<h3 id=\"{}\">{}</h3>\n",
+                name.name,
+                escape_html(&name.name)
+            ));
+            body.push_str(&render_body(schema, format, layout, used_by));
+        }
+    }
+    html_page(&protocol.name, &body)
+}
+
+// A whole HTML document titled `title`, holding `body` after the title
+fn html_page(title: &str, body: &str) -> String {
+    let title = escape_html(title);
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n</head>\n<body>\n<h1>{title}</h1>\n{body}</body>\n</html>\n"
+    )
+}
+
+// What is said of a named type under its title: its doc, aliases, the records
+// using it, and its fields, symbols or size
+fn render_body(
+    schema: &Schema,
+    format: DocFormat,
+    layout: DocLayout,
+    used_by: &HashMap<&str, Vec<&str>>,
+) -> String {
+    let Some((name, doc)) = named(schema) else {
+        return String::new();
+    };
+    let mut body = String::new();
+    if let Some(doc) = doc {
+        body.push_str(&format.paragraph(&format.escape(doc)));
+    }
+    let aliases = match schema {
+        Schema::Record { aliases, .. }
+        | Schema::Enum { aliases, .. }
+        | Schema::Fixed { aliases, .. } => aliases.as_deref().unwrap_or_default(),
+        _ => &[],
+    };
+    if !aliases.is_empty() {
+        let aliases: Vec<String> = aliases
+            .iter()
+            .map(|alias| format.code(&alias.fullname(None)))
+            .collect();
+        body.push_str(&format.paragraph(&format!("Aliases: {}", aliases.join(", "))));
+    }
+    if let Some(records) = used_by.get(name.name.as_str()) {
+        let links: Vec<String> = records
+            .iter()
+            .map(|record| format.link(record, layout))
+            .collect();
+        body.push_str(&format.paragraph(&format!("Used by: {}", links.join(", "))));
+    }
+    match (schema, format) {
+        (Schema::Record { fields, .. }, DocFormat::Markdown) => {
+            body.push_str("\n| Field | Type | Default | Doc |\n");
+            body.push_str("| --- | --- | --- | --- |\n");
+            for field in fields {
+                let [name, schema, default, doc] = field_cells(field, format, layout);
+                body.push_str(&format!("| {name} | {schema} | {default} | {doc} |\n"));
+            }
+        }
+        (Schema::Record { fields, .. }, DocFormat::Html) => {
+            body.push_str("<table>\n<thead>\n<tr><th>Field</th><th>Type</th><th>Default</th><th>Doc</th></tr>\n</thead>\n<tbody>\n");
+            for field in fields {
+                let [name, schema, default, doc] = field_cells(field, format, layout);
+                body.push_str(&format!(
+                    "<tr><td>{name}</td><td>{schema}</td><td>{default}</td><td>{doc}</td></tr>\n"
+                ));
+            }
+            body.push_str("</tbody>\n</table>\n");
+        }
+        (Schema::Enum { symbols, .. }, DocFormat::Markdown) => {
+            body.push('\n');
+            for symbol in symbols {
+                body.push_str(&format!("- {}\n", format.code(symbol)));
+            }
+        }
+        (Schema::Enum { symbols, .. }, DocFormat::Html) => {
+            body.push_str("<ul>\n");
+            for symbol in symbols {
+                body.push_str(&format!("<li>{}</li>\n", format.code(symbol)));
+            }
+            body.push_str("</ul>\n");
+        }
+        (Schema::Fixed { size, .. }, _) => {
+            body.push_str(&format.paragraph(&format!("Size: {size} bytes")));
+        }
+        _ => (),
+    }
+    body
+}
+
+// The name and doc of a named type
+fn named(schema: &Schema) -> Option<(&Name, &Option<String>)> {
+    match schema {
+        Schema::Record { name, doc, .. }
+        | Schema::Enum { name, doc, .. }
+        | Schema::Fixed { name, doc, .. } => Some((name, doc)),
+        _ => None,
+    }
+}
+
+// The records whose fields use each named type, in declaration order
+fn used_by(schemas: &[Schema]) -> HashMap<&str, Vec<&str>> {
+    let mut used_by: HashMap<&str, Vec<&str>> = HashMap::new();
+    for schema in schemas {
+        let Schema::Record { name, fields, .. } = schema else {
+            continue;
+        };
+        let mut used = Vec::new();
+        for field in fields {
+            referenced(&field.schema, &mut used);
+        }
+        for type_name in used {
+            let records = used_by.entry(type_name).or_default();
+            if type_name != name.name && !records.contains(&name.name.as_str()) {
+                records.push(&name.name);
+            }
+        }
+    }
+    used_by.retain(|_, records| !records.is_empty());
+    used_by
+}
+
+// The named types `schema` uses, by name
+fn referenced<'a>(schema: &'a Schema, used: &mut Vec<&'a str>) {
+    match schema {
+        Schema::Array(inner) | Schema::Map(inner) => referenced(inner, used),
+        Schema::Union(union) => {
+            for variant in union.variants() {
+                referenced(variant, used);
+            }
+        }
+        Schema::Record { name, .. }
+        | Schema::Enum { name, .. }
+        | Schema::Fixed { name, .. }
+        | Schema::Ref { name } => used.push(&name.name),
+        _ => (),
+    }
+}
+
+// Named types grouped by kind, in declaration order
+fn sections(schemas: &[Schema]) -> Vec<(&'static str, Vec<&Schema>)> {
+    let records = schemas
+        .iter()
+        .filter(|s| matches!(s, Schema::Record { .. }))
+        .collect::<Vec<_>>();
+    let enums = schemas
+        .iter()
+        .filter(|s| matches!(s, Schema::Enum { .. }))
+        .collect::<Vec<_>>();
+    let fixed = schemas
+        .iter()
+        .filter(|s| matches!(s, Schema::Fixed { .. }))
+        .collect::<Vec<_>>();
+    vec![("Records", records), ("Enums", enums), ("Fixed", fixed)]
+        .into_iter()
+        .filter(|(_, schemas)| !schemas.is_empty())
+        .collect()
+}
+
+fn field_cells(field: &RecordField, format: DocFormat, layout: DocLayout) -> [String; 4] {
+    let default = field
+        .default
+        .as_ref()
+        .map(|default| format.code(&default.to_string()))
+        .unwrap_or_default();
+    let doc = field
+        .doc
+        .as_ref()
+        .map(|doc| format.escape(&doc.lines().map(str::trim).collect::<Vec<_>>().join(" ")))
+        .unwrap_or_default();
+    [
+        format.code(&field.name),
+        render_type(&field.schema, format, layout),
+        default,
+        doc,
+    ]
+}
+
+/// Type of a field in IDL syntax, e.g. `array<Item>`, escaped for `format`
+/// and linking to the named types where `layout` puts them.
+pub fn render_type(schema: &Schema, format: DocFormat, layout: DocLayout) -> String {
+    match schema {
+        Schema::Array(items) => format!(
+            "{}{}{}",
+            format.escape("array<"),
+            render_type(items, format, layout),
+            format.escape(">")
+        ),
+        Schema::Map(values) => format!(
+            "{}{}{}",
+            format.escape("map<"),
+            render_type(values, format, layout),
+            format.escape(">")
+        ),
+        Schema::Union(union) => {
+            let variants: Vec<String> = union
+                .variants()
+                .iter()
+                .map(|variant| render_type(variant, format, layout))
+                .collect();
+            format!("union {{ {} }}", variants.join(", "))
+        }
+        Schema::Record { name, .. }
+        | Schema::Enum { name, .. }
+        | Schema::Fixed { name, .. }
+        | Schema::Ref { name } => format.link(&name.name, layout),
+        schema => format.escape(&type_name(schema)),
+    }
+}
+
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(
+            c,
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '|' | '#'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::options::ParserOptions;
+    use crate::parser::parse_protocol_with_options;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(DocFormat::Markdown, include_str!("../../../tests/golden/Shop.md"))]
+    #[case(DocFormat::Html, include_str!("../../../tests/golden/Shop.html"))]
+    fn test_render_protocol_docs(#[case] format: DocFormat, #[case] expected: &str) {
+        let input = include_str!("../../../tests/samples/Shop.avdl");
+        let (protocol, _warnings) =
+            parse_protocol_with_options(input, &ParserOptions::default()).unwrap();
+        let pages = render_protocol_docs(&protocol, format);
+        assert_eq!(pages.len(), 1);
+        assert_eq!(
+            pages[0].0,
+            PathBuf::from(format!("Shop.{}", format.extension()))
+        );
+        assert_eq!(pages[0].1, expected);
+    }
+
+    #[rstest]
+    #[case(DocFormat::Markdown, [
+        include_str!("../../../tests/golden/Shop/Item.md"),
+        include_str!("../../../tests/golden/Shop/Order.md"),
+        include_str!("../../../tests/golden/Shop/Kind.md"),
+        include_str!("../../../tests/golden/Shop/Hash.md"),
+    ])]
+    #[case(DocFormat::Html, [
+        include_str!("../../../tests/golden/Shop/Item.html"),
+        include_str!("../../../tests/golden/Shop/Order.html"),
+        include_str!("../../../tests/golden/Shop/Kind.html"),
+        include_str!("../../../tests/golden/Shop/Hash.html"),
+    ])]
+    fn test_render_page_per_type(#[case] format: DocFormat, #[case] expected: [&str; 4]) {
+        let input = include_str!("../../../tests/samples/Shop.avdl");
+        let (protocol, _warnings) =
+            parse_protocol_with_options(input, &ParserOptions::default()).unwrap();
+        let pages = render_docs(&protocol, format, DocLayout::PagePerType);
+        let paths: Vec<_> = pages.iter().map(|(path, _)| path.clone()).collect();
+        let extension = format.extension();
+        assert_eq!(
+            paths,
+            ["Kind", "Hash", "Item", "Order"]
+                .map(|name| PathBuf::from(format!("{name}.{extension}")))
+        );
+        let page = |name: &str| {
+            let path = PathBuf::from(format!("{name}.{extension}"));
+            &pages.iter().find(|(p, _)| *p == path).unwrap().1
+        };
+        for (name, expected) in ["Item", "Order", "Kind", "Hash"].into_iter().zip(expected) {
+            assert_eq!(page(name), expected, "{name}");
+        }
+    }
+
+    #[test]
+    fn test_aliases() {
+        let input = r#"protocol P {
+    @aliases(["Tier", "org.old.Level"])
+    enum Rank { LOW, HIGH }
+    record R { Rank rank; union { null, R } next = null; }
+}"#;
+        let (protocol, _warnings) =
+            parse_protocol_with_options(input, &ParserOptions::default()).unwrap();
+        let pages = render_docs(&protocol, DocFormat::Markdown, DocLayout::PagePerType);
+        assert_eq!(
+            pages[0].1,
+            "# Rank\n\nAliases: `Tier`, `org.old.Level`\n\nUsed by: [R](R.md)\n\n- `LOW`\n- `HIGH`\n"
+        );
+        // Records using themselves are not listed
+        assert!(!pages[1].1.contains("Used by"), "{}", pages[1].1);
+    }
+
+    #[test]
+    fn test_numbers_ignore_locale() {
+        // Formatting must not depend on the locale of the process
+        std::env::set_var("LC_ALL", "de_DE.UTF-8");
+        std::env::set_var("LC_NUMERIC", "de_DE.UTF-8");
+        let input = "protocol P {
+    record R {
+        double price = 1.5;
+        float ratio = 0.25;
+    }
+}";
+        let (protocol, _warnings) =
+            parse_protocol_with_options(input, &ParserOptions::default()).unwrap();
+        for format in [DocFormat::Markdown, DocFormat::Html] {
+            let (_path, page) = &render_protocol_docs(&protocol, format)[0];
+            assert!(page.contains("1.5"), "{page}");
+            assert!(page.contains("0.25"), "{page}");
+            assert!(!page.contains("1,5"), "{page}");
+        }
+    }
+
+    #[rstest]
+    #[case("a_b", "a\\_b")]
+    #[case("*bold* | [link]", "\\*bold\\* \\| \\[link\\]")]
+    #[case("<b>", "\\<b\\>")]
+    fn test_escape_markdown(#[case] input: &str, #[case] expected: &str) {
+        assert_eq!(escape_markdown(input), expected);
+    }
+
+    #[rstest]
+    #[case("<b>&</b>", "&lt;b&gt;&amp;&lt;/b&gt;")]
+    #[case("\"it's\"", "&quot;it&#39;s&quot;")]
+    fn test_escape_html(#[case] input: &str, #[case] expected: &str) {
+        assert_eq!(escape_html(input), expected);
+    }
+}
@@ -0,0 +1,854 @@
+use std::fmt;
+use std::num::{ParseFloatError, ParseIntError};
+use std::path::PathBuf;
+
+use nom::error::{ContextError, ErrorKind, FromExternalError, ParseError};
+use nom::InputLength;
+use thiserror::Error;
+
+/// Error produced by the nom combinators of this crate.
+///
+/// Unlike `nom::error::Error`, when several `alt` branches fail it keeps the
+/// one that got furthest into the input, and it can carry a short context
+/// message (e.g. "expected ';' after field declaration") and the reason a
+/// token was rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParserError<I> {
+    pub input: I,
+    pub kind: ErrorKind,
+    pub context: Option<&'static str>,
+    pub cause: Option<TokenError>,
+}
+
+/// Why a token matched by the grammar could not be converted to a value.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TokenError {
+    #[error("invalid integer")]
+    Int(#[from] ParseIntError),
+
+    #[error("invalid number")]
+    Float(#[from] ParseFloatError),
+
+    #[error("{0} is out of range for a float")]
+    FloatOutOfRange(String),
+
+    #[error("`{0}` is not a uuid")]
+    Uuid(String),
+
+    #[error("a duration is 12 bytes, written as characters up to \\u{{ff}}")]
+    InvalidDuration,
+
+    #[error("a decimal default is an integer of at most {0} digits in bytes, written as characters up to \\u{{ff}}")]
+    InvalidDecimal(usize),
+
+    #[error("decimal precision must be at least 1")]
+    DecimalPrecision,
+
+    #[error("decimal scale {scale} is larger than its precision {precision}")]
+    DecimalScale { precision: usize, scale: usize },
+
+    #[error("a fixed of {size} bytes holds decimals of at most {max} digits, not {precision}")]
+    DecimalSize {
+        size: usize,
+        max: usize,
+        precision: usize,
+    },
+
+    #[error("invalid name `{0}`")]
+    InvalidName(String),
+
+    #[error("field `{field}` of type `{type_name}` can't default to null, only a field of type `null` or a union whose first branch is null can")]
+    NullDefault { field: String, type_name: String },
+
+    #[error("enum default `{0}` is not one of its symbols")]
+    UnknownEnumDefault(String),
+
+    #[error("`{0}` is declared twice")]
+    Duplicate(String),
+
+    #[error("default cannot be written as JSON")]
+    UnsupportedDefault,
+
+    #[error("`{0}` is repeated with a different value")]
+    ConflictingAnnotation(String),
+
+    #[error("unsupported logical type `{0}`")]
+    UnsupportedLogicalType(String),
+
+    #[error("logical type `{logical}` applies to {expected}, not `{declared}`")]
+    LogicalTypeMismatch {
+        logical: &'static str,
+        expected: &'static str,
+        declared: String,
+    },
+
+    #[error("unions may not immediately contain other unions")]
+    NestedUnion,
+
+    #[error("unions may not immediately contain other unions (field {0})")]
+    NestedUnionInField(String),
+
+    #[error("unions may not contain `{0}` twice")]
+    DuplicateUnionType(String),
+
+    #[error("unions may not contain `{type_name}` twice (field {field})")]
+    DuplicateUnionTypeInField { type_name: String, field: String },
+}
+
+impl<I: InputLength> ParseError<I> for ParserError<I> {
+    fn from_error_kind(input: I, kind: ErrorKind) -> Self {
+        ParserError {
+            input,
+            kind,
+            context: None,
+            cause: None,
+        }
+    }
+
+    fn append(_input: I, _kind: ErrorKind, other: Self) -> Self {
+        other
+    }
+
+    fn or(self, other: Self) -> Self {
+        if other.input.input_len() <= self.input.input_len() {
+            other
+        } else {
+            self
+        }
+    }
+}
+
+impl<I> ContextError<I> for ParserError<I> {
+    fn add_context(_input: I, ctx: &'static str, mut other: Self) -> Self {
+        // Keep the innermost context, it is the most specific one
+        if other.context.is_none() {
+            other.context = Some(ctx);
+        }
+        other
+    }
+}
+
+impl<I, E: Into<TokenError>> FromExternalError<I, E> for ParserError<I> {
+    fn from_external_error(input: I, kind: ErrorKind, e: E) -> Self {
+        ParserError {
+            input,
+            kind,
+            context: None,
+            cause: Some(e.into()),
+        }
+    }
+}
+
+impl<I> fmt::Display for ParserError<I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.context {
+            Some(context) => f.write_str(context),
+            None => write!(f, "{} failed", self.kind.description()),
+        }
+    }
+}
+
+impl<I: fmt::Debug> std::error::Error for ParserError<I> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.cause
+            .as_ref()
+            .map(|cause| cause as &(dyn std::error::Error + 'static))
+    }
+}
+
+/// Position in the source text, both starting at 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Location {
+    /// Compute the line and column of a byte offset in `source`.
+    pub fn from_offset(source: &str, offset: usize) -> Location {
+        let mut offset = offset.min(source.len());
+        while !source.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        let before = &source[..offset];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let column = before[line_start..].chars().count() + 1;
+        Location { line, column }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// Byte range in the parsed input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// Span of `fragment`, which must be a slice of `source`.
+    pub fn of(source: &str, fragment: &str) -> Span {
+        let start = (fragment.as_ptr() as usize)
+            .saturating_sub(source.as_ptr() as usize)
+            .min(source.len());
+        Span {
+            start,
+            end: (start + fragment.len()).min(source.len()),
+        }
+    }
+
+    pub fn location(&self, source: &str) -> Location {
+        Location::from_offset(source, self.start)
+    }
+}
+
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum AvdlError {
+    #[error("error at {}{location}: {message}", file_prefix(.file))]
+    Parse {
+        file: Option<PathBuf>,
+        location: Location,
+        line_text: String,
+        message: String,
+        /// The token that could not be converted, if that is what failed.
+        #[source]
+        cause: Option<Box<TokenError>>,
+    },
+
+    #[error("failed to read {}", .path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("failed to read the input")]
+    Read(#[source] std::io::Error),
+
+    #[error("Failed to import Avsc")]
+    ImportAvscError(#[from] apache_avro::Error),
+
+    #[error("failed to import {}", .path.display())]
+    ImportIdlError {
+        path: PathBuf,
+        #[source]
+        source: Box<AvdlError>,
+    },
+
+    #[error("invalid json")]
+    Json(#[from] serde_json::Error),
+
+    #[error("invalid avsc at {path}: {message}")]
+    InvalidAvsc { path: String, message: String },
+
+    #[error("avsc would not round-trip, lost: {}", .0.join(", "))]
+    LossyAvsc(Vec<String>),
+
+    #[error("invalid default for field {field} of record {record} at {path}: expected {expected}")]
+    InvalidDefault {
+        record: String,
+        field: String,
+        path: String,
+        expected: String,
+        #[source]
+        found: Box<DefaultValue>,
+    },
+
+    #[error("invalid default for parameter {parameter} of message {message} at {path}: expected {expected}")]
+    InvalidParameterDefault {
+        message: String,
+        parameter: String,
+        path: String,
+        expected: String,
+        #[source]
+        found: Box<DefaultValue>,
+    },
+
+    #[error("invalid name `{0}`, names must start with [A-Za-z_] and contain only [A-Za-z0-9_]")]
+    InvalidName(String),
+
+    #[error("unresolved type {name} used by field {field} of record {record}")]
+    UnresolvedRef {
+        record: String,
+        field: String,
+        name: String,
+    },
+
+    #[error("unresolved type {name} used by message {message}")]
+    UnresolvedMessageRef { message: String, name: String },
+
+    #[error("unresolved type {0} used by the schema statement")]
+    UnresolvedSchema(String),
+
+    #[error("default of {field} nests deeper than {max_depth} levels at {path}")]
+    DefaultTooDeep {
+        field: String,
+        path: String,
+        max_depth: usize,
+    },
+
+    #[error(
+        "{} and {} differ only by case and would overwrite each other on case-insensitive filesystems",
+        .first.display(),
+        .second.display()
+    )]
+    CaseCollision { first: PathBuf, second: PathBuf },
+
+    #[error("`{first}` and `{second}` would both be written to {}", .path.display())]
+    OutputCollision {
+        first: String,
+        second: String,
+        path: PathBuf,
+    },
+
+    #[error("invalid filename template `{template}`: {message}")]
+    InvalidTemplate { template: String, message: String },
+
+    #[error("named types reference each other in a cycle: {}", .0.join(" -> "))]
+    ReferenceCycle(Vec<String>),
+
+    #[error("duplicate name `{0}`")]
+    Duplicate(String),
+
+    #[error("property `{0}` can't be written as an Avro IDL annotation")]
+    UnwritableProperty(String),
+}
+
+/// An error along with the span of the type or field it is about, when known,
+/// as returned by [`crate::parse_spanned`].
+#[derive(Error, Debug)]
+#[error("{error}")]
+pub struct SpannedError {
+    pub error: Box<AvdlError>,
+    pub span: Option<Span>,
+}
+
+/// A default value rejected by validation, kept as the source of
+/// [`AvdlError::InvalidDefault`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DefaultValue(pub serde_json::Value);
+
+impl fmt::Display for DefaultValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "found {}", self.0)
+    }
+}
+
+impl std::error::Error for DefaultValue {}
+
+fn file_prefix(file: &Option<PathBuf>) -> String {
+    match file {
+        Some(path) => format!("{}:", path.display()),
+        None => String::new(),
+    }
+}
+
+impl AvdlError {
+    /// Build a parse error out of a nom error, `source` must be the whole
+    /// input given to the parser.
+    pub fn from_nom(source: &str, err: nom::Err<ParserError<&str>>) -> AvdlError {
+        let (remaining, message, cause) = match err {
+            nom::Err::Error(e) | nom::Err::Failure(e) => {
+                let message = match e.context {
+                    Some(context) => context.to_string(),
+                    None => describe_unexpected(e.input),
+                };
+                (e.input, message, e.cause.map(Box::new))
+            }
+            nom::Err::Incomplete(_) => ("", String::from("unexpected end of input"), None),
+        };
+        let offset = source.len().saturating_sub(remaining.len());
+        let location = Location::from_offset(source, offset);
+        let line_text = source
+            .lines()
+            .nth(location.line - 1)
+            .unwrap_or_default()
+            .to_string();
+        AvdlError::Parse {
+            file: None,
+            location,
+            line_text,
+            message,
+            cause,
+        }
+    }
+
+    /// Attach the path of the parsed file, used when rendering the error.
+    pub fn with_file(mut self, path: impl Into<PathBuf>) -> AvdlError {
+        if let AvdlError::Parse { file, .. } = &mut self {
+            *file = Some(path.into());
+        }
+        self
+    }
+}
+
+fn describe_unexpected(remaining: &str) -> String {
+    let token: String = remaining
+        .chars()
+        .take_while(|c| !c.is_whitespace())
+        .take(32)
+        .collect();
+    if token.is_empty() {
+        String::from("unexpected end of input")
+    } else {
+        format!("unexpected '{token}'")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse;
+    use rstest::rstest;
+
+    fn parse_error(input: &str) -> AvdlError {
+        let err = parse(input).expect_err("input should not parse");
+        AvdlError::from_nom(input, err)
+    }
+
+    #[rstest]
+    #[case("", 0, (1, 1))]
+    #[case("abc", 2, (1, 3))]
+    #[case("abc\ndef", 4, (2, 1))]
+    #[case("abc\ndef\n  ghi", 10, (3, 3))]
+    #[case("ñandú\nx", 8, (2, 1))]
+    #[case("ñandú", 2, (1, 2))]
+    fn test_location_from_offset(
+        #[case] source: &str,
+        #[case] offset: usize,
+        #[case] expected: (usize, usize),
+    ) {
+        let location = Location::from_offset(source, offset);
+        assert_eq!((location.line, location.column), expected);
+    }
+
+    // Inputs that used to panic, found by fuzzing the parser
+    #[rstest]
+    #[case(
+        r#"protocol P { record R { @logicalType("decimal") bytes d; } }"#,
+        "unsupported logical type `decimal`"
+    )]
+    #[case(
+        "protocol P { record R { union { null, union { int, string } } x; } }",
+        "unions may not immediately contain other unions (field x)"
+    )]
+    #[case(
+        "protocol P { record R { union { int, int } x; } }",
+        "unions may not contain `int` twice (field x)"
+    )]
+    #[case(
+        r#"protocol P { record R { @logicalType("timestamp-micros") string ts; } }"#,
+        "logical type `timestamp-micros` applies to `long`, not `string`"
+    )]
+    #[case(
+        r#"protocol P { record R { @logicalType("date") long day; } }"#,
+        "logical type `date` applies to `int`, not `long`"
+    )]
+    #[case(
+        r#"protocol P { record R { array<@logicalType("timestamp-micros") int> ts; } }"#,
+        "logical type `timestamp-micros` applies to `long`, not `int`"
+    )]
+    #[case(
+        "protocol P { record R { decimal(0, 0) amount; } }",
+        "decimal precision must be at least 1"
+    )]
+    #[case(
+        "protocol P { record R { decimal(1, 2) amount; } }",
+        "decimal scale 2 is larger than its precision 1"
+    )]
+    #[case(
+        r#"protocol P { @logicalType("decimal") @precision(10) @scale(2) fixed Amount(4); }"#,
+        "a fixed of 4 bytes holds decimals of at most 9 digits, not 10"
+    )]
+    #[case(
+        r#"protocol P { record R { decimal(3, 2) amount = "\u{7}\u{d0}"; } }"#,
+        "a decimal default is an integer of at most 3 digits in bytes, written as characters up to \\u{ff}"
+    )]
+    #[case(
+        r#"protocol P { record R { @logicalType("duration") duration wait = "x"; } }"#,
+        "a duration is 12 bytes, written as characters up to \\u{ff}"
+    )]
+    #[case(
+        r#"protocol P { record R { @logicalType("duration") bytes wait; } }"#,
+        "logical type `duration` applies to a fixed of 12 bytes, not `bytes`"
+    )]
+    #[case(r#"protocol P { record R { uuid id = "12"; } }"#, "`12` is not a uuid")]
+    #[case("protocol P { record R { uuid id = \"\"; } }", "`` is not a uuid")]
+    #[case(
+        "protocol P { record R { string name = null; } }",
+        "field `name` of type `string` can't default to null, only a field of type `null` or a union whose first branch is null can"
+    )]
+    #[case(
+        "protocol P { record R { int count = null; } }",
+        "field `count` of type `int` can't default to null, only a field of type `null` or a union whose first branch is null can"
+    )]
+    #[case(
+        "protocol P { record R { array<string> tags = null; } }",
+        "field `tags` of type `array<string>` can't default to null, only a field of type `null` or a union whose first branch is null can"
+    )]
+    #[case(
+        "protocol P { record R { map<int> counts = /* none */ null; } }",
+        "field `counts` of type `map<int>` can't default to null, only a field of type `null` or a union whose first branch is null can"
+    )]
+    #[case(
+        "protocol P { record R { union { string, null } name = null; } }",
+        "field `name` of type `union { string, null }` can't default to null, only a field of type `null` or a union whose first branch is null can"
+    )]
+    #[case(
+        "protocol P { enum E { A, B } = /* none */ C; }",
+        "enum default `C` is not one of its symbols"
+    )]
+    #[case("protocol P { record Ré { int x; } }", "invalid name `Ré`")]
+    #[case("protocol P { enum Ké { A } }", "invalid name `Ké`")]
+    #[case("protocol P { fixed Kü(3); }", "invalid name `Kü`")]
+    fn test_rejected_token(#[case] input: &str, #[case] expected: &str) {
+        match parse_error(input) {
+            AvdlError::Parse {
+                cause: Some(cause), ..
+            } => assert_eq!(cause.to_string(), expected),
+            err => panic!("unexpected error {err:?}"),
+        }
+    }
+
+    #[rstest]
+    #[case(
+        r#"protocol P { record R { @logicalType("duration") fixed d = "x"; } }"#,
+        "invalid default value"
+    )]
+    #[case(
+        &format!("protocol P {{ record R {{ {}int{} x; }} }}", "array<".repeat(10_000), ">".repeat(10_000)),
+        "types are nested too deep"
+    )]
+    #[case(
+        &format!("protocol P {{ record R {{ {}int{} x; }} }}", "union { null, ".repeat(10_000), " }".repeat(10_000)),
+        "types are nested too deep"
+    )]
+    #[case(
+        &format!("protocol P {{ record R {{ array<@x({}1{}) int> x; }} }}", "[".repeat(10_000), "]".repeat(10_000)),
+        "annotation values are nested too deep"
+    )]
+    #[case(
+        &format!("@x({}{}) protocol P {{ }}", r#"{"a": "#.repeat(10_000), "}".repeat(10_000)),
+        "annotation values are nested too deep"
+    )]
+    fn test_rejected(#[case] input: &str, #[case] expected: &str) {
+        match parse_error(input) {
+            AvdlError::Parse { message, .. } => assert_eq!(message, expected),
+            err => panic!("unexpected error {err:?}"),
+        }
+    }
+
+    // Wherever a uuid default is nested, the error is at the string which is
+    // not a uuid and tells why
+    #[rstest]
+    #[case(r#"uuid id = "nope";"#)]
+    #[case(r#"array<uuid> ids = ["123e4567-e89b-12d3-a456-426614174000", "nope"];"#)]
+    #[case(r#"union { uuid, null } id = "nope";"#)]
+    #[case(r#"union { array<uuid>, null } ids = ["nope"];"#)]
+    #[case(r#"map<uuid> ids = {"a": "123e4567e89b12d3a456426614174000", "b": "nope"};"#)]
+    #[case(r#"@logicalType("uuid") string id = "nope";"#)]
+    fn test_invalid_uuid_default(#[case] field: &str) {
+        let input = format!("protocol P {{ record R {{ {field} }} }}");
+        match parse_error(&input) {
+            AvdlError::Parse {
+                location,
+                message,
+                cause: Some(cause),
+                ..
+            } => {
+                let column = input.find(r#""nope""#).unwrap() + 1;
+                assert_eq!(location, Location { line: 1, column });
+                assert_eq!(message, "invalid default value");
+                assert_eq!(cause.to_string(), "`nope` is not a uuid");
+            }
+            err => panic!("unexpected error {err:?}"),
+        }
+    }
+
+    // A union right in a union is an error at the inner one, naming the field
+    #[rstest]
+    #[case("union { null, union { int, string } } x;", "x")]
+    #[case("array<union { null, /* both */ union { int, string } }> xs;", "xs")]
+    #[case(
+        "map<union { null, union { int } }> @order(\"ignore\") counts;",
+        "counts"
+    )]
+    #[case(
+        "/** Maybe */ union { union { int }, null } @aliases([\"y\"]) x = 1;",
+        "x"
+    )]
+    fn test_nested_union(#[case] field: &str, #[case] name: &str) {
+        let input = format!("protocol P {{ record R {{ {field} }} }}");
+        match parse_error(&input) {
+            AvdlError::Parse {
+                location,
+                cause: Some(cause),
+                ..
+            } => {
+                let column = input.rfind("union").unwrap() + 1;
+                assert_eq!(location, Location { line: 1, column });
+                assert_eq!(
+                    cause.to_string(),
+                    format!("unions may not immediately contain other unions (field {name})")
+                );
+            }
+            err => panic!("unexpected error {err:?}"),
+        }
+    }
+
+    // A type repeated in a union is an error at its second branch, naming the
+    // type and the field
+    #[rstest]
+    #[case("union { string, /* again */ string } name;", "string", "name")]
+    #[case("union { null, int, int } count = null;", "int", "count")]
+    #[case("array<union { A, null, A }> items;", "A", "items")]
+    #[case("map<array<union { null, A, string, A }>> items;", "A", "items")]
+    fn test_duplicate_union_type(#[case] field: &str, #[case] type_name: &str, #[case] name: &str) {
+        let input = format!("protocol P {{ record A {{ int a; }} record R {{ {field} }} }}");
+        match parse_error(&input) {
+            AvdlError::Parse {
+                location,
+                cause: Some(cause),
+                ..
+            } => {
+                let column = input.rfind(&format!(" {type_name}")).unwrap() + 2;
+                assert_eq!(location, Location { line: 1, column });
+                assert_eq!(
+                    cause.to_string(),
+                    format!("unions may not contain `{type_name}` twice (field {name})")
+                );
+            }
+            err => panic!("unexpected error {err:?}"),
+        }
+    }
+
+    #[test]
+    fn test_missing_semicolon() {
+        let input = "protocol P {
+    record R {
+        string name
+        int age;
+    }
+}";
+        match parse_error(input) {
+            AvdlError::Parse {
+                location,
+                line_text,
+                message,
+                ..
+            } => {
+                assert_eq!(location, Location { line: 4, column: 9 });
+                assert_eq!(line_text, "        int age;");
+                assert_eq!(message, "expected ';' after field declaration");
+            }
+            err => panic!("unexpected error {err:?}"),
+        }
+    }
+
+    #[test]
+    fn test_unterminated_doc_comment() {
+        let input = "protocol P {
+    record R {
+        /** the age
+        int age;
+    }
+}";
+        match parse_error(input) {
+            AvdlError::Parse {
+                location, message, ..
+            } => {
+                assert_eq!(
+                    location,
+                    Location {
+                        line: 3,
+                        column: 12
+                    }
+                );
+                assert_eq!(message, "unterminated doc comment");
+            }
+            err => panic!("unexpected error {err:?}"),
+        }
+    }
+
+    #[test]
+    fn test_bad_default() {
+        let input = r#"protocol P {
+    record R {
+        string name;
+        int age = "old";
+    }
+}"#;
+        match parse_error(input) {
+            AvdlError::Parse {
+                location, message, ..
+            } => {
+                assert_eq!(
+                    location,
+                    Location {
+                        line: 4,
+                        column: 19
+                    }
+                );
+                assert_eq!(message, "invalid default value");
+            }
+            err => panic!("unexpected error {err:?}"),
+        }
+    }
+
+    #[test]
+    fn test_enum_default_missing_semicolon() {
+        let input = "protocol P {
+    enum Kind { A, B } = A
+    record R {
+        Kind kind;
+    }
+}";
+        match parse_error(input) {
+            AvdlError::Parse {
+                location,
+                line_text,
+                message,
+                ..
+            } => {
+                assert_eq!(location, Location { line: 3, column: 5 });
+                assert_eq!(line_text, "    record R {");
+                assert_eq!(message, "expected ';' after enum default");
+            }
+            err => panic!("unexpected error {err:?}"),
+        }
+    }
+
+    #[rstest]
+    #[case("double price = 1,5;", 25)]
+    #[case("double price = 1_000,5;", 25)]
+    #[case("union { float, null } price = 1,5;", 40)]
+    fn test_decimal_comma_hint(#[case] field: &str, #[case] column: usize) {
+        let input = format!("protocol P {{\n    record R {{\n        {field}\n    }}\n}}");
+        match parse_error(&input) {
+            AvdlError::Parse {
+                location, message, ..
+            } => {
+                assert_eq!(location, Location { line: 3, column });
+                assert_eq!(message, "use '.' as the decimal separator");
+            }
+            err => panic!("unexpected error {err:?}"),
+        }
+    }
+
+    // Same as the alternate rendering of anyhow, each error followed by its
+    // sources
+    fn render_chain(err: &dyn std::error::Error) -> String {
+        let mut rendered = err.to_string();
+        let mut source = err.source();
+        while let Some(err) = source {
+            rendered.push_str(&format!(": {err}"));
+            source = err.source();
+        }
+        rendered
+    }
+
+    #[test]
+    fn test_error_chain() {
+        let input = r#"protocol P {
+    record R {
+        uuid id = "not-a-uuid";
+    }
+}"#;
+        let err = AvdlError::ImportIdlError {
+            path: PathBuf::from("shared.avdl"),
+            source: Box::new(parse_error(input)),
+        };
+        let rendered = render_chain(&err);
+        assert_eq!(
+            rendered,
+            "failed to import shared.avdl: error at 3:19: invalid default value: `not-a-uuid` is not a uuid"
+        );
+        for level in ["failed to import", "invalid default value", "is not a uuid"] {
+            assert_eq!(rendered.matches(level).count(), 1, "{rendered}");
+        }
+    }
+
+    #[test]
+    fn test_render_with_file() {
+        let input = "protocol P {
+    record R {
+        string name
+        int age;
+    }
+}";
+        let err = parse_error(input).with_file("protocol.avdl");
+        assert_eq!(
+            err.to_string(),
+            "error at protocol.avdl:4:9: expected ';' after field declaration"
+        );
+    }
+
+    #[rstest]
+    #[case(
+        "protocol P {\n    record R {\n        void deleted;\n    }\n}",
+        Location { line: 3, column: 9 }
+    )]
+    #[case(
+        "protocol P {\n    record R {\n        union { null, void } deleted;\n    }\n}",
+        Location { line: 3, column: 23 }
+    )]
+    fn test_void_outside_message(#[case] input: &str, #[case] expected: Location) {
+        match parse_error(input) {
+            AvdlError::Parse {
+                location, message, ..
+            } => {
+                assert_eq!(location, expected);
+                assert_eq!(
+                    message,
+                    "`void` is only valid as a message return type; use `null` for a null-typed field"
+                );
+            }
+            err => panic!("unexpected error {err:?}"),
+        }
+    }
+
+    #[test]
+    fn test_void_message_return() {
+        let input = "protocol P {\n    void ping() oneway;\n}";
+        assert!(parse(input).is_ok());
+    }
+
+    #[rstest]
+    #[case(
+        "protocol P {\n    @namespace(\"a\") @namespace(\"b\")\n    record R { string name; }\n}",
+        Location { line: 2, column: 21 },
+        "`@namespace` is repeated with a different value"
+    )]
+    #[case(
+        "protocol P {\n    record R {\n        string @order(\"ignore\") @order(\"descending\") name;\n    }\n}",
+        Location { line: 3, column: 33 },
+        "`@order` is repeated with a different value"
+    )]
+    fn test_conflicting_annotations(
+        #[case] input: &str,
+        #[case] expected: Location,
+        #[case] cause: &str,
+    ) {
+        match parse_error(input) {
+            AvdlError::Parse {
+                location,
+                message,
+                cause: Some(token),
+                ..
+            } => {
+                assert_eq!(location, expected);
+                assert_eq!(message, "annotation repeated with a different value");
+                assert_eq!(token.to_string(), cause);
+            }
+            err => panic!("unexpected error {err:?}"),
+        }
+    }
+}
@@ -0,0 +1,300 @@
+//! Parsing a protocol as a stream of events, without keeping its types.
+
+use std::collections::{BTreeMap, HashSet};
+use std::mem;
+use std::ops::ControlFlow;
+
+use apache_avro::schema::{Name, Namespace, RecordField, Schema};
+use nom::error::{ErrorKind, FromExternalError};
+use serde_json::Value;
+
+use crate::error::{AvdlError, ParserError, TokenError};
+use crate::options::ParserOptions;
+use crate::parser::{
+    check_trailing, namespace_solver, parse_declaration, parse_protocol_declarations,
+    parse_protocol_header, Declaration,
+};
+use crate::protocol::{Import, Message};
+use crate::warning::{Warning, Warnings};
+
+/// Something found while parsing a protocol, in the order it is written.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseEvent {
+    ProtocolStart {
+        name: String,
+        namespace: Option<String>,
+        doc: Option<String>,
+        aliases: Vec<String>,
+        /// Set by the custom annotations of the protocol.
+        properties: BTreeMap<String, Value>,
+    },
+    Import(Import),
+    /// A named type starts. The fields of a record are taken out of it and
+    /// follow as [`ParseEvent::Field`], up to the [`ParseEvent::TypeEnd`].
+    TypeStart(Schema),
+    Field(RecordField),
+    TypeEnd,
+    Message(Message),
+    Warning(Warning),
+    ProtocolEnd,
+}
+
+/// Parse `input`, handing what is found to `on_event` as soon as it is
+/// parsed, so large protocols can be processed without holding all their
+/// types in memory.
+///
+/// Parsing stops as soon as `on_event` breaks, without reporting the errors
+/// further in the input. References are left as they are written, and the
+/// checks needing all the types, like duplicate names, are not done: use
+/// [`crate::parse_protocol_with_options`] for them.
+pub fn parse_events(
+    input: &str,
+    on_event: &mut impl FnMut(ParseEvent) -> ControlFlow<()>,
+) -> Result<(), AvdlError> {
+    let mut warnings = Warnings::new();
+    let result = parse_protocol_declarations(
+        input,
+        &mut warnings,
+        &ParserOptions::default(),
+        None,
+        &mut |declaration, warnings| {
+            // Warnings are found while parsing the declaration, so go first
+            for warning in warnings.drain(..) {
+                if on_event(ParseEvent::Warning(warning)).is_break() {
+                    return Ok(ControlFlow::Break(()));
+                }
+            }
+            Ok(emit(declaration, on_event))
+        },
+    );
+    match result {
+        Ok((_, ControlFlow::Continue(()))) => {
+            for warning in warnings {
+                if on_event(ParseEvent::Warning(warning)).is_break() {
+                    return Ok(());
+                }
+            }
+            let _ = on_event(ParseEvent::ProtocolEnd);
+            Ok(())
+        }
+        Ok((_, ControlFlow::Break(()))) => Ok(()),
+        Err(err) => Err(AvdlError::from_nom(input, err)),
+    }
+}
+
+/// Iterator over the named types of a protocol, returned by
+/// [`parse_protocol_iter`].
+pub struct ProtocolTypes<'a> {
+    input: &'a str,
+    // What is left of the body, None once it is parsed or failed
+    rest: Option<&'a str>,
+    namespace: Namespace,
+    names: HashSet<Name>,
+    warnings: Warnings,
+    options: ParserOptions,
+    // Failure of the header, reported by the first call to `next`
+    failed: Option<AvdlError>,
+}
+
+/// Parse the header of the protocol in `input`, and then iterate over its
+/// named types, each one parsed when it is asked for. Messages and imports
+/// are skipped.
+///
+/// The iterator ends after the first error, the first types being returned
+/// even when a later one is broken. References are left as they are written,
+/// as the types they point to may not be parsed yet.
+pub fn parse_protocol_iter(input: &str) -> ProtocolTypes<'_> {
+    let mut types = ProtocolTypes {
+        input,
+        rest: None,
+        namespace: None,
+        names: HashSet::new(),
+        warnings: Warnings::new(),
+        options: ParserOptions::default(),
+        failed: None,
+    };
+    match parse_protocol_header(input) {
+        Ok((rest, Declaration::Header { namespace, .. })) => {
+            types.rest = Some(rest);
+            types.namespace = namespace;
+        }
+        Ok(_) => unreachable!("the header is parsed as a header"),
+        Err(err) => types.failed = Some(AvdlError::from_nom(input, err)),
+    }
+    types
+}
+
+impl ProtocolTypes<'_> {
+    /// The warnings found in what has been parsed so far.
+    pub fn warnings(&self) -> &Warnings {
+        &self.warnings
+    }
+}
+
+impl Iterator for ProtocolTypes<'_> {
+    type Item = Result<Schema, AvdlError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(err) = self.failed.take() {
+            return Some(Err(err));
+        }
+        loop {
+            let rest = self.rest.take()?;
+            let parsed =
+                parse_declaration(self.input, rest, &mut self.warnings, &self.options, None);
+            let (tail, next) = match parsed {
+                Ok(parsed) => parsed,
+                Err(err) => return Some(Err(AvdlError::from_nom(self.input, err))),
+            };
+            let (start, mut schema) = match next {
+                Some((start, Declaration::Type(schema, _span))) => (start, schema),
+                Some(_) => {
+                    self.rest = Some(tail);
+                    continue;
+                }
+                // The body is closed, only comments may follow
+                None => return check_trailing(self.input, tail).err().map(Err),
+            };
+            if let Some(name) = schema.name() {
+                if !self.names.insert(name.clone()) {
+                    let err = ParserError::from_external_error(
+                        start,
+                        ErrorKind::MapRes,
+                        TokenError::Duplicate(name.fullname(None)),
+                    );
+                    return Some(Err(AvdlError::from_nom(self.input, nom::Err::Error(err))));
+                }
+            }
+            namespace_solver(&mut schema, &self.namespace);
+            self.rest = Some(tail);
+            return Some(Ok(schema));
+        }
+    }
+}
+
+fn emit(
+    declaration: Declaration,
+    on_event: &mut impl FnMut(ParseEvent) -> ControlFlow<()>,
+) -> ControlFlow<()> {
+    match declaration {
+        Declaration::Header {
+            doc,
+            namespace,
+            aliases,
+            properties,
+            name,
+        } => on_event(ParseEvent::ProtocolStart {
+            name,
+            namespace,
+            doc,
+            aliases,
+            properties,
+        }),
+        Declaration::Import(import) => on_event(ParseEvent::Import(import)),
+        Declaration::Type(mut schema, _span) => {
+            let fields = match &mut schema {
+                Schema::Record { fields, lookup, .. } => {
+                    lookup.clear();
+                    mem::take(fields)
+                }
+                _ => Vec::new(),
+            };
+            on_event(ParseEvent::TypeStart(schema))?;
+            for field in fields {
+                on_event(ParseEvent::Field(field))?;
+            }
+            on_event(ParseEvent::TypeEnd)
+        }
+        Declaration::Message(message) => on_event(ParseEvent::Message(message)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[test]
+    fn test_parse_events_stops_after_first_type() {
+        let input = "protocol P {
+    record First { string name; int age; }
+    record Second { string name; }
+    this is not valid
+}";
+        let mut events = Vec::new();
+        parse_events(input, &mut |event| {
+            let end = event == ParseEvent::TypeEnd;
+            events.push(event);
+            if end {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        })
+        .unwrap();
+        let [ParseEvent::ProtocolStart { name, .. }, ParseEvent::TypeStart(Schema::Record { name: record, .. }), ParseEvent::Field(first), ParseEvent::Field(second), ParseEvent::TypeEnd] =
+            &events[..]
+        else {
+            panic!("unexpected events {events:#?}");
+        };
+        assert_eq!(name, "P");
+        assert_eq!(record.name, "First");
+        assert_eq!((first.name.as_str(), second.name.as_str()), ("name", "age"));
+    }
+
+    #[test]
+    fn test_parse_events_reports_errors() {
+        let err = parse_events("protocol P { record R { string; } }", &mut |_event| {
+            ControlFlow::Continue(())
+        })
+        .unwrap_err();
+        assert!(matches!(err, AvdlError::Parse { .. }), "{err:?}");
+    }
+
+    #[test]
+    fn test_parse_protocol_iter_yields_types_before_an_error() {
+        let input = r#"@namespace("org.example")
+protocol P {
+    record First { string name; }
+    enum Second { A, B }
+    record Third { string name }
+    record Fourth { string name; }
+}"#;
+        let mut types = parse_protocol_iter(input);
+        let first = types.next().unwrap().unwrap();
+        assert_eq!(first.name().unwrap().fullname(None), "org.example.First");
+        let second = types.next().unwrap().unwrap();
+        assert_eq!(second.name().unwrap().name, "Second");
+        let err = types.next().unwrap().unwrap_err();
+        assert!(
+            matches!(&err, AvdlError::Parse { location, .. } if location.line == 5),
+            "{err:?}"
+        );
+        assert!(types.next().is_none());
+    }
+
+    #[rstest]
+    #[case("record R { string name; }")]
+    #[case("protocol P { record R { string name; } } }")]
+    #[case("protocol P { record R { string name; } record R { int id; } }")]
+    fn test_parse_protocol_iter_ends_with_the_error(#[case] input: &str) {
+        let results: Vec<_> = parse_protocol_iter(input).collect();
+        assert!(results.last().unwrap().is_err(), "{results:?}");
+        assert!(results[..results.len() - 1].iter().all(Result::is_ok));
+    }
+
+    #[test]
+    fn test_parse_protocol_iter_skips_messages() {
+        let input = "protocol P {
+    import idl \"other.avdl\";
+    record Greeting { string message; }
+    Greeting hello(Greeting greeting);
+    fixed Hash(16);
+}
+// done";
+        let names: Vec<String> = parse_protocol_iter(input)
+            .map(|schema| schema.unwrap().name().unwrap().name.clone())
+            .collect();
+        assert_eq!(names, vec!["Greeting", "Hash"]);
+    }
+}
@@ -0,0 +1,172 @@
+//! Making schemas self-contained, so each one can be read on its own.
+
+use std::collections::{HashMap, HashSet};
+
+use apache_avro::schema::{Name, Names, Namespace, Schema, UnionSchema};
+
+use crate::validate::{collect_names, declared_name};
+
+/// The named types of `schemas`, including the ones declared inside others,
+/// by name.
+pub fn registry(schemas: &[Schema]) -> Names {
+    let mut names = HashMap::new();
+    for schema in schemas {
+        collect_names(schema, &mut names);
+    }
+    names
+        .into_iter()
+        .map(|(name, schema)| (name, schema.clone()))
+        .collect()
+}
+
+/// Copy of `schema` where the first use of every named type is its
+/// definition and later uses are references, the way `avro-tools
+/// idl2schemata` writes schemas.
+///
+/// References are looked up in `registry`, and kept as they are when the
+/// type is not there. A record referencing itself is left as a reference.
+pub fn expand_refs(schema: &Schema, registry: &Names) -> Schema {
+    let mut defined = HashSet::new();
+    expand(schema, registry, &None, &mut defined)
+}
+
+fn expand(
+    schema: &Schema,
+    registry: &Names,
+    enclosing_namespace: &Namespace,
+    defined: &mut HashSet<Name>,
+) -> Schema {
+    match schema {
+        Schema::Record { name, .. } | Schema::Enum { name, .. } | Schema::Fixed { name, .. } => {
+            let fullname = name.fully_qualified_name(enclosing_namespace);
+            if !defined.insert(fullname.clone()) {
+                return Schema::Ref { name: name.clone() };
+            }
+            let mut schema = schema.clone();
+            if let Schema::Record { fields, .. } = &mut schema {
+                for field in fields.iter_mut() {
+                    field.schema = expand(&field.schema, registry, &fullname.namespace, defined);
+                }
+            }
+            schema
+        }
+        // Decimals stored in a fixed are named after it
+        Schema::Decimal { inner, .. } => match declared_name(inner) {
+            Some(name) if !defined.insert(name.fully_qualified_name(enclosing_namespace)) => {
+                Schema::Ref { name: name.clone() }
+            }
+            _ => schema.clone(),
+        },
+        Schema::Ref { name } => {
+            let fullname = name.fully_qualified_name(enclosing_namespace);
+            if defined.contains(&fullname) {
+                return schema.clone();
+            }
+            match registry.get(&fullname).or_else(|| registry.get(name)) {
+                Some(found) => expand(found, registry, enclosing_namespace, defined),
+                None => schema.clone(),
+            }
+        }
+        Schema::Array(items) => Schema::Array(Box::new(expand(
+            items,
+            registry,
+            enclosing_namespace,
+            defined,
+        ))),
+        Schema::Map(values) => Schema::Map(Box::new(expand(
+            values,
+            registry,
+            enclosing_namespace,
+            defined,
+        ))),
+        Schema::Union(union) => {
+            let mut expanded = defined.clone();
+            let variants = union
+                .variants()
+                .iter()
+                .map(|variant| expand(variant, registry, enclosing_namespace, &mut expanded))
+                .collect();
+            // A union can only hold one decimal, so one stored in a fixed is
+            // left as a reference next to another
+            match UnionSchema::new(variants) {
+                Ok(union) => {
+                    *defined = expanded;
+                    Schema::Union(union)
+                }
+                Err(_) => schema.clone(),
+            }
+        }
+        _ => schema.clone(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse;
+
+    fn field_schemas(schema: &Schema) -> Vec<&Schema> {
+        match schema {
+            Schema::Record { fields, .. } => fields.iter().map(|field| &field.schema).collect(),
+            _ => panic!("expected a record, got {schema:?}"),
+        }
+    }
+
+    #[test]
+    fn test_expand_refs_defines_each_type_once() {
+        let (_tail, schemas) = parse(
+            "protocol P {
+    enum Country { CH, AR }
+    record Address { Country country; }
+    record Person { Address home; Address work; Country born; }
+}",
+        )
+        .unwrap();
+        let person = expand_refs(&schemas[2], &registry(&schemas));
+        let [home, work, born] = field_schemas(&person)[..] else {
+            panic!("expected three fields");
+        };
+        assert!(matches!(home, Schema::Record { .. }));
+        assert!(matches!(field_schemas(home)[0], Schema::Enum { .. }));
+        assert!(matches!(work, Schema::Ref { name } if name.name == "Address"));
+        assert!(matches!(born, Schema::Ref { name } if name.name == "Country"));
+    }
+
+    #[test]
+    fn test_expand_refs_recursive_record() {
+        let (_tail, schemas) = parse(
+            "protocol P {
+    record Node { string value; union { null, Node } next = null; }
+}",
+        )
+        .unwrap();
+        let node = expand_refs(&schemas[0], &registry(&schemas));
+        let Schema::Union(next) = field_schemas(&node)[1] else {
+            panic!("expected a union");
+        };
+        assert!(matches!(&next.variants()[1], Schema::Ref { name } if name.name == "Node"));
+    }
+
+    #[test]
+    fn test_expand_refs_decimal_in_fixed() {
+        let (_tail, schemas) = parse(
+            r#"protocol P {
+    @logicalType("decimal") @precision(9) @scale(2) fixed Amount(4);
+    record Order { Amount total; Amount tip; union { null, decimal(4, 2), Amount } refund; }
+}"#,
+        )
+        .unwrap();
+        let order = expand_refs(&schemas[1], &registry(&schemas));
+        let [total, tip, refund] = field_schemas(&order)[..] else {
+            panic!("expected three fields");
+        };
+        assert!(
+            matches!(total, Schema::Decimal { inner, .. } if matches!(**inner, Schema::Fixed { .. }))
+        );
+        assert!(matches!(tip, Schema::Ref { name } if name.name == "Amount"));
+        let Schema::Union(refund) = refund else {
+            panic!("expected a union");
+        };
+        assert!(matches!(&refund.variants()[2], Schema::Ref { name } if name.name == "Amount"));
+    }
+}
@@ -0,0 +1,107 @@
+//! Fingerprints of schemas, as defined by the Avro specification.
+
+use apache_avro::schema::{Schema, SchemaFingerprint};
+use digest::Digest;
+
+use crate::canonical::canonical_form;
+
+const EMPTY: u64 = 0xc15d213aa4d7a795;
+
+// Lookup table of the CRC-64-AVRO polynomial, one entry per byte value
+const TABLE: [u64; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut fingerprint = i as u64;
+        let mut bit = 0;
+        while bit < 8 {
+            fingerprint = (fingerprint >> 1) ^ (EMPTY & (fingerprint & 1).wrapping_neg());
+            bit += 1;
+        }
+        table[i] = fingerprint;
+        i += 1;
+    }
+    table
+};
+
+/// 64-bit Rabin fingerprint of `bytes`, the algorithm given by the
+/// specification.
+pub fn rabin(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(EMPTY, |fingerprint, byte| {
+        (fingerprint >> 8) ^ TABLE[((fingerprint ^ *byte as u64) & 0xff) as usize]
+    })
+}
+
+/// Fingerprints computed over the Parsing Canonical Form of a schema, as
+/// written by [`canonical_form`].
+pub trait Fingerprint {
+    /// The 64-bit Rabin fingerprint, the one used by single object encoding
+    /// and most schema registries.
+    fn fingerprint_rabin(&self) -> u64;
+
+    /// Fingerprint with the digest `D`, e.g. `fingerprint_digest::<Sha256>()`.
+    fn fingerprint_digest<D: Digest>(&self) -> SchemaFingerprint;
+}
+
+impl Fingerprint for Schema {
+    fn fingerprint_rabin(&self) -> u64 {
+        rabin(canonical_form(self).as_bytes())
+    }
+
+    fn fingerprint_digest<D: Digest>(&self) -> SchemaFingerprint {
+        SchemaFingerprint {
+            bytes: D::digest(canonical_form(self).as_bytes()).to_vec(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::avsc::from_avsc_str;
+    use md5::Md5;
+    use rstest::rstest;
+    use sha2::Sha256;
+
+    // Test vectors of the Avro specification, as signed longs the way the
+    // Java implementation prints them
+    #[rstest]
+    #[case(r#""null""#, 7195948357588979594)]
+    #[case(r#"{"type": "null"}"#, 7195948357588979594)]
+    #[case(r#""boolean""#, -6970731678124411036)]
+    #[case(r#""int""#, 8247732601305521295)]
+    #[case(r#""long""#, -3434872931120570953)]
+    #[case(r#""string""#, -8142146995180207161)]
+    #[case(r#"{"type": "fixed", "name": "foo", "size": 15}"#, 1756455273707447556)]
+    #[case(
+        r#"{"type": "enum", "name": "foo", "symbols": ["A1"], "doc": "ignored"}"#,
+        -6342190197741309591
+    )]
+    #[case(
+        r#"{"type": "record", "name": "foo", "fields": [{"name": "f1", "type": "boolean"}]}"#,
+        7843277075252814651
+    )]
+    #[case(r#"{"type": "array", "items": "int"}"#, 5920968314789803198)]
+    #[case(r#"{"type": "map", "values": "string"}"#, -8732877298790414990)]
+    fn test_fingerprint_rabin(#[case] input: &str, #[case] expected: i64) {
+        let schema = from_avsc_str(input).unwrap();
+        assert_eq!(schema.fingerprint_rabin() as i64, expected);
+    }
+
+    #[rstest]
+    #[case(
+        r#""int""#,
+        "3f2b87a9fe7cc9b13835598c3981cd45e3e355309e5090aa0933d7becb6fba45",
+        "ef524ea1b91e73173d938ade36c1db32"
+    )]
+    #[case(
+        r#"{"type": "record", "name": "foo", "fields": [{"name": "f1", "type": "boolean"}]}"#,
+        "87c762d52b47d4508fa3eaffb75040e83ce042e73d214a986319f3d58d3cc206",
+        "a4678ca1c06038a1246f38953c3effab"
+    )]
+    fn test_fingerprint_digests(#[case] input: &str, #[case] sha256: &str, #[case] md5: &str) {
+        let schema = from_avsc_str(input).unwrap();
+        assert_eq!(schema.fingerprint_digest::<Sha256>().to_string(), sha256);
+        assert_eq!(schema.fingerprint_digest::<Md5>().to_string(), md5);
+    }
+}
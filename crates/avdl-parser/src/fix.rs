@@ -0,0 +1,216 @@
+//! Rewrites of IDL sources fixing common mistakes, keeping the rest of the
+//! text untouched.
+
+use std::fmt;
+
+use apache_avro::schema::Schema;
+
+use crate::error::{AvdlError, Span};
+use crate::parser::{parse, parse_field};
+
+/// A place the rewriter changed, or left alone with the reason.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixNote {
+    pub span: Span,
+    pub applied: bool,
+    pub message: String,
+}
+
+impl fmt::Display for FixNote {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = if self.applied { "fixed" } else { "skipped" };
+        write!(f, "{status}: {}", self.message)
+    }
+}
+
+/// Move `null` to the front of the unions of record fields which have it
+/// after another type.
+///
+/// A union is only reordered when the field has no default, since a default
+/// must match the first type and reordering would invalidate it. The input
+/// must parse.
+pub fn fix_union_null_order(input: &str) -> Result<(String, Vec<FixNote>), AvdlError> {
+    parse(input).map_err(|err| AvdlError::from_nom(input, err))?;
+
+    let mut output = String::with_capacity(input.len());
+    let mut notes = Vec::new();
+    let mut copied = 0;
+    for start in union_keywords(input) {
+        if start < copied {
+            continue;
+        }
+        let declaration = &input[start..];
+        let (tail, (schema, _doc, _order, _aliases, field, default)) =
+            match parse_field(declaration) {
+                Ok(parsed) => parsed,
+                // Not a field, e.g. a union nested in an array
+                Err(_) => continue,
+            };
+        let variants = match &schema {
+            Schema::Union(union) => union.variants(),
+            _ => continue,
+        };
+        if !matches!(variants.iter().position(|v| *v == Schema::Null), Some(p) if p > 0) {
+            continue;
+        }
+        let (open, close) = match union_braces(declaration) {
+            Some(braces) => braces,
+            None => continue,
+        };
+        let span = Span::of(input, &declaration[..declaration.len() - tail.len()]);
+        if default.is_some() {
+            notes.push(FixNote {
+                span,
+                applied: false,
+                message: format!(
+                    "`{field}` has a default, which must match the first type of the union"
+                ),
+            });
+            continue;
+        }
+        let members = split_members(&declaration[open + 1..close]);
+        let (nulls, others): (Vec<&str>, Vec<&str>) =
+            members.into_iter().partition(|member| *member == "null");
+        let reordered: Vec<&str> = nulls.into_iter().chain(others).collect();
+
+        output.push_str(&input[copied..start + open]);
+        output.push_str(&format!("{{ {} }}", reordered.join(", ")));
+        copied = start + close + 1;
+        notes.push(FixNote {
+            span,
+            applied: true,
+            message: format!("moved null first in the union of `{field}`"),
+        });
+    }
+    output.push_str(&input[copied..]);
+    Ok((output, notes))
+}
+
+// Offsets of the `union` keywords outside of comments and strings
+fn union_keywords(input: &str) -> Vec<usize> {
+    let is_ident = |c: u8| c.is_ascii_alphanumeric() || c == b'_';
+    let bytes = input.as_bytes();
+    let mut found = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let rest = &input[i..];
+        if rest.starts_with("//") {
+            i += rest.find('\n').unwrap_or(rest.len());
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            i += comment.find("*/").map(|end| end + 4).unwrap_or(rest.len());
+        } else if bytes[i] == b'"' {
+            i += 1;
+            while i < bytes.len() && bytes[i] != b'"' {
+                i += if bytes[i] == b'\\' { 2 } else { 1 };
+            }
+            i += 1;
+        } else if rest.starts_with("union")
+            && (i == 0 || !is_ident(bytes[i - 1]))
+            && !rest[5..].bytes().next().map(is_ident).unwrap_or(false)
+        {
+            found.push(i);
+            i += 5;
+        } else {
+            i += rest.chars().next().map(char::len_utf8).unwrap_or(1);
+        }
+    }
+    found
+}
+
+// Offsets of the braces around the union members
+fn union_braces(declaration: &str) -> Option<(usize, usize)> {
+    let open = declaration.find('{')?;
+    let mut depth = 0;
+    for (i, c) in declaration[open..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some((open, open + i));
+                }
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+// Members of a union body, split on the commas which are not nested
+fn split_members(body: &str) -> Vec<&str> {
+    let mut members = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    let mut in_string = false;
+    for (i, c) in body.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '<' | '(' | '[' | '{' if !in_string => depth += 1,
+            '>' | ')' | ']' | '}' if !in_string => depth -= 1,
+            ',' if !in_string && depth == 0 => {
+                members.push(body[start..i].trim());
+                start = i + 1;
+            }
+            _ => (),
+        }
+    }
+    members.push(body[start..].trim());
+    members
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::error::Location;
+
+    #[test]
+    fn test_fix_union_null_order() {
+        let input = "protocol P {
+    record User {
+        union { string, null } nickname;
+        union { null, string } email = null;
+        // union { string, null } commented;
+        union { array<int>, null } scores;
+    }
+}";
+        let (output, notes) = fix_union_null_order(input).unwrap();
+        assert_eq!(
+            output,
+            "protocol P {
+    record User {
+        union { null, string } nickname;
+        union { null, string } email = null;
+        // union { string, null } commented;
+        union { null, array<int> } scores;
+    }
+}"
+        );
+        assert_eq!(notes.len(), 2);
+        assert!(notes.iter().all(|note| note.applied));
+        assert_eq!(
+            notes[0].to_string(),
+            "fixed: moved null first in the union of `nickname`"
+        );
+        assert_eq!(
+            notes[0].span.location(input),
+            Location { line: 3, column: 9 }
+        );
+    }
+
+    #[test]
+    fn test_fix_union_null_order_keeps_non_null_default() {
+        let input = r#"protocol P {
+    record User {
+        union { string, null } country = "CH";
+    }
+}"#;
+        let (output, notes) = fix_union_null_order(input).unwrap();
+        assert_eq!(output, input);
+        assert_eq!(notes.len(), 1);
+        assert!(!notes[0].applied);
+        assert_eq!(
+            notes[0].to_string(),
+            "skipped: `country` has a default, which must match the first type of the union"
+        );
+    }
+}
@@ -0,0 +1,690 @@
+//! Dependency graphs between files and between named types, rendered as
+//! Graphviz DOT.
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+
+use apache_avro::schema::{Name, Namespace, Schema};
+
+use crate::error::AvdlError;
+use crate::protocol::Protocol;
+use crate::validate::declared_name;
+
+/// What a node of a [`Graph`] stands for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    File,
+    Record,
+    /// A record thrown by a message.
+    Error,
+    Enum,
+    Fixed,
+    /// Referenced, but not declared in the files the graph was built from.
+    External,
+}
+
+impl NodeKind {
+    fn dot_attributes(self) -> &'static str {
+        match self {
+            NodeKind::File => "shape=note",
+            NodeKind::Record => "shape=box",
+            NodeKind::Error => "shape=octagon",
+            NodeKind::Enum => "shape=ellipse",
+            NodeKind::Fixed => "shape=diamond",
+            NodeKind::External => "shape=box, style=dashed",
+        }
+    }
+}
+
+/// How a record uses a named type, the label of the edge between them.
+///
+/// Types inside unions, arrays and maps are told apart by what holds them
+/// directly, e.g. `union { null, array<Item> }` holds `Item` as an element.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Reference {
+    /// The type of a field.
+    Field,
+    /// A branch of a union.
+    UnionMember,
+    /// The items of an array, or the values of a map.
+    Element,
+}
+
+impl Reference {
+    fn label(self) -> &'static str {
+        match self {
+            Reference::Field => "field of",
+            Reference::UnionMember => "union member of",
+            Reference::Element => "element of",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node {
+    pub name: String,
+    pub kind: NodeKind,
+}
+
+/// A directed graph, edges go from a node to the nodes it depends on.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Graph {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<(usize, usize)>,
+    /// How the source of an edge uses its target, for type graphs.
+    pub references: BTreeMap<(usize, usize), Vec<Reference>>,
+}
+
+impl Graph {
+    // Index of the node called `name`, added if missing. A declared kind
+    // replaces `External`.
+    fn node(&mut self, name: &str, kind: NodeKind) -> usize {
+        match self.nodes.iter().position(|node| node.name == name) {
+            Some(i) => {
+                if self.nodes[i].kind == NodeKind::External {
+                    self.nodes[i].kind = kind;
+                }
+                i
+            }
+            None => {
+                self.nodes.push(Node {
+                    name: name.to_string(),
+                    kind,
+                });
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    fn edge(&mut self, from: usize, to: usize) {
+        if !self.edges.contains(&(from, to)) {
+            self.edges.push((from, to));
+        }
+    }
+
+    fn reference(&mut self, from: usize, to: usize, reference: Reference) {
+        self.edge(from, to);
+        let references = self.references.entry((from, to)).or_default();
+        if !references.contains(&reference) {
+            references.push(reference);
+            references.sort();
+        }
+    }
+
+    /// Nodes which are part of a cycle.
+    pub fn cycles(&self) -> HashSet<usize> {
+        let mut tarjan = Tarjan {
+            graph: self,
+            index: vec![None; self.nodes.len()],
+            low: vec![0; self.nodes.len()],
+            stack: Vec::new(),
+            on_stack: vec![false; self.nodes.len()],
+            next: 0,
+            cycles: HashSet::new(),
+        };
+        for node in 0..self.nodes.len() {
+            if tarjan.index[node].is_none() {
+                tarjan.visit(node);
+            }
+        }
+        tarjan.cycles
+    }
+
+    /// Render the graph in the DOT language, with nodes and edges sorted by
+    /// name. Nodes and edges of cycles are drawn in red, and the nodes get the
+    /// `cycle` class.
+    pub fn to_dot(&self, name: &str) -> String {
+        self.render_dot(name, false)
+    }
+
+    /// Render the graph like [`Graph::to_dot`], with the named types of each
+    /// namespace in a cluster.
+    pub fn to_dot_with_namespace_clusters(&self, name: &str) -> String {
+        self.render_dot(name, true)
+    }
+
+    fn render_dot(&self, name: &str, namespace_clusters: bool) -> String {
+        let cycles = self.cycles();
+        let mut nodes: Vec<usize> = (0..self.nodes.len()).collect();
+        nodes.sort_by(|a, b| self.nodes[*a].name.cmp(&self.nodes[*b].name));
+        let node_line = |i: usize, indent: &str| {
+            let node = &self.nodes[i];
+            let mut attributes = node.kind.dot_attributes().to_string();
+            if cycles.contains(&i) {
+                attributes.push_str(", class=\"cycle\", color=\"red\"");
+            }
+            format!("{indent}{} [{attributes}];\n", dot_id(&node.name))
+        };
+
+        let mut dot = format!("digraph {} {{\n", dot_id(name));
+        let mut clusters: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+        for &i in &nodes {
+            let node = &self.nodes[i];
+            match node.name.rsplit_once('.') {
+                Some((namespace, _)) if namespace_clusters && node.kind != NodeKind::File => {
+                    clusters.entry(namespace).or_default().push(i)
+                }
+                _ => dot.push_str(&node_line(i, "    ")),
+            }
+        }
+        for (namespace, nodes) in clusters {
+            dot.push_str(&format!(
+                "    subgraph {} {{\n        label={};\n",
+                dot_id(&format!("cluster_{namespace}")),
+                dot_id(namespace)
+            ));
+            for i in nodes {
+                dot.push_str(&node_line(i, "        "));
+            }
+            dot.push_str("    }\n");
+        }
+
+        let mut edges = self.edges.clone();
+        edges.sort_by(|(a, b), (c, d)| {
+            let name = |i: &usize| &self.nodes[*i].name;
+            (name(a), name(b)).cmp(&(name(c), name(d)))
+        });
+        for (from, to) in edges {
+            let (source, target) = (&self.nodes[from].name, &self.nodes[to].name);
+            dot.push_str(&format!("    {} -> {}", dot_id(source), dot_id(target)));
+            let mut attributes = Vec::new();
+            if let Some(references) = self.references.get(&(from, to)) {
+                let labels: Vec<&str> = references.iter().map(|r| r.label()).collect();
+                attributes.push(format!("label={}", dot_id(&labels.join(", "))));
+            }
+            if cycles.contains(&from) && cycles.contains(&to) && self.reaches(to, from) {
+                attributes.push("color=\"red\"".to_string());
+            }
+            if !attributes.is_empty() {
+                dot.push_str(&format!(" [{}]", attributes.join(", ")));
+            }
+            dot.push_str(";\n");
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    fn reaches(&self, from: usize, to: usize) -> bool {
+        let mut seen = HashSet::new();
+        let mut pending = vec![from];
+        while let Some(node) = pending.pop() {
+            if node == to {
+                return true;
+            }
+            if seen.insert(node) {
+                pending.extend(
+                    self.edges
+                        .iter()
+                        .filter(|(source, _)| *source == node)
+                        .map(|(_, target)| *target),
+                );
+            }
+        }
+        false
+    }
+}
+
+// Strongly connected components, the ones with more than a node or with a
+// node depending on itself are cycles
+struct Tarjan<'a> {
+    graph: &'a Graph,
+    index: Vec<Option<usize>>,
+    low: Vec<usize>,
+    stack: Vec<usize>,
+    on_stack: Vec<bool>,
+    next: usize,
+    cycles: HashSet<usize>,
+}
+
+impl Tarjan<'_> {
+    fn visit(&mut self, node: usize) {
+        self.index[node] = Some(self.next);
+        self.low[node] = self.next;
+        self.next += 1;
+        self.stack.push(node);
+        self.on_stack[node] = true;
+
+        let graph = self.graph;
+        // A type referencing itself, e.g. a linked list, is no cycle
+        for &(_, target) in graph
+            .edges
+            .iter()
+            .filter(|(source, target)| *source == node && *target != node)
+        {
+            match self.index[target] {
+                None => {
+                    self.visit(target);
+                    self.low[node] = self.low[node].min(self.low[target]);
+                }
+                Some(index) if self.on_stack[target] => {
+                    self.low[node] = self.low[node].min(index);
+                }
+                Some(_) => (),
+            }
+        }
+
+        if Some(self.low[node]) == self.index[node] {
+            let mut component = Vec::new();
+            while let Some(member) = self.stack.pop() {
+                self.on_stack[member] = false;
+                component.push(member);
+                if member == node {
+                    break;
+                }
+            }
+            if component.len() > 1 || graph.edges.contains(&(node, node)) {
+                self.cycles.extend(component);
+            }
+        }
+    }
+}
+
+/// Graph of the `import` statements between `files`.
+///
+/// Imported paths are relative to the importing file. Files which are
+/// imported but not given, e.g. `.avsc` schemas, are `External` nodes.
+pub fn file_graph(files: &[(PathBuf, Protocol)]) -> Graph {
+    let mut graph = Graph::default();
+    for (path, _) in files {
+        graph.node(&normalize(path).display().to_string(), NodeKind::File);
+    }
+    for (path, protocol) in files {
+        let from = graph.node(&normalize(path).display().to_string(), NodeKind::File);
+        let folder = path.parent().unwrap_or(Path::new(""));
+        for import in &protocol.imports {
+            let imported = normalize(&folder.join(&import.path));
+            let to = graph.node(&imported.display().to_string(), NodeKind::External);
+            graph.edge(from, to);
+        }
+    }
+    graph
+}
+
+/// Graph of the references between the named types of `protocols`, by full
+/// name, with the ways records use each type. Records thrown by a message are
+/// `Error` nodes.
+pub fn type_graph(protocols: &[Protocol]) -> Graph {
+    let mut graph = Graph::default();
+    for protocol in protocols {
+        add_types(&mut graph, &protocol.types, &protocol.namespace);
+    }
+    for protocol in protocols {
+        add_references(&mut graph, &protocol.types, &protocol.namespace);
+        for message in &protocol.messages {
+            for error in message.errors.iter().flat_map(references) {
+                let i = graph.node(&fullname(error, &protocol.namespace), NodeKind::Error);
+                if graph.nodes[i].kind == NodeKind::Record {
+                    graph.nodes[i].kind = NodeKind::Error;
+                }
+            }
+        }
+    }
+    graph
+}
+
+/// The named types of `schemas`, each one after the types it references.
+///
+/// Types keep their relative order when they do not depend on each other.
+/// References to types which are not in `schemas` are ignored. Fails with
+/// [`AvdlError::ReferenceCycle`] when types reference each other in a cycle,
+/// since no order works then.
+pub fn topological_order<'a>(
+    schemas: &'a [Schema],
+    namespace: &Namespace,
+) -> Result<Vec<&'a Schema>, AvdlError> {
+    let named: Vec<&Schema> = schemas
+        .iter()
+        .filter(|schema| declared_name(schema).is_some())
+        .collect();
+    let mut graph = Graph::default();
+    add_types(&mut graph, schemas, namespace);
+    add_references(&mut graph, schemas, namespace);
+
+    let mut sorter = TopologicalSort {
+        graph: &graph,
+        visiting: Vec::new(),
+        done: vec![false; graph.nodes.len()],
+        order: Vec::new(),
+    };
+    for node in 0..named.len() {
+        sorter.visit(node)?;
+    }
+    Ok(sorter
+        .order
+        .into_iter()
+        .filter_map(|node| named.get(node).copied())
+        .collect())
+}
+
+// Depth first, a node is added once everything it references is
+struct TopologicalSort<'a> {
+    graph: &'a Graph,
+    visiting: Vec<usize>,
+    done: Vec<bool>,
+    order: Vec<usize>,
+}
+
+impl TopologicalSort<'_> {
+    fn visit(&mut self, node: usize) -> Result<(), AvdlError> {
+        if self.done[node] {
+            return Ok(());
+        }
+        if let Some(start) = self.visiting.iter().position(|visiting| *visiting == node) {
+            let names = self.visiting[start..]
+                .iter()
+                .chain(Some(&node))
+                .map(|node| self.graph.nodes[*node].name.clone())
+                .collect();
+            return Err(AvdlError::ReferenceCycle(names));
+        }
+        self.visiting.push(node);
+        let graph = self.graph;
+        // A type referencing itself, e.g. a linked list, is no cycle
+        for &(_, target) in graph
+            .edges
+            .iter()
+            .filter(|(source, target)| *source == node && *target != node)
+        {
+            self.visit(target)?;
+        }
+        self.visiting.pop();
+        self.done[node] = true;
+        self.order.push(node);
+        Ok(())
+    }
+}
+
+fn add_types(graph: &mut Graph, schemas: &[Schema], namespace: &Namespace) {
+    for schema in schemas {
+        let (name, kind) = match schema {
+            Schema::Record { name, .. } => (name, NodeKind::Record),
+            Schema::Enum { name, .. } => (name, NodeKind::Enum),
+            Schema::Fixed { name, .. } => (name, NodeKind::Fixed),
+            Schema::Decimal { inner, .. } => match inner.as_ref() {
+                Schema::Fixed { name, .. } => (name, NodeKind::Fixed),
+                _ => continue,
+            },
+            _ => continue,
+        };
+        graph.node(&fullname(name, namespace), kind);
+    }
+}
+
+fn add_references(graph: &mut Graph, schemas: &[Schema], namespace: &Namespace) {
+    for schema in schemas {
+        if let Schema::Record { name, fields, .. } = schema {
+            let from = graph.node(&fullname(name, namespace), NodeKind::Record);
+            for field in fields {
+                for (name, reference) in typed_references(&field.schema, Reference::Field) {
+                    let to = graph.node(&fullname(name, namespace), NodeKind::External);
+                    graph.reference(from, to, reference);
+                }
+            }
+        }
+    }
+}
+
+fn fullname(name: &Name, namespace: &Namespace) -> String {
+    name.fully_qualified_name(namespace).fullname(None)
+}
+
+// Named types `schema` uses, without looking inside them
+fn references(schema: &Schema) -> Vec<&Name> {
+    typed_references(schema, Reference::Field)
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect()
+}
+
+// Named types `schema` uses and how, `reference` when `schema` is one
+fn typed_references(schema: &Schema, reference: Reference) -> Vec<(&Name, Reference)> {
+    match schema {
+        Schema::Record { name, .. }
+        | Schema::Enum { name, .. }
+        | Schema::Fixed { name, .. }
+        | Schema::Ref { name } => vec![(name, reference)],
+        Schema::Array(inner) | Schema::Map(inner) => typed_references(inner, Reference::Element),
+        Schema::Union(union) => union
+            .variants()
+            .iter()
+            .flat_map(|variant| typed_references(variant, Reference::UnionMember))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+// Drop the `.` and `a/..` parts of a path, without touching the filesystem
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir
+                if matches!(
+                    normalized.components().next_back(),
+                    Some(Component::Normal(_))
+                ) =>
+            {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+// A quoted DOT identifier
+fn dot_id(name: &str) -> String {
+    format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::options::ParserOptions;
+    use crate::parser::parse_protocol_with_options;
+    use rstest::rstest;
+
+    fn fixtures() -> Vec<(PathBuf, Protocol)> {
+        let options = ParserOptions {
+            allow_unresolved_refs: true,
+            ..Default::default()
+        };
+        [
+            (
+                "graph/a.avdl",
+                include_str!("../../../tests/samples/graph/a.avdl"),
+            ),
+            (
+                "graph/b.avdl",
+                include_str!("../../../tests/samples/graph/b.avdl"),
+            ),
+            (
+                "graph/c.avdl",
+                include_str!("../../../tests/samples/graph/c.avdl"),
+            ),
+            (
+                "graph/d.avdl",
+                include_str!("../../../tests/samples/graph/d.avdl"),
+            ),
+            (
+                "graph/e.avdl",
+                include_str!("../../../tests/samples/graph/e.avdl"),
+            ),
+            (
+                "graph/f.avdl",
+                include_str!("../../../tests/samples/graph/f.avdl"),
+            ),
+        ]
+        .into_iter()
+        .map(|(path, input)| {
+            let (protocol, _warnings) = parse_protocol_with_options(input, &options).unwrap();
+            (PathBuf::from(path), protocol)
+        })
+        .collect()
+    }
+
+    #[test]
+    fn test_file_graph_to_dot() {
+        let dot = file_graph(&fixtures()).to_dot("files");
+        assert_eq!(
+            dot,
+            r#"digraph "files" {
+    "graph/a.avdl" [shape=note];
+    "graph/b.avdl" [shape=note];
+    "graph/c.avdl" [shape=note];
+    "graph/d.avdl" [shape=note];
+    "graph/e.avdl" [shape=note, class="cycle", color="red"];
+    "graph/f.avdl" [shape=note, class="cycle", color="red"];
+    "graph/a.avdl" -> "graph/b.avdl";
+    "graph/a.avdl" -> "graph/c.avdl";
+    "graph/b.avdl" -> "graph/d.avdl";
+    "graph/c.avdl" -> "graph/d.avdl";
+    "graph/e.avdl" -> "graph/f.avdl" [color="red"];
+    "graph/f.avdl" -> "graph/e.avdl" [color="red"];
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_type_graph_to_dot() {
+        let protocols: Vec<Protocol> = fixtures().into_iter().map(|(_, p)| p).collect();
+        let dot = type_graph(&protocols).to_dot("types");
+        assert_eq!(
+            dot,
+            r#"digraph "types" {
+    "Bottom" [shape=box];
+    "Color" [shape=ellipse];
+    "Hash" [shape=diamond];
+    "Left" [shape=box];
+    "Oops" [shape=octagon];
+    "Ping" [shape=box, class="cycle", color="red"];
+    "Pong" [shape=box, class="cycle", color="red"];
+    "Right" [shape=box];
+    "Top" [shape=box];
+    "Bottom" -> "Color" [label="field of"];
+    "Bottom" -> "Hash" [label="field of"];
+    "Left" -> "Bottom" [label="field of"];
+    "Ping" -> "Pong" [label="union member of", color="red"];
+    "Pong" -> "Ping" [label="union member of", color="red"];
+    "Right" -> "Bottom" [label="field of"];
+    "Top" -> "Left" [label="field of"];
+    "Top" -> "Right" [label="field of"];
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_type_graph_namespace_clusters() {
+        let protocols = [
+            r#"@namespace("org.common")
+protocol Common {
+    enum Currency { EUR, USD }
+    record Money { long cents; Currency currency; }
+}"#,
+            r#"@namespace("org.shop")
+protocol Shop {
+    record Item { string id; }
+    record Order {
+        array<Item> items;
+        union { null, Item } gift = null;
+        map<Item> by_id;
+        union { null, array<Item> } returns = null;
+    }
+}"#,
+        ]
+        .map(|input| {
+            let (protocol, _warnings) =
+                parse_protocol_with_options(input, &ParserOptions::default()).unwrap();
+            protocol
+        });
+        let dot = type_graph(&protocols).to_dot_with_namespace_clusters("types");
+        assert_eq!(
+            dot,
+            r#"digraph "types" {
+    subgraph "cluster_org.common" {
+        label="org.common";
+        "org.common.Currency" [shape=ellipse];
+        "org.common.Money" [shape=box];
+    }
+    subgraph "cluster_org.shop" {
+        label="org.shop";
+        "org.shop.Item" [shape=box];
+        "org.shop.Order" [shape=box];
+    }
+    "org.common.Money" -> "org.common.Currency" [label="field of"];
+    "org.shop.Order" -> "org.shop.Item" [label="union member of, element of"];
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_file_graph_external_import() {
+        let (protocol, _warnings) = parse_protocol_with_options(
+            r#"protocol P { import schema "../schemas/User.avsc"; }"#,
+            &ParserOptions::default(),
+        )
+        .unwrap();
+        let graph = file_graph(&[(PathBuf::from("idl/p.avdl"), protocol)]);
+        assert_eq!(graph.nodes[1].name, "schemas/User.avsc");
+        assert_eq!(graph.nodes[1].kind, NodeKind::External);
+    }
+
+    #[rstest]
+    #[case("Plain", r#""Plain""#)]
+    #[case(r#"say "hi""#, r#""say \"hi\"""#)]
+    #[case(r"C:\avro", r#""C:\\avro""#)]
+    fn test_dot_id(#[case] name: &str, #[case] expected: &str) {
+        assert_eq!(dot_id(name), expected);
+    }
+
+    #[test]
+    fn test_topological_order() {
+        let (_tail, schemas) = crate::parser::parse(
+            "protocol P {
+    record Person { string name; Address address; }
+    record Address { Country country; union { null, Country } previous = null; }
+    enum Country { CH, AR }
+    record Unrelated { string id; }
+}",
+        )
+        .unwrap();
+        let names: Vec<String> = topological_order(&schemas, &None)
+            .unwrap()
+            .into_iter()
+            .filter_map(|schema| schema.name().map(|name| name.name.clone()))
+            .collect();
+        assert_eq!(names, vec!["Country", "Address", "Person", "Unrelated"]);
+    }
+
+    #[test]
+    fn test_topological_order_recursive() {
+        let (_tail, schemas) = crate::parser::parse(
+            "protocol P {
+    record Node { string value; union { null, Node } next = null; }
+}",
+        )
+        .unwrap();
+        assert_eq!(topological_order(&schemas, &None).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_topological_order_cycle() {
+        let (_tail, schemas) = crate::parser::parse(
+            "protocol P {
+    record Start { Ping ping; }
+    record Ping { union { null, Pong } pong = null; }
+    record Pong { union { null, Ping } ping = null; }
+}",
+        )
+        .unwrap();
+        let err = topological_order(&schemas, &None).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "named types reference each other in a cycle: Ping -> Pong -> Ping"
+        );
+    }
+}
@@ -0,0 +1,750 @@
+//! Writing protocols back as Avro IDL.
+//!
+//! The output is deterministic and indented with 4 spaces, so rendering a
+//! parsed file also works as a formatter.
+
+use std::collections::HashSet;
+
+use apache_avro::schema::{Alias, Name, Namespace, RecordField, RecordFieldOrder, Schema};
+use serde_json::Value;
+
+use crate::error::{AvdlError, Span};
+use crate::options::ParserOptions;
+use crate::parser::{is_property_key, parse_protocol_with_options};
+use crate::protocol::{ImportKind, Message, Protocol};
+use crate::validate::declared_name;
+use crate::visit::{SchemaVisitorMut, Walk};
+
+const INDENT: &str = "    ";
+const FIELD_INDENT: &str = "        ";
+
+/// Render `protocol` as Avro IDL.
+///
+/// Parsing the output gives back an equal protocol. Named types only used
+/// inside other types, as found in `.avpr` files, are declared before the
+/// first type using them. Properties whose key is not a valid annotation
+/// name, as may be found in `.avpr` files, are an error.
+pub fn to_idl(protocol: &Protocol) -> Result<String, AvdlError> {
+    if let Some(key) = protocol.properties.keys().find(|key| !is_property_key(key)) {
+        return Err(AvdlError::UnwritableProperty(key.clone()));
+    }
+    Ok(write_idl(protocol))
+}
+
+fn write_idl(protocol: &Protocol) -> String {
+    let mut writer = IdlWriter {
+        namespace: &protocol.namespace,
+        top_level: HashSet::new(),
+        written: HashSet::new(),
+        declarations: Vec::new(),
+    };
+    writer.top_level = protocol
+        .types
+        .iter()
+        .filter_map(declared_name)
+        .map(|name| writer.key(name))
+        .collect();
+    if !protocol.imports.is_empty() {
+        let imports: Vec<String> = protocol
+            .imports
+            .iter()
+            .map(|import| {
+                let kind = match import.kind {
+                    ImportKind::Idl => "idl",
+                    ImportKind::Protocol => "protocol",
+                    ImportKind::Schema => "schema",
+                };
+                format!("{INDENT}import {kind} {};\n", string_literal(&import.path))
+            })
+            .collect();
+        writer.declarations.push(imports.concat());
+    }
+    for schema in &protocol.types {
+        writer.declare(schema);
+    }
+    for message in &protocol.messages {
+        for parameter in &message.request {
+            writer.declare_nested(&parameter.schema);
+        }
+        writer.declare_nested(&message.response);
+        for schema in &message.errors {
+            writer.declare_nested(schema);
+        }
+        writer.declarations.push(message_declaration(message));
+    }
+
+    let mut idl = doc_comment(&protocol.doc, "");
+    if let Some(namespace) = &protocol.namespace {
+        idl.push_str(&format!("@namespace(\"{namespace}\")\n"));
+    }
+    if !protocol.aliases.is_empty() {
+        let aliases: Vec<String> = protocol
+            .aliases
+            .iter()
+            .map(|alias| string_literal(alias))
+            .collect();
+        idl.push_str(&format!("@aliases([{}])\n", aliases.join(", ")));
+    }
+    for (key, value) in &protocol.properties {
+        idl.push_str(&format!("@{key}({})\n", json_literal(value)));
+    }
+    idl.push_str(&format!("protocol {} {{\n", protocol.name));
+    idl.push_str(&writer.declarations.join("\n"));
+    idl.push_str("}\n");
+    idl
+}
+
+/// Format the Avro IDL in `input` as [`to_idl`] writes it, returning the
+/// spans of its comments other than doc comments along with it. The parser
+/// only keeps doc comments, so the others are missing from the output.
+pub fn format_idl(input: &str, options: &ParserOptions) -> Result<(String, Vec<Span>), AvdlError> {
+    let (protocol, _warnings) = parse_protocol_with_options(input, options)?;
+    Ok((to_idl(&protocol)?, plain_comments(input)))
+}
+
+// Spans of the `//` and `/* */` comments of `input`, skipping doc comments
+// and string literals
+fn plain_comments(input: &str) -> Vec<Span> {
+    let mut comments = Vec::new();
+    let bytes = input.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        let rest = &input[i..];
+        if bytes[i] == b'"' {
+            i += 1;
+            while i < bytes.len() && bytes[i] != b'"' {
+                i += if bytes[i] == b'\\' { 2 } else { 1 };
+            }
+            i += 1;
+        } else if rest.starts_with("//") {
+            let end = rest.find('\n').map_or(input.len(), |end| i + end);
+            comments.push(Span { start: i, end });
+            i = end;
+        } else if let Some(body) = rest.strip_prefix("/*") {
+            let end = body.find("*/").map_or(input.len(), |end| i + end + 4);
+            // `/**/` is an empty comment, not an empty doc
+            if !rest.starts_with("/**") || rest.starts_with("/**/") {
+                comments.push(Span { start: i, end });
+            }
+            i = end;
+        } else {
+            i += 1;
+        }
+    }
+    comments
+}
+
+/// Render `schemas` as the types of a protocol called `name`, in the
+/// namespace of the first one.
+///
+/// As in `.avsc` files, names without a namespace belong to the one of the
+/// named type they are declared in.
+pub fn schemas_to_idl(name: &str, schemas: &[Schema]) -> String {
+    let mut types = schemas.to_vec();
+    for schema in types.iter_mut() {
+        schema.walk_mut(&mut Qualifier::default());
+    }
+    let namespace = types
+        .first()
+        .and_then(declared_name)
+        .and_then(|name| name.namespace.clone());
+    write_idl(&Protocol {
+        name: name.to_string(),
+        namespace,
+        doc: None,
+        aliases: Vec::new(),
+        imports: Vec::new(),
+        types,
+        messages: Vec::new(),
+        properties: Default::default(),
+    })
+}
+
+// Gives every name the namespace it inherits from the enclosing named types
+#[derive(Default)]
+struct Qualifier {
+    enclosing: Vec<Namespace>,
+}
+
+impl SchemaVisitorMut for Qualifier {
+    fn enter(&mut self, schema: &mut Schema) {
+        let enclosing = self.enclosing.last().cloned().flatten();
+        match schema {
+            Schema::Record { name, .. }
+            | Schema::Enum { name, .. }
+            | Schema::Fixed { name, .. } => {
+                *name = name.fully_qualified_name(&enclosing);
+                self.enclosing.push(name.namespace.clone());
+            }
+            Schema::Ref { name } => *name = name.fully_qualified_name(&enclosing),
+            _ => (),
+        }
+    }
+
+    fn leave(&mut self, schema: &mut Schema) {
+        if let Schema::Record { .. } | Schema::Enum { .. } | Schema::Fixed { .. } = schema {
+            self.enclosing.pop();
+        }
+    }
+}
+
+struct IdlWriter<'a> {
+    namespace: &'a Namespace,
+    // Types of the protocol, declared in their own position
+    top_level: HashSet<String>,
+    written: HashSet<String>,
+    declarations: Vec<String>,
+}
+
+impl IdlWriter<'_> {
+    fn key(&self, name: &Name) -> String {
+        name.fully_qualified_name(self.namespace).fullname(None)
+    }
+
+    // Declare the named types used by `schema` which are not in the types of
+    // the protocol
+    fn declare_nested(&mut self, schema: &Schema) {
+        match schema {
+            Schema::Array(inner) | Schema::Map(inner) => self.declare_nested(inner),
+            Schema::Union(union) => {
+                for variant in union.variants() {
+                    self.declare_nested(variant);
+                }
+            }
+            schema => match declared_name(schema) {
+                Some(name) if !self.top_level.contains(&self.key(name)) => self.declare(schema),
+                _ => (),
+            },
+        }
+    }
+
+    fn declare(&mut self, schema: &Schema) {
+        let Some(name) = declared_name(schema) else {
+            return;
+        };
+        if !self.written.insert(self.key(name)) {
+            return;
+        }
+        let declaration = match schema {
+            Schema::Record {
+                name,
+                aliases,
+                doc,
+                fields,
+                ..
+            } => {
+                for field in fields {
+                    self.declare_nested(&field.schema);
+                }
+                let mut declaration = doc_comment(doc, INDENT);
+                if let Some(aliases) = aliases {
+                    declaration.push_str(&format!("{INDENT}{}\n", aliases_annotation(aliases)));
+                }
+                match &name.namespace {
+                    Some(namespace) if name.namespace != *self.namespace => {
+                        declaration.push_str(&format!("{INDENT}@namespace(\"{namespace}\")\n"));
+                    }
+                    _ => (),
+                }
+                declaration.push_str(&format!("{INDENT}record {} {{\n", name.name));
+                for field in fields {
+                    declaration.push_str(&field_declaration(field));
+                }
+                declaration.push_str(&format!("{INDENT}}}\n"));
+                declaration
+            }
+            Schema::Enum {
+                name,
+                aliases,
+                doc,
+                symbols,
+                attributes,
+            } => {
+                let mut declaration = doc_comment(doc, INDENT);
+                if let Some(aliases) = aliases {
+                    declaration.push_str(&format!("{INDENT}{}\n", aliases_annotation(aliases)));
+                }
+                declaration.push_str(&format!(
+                    "{INDENT}enum {} {{ {} }}",
+                    name.name,
+                    symbols.join(", ")
+                ));
+                if let Some(Value::String(default)) = attributes.get("default") {
+                    declaration.push_str(&format!(" = {default};"));
+                }
+                declaration.push('\n');
+                declaration
+            }
+            Schema::Fixed { .. } => fixed_declaration(schema, None),
+            Schema::Decimal {
+                precision,
+                scale,
+                inner,
+            } => fixed_declaration(inner, Some((*precision, *scale))),
+            _ => return,
+        };
+        self.declarations.push(declaration);
+    }
+}
+
+// A fixed, annotated as a decimal of the given precision and scale if any
+fn fixed_declaration(fixed: &Schema, decimal: Option<(usize, usize)>) -> String {
+    let Schema::Fixed {
+        name,
+        aliases,
+        doc,
+        size,
+        attributes,
+    } = fixed
+    else {
+        return String::new();
+    };
+    let mut declaration = doc_comment(doc, INDENT);
+    if let Some((precision, scale)) = decimal {
+        declaration.push_str(&format!(
+            "{INDENT}@logicalType(\"decimal\") @precision({precision}) @scale({scale})\n"
+        ));
+    } else if attributes.get("logicalType") == Some(&Value::from("duration")) {
+        declaration.push_str(&format!("{INDENT}@logicalType(\"duration\")\n"));
+    }
+    declaration.push_str(&format!("{INDENT}fixed "));
+    if let Some(aliases) = aliases {
+        declaration.push_str(&format!("{} ", aliases_annotation(aliases)));
+    }
+    declaration.push_str(&format!("{}({size});\n", name.name));
+    declaration
+}
+
+fn doc_comment(doc: &Option<String>, indent: &str) -> String {
+    match doc {
+        Some(doc) => format!("{indent}/** {} */\n", escape_doc(doc)),
+        None => String::new(),
+    }
+}
+
+// A `*` followed by a `/`, which would end the comment, or by a `\` is
+// escaped with a `\`, which the parser removes
+fn escape_doc(doc: &str) -> String {
+    let mut escaped = String::with_capacity(doc.len());
+    let mut chars = doc.chars().peekable();
+    while let Some(c) = chars.next() {
+        escaped.push(c);
+        if c == '*' && matches!(chars.peek(), Some('/' | '\\')) {
+            escaped.push('\\');
+        }
+    }
+    escaped
+}
+
+fn aliases_annotation(aliases: &[Alias]) -> String {
+    let aliases: Vec<String> = aliases
+        .iter()
+        .map(|alias| format!("\"{}\"", alias.fullname(None)))
+        .collect();
+    format!("@aliases([{}])", aliases.join(", "))
+}
+
+fn field_declaration(field: &RecordField) -> String {
+    let mut declaration = doc_comment(&field.doc, FIELD_INDENT);
+    declaration.push_str(FIELD_INDENT);
+    declaration.push_str(&type_name(&field.schema));
+    match field.order {
+        RecordFieldOrder::Ascending => (),
+        RecordFieldOrder::Descending => declaration.push_str(" @order(\"descending\")"),
+        RecordFieldOrder::Ignore => declaration.push_str(" @order(\"ignore\")"),
+    }
+    if let Some(aliases) = &field.aliases {
+        let aliases: Vec<String> = aliases.iter().map(|alias| format!("\"{alias}\"")).collect();
+        declaration.push_str(&format!(" @aliases([{}])", aliases.join(", ")));
+    }
+    declaration.push_str(&format!(" {}", field.name));
+    if let Some(default) = &field.default {
+        declaration.push_str(&format!(" = {}", default_literal(&field.schema, default)));
+    }
+    declaration.push_str(";\n");
+    declaration
+}
+
+// A parameter is written on the line of its message, along with its doc
+fn parameter_declaration(parameter: &RecordField) -> String {
+    let mut declaration = match &parameter.doc {
+        Some(doc) => format!("/** {} */ ", escape_doc(doc)),
+        None => String::new(),
+    };
+    declaration.push_str(&format!(
+        "{} {}",
+        type_name(&parameter.schema),
+        parameter.name
+    ));
+    if let Some(default) = &parameter.default {
+        declaration.push_str(&format!(
+            " = {}",
+            default_literal(&parameter.schema, default)
+        ));
+    }
+    declaration
+}
+
+fn message_declaration(message: &Message) -> String {
+    let mut declaration = doc_comment(&message.doc, INDENT);
+    let response = match &message.response {
+        Schema::Null => "void".to_string(),
+        schema => type_name(schema),
+    };
+    let parameters: Vec<String> = message.request.iter().map(parameter_declaration).collect();
+    declaration.push_str(&format!(
+        "{INDENT}{response} {}({})",
+        message.name,
+        parameters.join(", ")
+    ));
+    if !message.errors.is_empty() {
+        let errors: Vec<String> = message.errors.iter().map(type_name).collect();
+        declaration.push_str(&format!(" throws {}", errors.join(", ")));
+    }
+    if message.one_way {
+        declaration.push_str(" oneway");
+    }
+    declaration.push_str(";\n");
+    declaration
+}
+
+/// Type in IDL syntax, named types are written by name.
+fn type_name(schema: &Schema) -> String {
+    match schema {
+        Schema::Null => "null".to_string(),
+        Schema::Boolean => "boolean".to_string(),
+        Schema::Int => "int".to_string(),
+        Schema::Long => "long".to_string(),
+        Schema::Float => "float".to_string(),
+        Schema::Double => "double".to_string(),
+        Schema::Bytes => "bytes".to_string(),
+        Schema::String => "string".to_string(),
+        Schema::Array(items) => format!("array<{}>", type_name(items)),
+        Schema::Map(values) => format!("map<{}>", type_name(values)),
+        Schema::Union(union) => {
+            let variants: Vec<String> = union.variants().iter().map(type_name).collect();
+            format!("union {{ {} }}", variants.join(", "))
+        }
+        Schema::Record { name, .. }
+        | Schema::Enum { name, .. }
+        | Schema::Fixed { name, .. }
+        | Schema::Ref { name } => name.name.clone(),
+        Schema::Decimal { inner, .. } if matches!(**inner, Schema::Fixed { .. }) => {
+            type_name(inner)
+        }
+        Schema::Decimal {
+            precision, scale, ..
+        } => format!("decimal({precision}, {scale})"),
+        Schema::Uuid => "uuid".to_string(),
+        Schema::Date => "date".to_string(),
+        Schema::TimeMillis => "time_ms".to_string(),
+        Schema::TimestampMillis => "timestamp_ms".to_string(),
+        // Logical types without a keyword
+        Schema::TimeMicros => logical_type("time-micros", "long"),
+        Schema::TimestampMicros => logical_type("timestamp-micros", "long"),
+        Schema::LocalTimestampMillis => logical_type("local-timestamp-millis", "long"),
+        Schema::LocalTimestampMicros => logical_type("local-timestamp-micros", "long"),
+        // Named after the fixed it is written as in `.avsc`
+        Schema::Duration => logical_type("duration", "duration"),
+    }
+}
+
+fn logical_type(logical_type: &str, base: &str) -> String {
+    format!("@logicalType(\"{logical_type}\") {base}")
+}
+
+/// Default value in IDL syntax, it must match the first type of unions.
+fn default_literal(schema: &Schema, value: &Value) -> String {
+    match (schema, value) {
+        (Schema::Union(union), value) => match union.variants().first() {
+            Some(first) => default_literal(first, value),
+            None => json_literal(value),
+        },
+        (Schema::Enum { .. } | Schema::Ref { .. }, Value::String(symbol)) => symbol.clone(),
+        // One character per byte, as the parser reads them
+        (Schema::Decimal { .. }, Value::Array(bytes)) => {
+            let text: Option<String> = bytes
+                .iter()
+                .map(|byte| byte.as_u64().and_then(|byte| u8::try_from(byte).ok()))
+                .map(|byte| byte.map(char::from))
+                .collect();
+            match text {
+                Some(text) => string_literal(&text),
+                None => value.to_string(),
+            }
+        }
+        // The parser keeps the bytes of the string
+        (Schema::Bytes | Schema::Fixed { .. }, Value::Array(bytes)) => {
+            let bytes: Option<Vec<u8>> = bytes
+                .iter()
+                .map(|byte| byte.as_u64().and_then(|byte| u8::try_from(byte).ok()))
+                .collect();
+            match bytes.and_then(|bytes| String::from_utf8(bytes).ok()) {
+                Some(text) => string_literal(&text),
+                None => value.to_string(),
+            }
+        }
+        // Written as the months, days and milliseconds they hold
+        (Schema::Duration, Value::Array(bytes)) => {
+            let bytes: Option<Vec<u8>> = bytes
+                .iter()
+                .map(|byte| byte.as_u64().and_then(|byte| u8::try_from(byte).ok()))
+                .collect();
+            match bytes.and_then(|bytes| <[u8; 12]>::try_from(bytes).ok()) {
+                Some(bytes) => {
+                    let part = |i: usize| {
+                        u32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]])
+                    };
+                    format!(
+                        "{{\"months\": {}, \"days\": {}, \"millis\": {}}}",
+                        part(0),
+                        part(4),
+                        part(8)
+                    )
+                }
+                None => value.to_string(),
+            }
+        }
+        (Schema::Array(items), Value::Array(values)) => {
+            let values: Vec<String> = values
+                .iter()
+                .map(|value| default_literal(items, value))
+                .collect();
+            format!("[{}]", values.join(", "))
+        }
+        (Schema::Map(schema), Value::Object(entries)) => {
+            let entries: Vec<String> = entries
+                .iter()
+                .map(|(key, value)| {
+                    format!(
+                        "{}: {}",
+                        string_literal(key),
+                        default_literal(schema, value)
+                    )
+                })
+                .collect();
+            format!("{{{}}}", entries.join(", "))
+        }
+        (_, value) => json_literal(value),
+    }
+}
+
+// Any JSON value, with its strings written as `string_literal` does
+fn json_literal(value: &Value) -> String {
+    match value {
+        Value::String(text) => string_literal(text),
+        Value::Array(values) => {
+            let values: Vec<String> = values.iter().map(json_literal).collect();
+            format!("[{}]", values.join(", "))
+        }
+        Value::Object(entries) => {
+            let entries: Vec<String> = entries
+                .iter()
+                .map(|(key, value)| format!("{}: {}", string_literal(key), json_literal(value)))
+                .collect();
+            format!("{{{}}}", entries.join(", "))
+        }
+        value => value.to_string(),
+    }
+}
+
+// Escapes understood by `string_parser`
+fn string_literal(text: &str) -> String {
+    let mut literal = String::with_capacity(text.len() + 2);
+    literal.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            '\t' => literal.push_str("\\t"),
+            c if c.is_control() => literal.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::avsc::from_avsc_str;
+    use rstest::rstest;
+
+    fn parse(input: &str) -> Protocol {
+        let (protocol, _warnings) =
+            parse_protocol_with_options(input, &ParserOptions::default()).unwrap();
+        protocol
+    }
+
+    const KITCHEN: &str = r#"/** Everything the writer knows about */
+@namespace("org.example")
+protocol Kitchen {
+    @aliases(["org.old.Shape"])
+    enum Shape { ROUND, SQUARE } = SQUARE;
+
+    fixed @aliases(["Md5"]) Hash(16);
+
+    /** In cents */
+    @logicalType("decimal") @precision(9) @scale(2)
+    fixed Amount(4);
+
+    @logicalType("duration")
+    fixed Span(12);
+
+    /** Something to eat on, not a *\/ nor a *\\ */
+    @aliases(["org.old.Plate"])
+    record Plate {
+        Shape shape = ROUND;
+        /** Shown in the menu */
+        string @order("descending") @aliases(["title"]) name = "a \"plate\"";
+        decimal(4, 2) price = "\u{7}Ð";
+        Amount deposit;
+        @logicalType("timestamp-micros") long created;
+        time_ms cooked;
+        union { null, map<string> } extras = null;
+        map<int> counts = {"a": 1, "b": 2};
+        union { null, array<string> } tags = null;
+        union { null, @logicalType("timestamp-micros") long } eaten = null;
+        bytes raw = "abc";
+        @logicalType("duration") duration rest = {"months": 0, "days": 1, "millis": 500};
+        Hash hash;
+        Span washing;
+    }
+
+    record Oops {
+        string reason;
+    }
+
+    @namespace("org.other")
+    record Legacy {
+        string id;
+    }
+
+    /** Count plates */
+    int count(Shape shape, union { null, string } like) throws Oops;
+
+    @logicalType("timestamp-micros") long served(@logicalType("local-timestamp-millis") long at, array<@logicalType("duration") duration> waits);
+
+    void clean() oneway;
+}
+"#;
+
+    #[rstest]
+    #[case(KITCHEN)]
+    #[case(include_str!("../../../tests/samples/Shop.avdl"))]
+    fn test_to_idl_keeps_formatted_input(#[case] input: &str) {
+        assert_eq!(to_idl(&parse(input)).unwrap(), input);
+    }
+
+    #[rstest]
+    #[case(include_str!("../../../tests/samples/Docs.avdl"))]
+    #[case(include_str!("../../../tests/samples/HelloArray.avdl"))]
+    #[case(include_str!("../../../tests/samples/Library.avdl"))]
+    #[case(include_str!("../../../tests/samples/MinimalProtocol.avdl"))]
+    #[case(include_str!("../../../tests/samples/MyUnion.avdl"))]
+    #[case(include_str!("../../../tests/samples/NativeFields.avdl"))]
+    #[case(include_str!("../../../tests/samples/Order.avdl"))]
+    #[case(include_str!("../../../tests/samples/simple_alias.avdl"))]
+    #[case(KITCHEN)]
+    fn test_to_idl_round_trip(#[case] input: &str) {
+        let protocol = parse(input);
+        let written = to_idl(&protocol).unwrap();
+        let parsed = parse(&written);
+        assert_eq!(parsed, protocol);
+        // Docs, aliases and defaults are not part of the canonical form
+        assert_eq!(to_idl(&parsed).unwrap(), written);
+    }
+
+    #[test]
+    fn test_format_idl() {
+        let input = include_str!("../../../tests/samples/Unformatted.avdl");
+        let (formatted, comments) = format_idl(input, &ParserOptions::default()).unwrap();
+        assert_eq!(
+            formatted,
+            include_str!("../../../tests/golden/Unformatted.avdl")
+        );
+        let comments: Vec<&str> = comments
+            .iter()
+            .map(|span| &input[span.start..span.end])
+            .collect();
+        assert_eq!(comments, vec!["// prices in cents", "/* legacy */"]);
+        // Formatting twice changes nothing
+        let (again, comments) = format_idl(&formatted, &ParserOptions::default()).unwrap();
+        assert_eq!(again, formatted);
+        assert!(comments.is_empty());
+    }
+
+    #[test]
+    fn test_to_idl_declares_nested_types() {
+        let input = r#"{"protocol": "P", "types": [{"type": "record", "name": "Order", "fields": [
+            {"name": "status", "type": {"type": "enum", "name": "Status", "symbols": ["OPEN"]}}
+        ]}]}"#;
+        let protocol = Protocol::from_avpr_str(input).unwrap();
+        assert_eq!(
+            to_idl(&protocol).unwrap(),
+            "protocol P {
+    enum Status { OPEN }
+
+    record Order {
+        Status status;
+    }
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_to_idl_writes_properties() {
+        let input = r#"{"protocol": "P", "x-note": "a\u0001b", "owner": {"tags": ["\u0007", 1]}}"#;
+        let protocol = Protocol::from_avpr_str(input).unwrap();
+        let written = to_idl(&protocol).unwrap();
+        assert_eq!(
+            written,
+            "@owner({\"tags\": [\"\\u{7}\", 1]})\n@x-note(\"a\\u{1}b\")\nprotocol P {\n}\n"
+        );
+        assert_eq!(parse(&written), protocol);
+    }
+
+    #[rstest]
+    #[case(r#"{"protocol": "P", "my key": 1}"#)]
+    #[case(r#"{"protocol": "P", "": 1}"#)]
+    #[case(r#"{"protocol": "P", "order": "ascending"}"#)]
+    fn test_to_idl_rejects_property_keys(#[case] input: &str) {
+        let protocol = Protocol::from_avpr_str(input).unwrap();
+        assert!(matches!(
+            to_idl(&protocol),
+            Err(AvdlError::UnwritableProperty(_))
+        ));
+    }
+
+    #[test]
+    fn test_schemas_to_idl_inherits_namespaces() {
+        let item = from_avsc_str(
+            r#"{"type": "record", "name": "Item", "namespace": "org.example", "fields": [
+            {"name": "kind", "type": {"type": "enum", "name": "Kind", "symbols": ["BOOK"]}},
+            {"name": "previous", "type": ["null", "Kind"]}
+        ]}"#,
+        )
+        .unwrap();
+        let idl = schemas_to_idl("Shop", &[item]);
+        assert!(idl.starts_with("@namespace(\"org.example\")\nprotocol Shop {\n"));
+        let protocol = parse(&idl);
+        let names: Vec<String> = protocol
+            .types
+            .iter()
+            .map(|schema| schema.name().unwrap().fullname(protocol.namespace.clone()))
+            .collect();
+        assert_eq!(names, vec!["org.example.Kind", "org.example.Item"]);
+    }
+
+    #[rstest]
+    #[case("plain", r#""plain""#)]
+    #[case("say \"hi\"\n", r#""say \"hi\"\n""#)]
+    #[case("\u{1}", r#""\u{1}""#)]
+    fn test_string_literal(#[case] text: &str, #[case] expected: &str) {
+        assert_eq!(string_literal(text), expected);
+    }
+}
@@ -1,3 +1,38 @@
-pub mod string_parser;
+pub mod avpr;
+pub mod avsc;
+pub mod builder;
+pub mod canonical;
+pub mod codegen;
+pub mod compat;
+pub mod diff;
+pub mod docs;
+pub mod error;
+pub mod events;
+pub mod expand;
+pub mod fingerprint;
+pub mod fix;
+pub mod graph;
+pub mod idl;
+pub mod lint;
+pub mod namespace;
+pub mod options;
+pub mod output;
 pub mod parser;
-pub use parser::parse;
+pub mod protocol;
+pub mod spans;
+pub mod stats;
+pub mod string_parser;
+pub mod strip;
+pub mod validate;
+pub mod value;
+pub mod visit;
+pub mod warning;
+pub use error::AvdlError;
+pub use options::ParserOptions;
+pub use parser::{
+    check_protocol, declares_protocol, parse, parse_file, parse_file_with_options, parse_idl_file,
+    parse_idl_file_with_options, parse_lenient, parse_protocol_with_options, parse_reader,
+    parse_spanned, parse_str, parse_type_str, parse_with_warnings,
+};
+pub use protocol::{IdlFile, Import, ImportKind, Message, Protocol, SchemaFile};
+pub use warning::{Warning, WarningCode, Warnings};
@@ -0,0 +1,268 @@
+//! Naming and documentation conventions checked over a parsed protocol.
+//!
+//! Each rule has a code, e.g. `AVDL001`, so that it can be turned off.
+
+use std::fmt;
+use std::str::FromStr;
+
+use apache_avro::schema::Schema;
+
+use crate::error::Span;
+use crate::protocol::Protocol;
+use crate::spans::SourceMap;
+use crate::validate::{type_name, union_null_not_first};
+
+/// A convention checked by [`lint`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Rule {
+    /// Record names are PascalCase, e.g. `OrderLine`.
+    RecordName,
+    /// Field names are camelCase, e.g. `placedAt`.
+    FieldName,
+    /// Enum symbols are UPPER_SNAKE_CASE, e.g. `ON_HOLD`.
+    EnumSymbol,
+    /// Records have a namespace, their own or the one of the protocol.
+    RecordNamespace,
+    /// Records have a doc comment.
+    RecordDoc,
+    /// Unions of fields defaulting to `null`, or without default, have
+    /// `null` first.
+    UnionNullFirst,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 6] = [
+        Rule::RecordName,
+        Rule::FieldName,
+        Rule::EnumSymbol,
+        Rule::RecordNamespace,
+        Rule::RecordDoc,
+        Rule::UnionNullFirst,
+    ];
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Rule::RecordName => "AVDL001",
+            Rule::FieldName => "AVDL002",
+            Rule::EnumSymbol => "AVDL003",
+            Rule::RecordNamespace => "AVDL004",
+            Rule::RecordDoc => "AVDL005",
+            Rule::UnionNullFirst => "AVDL006",
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    /// Parse a rule code, ignoring case.
+    fn from_str(code: &str) -> Result<Rule, String> {
+        Rule::ALL
+            .into_iter()
+            .find(|rule| rule.code().eq_ignore_ascii_case(code))
+            .ok_or_else(|| format!("unknown lint rule `{code}`"))
+    }
+}
+
+/// A declaration breaking a rule, with its span when the protocol was parsed
+/// with [`crate::parse_spanned`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub rule: Rule,
+    pub message: String,
+    pub span: Option<Span>,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} [{}]", self.message, self.rule)
+    }
+}
+
+/// Check the types of `protocol` against every rule but the `allowed` ones,
+/// in declaration order. `spans` locates the findings, and may be empty.
+pub fn lint(protocol: &Protocol, spans: &SourceMap, allowed: &[Rule]) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut report = |rule: Rule, message: String, span: Option<Span>| {
+        if !allowed.contains(&rule) {
+            findings.push(Finding {
+                rule,
+                message,
+                span,
+            });
+        }
+    };
+    for schema in &protocol.types {
+        match schema {
+            Schema::Record {
+                name, doc, fields, ..
+            } => {
+                let span = spans.type_span(name);
+                if !is_pascal_case(&name.name) {
+                    let message = format!("record `{}` should be PascalCase", name.name);
+                    report(Rule::RecordName, message, span);
+                }
+                if name.namespace.is_none() && protocol.namespace.is_none() {
+                    let message = format!("record `{}` has no namespace", name.name);
+                    report(Rule::RecordNamespace, message, span);
+                }
+                if doc.as_deref().is_none_or(|doc| doc.trim().is_empty()) {
+                    let message = format!("record `{}` has no doc comment", name.name);
+                    report(Rule::RecordDoc, message, span);
+                }
+                for field in fields {
+                    if !is_camel_case(&field.name) {
+                        let message = format!(
+                            "field `{}` of record `{}` should be camelCase",
+                            field.name, name.name
+                        );
+                        report(
+                            Rule::FieldName,
+                            message,
+                            spans.field_span(name, &field.name),
+                        );
+                    }
+                }
+            }
+            Schema::Enum { name, symbols, .. } => {
+                for symbol in symbols.iter().filter(|symbol| !is_upper_snake_case(symbol)) {
+                    let message = format!(
+                        "symbol `{symbol}` of enum `{}` should be UPPER_SNAKE_CASE",
+                        name.name
+                    );
+                    report(Rule::EnumSymbol, message, spans.type_span(name));
+                }
+            }
+            _ => (),
+        }
+    }
+    for (record, field) in union_null_not_first(&protocol.types) {
+        let message = format!(
+            "field `{}` of record `{}` should have null first in `{}`",
+            field.name,
+            record.name,
+            type_name(&field.schema)
+        );
+        report(
+            Rule::UnionNullFirst,
+            message,
+            spans.field_span(record, &field.name),
+        );
+    }
+    // The union findings come last, put them back in the order of the file
+    findings.sort_by_key(|finding| finding.span.map_or(usize::MAX, |span| span.start));
+    findings
+}
+
+fn is_pascal_case(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_uppercase())
+        && name.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+fn is_camel_case(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_lowercase())
+        && name.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+fn is_upper_snake_case(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_uppercase())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::options::ParserOptions;
+    use crate::parser::parse_spanned;
+    use rstest::rstest;
+
+    // The findings of `input`, as `rule line:column message`
+    fn findings(input: &str, allowed: &[Rule]) -> Vec<String> {
+        let (protocol, _warnings, spans) = parse_spanned(input, &ParserOptions::default()).unwrap();
+        lint(&protocol, &spans, allowed)
+            .into_iter()
+            .map(|finding| {
+                let location = finding.span.unwrap().location(input);
+                format!("{} {location} {}", finding.rule, finding.message)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_conventions_followed() {
+        let input = r#"@namespace("org.example")
+protocol Shop {
+    enum Status { ON_HOLD, SHIPPED2 }
+    /** A line of an order */
+    record OrderLine {
+        string itemId;
+        union { null, Status } status = null;
+        union { int, null } quantity = 1;
+    }
+}"#;
+        assert_eq!(findings(input, &[]), Vec::<String>::new());
+    }
+
+    #[rstest]
+    #[case::record_name(
+        "/** Line */ record order_line { string id; }",
+        "AVDL001 3:5 record `order_line` should be PascalCase"
+    )]
+    #[case::field_name(
+        "/** Line */ record OrderLine {\n        string item_id;\n    }",
+        "AVDL002 4:9 field `item_id` of record `OrderLine` should be camelCase"
+    )]
+    #[case::enum_symbol(
+        "enum Status { ACTIVE, onHold }",
+        "AVDL003 3:5 symbol `onHold` of enum `Status` should be UPPER_SNAKE_CASE"
+    )]
+    #[case::record_doc(
+        "record OrderLine { string id; }",
+        "AVDL005 3:5 record `OrderLine` has no doc comment"
+    )]
+    #[case::union_null_first(
+        "/** Line */ record OrderLine {\n        union { string, null } note;\n    }",
+        "AVDL006 4:9 field `note` of record `OrderLine` should have null first in `union { string, null }`"
+    )]
+    fn test_rule(#[case] declaration: &str, #[case] expected: &str) {
+        let input = format!("@namespace(\"org.example\")\nprotocol Shop {{\n    {declaration}\n}}");
+        assert_eq!(findings(&input, &[]), vec![expected]);
+    }
+
+    #[test]
+    fn test_record_namespace() {
+        let input = "protocol Shop {\n    /** Line */ record OrderLine { string id; }\n}";
+        assert_eq!(
+            findings(input, &[]),
+            vec!["AVDL004 2:5 record `OrderLine` has no namespace"]
+        );
+        let input = "protocol Shop {\n    /** Line */ @namespace(\"org.example\") record OrderLine { string id; }\n}";
+        assert_eq!(findings(input, &[]), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_allowed_rules() {
+        let input = "protocol Shop {\n    record order_line { string item_id; }\n}";
+        assert_eq!(findings(input, &[]).len(), 4);
+        let allowed = [Rule::RecordDoc, "avdl004".parse().unwrap()];
+        assert_eq!(
+            findings(input, &allowed),
+            vec![
+                "AVDL001 2:5 record `order_line` should be PascalCase",
+                "AVDL002 2:25 field `item_id` of record `order_line` should be camelCase",
+            ]
+        );
+        assert_eq!(
+            "AVDL999".parse::<Rule>(),
+            Err("unknown lint rule `AVDL999`".to_string())
+        );
+    }
+}
@@ -0,0 +1,206 @@
+//! Moving named types to another namespace.
+
+use apache_avro::schema::{Alias, Name, Namespace, Schema};
+
+use crate::protocol::Protocol;
+use crate::visit::{SchemaVisitorMut, Walk};
+
+/// Move the named types of namespace `from` in `protocol` to namespace `to`,
+/// along with every reference and alias to them, so the protocol stays
+/// consistent.
+///
+/// Namespaces nested in `from` are moved too: from `com.vendor` to
+/// `org.mycorp`, `com.vendor.billing` becomes `org.mycorp.billing`, while
+/// `com.other` is left alone. `from` is `None` for the types without a
+/// namespace. The namespace of the protocol is moved the same way.
+pub fn rewrite_namespace(protocol: &mut Protocol, from: Option<&str>, to: &str) {
+    let mut rewriter = NamespaceRewriter::new(from, to, protocol.namespace.clone());
+    for schema in protocol.types.iter_mut() {
+        schema.walk_mut(&mut rewriter);
+    }
+    for message in protocol.messages.iter_mut() {
+        let parameters = message.request.iter_mut().map(|field| &mut field.schema);
+        for schema in parameters
+            .chain([&mut message.response])
+            .chain(message.errors.iter_mut())
+        {
+            schema.walk_mut(&mut rewriter);
+        }
+    }
+    if let Some(namespace) = rewriter.rewritten(&protocol.namespace) {
+        protocol.namespace = Some(namespace);
+    }
+}
+
+/// Same as [`rewrite_namespace`], for a schema on its own.
+pub fn rewrite_schema_namespace(schema: &mut Schema, from: Option<&str>, to: &str) {
+    schema.walk_mut(&mut NamespaceRewriter::new(from, to, None));
+}
+
+struct NamespaceRewriter<'a> {
+    from: Option<&'a str>,
+    to: &'a str,
+    // Namespaces of the named types being walked, before the rewrite, which
+    // the names without a namespace inherit
+    enclosing: Vec<Namespace>,
+}
+
+impl<'a> NamespaceRewriter<'a> {
+    fn new(from: Option<&'a str>, to: &'a str, namespace: Namespace) -> NamespaceRewriter<'a> {
+        NamespaceRewriter {
+            from,
+            to,
+            enclosing: vec![namespace],
+        }
+    }
+
+    // Where `namespace` moves to, if it does
+    fn rewritten(&self, namespace: &Namespace) -> Option<String> {
+        match (namespace.as_deref(), self.from) {
+            (None, None) => Some(self.to.to_string()),
+            (Some(namespace), Some(from)) if namespace == from => Some(self.to.to_string()),
+            (Some(namespace), Some(from)) => namespace
+                .strip_prefix(from)
+                .and_then(|nested| nested.strip_prefix('.'))
+                .map(|nested| format!("{}.{nested}", self.to)),
+            _ => None,
+        }
+    }
+
+    // Rewrites `name`, returning its namespace before the rewrite
+    fn rewrite(&self, name: &mut Name) -> Namespace {
+        let enclosing = self.enclosing.last().cloned().flatten();
+        let fullname = name.fully_qualified_name(&enclosing);
+        if let Some(namespace) = self.rewritten(&fullname.namespace) {
+            name.namespace = Some(namespace);
+        }
+        fullname.namespace
+    }
+
+    fn rewrite_aliases(&self, aliases: &mut Option<Vec<Alias>>, namespace: &Namespace) {
+        for alias in aliases.iter_mut().flatten() {
+            let fullname = alias.fully_qualified_name(namespace);
+            if let Some(rewritten) = self.rewritten(&fullname.namespace) {
+                let fullname = format!("{rewritten}.{}", fullname.name);
+                *alias = Alias::new(&fullname).expect("the namespace of a valid alias is valid");
+            }
+        }
+    }
+}
+
+impl SchemaVisitorMut for NamespaceRewriter<'_> {
+    fn enter(&mut self, schema: &mut Schema) {
+        match schema {
+            Schema::Record { name, aliases, .. }
+            | Schema::Enum { name, aliases, .. }
+            | Schema::Fixed { name, aliases, .. } => {
+                let namespace = self.rewrite(name);
+                self.rewrite_aliases(aliases, &namespace);
+                self.enclosing.push(namespace);
+            }
+            Schema::Ref { name } => {
+                self.rewrite(name);
+            }
+            _ => (),
+        }
+    }
+
+    fn leave(&mut self, schema: &mut Schema) {
+        if let Schema::Record { .. } | Schema::Enum { .. } | Schema::Fixed { .. } = schema {
+            self.enclosing.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::avpr::{from_avpr_str, to_avpr_string};
+    use crate::avsc::{from_avsc_str, to_avsc_string};
+    use crate::expand::registry;
+    use crate::options::ParserOptions;
+    use crate::parser::parse_protocol_with_options;
+    use crate::validate::unresolved_refs;
+
+    #[test]
+    fn test_rewrite_schema_namespace() {
+        let mut schema = from_avsc_str(
+            r#"{
+  "type": "record",
+  "name": "Person",
+  "namespace": "vendor",
+  "aliases": ["vendor.Human"],
+  "fields": [
+    {"name": "status", "type": {"type": "enum", "name": "Status", "namespace": "vendor", "symbols": ["ACTIVE"]}},
+    {"name": "previous", "type": ["null", "vendor.Status"]},
+    {"name": "other", "type": {"type": "fixed", "name": "Hash", "namespace": "other", "size": 4}}
+  ]
+}"#,
+        )
+        .unwrap();
+        rewrite_schema_namespace(&mut schema, Some("vendor"), "org.mycorp");
+        let written = to_avsc_string(&schema).unwrap();
+        assert!(
+            written.contains(r#""namespace": "org.mycorp""#),
+            "{written}"
+        );
+        assert!(written.contains(r#""org.mycorp.Human""#), "{written}");
+        assert_eq!(from_avsc_str(&written).unwrap(), schema);
+        assert!(written.contains(r#""namespace": "other""#), "{written}");
+        assert!(!written.contains("vendor"), "{written}");
+    }
+
+    fn fullname(schema: &Schema) -> String {
+        schema.name().unwrap().fullname(None)
+    }
+
+    #[test]
+    fn test_rewrite_namespace_keeps_references() {
+        let input = r#"@namespace("com.vendor")
+protocol Vendored {
+    enum Status { ACTIVE, CLOSED }
+    @namespace("com.vendor.billing") record Invoice { string id; }
+    @namespace("com.other") record Other { string id; }
+    record Account { Status status; union { null, Status } previous = null; }
+    Status status(Account account);
+}"#;
+        let (mut protocol, _warnings) =
+            parse_protocol_with_options(input, &ParserOptions::default()).unwrap();
+        rewrite_namespace(&mut protocol, Some("com.vendor"), "org.mycorp");
+
+        assert_eq!(protocol.namespace.as_deref(), Some("org.mycorp"));
+        let names: Vec<String> = protocol.types.iter().map(fullname).collect();
+        assert_eq!(
+            names,
+            vec![
+                "org.mycorp.Status",
+                "org.mycorp.billing.Invoice",
+                "com.other.Other",
+                "org.mycorp.Account"
+            ]
+        );
+        let Schema::Record { fields, .. } = &protocol.types[3] else {
+            panic!("expected a record");
+        };
+        let Schema::Union(previous) = &fields[1].schema else {
+            panic!("expected a union");
+        };
+        assert_eq!(fullname(&fields[0].schema), "org.mycorp.Status");
+        assert_eq!(fullname(&previous.variants()[1]), "org.mycorp.Status");
+        assert_eq!(
+            fullname(&protocol.messages[0].response),
+            "org.mycorp.Status"
+        );
+        assert!(unresolved_refs(
+            &protocol.types,
+            &registry(&protocol.types),
+            &protocol.namespace
+        )
+        .is_empty());
+
+        // Written and read back, the references are still found
+        let written = to_avpr_string(&protocol).unwrap();
+        assert!(!written.contains("com.vendor"), "{written}");
+        assert_eq!(from_avpr_str(&written).unwrap(), protocol);
+    }
+}
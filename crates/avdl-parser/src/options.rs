@@ -0,0 +1,54 @@
+use std::path::PathBuf;
+
+/// How deep default values may nest unless configured otherwise.
+pub const DEFAULT_MAX_DEPTH: usize = 64;
+
+/// Settings changing which checks the parser runs.
+///
+/// The default matches the behavior of [`crate::parse`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParserOptions {
+    /// Reject what the specification does not allow even if the parser can
+    /// make sense of it, e.g. names with non ASCII letters.
+    pub strict: bool,
+    /// Accept a trailing comma in enum symbols, `enum E { A, B, }`.
+    pub allow_trailing_commas: bool,
+    /// Warn about record fields named exactly like a type of the protocol,
+    /// e.g. `string Status;` when there is an `enum Status`.
+    pub lint_field_shadowing: bool,
+    /// Fields, written as `Record.field`, the shadowing lint should not
+    /// report.
+    pub allow_field_shadowing: Vec<String>,
+    /// Warn about unions with `null` after another type when the field has
+    /// no default, e.g. `union { string, null } nickname;`.
+    pub lint_union_null_order: bool,
+    /// Maximum nesting of default values, e.g. `[[1]]` is nested 2 levels.
+    pub max_depth: usize,
+    /// Accept references to types which are not declared, for schemas
+    /// provided separately.
+    pub allow_unresolved_refs: bool,
+    /// Keep doc comments as written between `/**` and `*/`, instead of
+    /// trimming them and removing the `*` starting their lines like the Java
+    /// tool does.
+    pub preserve_docs: bool,
+    /// Directory the files named by `import` statements are read from,
+    /// usually the one of the file being parsed. Without it imports are
+    /// kept as they are written and not read.
+    pub import_dir: Option<PathBuf>,
+}
+
+impl Default for ParserOptions {
+    fn default() -> Self {
+        ParserOptions {
+            strict: false,
+            allow_trailing_commas: false,
+            lint_field_shadowing: false,
+            allow_field_shadowing: Vec::new(),
+            lint_union_null_order: false,
+            max_depth: DEFAULT_MAX_DEPTH,
+            allow_unresolved_refs: false,
+            preserve_docs: false,
+            import_dir: None,
+        }
+    }
+}
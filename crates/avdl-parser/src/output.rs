@@ -0,0 +1,357 @@
+//! Planning the files `convert` writes, before any of them is written.
+
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+use apache_avro::schema::{Name, Schema};
+
+use crate::error::AvdlError;
+use crate::validate::declared_name;
+
+/// How the `.avsc` file of a named type is called.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum FileNaming {
+    /// `Event.avsc`
+    #[default]
+    Name,
+    /// `org.example.Event.avsc`
+    Fullname,
+    /// `org/example/Event.avsc`
+    Dirs,
+    /// Given by a template, like `{namespace}.{name}.schema.json`
+    Template(FilenameTemplate),
+}
+
+/// A file name with placeholders, replaced for each named type by its
+/// `{name}`, `{namespace}` and `{fullname}`, the `{protocol}` declaring it,
+/// and the `{ext}` of schema files, `avsc`.
+///
+/// Types without a namespace replace `{namespace}` by nothing, and drop the
+/// `.` or `/` after it, so `{namespace}.{name}.avsc` gives `Event.avsc` for
+/// them. A `/` in the template is a folder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilenameTemplate {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Name,
+    Namespace,
+    Fullname,
+    Protocol,
+    Ext,
+}
+
+impl FilenameTemplate {
+    /// Parse `template`, failing on unknown placeholders, unbalanced braces,
+    /// and paths leaving the output folder.
+    pub fn parse(template: &str) -> Result<Self, AvdlError> {
+        let invalid = |message: String| AvdlError::InvalidTemplate {
+            template: template.to_string(),
+            message,
+        };
+        if template.is_empty() {
+            return Err(invalid("it is empty".to_string()));
+        }
+        let path = Path::new(template);
+        if path.has_root() || path.components().any(|c| c == Component::ParentDir) {
+            return Err(invalid(
+                "files must go inside the output folder".to_string(),
+            ));
+        }
+        let mut parts = Vec::new();
+        let mut rest = template;
+        while !rest.is_empty() {
+            let start = rest.find(['{', '}']).unwrap_or(rest.len());
+            if start > 0 {
+                parts.push(Part::Text(rest[..start].to_string()));
+            }
+            rest = &rest[start..];
+            if rest.starts_with('}') {
+                return Err(invalid("`}` has no matching `{`".to_string()));
+            }
+            if rest.is_empty() {
+                break;
+            }
+            let end = rest
+                .find('}')
+                .ok_or_else(|| invalid("`{` has no matching `}`".to_string()))?;
+            parts.push(match &rest[1..end] {
+                "name" => Part::Name,
+                "namespace" => Part::Namespace,
+                "fullname" => Part::Fullname,
+                "protocol" => Part::Protocol,
+                "ext" => Part::Ext,
+                placeholder => {
+                    return Err(invalid(format!(
+                        "unknown placeholder `{{{placeholder}}}`, expected one of {{name}}, {{namespace}}, {{fullname}}, {{protocol}} or {{ext}}"
+                    )))
+                }
+            });
+            rest = &rest[end + 1..];
+        }
+        Ok(FilenameTemplate { parts })
+    }
+
+    /// Path of the file of `name`, declared by `protocol`.
+    pub fn render(&self, name: &Name, protocol: &str) -> PathBuf {
+        let mut path = String::new();
+        let mut drop_separator = false;
+        for part in &self.parts {
+            match part {
+                Part::Text(text) if drop_separator => {
+                    path.push_str(text.strip_prefix(['.', '/']).unwrap_or(text))
+                }
+                Part::Text(text) => path.push_str(text),
+                Part::Name => path.push_str(&name.name),
+                Part::Namespace => path.push_str(name.namespace.as_deref().unwrap_or_default()),
+                Part::Fullname => path.push_str(&name.fullname(None)),
+                Part::Protocol => path.push_str(protocol),
+                Part::Ext => path.push_str("avsc"),
+            }
+            drop_separator = *part == Part::Namespace && name.namespace.is_none();
+        }
+        PathBuf::from(path)
+    }
+}
+
+/// Path of the `.avsc` file of `name`, declared by `protocol`, relative to
+/// the output folder.
+pub fn output_path(name: &Name, naming: &FileNaming, protocol: &str) -> PathBuf {
+    match (naming, &name.namespace) {
+        (FileNaming::Template(template), _) => template.render(name, protocol),
+        (FileNaming::Name, _) | (_, None) => PathBuf::from(format!("{}.avsc", name.name)),
+        (FileNaming::Fullname, Some(namespace)) => {
+            PathBuf::from(format!("{namespace}.{}.avsc", name.name))
+        }
+        (FileNaming::Dirs, Some(namespace)) => namespace
+            .split('.')
+            .collect::<PathBuf>()
+            .join(format!("{}.avsc", name.name)),
+    }
+}
+
+/// Paths the named types of `schemas`, records, enums and fixed, declared by
+/// `protocol`, are written to. Other schemas have no name to write them
+/// under, and are left out.
+///
+/// Fails when two types would be written to the same path, like `Event` of
+/// two namespaces with [`FileNaming::Name`]. Fails too when two paths, or
+/// two of their folders, are equal once case is ignored, since one would
+/// overwrite the other on case-insensitive filesystems, unless
+/// `allow_case_collisions` is set.
+pub fn plan_outputs<'a>(
+    schemas: &'a [Schema],
+    naming: &FileNaming,
+    protocol: &str,
+    allow_case_collisions: bool,
+) -> Result<Vec<(PathBuf, &'a Schema)>, AvdlError> {
+    let outputs: Vec<(PathBuf, &Schema)> = schemas
+        .iter()
+        .filter_map(|schema| {
+            declared_name(schema).map(|name| (output_path(name, naming, protocol), schema))
+        })
+        .collect();
+    check_collisions(&outputs)?;
+    if !allow_case_collisions {
+        check_case_collisions(outputs.iter().map(|(path, _)| path.as_path()))?;
+    }
+    Ok(outputs)
+}
+
+fn check_collisions(outputs: &[(PathBuf, &Schema)]) -> Result<(), AvdlError> {
+    let mut seen: HashMap<&Path, &Name> = HashMap::new();
+    for (path, schema) in outputs {
+        let Some(name) = declared_name(schema) else {
+            continue;
+        };
+        if let Some(other) = seen.insert(path, name) {
+            return Err(AvdlError::OutputCollision {
+                first: other.fullname(None),
+                second: name.fullname(None),
+                path: path.clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+fn check_case_collisions<'a>(paths: impl Iterator<Item = &'a Path>) -> Result<(), AvdlError> {
+    let mut seen: HashMap<String, &Path> = HashMap::new();
+    for path in paths {
+        for prefix in path.ancestors().filter(|p| !p.as_os_str().is_empty()) {
+            let folded = prefix.to_string_lossy().to_lowercase();
+            match seen.get(&folded) {
+                Some(other) if *other != prefix => {
+                    return Err(AvdlError::CaseCollision {
+                        first: other.to_path_buf(),
+                        second: prefix.to_path_buf(),
+                    });
+                }
+                _ => {
+                    seen.insert(folded, prefix);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse;
+    use rstest::rstest;
+
+    fn schemas(input: &str) -> Vec<Schema> {
+        parse(input).unwrap().1
+    }
+
+    #[rstest]
+    #[case(FileNaming::Name, "Event.avsc")]
+    #[case(FileNaming::Fullname, "org.example.Event.avsc")]
+    #[case(FileNaming::Dirs, "org/example/Event.avsc")]
+    fn test_output_path(#[case] naming: FileNaming, #[case] expected: &str) {
+        let name = Name::new("org.example.Event").unwrap();
+        assert_eq!(output_path(&name, &naming, "P"), PathBuf::from(expected));
+    }
+
+    #[rstest]
+    #[case("{namespace}.{name}.{ext}", "org.example.Event.avsc", "Event.avsc")]
+    #[case("{name}.schema.json", "Event.schema.json", "Event.schema.json")]
+    #[case("{namespace}/{name}.avsc", "org.example/Event.avsc", "Event.avsc")]
+    #[case(
+        "{protocol}/{fullname}.avsc",
+        "Shop/org.example.Event.avsc",
+        "Shop/Event.avsc"
+    )]
+    fn test_filename_template(
+        #[case] template: &str,
+        #[case] expected: &str,
+        #[case] expected_without_namespace: &str,
+    ) {
+        let template = FilenameTemplate::parse(template).unwrap();
+        let name = Name::new("org.example.Event").unwrap();
+        assert_eq!(template.render(&name, "Shop"), PathBuf::from(expected));
+        let name = Name::new("Event").unwrap();
+        assert_eq!(
+            template.render(&name, "Shop"),
+            PathBuf::from(expected_without_namespace)
+        );
+    }
+
+    #[rstest]
+    #[case("", "it is empty")]
+    #[case("{name", "`{` has no matching `}`")]
+    #[case("name}.avsc", "`}` has no matching `{`")]
+    #[case("{version}.avsc", "unknown placeholder `{version}`, expected one of {name}, {namespace}, {fullname}, {protocol} or {ext}")]
+    #[case("../{name}.avsc", "files must go inside the output folder")]
+    #[case("/tmp/{name}.avsc", "files must go inside the output folder")]
+    fn test_filename_template_invalid(#[case] template: &str, #[case] message: &str) {
+        let err = FilenameTemplate::parse(template).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("invalid filename template `{template}`: {message}")
+        );
+    }
+
+    #[test]
+    fn test_plan_outputs_template_collision() {
+        let schemas = schemas(
+            r#"protocol P {
+    @namespace("org.acme.billing") record Event { string id; }
+    @namespace("org.acme.shipping") record Event { string id; }
+}"#,
+        );
+        let naming = FileNaming::Template(FilenameTemplate::parse("{name}.json").unwrap());
+        let err = plan_outputs(&schemas, &naming, "P", false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "`org.acme.billing.Event` and `org.acme.shipping.Event` would both be written to Event.json"
+        );
+        let naming =
+            FileNaming::Template(FilenameTemplate::parse("{namespace}.{name}.json").unwrap());
+        let planned = plan_outputs(&schemas, &naming, "P", false).unwrap();
+        assert_eq!(planned[0].0, PathBuf::from("org.acme.billing.Event.json"));
+        assert_eq!(planned[1].0, PathBuf::from("org.acme.shipping.Event.json"));
+    }
+
+    #[test]
+    fn test_plan_outputs_named_types() {
+        let schemas = schemas(
+            "protocol P {
+    enum Suit { HEARTS, SPADES }
+    fixed MD5(16);
+    record Card { Suit suit; MD5 hash; }
+}",
+        );
+        let planned: Vec<PathBuf> = plan_outputs(&schemas, &FileNaming::Name, "P", false)
+            .unwrap()
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        assert_eq!(
+            planned,
+            vec![
+                PathBuf::from("Suit.avsc"),
+                PathBuf::from("MD5.avsc"),
+                PathBuf::from("Card.avsc")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plan_outputs_collision() {
+        let schemas = schemas(
+            r#"protocol P {
+    @namespace("org.acme.billing") record Event { string id; }
+    @namespace("org.acme.shipping") record Event { string id; }
+}"#,
+        );
+        let err = plan_outputs(&schemas, &FileNaming::Name, "P", true).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "`org.acme.billing.Event` and `org.acme.shipping.Event` would both be written to Event.avsc"
+        );
+        let planned = plan_outputs(&schemas, &FileNaming::Dirs, "P", false).unwrap();
+        assert_eq!(planned[0].0, PathBuf::from("org/acme/billing/Event.avsc"));
+        assert_eq!(planned[1].0, PathBuf::from("org/acme/shipping/Event.avsc"));
+    }
+
+    #[test]
+    fn test_plan_outputs_case_collision() {
+        let schemas = schemas(
+            "protocol P {
+    record Event { string id; }
+    record EVENT { string id; }
+}",
+        );
+        let err = plan_outputs(&schemas, &FileNaming::Name, "P", false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Event.avsc and EVENT.avsc differ only by case and would overwrite each other on case-insensitive filesystems"
+        );
+        let planned = plan_outputs(&schemas, &FileNaming::Name, "P", true).unwrap();
+        assert_eq!(planned.len(), 2);
+    }
+
+    #[test]
+    fn test_plan_outputs_dirs_collision() {
+        let schemas = schemas(
+            r#"protocol P {
+    @namespace("org.Example") record Event { string id; }
+    @namespace("org.example") record Order { string id; }
+}"#,
+        );
+        assert!(plan_outputs(&schemas, &FileNaming::Name, "P", false).is_ok());
+        match plan_outputs(&schemas, &FileNaming::Dirs, "P", false) {
+            Err(AvdlError::CaseCollision { first, second }) => {
+                assert_eq!(first, PathBuf::from("org/Example"));
+                assert_eq!(second, PathBuf::from("org/example"));
+            }
+            planned => panic!("unexpected plan {planned:?}"),
+        }
+    }
+}
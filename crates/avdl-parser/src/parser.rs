@@ -1,37 +1,80 @@
-use std::collections::{BTreeMap, HashMap};
-use std::fs;
+//! The Avro IDL parser.
+//!
+//! [`parse_str`] and the other functions re-exported at the root of the crate
+//! parse a whole protocol, check it and resolve the references between its
+//! types. The [nom](https://docs.rs/nom) parsers here parse a part of one,
+//! returning what is left of the input along with what they parsed:
+//!
+//! - [`parse_protocol`] for a protocol,
+//! - [`parse_record`], [`parse_enum`] and [`parse_fixed`] for a declaration,
+//! - [`parse_record_field`] for a field of a record,
+//! - [`map_type_to_schema`] for a type.
+//!
+//! Named types they come across are left as [`Schema::Ref`], and nothing is
+//! checked that needs the rest of the protocol, like a reference to an
+//! undeclared type. The other parsers are internal to the crate.
 
-use thiserror::Error;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io::Read;
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
 
+use crate::avsc::from_avsc_str;
+use crate::error::{AvdlError, ParserError, Span, SpannedError, TokenError};
+use crate::options::ParserOptions;
+use crate::protocol::{IdlFile, Import, ImportKind, Message, Protocol, SchemaFile};
+use crate::spans::SourceMap;
 use crate::string_parser::parse_string as parse_string_uni;
-use apache_avro::schema::{Alias, Name, Namespace, RecordFieldOrder};
-use apache_avro::schema::{RecordField, Schema, UnionSchema};
+use crate::validate::{
+    declared_name, declared_names, max_decimal_digits, type_name, unscaled_digits, validate,
+};
+use crate::warning::{Warning, WarningCode, Warnings};
+use apache_avro::schema::{Alias, Name, Names, Namespace, RecordFieldOrder};
+use apache_avro::schema::{RecordField, Schema, SchemaKind, UnionSchema};
 use apache_avro::types::Value as AvroValue;
+use apache_avro::{Days, Duration, Millis, Months};
 use nom::bytes::complete::take_till;
-use nom::character::complete::space0;
 
 use nom::combinator::verify;
 
 use nom::multi::separated_list0;
-use nom::sequence::pair;
+use nom::number::complete::recognize_float;
+use nom::sequence::{pair, separated_pair};
 use nom::{
     branch::alt,
-    bytes::complete::{tag, take_until, take_while, take_while1},
-    character::complete::{char, digit1, multispace0},
-    combinator::{cut, map, map_res, opt, value},
-    multi::{many1, separated_list1},
+    bytes::complete::{is_not, tag, take_until, take_while, take_while1},
+    character::complete::{char, digit1, multispace0, multispace1, satisfy},
+    combinator::{
+        cond, consumed, cut, eof, fail, map, map_opt, map_res, not, opt, peek, recognize, value,
+    },
+    error::{context, ContextError, ErrorKind, FromExternalError},
+    multi::{many0, many_till, separated_list1},
     sequence::{delimited, preceded, terminated, tuple},
-    AsChar, IResult, InputTake, InputTakeAtPosition, Parser,
+    AsChar, InputTake, InputTakeAtPosition, Parser,
 };
-use nom_permutation::permutation_opt;
 use serde_json::Value;
 use std::str::FromStr;
 use uuid::Uuid;
 
+/// What the parsers of this module return: the input left and what was
+/// parsed, or where and why parsing failed.
+pub type IResult<I, O, E = ParserError<I>> = nom::IResult<I, O, E>;
+
 // Alias to give more clarity on what is being returned
 type VarName<'a> = &'a str;
 type EnumSymbol<'a> = &'a str;
 type Doc = String;
+// Type, doc, order, aliases, name and default of a field
+type FieldParts<'a> = (
+    Schema,
+    Option<Doc>,
+    Option<RecordFieldOrder>,
+    Option<Vec<String>>,
+    VarName<'a>,
+    Option<Value>,
+);
 
 // Sample:
 // `/* Hello */`
@@ -51,8 +94,12 @@ where
     <T as InputTakeAtPosition>::Item: PartialEq<char>,
 {
     alt((
-        delimited(tag("/*"), take_until("*/"), tag("*/")),
-        delimited(tag("//"), take_till(|c| c == '\n'), tag("\n")),
+        // Doc comments are left for `parse_doc`, except for the empty `/**/`
+        preceded(
+            not(pair(tag("/**"), not(tag("/")))),
+            delimited(tag("/*"), take_until("*/"), tag("*/")),
+        ),
+        delimited(tag("//"), take_till(|c| c == '\n'), alt((tag("\n"), eof))),
     ))(input)
 }
 
@@ -94,16 +141,116 @@ where
 
 // Sample
 // ```
-// /** This is a doc */
+//   /* ends here */ ;  // and this is skipped too
 // ```
-fn parse_doc(input: &str) -> IResult<&str, Doc> {
+// The `;` ending a field, message, import or declaration, after any spaces
+// and comments, along with the comment after it
+fn end_of_statement(input: &str) -> IResult<&str, &str> {
     delimited(
+        many0(alt((multispace1, parse_comment))),
+        tag(";"),
+        space_delimited(opt(parse_comment)),
+    )(input)
+}
+
+// The name of a declared type, failing at `name` when it is not a valid Avro
+// name. `name` must be a slice of `input`
+fn parse_name<'a>(input: &'a str, name: &'a str) -> Result<Name, nom::Err<ParserError<&'a str>>> {
+    Name::new(name).map_err(|_| {
+        nom::Err::Failure(ParserError::from_external_error(
+            &input[Span::of(input, name).start..],
+            ErrorKind::Verify,
+            TokenError::InvalidName(name.to_string()),
+        ))
+    })
+}
+
+// Sample
+// ```
+// /** This is a doc */
+// ```
+// Borrowed and as written, see `normalize_doc` for what is stored
+fn parse_doc(input: &str) -> IResult<&str, &str> {
+    preceded(
         tag("/**"),
-        map(take_until("*/"), |v: &str| String::from(v.trim())),
-        tag("*/"),
+        cut(context(
+            "unterminated doc comment",
+            terminated(take_until("*/"), tag("*/")),
+        )),
     )(input)
 }
 
+// The text of a doc comment the way the Java tool stores it. When every line
+// after the first starts with a `*`, it is removed along with the space
+// following it, otherwise the indentation those lines share is removed. The
+// text is trimmed after, and the `\` escaping a `/` or a `\` after a `*` is
+// removed, which is how a doc holds `*/`.
+pub(crate) fn normalize_doc(doc: &str) -> String {
+    let mut lines = doc.lines();
+    let first = lines.next().unwrap_or_default();
+    let rest: Vec<&str> = lines.collect();
+    let filled = || rest.iter().filter(|line| !line.trim().is_empty());
+    let starred = filled().count() > 0 && filled().all(|line| line.trim_start().starts_with('*'));
+    let rest: Vec<&str> = if starred {
+        rest.iter()
+            .map(|line| match line.trim_start().strip_prefix('*') {
+                Some(text) => text.strip_prefix(' ').unwrap_or(text),
+                None => line,
+            })
+            .collect()
+    } else {
+        let indent = filled()
+            .map(|line| line.len() - line.trim_start().len())
+            .min()
+            .unwrap_or(0);
+        rest.iter()
+            .map(|line| line.get(indent..).unwrap_or_else(|| line.trim_start()))
+            .collect()
+    };
+    let text = std::iter::once(first)
+        .chain(rest)
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join("\n");
+    unescape_doc(text.trim())
+}
+
+fn unescape_doc(doc: &str) -> String {
+    let mut text = String::with_capacity(doc.len());
+    let mut chars = doc.chars();
+    while let Some(c) = chars.next() {
+        text.push(c);
+        if c == '*' {
+            let mut rest = chars.clone();
+            if rest.next() == Some('\\') && matches!(rest.next(), Some('/' | '\\')) {
+                chars.next();
+            }
+        }
+    }
+    text
+}
+
+fn normalize_optional_doc(doc: &mut Option<Doc>) {
+    if let Some(text) = doc {
+        *text = normalize_doc(text);
+    }
+}
+
+// Normalizes the doc of a declared type and those of its fields
+fn normalize_docs(schema: &mut Schema) {
+    match schema {
+        Schema::Record { doc, fields, .. } => {
+            normalize_optional_doc(doc);
+            for field in fields {
+                normalize_optional_doc(&mut field.doc);
+            }
+        }
+        Schema::Enum { doc, .. } | Schema::Fixed { doc, .. } => normalize_optional_doc(doc),
+        Schema::Decimal { inner, .. } => normalize_docs(inner),
+        _ => {}
+    }
+}
+
 // The name portion of the fullname of named types, record field names, and enum symbols must:
 //
 // - start with [A-Za-z_]
@@ -132,7 +279,10 @@ fn parse_aliases(i: &str) -> IResult<&str, Vec<String>> {
             space_or_comment_delimited(tag("(")),
             delimited(
                 tag("["),
-                separated_list1(tag(","), space_or_comment_delimited(parse_namespace_value)),
+                separated_list1(
+                    tag(","),
+                    space_or_comment_delimited(map(parse_namespace_value, String::from)),
+                ),
                 space_or_comment_delimited(tag("]")),
             ),
             space_or_comment_delimited(tag(")")),
@@ -154,7 +304,8 @@ fn parse_namespaced_aliases(i: &str) -> IResult<&str, Vec<Alias>> {
                 separated_list1(
                     tag(","),
                     space_or_comment_delimited(map_res(parse_namespace_value, |namespace| {
-                        Alias::new(&namespace)
+                        Alias::new(namespace)
+                            .map_err(|_| TokenError::InvalidName(namespace.to_string()))
                     })),
                 ),
                 space_or_comment_delimited(tag("]")),
@@ -173,32 +324,76 @@ fn parse_logical_type(i: &str) -> IResult<&str, Schema> {
         tag("@logicalType"),
         delimited(
             tag("("),
-            map(parse_string_uni, |s| match s.as_str() {
-                "timestamp-micros" => {
-                    return Schema::TimestampMicros;
-                }
-                "time-micros" => Schema::TimeMicros,
-                "duration" => Schema::Duration,
-                _ => todo!(),
-            }),
+            cut(map_res(parse_string_uni, |s| match s.as_ref() {
+                "uuid" => Ok(Schema::Uuid),
+                "date" => Ok(Schema::Date),
+                "time-millis" => Ok(Schema::TimeMillis),
+                "timestamp-millis" => Ok(Schema::TimestampMillis),
+                "timestamp-micros" => Ok(Schema::TimestampMicros),
+                "time-micros" => Ok(Schema::TimeMicros),
+                "local-timestamp-millis" => Ok(Schema::LocalTimestampMillis),
+                "local-timestamp-micros" => Ok(Schema::LocalTimestampMicros),
+                "duration" => Ok(Schema::Duration),
+                other => Err(TokenError::UnsupportedLogicalType(other.to_string())),
+            })),
             space_or_comment_delimited(tag(")")),
         ),
     )(i)
 }
 
+// The type following `@logicalType` must be the one the logical type is
+// stored as. A duration is a fixed of 12 bytes, which a field can only refer
+// to by name, so any named type is taken for it, while a fixed declared as a
+// duration must have those 12 bytes
+fn check_logical_type(logical: &Schema, declared: &Schema) -> Result<(), TokenError> {
+    let (name, expected, valid) = match logical {
+        Schema::Uuid => ("uuid", "`string`", *declared == Schema::String),
+        Schema::Date => ("date", "`int`", *declared == Schema::Int),
+        Schema::TimeMillis => ("time-millis", "`int`", *declared == Schema::Int),
+        Schema::TimeMicros => ("time-micros", "`long`", *declared == Schema::Long),
+        Schema::TimestampMillis => ("timestamp-millis", "`long`", *declared == Schema::Long),
+        Schema::TimestampMicros => ("timestamp-micros", "`long`", *declared == Schema::Long),
+        Schema::LocalTimestampMillis => (
+            "local-timestamp-millis",
+            "`long`",
+            *declared == Schema::Long,
+        ),
+        Schema::LocalTimestampMicros => (
+            "local-timestamp-micros",
+            "`long`",
+            *declared == Schema::Long,
+        ),
+        Schema::Duration => (
+            "duration",
+            "a fixed of 12 bytes",
+            matches!(
+                declared,
+                Schema::Ref { .. } | Schema::Fixed { size: 12, .. }
+            ),
+        ),
+        _ => return Ok(()),
+    };
+    if valid {
+        return Ok(());
+    }
+    Err(TokenError::LogicalTypeMismatch {
+        logical: name,
+        expected,
+        declared: type_name(declared),
+    })
+}
+
 // TODO: First and last letter should be alpha only
-fn parse_namespace_value(input: &str) -> IResult<&str, String> {
+fn parse_namespace_value(input: &str) -> IResult<&str, &str> {
     let ns = take_while(|c| char::is_alphanumeric(c) || c == '.' || c == '_');
-    map(delimited(char('"'), ns, char('"')), |s: &str| {
-        String::from(s)
-    })(input)
+    delimited(char('"'), ns, char('"'))(input)
 }
 
 // Example:
 // ```
 // @namespace("org.foo.KindOf")
 // ```
-fn parse_namespace(input: &str) -> IResult<&str, String> {
+fn parse_namespace(input: &str) -> IResult<&str, &str> {
     preceded(
         tag("@namespace"),
         delimited(
@@ -209,13 +404,115 @@ fn parse_namespace(input: &str) -> IResult<&str, String> {
     )(input)
 }
 
+// A custom annotation, kept as a property. Keys may contain `-` and `.`, the
+// ones of the annotations Avro defines are left to their own parser
+// Example:
+// ```
+// @version("1.2")
+// @java-class({"name": "Foo", "generic": true})
+// ```
+fn parse_property(input: &str) -> IResult<&str, (&str, Value)> {
+    preceded(
+        char('@'),
+        pair(
+            verify(
+                take_while1(|c: char| c.is_alphanumeric() || matches!(c, '_' | '-' | '.')),
+                |key: &str| is_property_key(key),
+            ),
+            delimited(
+                space_or_comment_delimited(tag("(")),
+                parse_json,
+                space_or_comment_delimited(tag(")")),
+            ),
+        ),
+    )(input)
+}
+
+// Whether `key` is read as the key of a custom annotation
+pub(crate) fn is_property_key(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'))
+        && !matches!(key, "namespace" | "aliases" | "order" | "logicalType")
+}
+
+// Any JSON value, as given to a custom annotation
+// Sample
+// ```
+// {"owner": "ops", "tags": ["pii", 1, null]}
+// ```
+fn parse_json(input: &str) -> IResult<&str, Value> {
+    parse_nested_json(input, 0)
+}
+
+// Annotation values nested deeper than this are rejected before they overflow
+// the stack
+const MAX_JSON_DEPTH: usize = 64;
+
+// A JSON value inside `depth` arrays and objects
+fn parse_nested_json(input: &str, depth: usize) -> IResult<&str, Value> {
+    if depth > MAX_JSON_DEPTH {
+        return Err(nom::Err::Failure(ParserError {
+            input,
+            kind: ErrorKind::TooLarge,
+            context: Some("annotation values are nested too deep"),
+            cause: None,
+        }));
+    }
+    let nested = |input| parse_nested_json(input, depth + 1);
+    alt((
+        map(parse_string_uni, |s| Value::String(s.into_owned())),
+        value(Value::Bool(true), tag("true")),
+        value(Value::Bool(false), tag("false")),
+        value(Value::Null, tag("null")),
+        // Integers as serde_json reads them, other numbers to the closest double
+        map_opt(recognize_float, |number: &str| {
+            match serde_json::from_str::<Value>(number) {
+                Ok(value) if !value.is_f64() => Some(value),
+                _ => number
+                    .parse()
+                    .ok()
+                    .and_then(serde_json::Number::from_f64)
+                    .map(Value::Number),
+            }
+        }),
+        map(
+            delimited(
+                terminated(tag("["), multispace0),
+                separated_list0(space_delimited(tag(",")), nested),
+                preceded(multispace0, tag("]")),
+            ),
+            Value::Array,
+        ),
+        map(
+            delimited(
+                terminated(tag("{"), multispace0),
+                separated_list0(
+                    space_delimited(tag(",")),
+                    separated_pair(parse_string_uni, space_delimited(tag(":")), nested),
+                ),
+                preceded(multispace0, tag("}")),
+            ),
+            |entries| {
+                Value::Object(
+                    entries
+                        .into_iter()
+                        .map(|(key, value)| (key.into_owned(), value))
+                        .collect(),
+                )
+            },
+        ),
+    ))(input)
+}
+
 // Example:
 // ```
 // @order("ascending")  // default
 // @order("descending")
 // @order("ignore")
 // ```
-pub fn parse_order(input: &str) -> IResult<&str, RecordFieldOrder> {
+pub(crate) fn parse_order(input: &str) -> IResult<&str, RecordFieldOrder> {
     let ascending = value(RecordFieldOrder::Ascending, tag(r#""ascending""#));
     let descending = value(RecordFieldOrder::Descending, tag(r#""descending""#));
     let ignore = value(RecordFieldOrder::Ignore, tag(r#""ignore""#));
@@ -230,6 +527,203 @@ pub fn parse_order(input: &str) -> IResult<&str, RecordFieldOrder> {
     )(input)
 }
 
+// An annotation, before a declaration or after the type of a field
+#[derive(Debug, Clone, PartialEq)]
+enum Annotation {
+    Aliases(Vec<String>),
+    Namespace(String),
+    Order(RecordFieldOrder),
+    LogicalType(Schema),
+    // Any other annotation, with a JSON value
+    Property(String, Value),
+}
+
+impl Annotation {
+    fn name(&self) -> String {
+        match self {
+            Annotation::Aliases(_) => "@aliases".to_string(),
+            Annotation::Namespace(_) => "@namespace".to_string(),
+            Annotation::Order(_) => "@order".to_string(),
+            Annotation::LogicalType(_) => "@logicalType".to_string(),
+            Annotation::Property(key, _) => format!("@{key}"),
+        }
+    }
+}
+
+// An annotation which is accepted but has no effect of its own
+#[derive(Debug, Clone, PartialEq)]
+struct IgnoredAnnotation<'a> {
+    name: String,
+    source: &'a str,
+    // Given again on the same declaration, merged or ignored instead of
+    // failing, rather than put on a type which keeps no properties
+    repeated: bool,
+}
+
+impl IgnoredAnnotation<'_> {
+    fn warning(&self, input: &str) -> Warning {
+        let (code, message) = match (self.repeated, self.name.as_str()) {
+            (true, "@aliases") => (
+                WarningCode::RepeatedAnnotation,
+                "repeated `@aliases` annotation, the aliases are merged".to_string(),
+            ),
+            (true, name) => (
+                WarningCode::RepeatedAnnotation,
+                format!("repeated `{name}` annotation with the same value is ignored"),
+            ),
+            (false, name) => (
+                WarningCode::AnnotationIgnored,
+                format!("`{name}` annotation is ignored, the type it is on keeps no properties"),
+            ),
+        };
+        Warning::new(code, message, Some(Span::of(input, self.source)))
+    }
+}
+
+// The annotations of one declaration
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Annotations<'a> {
+    aliases: Option<Vec<String>>,
+    namespace: Option<String>,
+    order: Option<RecordFieldOrder>,
+    logical_type: Option<Schema>,
+    properties: BTreeMap<String, Value>,
+    // Where each property was first given
+    property_sources: Vec<(String, &'a str)>,
+    repeated: Vec<IgnoredAnnotation<'a>>,
+}
+
+impl<'a> Annotations<'a> {
+    // Aliases are merged, other annotations may only be repeated with the
+    // same value
+    fn add(&mut self, annotation: Annotation, source: &'a str) -> Result<(), TokenError> {
+        let name = annotation.name();
+        let same = match annotation {
+            Annotation::Aliases(aliases) => match &mut self.aliases {
+                Some(merged) => {
+                    for alias in aliases {
+                        if !merged.contains(&alias) {
+                            merged.push(alias);
+                        }
+                    }
+                    true
+                }
+                None => {
+                    self.aliases = Some(aliases);
+                    return Ok(());
+                }
+            },
+            Annotation::Namespace(namespace) => match &self.namespace {
+                Some(first) => *first == namespace,
+                None => {
+                    self.namespace = Some(namespace);
+                    return Ok(());
+                }
+            },
+            Annotation::Order(order) => match &self.order {
+                Some(first) => *first == order,
+                None => {
+                    self.order = Some(order);
+                    return Ok(());
+                }
+            },
+            // Logical types are equal to their base type in canonical form
+            Annotation::LogicalType(logical_type) => match &self.logical_type {
+                Some(first) => SchemaKind::from(first) == SchemaKind::from(&logical_type),
+                None => {
+                    self.logical_type = Some(logical_type);
+                    return Ok(());
+                }
+            },
+            Annotation::Property(key, value) => match self.properties.get(&key) {
+                Some(first) => *first == value,
+                None => {
+                    self.properties.insert(key, value);
+                    self.property_sources.push((name, source));
+                    return Ok(());
+                }
+            },
+        };
+        if !same {
+            return Err(TokenError::ConflictingAnnotation(name));
+        }
+        self.repeated.push(IgnoredAnnotation {
+            name,
+            source,
+            repeated: true,
+        });
+        Ok(())
+    }
+
+    // Aliases of named types are checked by `parse_namespaced_aliases`
+    fn named_aliases(&self) -> Option<Vec<Alias>> {
+        self.aliases.as_ref().map(|aliases| {
+            aliases
+                .iter()
+                .map(|alias| Alias::new(alias).expect("alias should be a valid name"))
+                .collect()
+        })
+    }
+}
+
+// Collect the annotations accepted by `annotation`, in any order, repeated
+// ones included.
+// Sample:
+// ```
+// @aliases(["a"]) @namespace("org.foo") @aliases(["b"])
+// ```
+fn parse_annotations<'a>(
+    annotation: impl Parser<&'a str, Annotation, ParserError<&'a str>> + 'a,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Annotations<'a>> {
+    let mut found = many0(space_or_comment_delimited(consumed(annotation)));
+    move |input| {
+        let (tail, found) = found(input)?;
+        let mut annotations = Annotations::default();
+        for (source, annotation) in found {
+            let source = source.trim_end();
+            annotations.add(annotation, source).map_err(|err| {
+                // Point at the annotation, followed by the rest of the input
+                let at = &input[Span::of(input, source).start..];
+                let err = ParserError::from_external_error(at, ErrorKind::Verify, err);
+                nom::Err::Failure(ParserError::add_context(
+                    at,
+                    "annotation repeated with a different value",
+                    err,
+                ))
+            })?;
+        }
+        Ok((tail, annotations))
+    }
+}
+
+// Aliases of a named type
+fn named_aliases_annotation(input: &str) -> IResult<&str, Annotation> {
+    map(parse_namespaced_aliases, |aliases| {
+        Annotation::Aliases(aliases.iter().map(|alias| alias.fullname(None)).collect())
+    })(input)
+}
+
+// Annotations written before the `protocol` keyword
+fn protocol_annotation(input: &str) -> IResult<&str, Annotation> {
+    alt((
+        map(parse_namespace, |namespace| {
+            Annotation::Namespace(namespace.to_string())
+        }),
+        named_aliases_annotation,
+        map(parse_property, |(key, value)| {
+            Annotation::Property(key.to_string(), value)
+        }),
+    ))(input)
+}
+
+// Annotations written after the type of a field
+fn field_annotation(input: &str) -> IResult<&str, Annotation> {
+    alt((
+        map(parse_order, Annotation::Order),
+        map(parse_aliases, Annotation::Aliases),
+    ))(input)
+}
+
 /** ***************************** */
 /** Map Native and Logical Types  */
 /** ***************************** */
@@ -239,30 +733,118 @@ pub fn parse_order(input: &str) -> IResult<&str, RecordFieldOrder> {
 // "pepe"
 // ```
 fn map_string(input: &str) -> IResult<&str, AvroValue> {
-    map(parse_string_uni, |v| AvroValue::String(v))(input)
+    map(parse_string_uni, |v| AvroValue::String(v.into_owned()))(input)
 }
 
+// Any of the forms `Uuid` reads, written back hyphenated. A string which is
+// not a uuid fails right away, so that the error is at it even when nested in
+// an array or a map
 fn map_uuid(input: &str) -> IResult<&str, AvroValue> {
-    map_res(parse_string_uni, |v| -> Result<AvroValue, String> {
-        let uuid_val = Uuid::from_str(&v).map_err(|_e| "not a valid uuid".to_string())?;
-        Ok(AvroValue::Uuid(uuid_val))
-    })(input)
+    let (tail, v) = parse_string_uni(input)?;
+    match Uuid::from_str(&v) {
+        Ok(uuid) => Ok((tail, AvroValue::Uuid(uuid))),
+        Err(_) => Err(nom::Err::Failure(ParserError::from_external_error(
+            input,
+            ErrorKind::MapRes,
+            TokenError::Uuid(v.into_owned()),
+        ))),
+    }
 }
 
 fn map_bytes(input: &str) -> IResult<&str, AvroValue> {
     map(parse_string_uni, |v| {
-        let v: Vec<u8> = Vec::from(v);
-        AvroValue::Bytes(v)
+        AvroValue::Bytes(v.into_owned().into_bytes())
     })(input)
 }
 
-fn map_decimal(input: &str) -> IResult<&str, AvroValue> {
-    map(parse_string_uni, |v| {
-        let v: Vec<u8> = Vec::from(v);
-        AvroValue::Decimal(v.into())
+// Decimals are the unscaled value as a two's complement big-endian integer,
+// e.g. 20.00 in a `decimal(4, 2)`
+// ```
+// "\u{7}\u{d0}"
+// ```
+fn map_decimal(input: &str, precision: usize) -> IResult<&str, AvroValue> {
+    map_res(parse_string_uni, |v| -> Result<AvroValue, TokenError> {
+        let bytes = v
+            .chars()
+            .map(u8::try_from)
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|_| TokenError::InvalidDecimal(precision))?;
+        if unscaled_digits(&bytes) > precision {
+            return Err(TokenError::InvalidDecimal(precision));
+        }
+        Ok(AvroValue::Decimal(bytes.into()))
     })(input)
 }
 
+// The precision of a decimal is at least 1 and its scale, and fits in the
+// fixed it is stored in, if any
+fn check_decimal(precision: usize, scale: usize, size: Option<usize>) -> Result<(), TokenError> {
+    if precision == 0 {
+        return Err(TokenError::DecimalPrecision);
+    }
+    if scale > precision {
+        return Err(TokenError::DecimalScale { precision, scale });
+    }
+    match size.map(|size| (size, max_decimal_digits(size))) {
+        Some((size, max)) if precision > max => Err(TokenError::DecimalSize {
+            size,
+            max,
+            precision,
+        }),
+        _ => Ok(()),
+    }
+}
+
+// Durations are 12 bytes, the months, days and milliseconds as little
+// endian integers, or an object with any of the three. A string which is not
+// 12 bytes fails right away, to tell why
+// ```
+// "\u{1}\u{0}\u{0}\u{0}\u{2}\u{0}\u{0}\u{0}\u{3}\u{0}\u{0}\u{0}"
+// {"months": 1, "days": 2, "millis": 3}
+// ```
+fn map_duration(input: &str) -> IResult<&str, AvroValue> {
+    let bytes = map_res(parse_string_uni, |v| -> Result<AvroValue, TokenError> {
+        let bytes = v
+            .chars()
+            .map(u8::try_from)
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|_| TokenError::InvalidDuration)?;
+        let bytes: [u8; 12] = bytes.try_into().map_err(|_| TokenError::InvalidDuration)?;
+        Ok(AvroValue::Duration(Duration::from(bytes)))
+    });
+    let part = separated_pair(
+        delimited(
+            char('"'),
+            alt((tag("months"), tag("days"), tag("millis"))),
+            char('"'),
+        ),
+        space_delimited(tag(":")),
+        map_res(digit1, |v: &str| v.parse::<u32>()),
+    );
+    let parts = map_res(
+        delimited(
+            pair(tag("{"), multispace0),
+            separated_list0(space_delimited(tag(",")), part),
+            pair(multispace0, tag("}")),
+        ),
+        |parts| -> Result<AvroValue, TokenError> {
+            let mut found = HashMap::new();
+            for (unit, amount) in parts {
+                if found.insert(unit, amount).is_some() {
+                    return Err(TokenError::Duplicate(unit.to_string()));
+                }
+            }
+            let amount = |unit| found.get(unit).copied().unwrap_or(0);
+            Ok(AvroValue::Duration(Duration::new(
+                Months::new(amount("months")),
+                Days::new(amount("days")),
+                Millis::new(amount("millis")),
+            )))
+        },
+    );
+    alt((preceded(peek(char('"')), cut(bytes)), parts))(input)
+}
+
 // Sample
 // ```
 // null
@@ -283,57 +865,55 @@ fn map_bool(input: &str) -> IResult<&str, AvroValue> {
 
 // Sample:
 // ```
-// 20
+// -20
 // ```
 fn map_int(input: &str) -> IResult<&str, AvroValue> {
-    map(map_res(digit1, |v: &str| v.parse::<i32>()), |v| {
+    map(map_res(integer, |v: &str| v.parse::<i32>()), |v| {
         AvroValue::Int(v)
     })(input)
 }
 
 // Sample:
 // ```
-// 20
+// -20
 // ```
 fn map_long(input: &str) -> IResult<&str, AvroValue> {
-    map(map_res(digit1, |v: &str| v.parse::<i64>()), |v| {
+    map(map_res(integer, |v: &str| v.parse::<i64>()), |v| {
         AvroValue::Long(v)
     })(input)
 }
 
+fn integer(input: &str) -> IResult<&str, &str> {
+    recognize(pair(opt(char('-')), digit1))(input)
+}
+
 // Sample:
 // ```
-// 20.0
+// -20.5e3
 // ```
 fn map_float(input: &str) -> IResult<&str, AvroValue> {
     map(
-        map_res(
-            take_while1(|c| char::is_digit(c, 10) || c == '.' || c == 'e'),
-            |v: &str| {
-                // Hack to properly deal with float + avro
-                let val = v.parse::<f32>().map_err(|e| e.to_string())?;
-                if val.is_infinite() {
-                    return Err("Invalid float".to_string());
-                }
+        map_res(recognize_float, |v: &str| -> Result<f64, TokenError> {
+            // Hack to properly deal with float + avro
+            let val = v.parse::<f32>()?;
+            if val.is_infinite() {
+                return Err(TokenError::FloatOutOfRange(v.to_string()));
+            }
 
-                v.parse::<f64>().map_err(|e| e.to_string())
-            },
-        ),
-        |v| AvroValue::Double(v),
+            Ok(v.parse::<f64>()?)
+        }),
+        AvroValue::Double,
     )(input)
 }
 
 // Sample:
 // ```
-// 20.0
+// -20.5e3
 // ```
 fn map_double(input: &str) -> IResult<&str, AvroValue> {
     map(
-        map_res(
-            take_while1(|c| char::is_digit(c, 10) || c == '.' || c == 'e'),
-            |v: &str| v.parse::<f64>(),
-        ),
-        |v| AvroValue::Double(v),
+        map_res(recognize_float, |v: &str| v.parse::<f64>()),
+        AvroValue::Double,
     )(input)
 }
 
@@ -342,351 +922,451 @@ fn map_usize(input: &str) -> IResult<&str, usize> {
     map_res(digit1, |v: &str| v.parse::<usize>())(input)
 }
 
-// Identify correct Schema
-fn map_type_to_schema(input: &str) -> IResult<&str, Schema> {
+// `void` is a keyword, only valid as the return type of a message
+fn parse_void(input: &str) -> IResult<&str, &str> {
+    terminated(
+        tag("void"),
+        not(satisfy(|c: char| c.is_alphanumeric() || c == '_')),
+    )(input)
+}
+
+// The schema of a primitive or logical type keyword
+fn primitive_schema(keyword: &str) -> Option<Schema> {
+    let schema = match keyword {
+        "null" => Schema::Null,
+        "boolean" => Schema::Boolean,
+        "string" => Schema::String,
+        "int" => Schema::Int,
+        "double" => Schema::Double,
+        "float" => Schema::Float,
+        "long" => Schema::Long,
+        "bytes" => Schema::Bytes,
+        "time_ms" => Schema::TimeMillis,
+        "timestamp_ms" => Schema::TimestampMillis,
+        "date" => Schema::Date,
+        "uuid" => Schema::Uuid,
+        _ => return None,
+    };
+    Some(schema)
+}
+
+// Annotations of the type inside the angle brackets of an array or a map
+fn type_annotation(input: &str) -> IResult<&str, Annotation> {
     alt((
-        preceded(
-            tag("array"),
+        map(parse_logical_type, Annotation::LogicalType),
+        map(parse_property, |(key, value)| {
+            Annotation::Property(key.to_string(), value)
+        }),
+    ))(input)
+}
+
+// A type which may be annotated: the items of an array, the values of a map,
+// a branch of a union, or the type of a message parameter or response. A
+// logical type replaces the type it annotates. Other annotations are
+// returned as ignored: apache_avro keeps no attributes on arrays, maps,
+// primitives or references, so they have nowhere to be stored
+// Sample
+// ```
+// @logicalType("timestamp-micros") long
+// ```
+fn map_annotated_type(
+    input: &str,
+    depth: usize,
+) -> IResult<&str, (Schema, Vec<IgnoredAnnotation<'_>>)> {
+    let (tail, (source, annotations)) = consumed(parse_annotations(type_annotation))(input)?;
+    let (tail, (schema, mut ignored)) = map_nested_type_to_schema(tail, depth)?;
+    let dropped = annotations
+        .property_sources
+        .into_iter()
+        .map(|(name, source)| IgnoredAnnotation {
+            name,
+            source,
+            repeated: false,
+        });
+    ignored.splice(0..0, annotations.repeated.into_iter().chain(dropped));
+    match annotations.logical_type {
+        Some(logical_type) => {
+            check_logical_type(&logical_type, &schema).map_err(|err| {
+                // Point at the annotation
+                let at = &input[Span::of(input, source).start + source.find('@').unwrap_or(0)..];
+                nom::Err::Failure(ParserError::from_external_error(at, ErrorKind::Verify, err))
+            })?;
+            Ok((tail, (logical_type, ignored)))
+        }
+        None => Ok((tail, (schema, ignored))),
+    }
+}
+
+// Types nested deeper than this are rejected before they overflow the stack
+const MAX_TYPE_DEPTH: usize = 64;
+
+/// Parse a type, as written before the name of a field or a message
+/// parameter.
+///
+/// The keyword or name a type starts with is read once, and only the parser
+/// for that kind of type runs on what follows. Names are taken as references.
+///
+/// ```
+/// use apache_avro::schema::Schema;
+/// use avdl_parser::parser::map_type_to_schema;
+///
+/// let (tail, schema) = map_type_to_schema("array<string> tags").unwrap();
+/// assert_eq!(schema, Schema::Array(Box::new(Schema::String)));
+/// assert_eq!(tail.trim(), "tags");
+///
+/// let (_tail, schema) = map_type_to_schema("Address home").unwrap();
+/// assert!(matches!(schema, Schema::Ref { name } if name.name == "Address"));
+/// ```
+pub fn map_type_to_schema(input: &str) -> IResult<&str, Schema> {
+    map(
+        |input| map_nested_type_to_schema(input, 0),
+        |(schema, _ignored)| schema,
+    )(input)
+}
+
+// Two branches of a union are the same type when they have the same name as
+// written, or have no name and are of the same kind. Different named types
+// may share a union
+fn same_union_branch(a: &Schema, b: &Schema) -> bool {
+    match (a.name(), b.name()) {
+        (Some(a), Some(b)) => a == b,
+        (None, None) => SchemaKind::from(a) == SchemaKind::from(b),
+        _ => false,
+    }
+}
+
+// A type inside `depth` arrays, maps and unions, along with the annotations
+// ignored inside it
+fn map_nested_type_to_schema(
+    input: &str,
+    depth: usize,
+) -> IResult<&str, (Schema, Vec<IgnoredAnnotation<'_>>)> {
+    if depth > MAX_TYPE_DEPTH {
+        return Err(nom::Err::Failure(ParserError {
+            input,
+            kind: ErrorKind::TooLarge,
+            context: Some("types are nested too deep"),
+            cause: None,
+        }));
+    }
+    let (tail, keyword) = preceded(
+        cut(context(
+            "`void` is only valid as a message return type; use `null` for a null-typed field",
+            not(space_or_comment_delimited(parse_void)),
+        )),
+        space_or_comment_delimited(parse_var_name),
+    )(input)?;
+    match keyword {
+        "array" => map(
             delimited(
-                tag("<"),
-                map(map_type_to_schema, |s| Schema::Array(Box::new(s))),
-                tag(">"),
+                space_or_comment_delimited(tag("<")),
+                |input| map_annotated_type(input, depth + 1),
+                space_or_comment_delimited(tag(">")),
             ),
-        ),
-        map(
-            preceded(
-                space_or_comment_delimited(tag("union")),
-                delimited(
-                    space_delimited(tag("{")),
-                    separated_list1(space_delimited(tag(",")), map_type_to_schema),
-                    space_delimited(tag("}")),
-                ),
+            |(s, ignored)| (Schema::Array(Box::new(s)), ignored),
+        )(tail),
+        "map" => map(
+            delimited(
+                space_or_comment_delimited(tag("<")),
+                |input| map_annotated_type(input, depth + 1),
+                space_or_comment_delimited(tag(">")),
             ),
-            |union_schemas| {
-                Schema::Union(
-                    UnionSchema::new(union_schemas).expect("Failed to create union schema"),
-                )
-            },
-        ),
-        value(Schema::Null, space_or_comment_delimited(tag("null"))),
-        value(Schema::Boolean, space_or_comment_delimited(tag("boolean"))),
-        value(Schema::String, space_or_comment_delimited(tag("string"))),
-        value(Schema::Int, space_or_comment_delimited(tag("int"))),
-        value(Schema::Double, space_or_comment_delimited(tag("double"))),
-        value(Schema::Float, space_or_comment_delimited(tag("float"))),
-        value(Schema::Long, space_or_comment_delimited(tag("long"))),
-        value(Schema::Bytes, space_or_comment_delimited(tag("bytes"))),
-        value(
-            Schema::TimeMillis,
-            space_or_comment_delimited(tag("time_ms")),
-        ),
-        value(
-            Schema::TimestampMillis,
-            space_or_comment_delimited(tag("timestamp_ms")),
-        ),
-        value(Schema::Date, space_or_comment_delimited(tag("date"))),
-        value(Schema::Uuid, space_or_comment_delimited(tag("uuid"))),
-        map(
-            preceded(
-                space_or_comment_delimited(tag("decimal")),
-                delimited(
-                    tag("("),
-                    pair(terminated(map_usize, space_delimited(tag(","))), map_usize),
-                    tag(")"),
+            |(s, ignored)| (Schema::Map(Box::new(s)), ignored),
+        )(tail),
+        "union" => {
+            let (rest, branches) = delimited(
+                space_or_comment_delimited(tag("{")),
+                separated_list1(
+                    space_or_comment_delimited(tag(",")),
+                    consumed(|input| map_annotated_type(input, depth + 1)),
                 ),
+                space_or_comment_delimited(tag("}")),
+            )(tail)?;
+            // A union right in a union is an error at the inner one, which
+            // no other reading of the input can fix
+            if let Some((source, _)) = branches
+                .iter()
+                .find(|(_, (schema, _))| matches!(schema, Schema::Union(_)))
+            {
+                // Point at the inner union, followed by the rest of the input
+                let at = skip_comments(&tail[Span::of(tail, source).start..]);
+                return Err(nom::Err::Failure(ParserError::from_external_error(
+                    at,
+                    ErrorKind::Verify,
+                    TokenError::NestedUnion,
+                )));
+            }
+            // So is a type repeated in a union, at its second branch
+            let repeated = branches
+                .iter()
+                .enumerate()
+                .find_map(|(i, (source, (schema, _)))| {
+                    branches[..i]
+                        .iter()
+                        .any(|(_, (earlier, _))| same_union_branch(earlier, schema))
+                        .then_some((source, schema))
+                });
+            if let Some((source, schema)) = repeated {
+                let at = skip_comments(&tail[Span::of(tail, source).start..]);
+                return Err(nom::Err::Failure(ParserError::from_external_error(
+                    at,
+                    ErrorKind::Verify,
+                    TokenError::DuplicateUnionType(type_name(schema)),
+                )));
+            }
+            let (branches, ignored): (Vec<Schema>, Vec<_>) =
+                branches.into_iter().map(|(_, branch)| branch).unzip();
+            let union = UnionSchema::new(branches).expect("union branches are checked");
+            Ok((rest, (Schema::Union(union), ignored.concat())))
+        }
+        "decimal" => cut(map_res(
+            delimited(
+                space_or_comment_delimited(tag("(")),
+                pair(
+                    terminated(map_usize, space_or_comment_delimited(tag(","))),
+                    map_usize,
+                ),
+                space_or_comment_delimited(tag(")")),
             ),
-            |(precision, scale)| {
-                // TODO: Review If inner should be float or calculated differently
-                Schema::Decimal {
-                    precision: precision,
-                    scale: scale,
+            |(precision, scale)| -> Result<_, TokenError> {
+                check_decimal(precision, scale, None)?;
+                let decimal = Schema::Decimal {
+                    precision,
+                    scale,
                     inner: Box::new(Schema::Bytes),
-                }
-            },
-        ),
-        map_res(
-            space_or_comment_delimited(parse_var_name),
-            |reference_name| -> Result<Schema, String> {
-                let name = Name::new(reference_name).map_err(|_e| "Invalid reference name")?;
-                Ok(Schema::Ref { name })
+                };
+                Ok((decimal, Vec::new()))
             },
-        ),
-    ))(input)
+        ))(tail),
+        keyword => match primitive_schema(keyword) {
+            Some(schema) => Ok((tail, (schema, Vec::new()))),
+            None => {
+                let name = Name::new(keyword).map_err(|_| {
+                    nom::Err::Error(ParserError::from_external_error(
+                        input,
+                        ErrorKind::MapRes,
+                        TokenError::InvalidName(keyword.to_string()),
+                    ))
+                })?;
+                Ok((tail, (Schema::Ref { name }, Vec::new())))
+            }
+        },
+    }
 }
 
-// Identify default parser based on the given Schema
-fn parse_based_on_schema<'r>(
-    schema: Box<Schema>,
-) -> Box<dyn FnMut(&'r str) -> IResult<&'r str, AvroValue>> {
-    match *schema {
-        Schema::Null => Box::new(map_null),
-        Schema::Boolean => Box::new(map_bool),
-        Schema::Int => Box::new(map_int),
-        Schema::Long => Box::new(map_long),
-        Schema::Float => Box::new(map_float),
-        Schema::Double => Box::new(map_double),
-        Schema::Bytes => Box::new(map_bytes),
-        Schema::String => Box::new(map_string),
-        Schema::Array(schema) => Box::new(move |input: &'r str| {
-            delimited(
-                tag("["),
-                map(
-                    separated_list0(tag(","), parse_based_on_schema(schema.clone())),
-                    |s| AvroValue::Array(s),
-                ),
-                tag("]"),
-            )(input)
-        })
-            as Box<dyn FnMut(&'r str) -> IResult<&'r str, AvroValue> + '_>,
-        Schema::Union(union_schema) => {
-            let schema = union_schema
-                .variants()
-                .first()
-                .expect("There should be at least 2 schemas in the union");
-
-            parse_based_on_schema(Box::new(schema.clone()))
-        }
+// Parse a default value of `schema`
+// Fails at a `null` default of a field which can't be null, since only
+// `null` and unions whose first branch is null take it. A reference is left
+// alone, the type it refers to is not known yet
+fn reject_null_default<'a>(input: &'a str, field: &str, schema: &Schema) -> IResult<&'a str, ()> {
+    let nullable = match schema {
+        Schema::Null | Schema::Ref { .. } => true,
+        Schema::Union(union) => union.variants().first() == Some(&Schema::Null),
+        _ => false,
+    };
+    let null = terminated(
+        tag::<_, _, ParserError<&str>>("null"),
+        not(satisfy(|c| c.is_alphanumeric() || c == '_')),
+    )(input);
+    if null.is_ok() && !nullable {
+        return Err(nom::Err::Failure(ParserError {
+            input,
+            kind: ErrorKind::Verify,
+            context: Some("invalid default value"),
+            cause: Some(TokenError::NullDefault {
+                field: field.to_string(),
+                type_name: type_name(schema),
+            }),
+        }));
+    }
+    Ok((input, ()))
+}
+
+fn parse_default<'r>(input: &'r str, schema: &Schema) -> IResult<&'r str, AvroValue> {
+    match schema {
+        Schema::Null => map_null(input),
+        Schema::Boolean => map_bool(input),
+        Schema::Int => map_int(input),
+        Schema::Long => map_long(input),
+        Schema::Float => map_float(input),
+        Schema::Double => map_double(input),
+        Schema::Bytes => map_bytes(input),
+        Schema::String => map_string(input),
+        Schema::Array(items) => delimited(
+            tag("["),
+            map(
+                separated_list0(space_delimited(tag(",")), |input| {
+                    parse_default(input, items)
+                }),
+                AvroValue::Array,
+            ),
+            tag("]"),
+        )(input),
+        // Defaults of unions are of their first type
+        Schema::Union(union_schema) => match union_schema.variants().first() {
+            Some(schema) => parse_default(input, schema),
+            None => fail(input),
+        },
 
         // Logical Types
-        Schema::Date => Box::new(map_int),
-        Schema::TimeMillis => Box::new(map_int),
-        Schema::TimestampMillis => Box::new(map_long),
-        Schema::Uuid => Box::new(map_uuid),
-        Schema::Decimal {
-            precision: _,
-            scale: _,
-            inner: _,
-        } => Box::new(map_decimal),
-        Schema::TimestampMicros => Box::new(map_long),
-        Schema::TimeMicros => Box::new(map_long),
-        Schema::Duration => todo!("This should be fixed"),
-        Schema::Ref { name: _ } => Box::new(parse_enum_default_symbol),
+        Schema::Date => map_int(input),
+        Schema::TimeMillis => map_int(input),
+        Schema::TimestampMillis => map_long(input),
+        Schema::Uuid => map_uuid(input),
+        Schema::Decimal { precision, .. } => map_decimal(input, *precision),
+        Schema::TimestampMicros => map_long(input),
+        Schema::TimeMicros => map_long(input),
+        Schema::LocalTimestampMillis => map_long(input),
+        Schema::LocalTimestampMicros => map_long(input),
+        Schema::Ref { name: _ } => parse_enum_default_symbol(input),
+        Schema::Map(values) => parse_map_default(input, values),
+        Schema::Duration => map_duration(input),
 
-        _ => unimplemented!("Not implemented yet"),
+        // Inline named types take no default yet
+        _ => fail(input),
     }
 }
 
 // Sample:
 // ```
-// string name = "jon";
-// bytes name = "jon";
-// float age = 20;
-// double age = 20.0;
+// {"a": 1, "b": 2}
 // ```
-fn parse_field(
-    input: &str,
-) -> IResult<
-    &str,
-    (
-        Schema,
-        Option<Doc>,
-        Option<RecordFieldOrder>,
-        Option<Vec<String>>,
-        VarName,
-        Option<Value>,
-    ),
-> {
-    let (tail, doc) = opt(parse_doc)(input)?;
-    let (tail, logical_schema) = opt(space_or_comment_delimited(parse_logical_type))(tail)?;
-    let (tail, schema) = map_type_to_schema(tail)?;
-
-    let schema = match logical_schema {
-        Some(s) => s,
-        None => schema,
-    };
-
-    let boxed_schema = Box::new(schema.clone());
-    // let default_parser = ;
-    let (tail, ((order, aliases), varname, defaults)) = terminated(
-        tuple((
-            permutation_opt((
-                space_or_comment_delimited(parse_order),
-                space_or_comment_delimited(parse_aliases),
-            )),
-            space_or_comment_delimited(parse_var_name),
-            // default
-            opt(preceded(
-                space_or_comment_delimited(tag("=")),
-                map_res(parse_based_on_schema(boxed_schema), |value| {
-                    value.try_into()
-                }),
-            )),
-        )),
-        preceded(space0, space_or_comment_delimited(tag(";"))),
-    )(tail)?;
+fn parse_map_default<'r>(input: &'r str, values: &Schema) -> IResult<&'r str, AvroValue> {
+    delimited(
+        tag("{"),
+        map(
+            separated_list0(
+                space_delimited(tag(",")),
+                pair(
+                    map(parse_string_uni, Cow::into_owned),
+                    preceded(space_delimited(tag(":")), |input| {
+                        parse_default(input, values)
+                    }),
+                ),
+            ),
+            |v| AvroValue::Map(HashMap::from_iter(v)),
+        ),
+        tag("}"),
+    )(input)
+}
 
-    Ok((tail, (schema, doc, order, aliases, varname, defaults)))
+// Defaults are kept as JSON in the schema
+fn to_json(value: AvroValue) -> Result<Value, TokenError> {
+    value.try_into().map_err(|_| TokenError::UnsupportedDefault)
 }
 
-/** ***************  */
-/**  Complex Types  */
-/** *************** */
+fn is_numeric(schema: &Schema) -> bool {
+    matches!(
+        schema,
+        Schema::Int | Schema::Long | Schema::Float | Schema::Double
+    )
+}
 
-// Samples
+// Fails on what follows a number written with a decimal comma, to point out
+// the separator must be a '.'
 // ```
-// array<long> arrayOfLongs;
-// array<long> @aliases(["vecOfLongs"]) arrayOfLongs;
+// ,5
+// _000,5
 // ```
-fn parse_array(
-    input: &str,
-) -> IResult<
-    &str,
-    (
-        Schema,
-        Option<Doc>,
-        Option<RecordFieldOrder>,
-        Option<Vec<String>>,
-        VarName,
-        Option<Value>,
-    ),
-> {
-    let (tail, doc) = opt(parse_doc)(input)?;
-    let (tail, schema_array_type) = preceded(
-        space_or_comment_delimited(tag("array")),
-        delimited(tag("<"), map_type_to_schema, tag(">")),
-    )(tail)?;
-    let schema = Box::new(schema_array_type.clone());
-    let array_default_parser = parse_based_on_schema(schema);
-    let (tail, ((order, aliases), varname, defaults)) = terminated(
-        tuple((
-            permutation_opt((
-                space_or_comment_delimited(parse_order),
-                space_or_comment_delimited(parse_aliases),
-            )),
-            space_delimited(parse_var_name),
-            // default
-            opt(preceded(
-                space_delimited(tag("=")),
-                delimited(
-                    tag("["),
-                    map_res(
-                        separated_list0(tag(","), array_default_parser),
-                        |value| AvroValue::Array(value).try_into(),
-                        // Value::Array,
-                    ),
-                    tag("]"),
-                ),
-            )),
-        )),
-        tag(";"),
-    )(tail)?;
-
-    Ok((
-        tail,
-        (
-            Schema::Array(Box::new(schema_array_type)),
-            doc,
-            order,
-            aliases,
-            varname,
-            defaults,
-        ),
-    ))
+fn decimal_comma_hint(input: &str) -> IResult<&str, ()> {
+    cut(context(
+        "use '.' as the decimal separator",
+        not(tuple((
+            take_while(|c: char| c.is_ascii_digit() || c == '_'),
+            char(','),
+            digit1,
+        ))),
+    ))(input)
 }
 
 // Sample:
 // ```
+// string name = "jon";
+// bytes name = "jon";
+// float age = 20;
+// double age = 20.0;
+// array<long> @aliases(["vecOfLongs"]) arrayOfLongs;
 // map<int> foo2 = {};
+// union { null, string } item = null;
 // ```
-fn parse_map(
+pub(crate) fn parse_field(input: &str) -> IResult<&str, FieldParts<'_>> {
+    map(parse_field_annotated, |(parts, _repeated)| parts)(input)
+}
+
+fn parse_field_annotated(
     input: &str,
-) -> IResult<
-    &str,
-    (
-        Schema,
-        Option<Doc>,
-        Option<RecordFieldOrder>,
-        Option<Vec<String>>,
-        VarName,
-        Option<Value>,
-    ),
-> {
+) -> IResult<&str, (FieldParts<'_>, Vec<IgnoredAnnotation<'_>>)> {
     let (tail, doc) = opt(parse_doc)(input)?;
-    let (tail, schema) = preceded(
-        space_or_comment_delimited(tag("map")),
-        delimited(tag("<"), map_type_to_schema, tag(">")),
+    let (tail, (logical_source, logical)) = consumed(parse_annotations(map(
+        parse_logical_type,
+        Annotation::LogicalType,
+    )))(tail)?;
+    let (tail, (schema, ignored)) = map_nested_type_to_schema(tail, 0)?;
+
+    let schema = match logical.logical_type {
+        Some(logical_type) => {
+            check_logical_type(&logical_type, &schema).map_err(|err| {
+                // Point at the annotation
+                let start =
+                    Span::of(input, logical_source).start + logical_source.find('@').unwrap_or(0);
+                let at = &input[start..];
+                nom::Err::Failure(ParserError::from_external_error(at, ErrorKind::Verify, err))
+            })?;
+            logical_type
+        }
+        None => schema,
+    };
+
+    // A number given to a union is for its first type
+    let numeric = match &schema {
+        Schema::Union(union) => {
+            matches!(union.variants().first(), Some(first) if is_numeric(first))
+        }
+        schema => is_numeric(schema),
+    };
+    let (tail, (annotations, varname)) = pair(
+        parse_annotations(field_annotation),
+        space_or_comment_delimited(parse_var_name),
     )(tail)?;
-    let schema_for_parser = Box::new(schema.clone());
-    let map_default_parser = parse_based_on_schema(schema_for_parser);
-    let (tail, ((order, aliases), varname, defaults)) = terminated(
-        tuple((
-            permutation_opt((
-                space_or_comment_delimited(parse_order),
-                space_or_comment_delimited(parse_aliases),
-            )),
-            space_delimited(parse_var_name),
-            // default
-            opt(preceded(
-                space_delimited(tag("=")),
-                delimited(
-                    tag("{"),
-                    map_res(
-                        separated_list0(
-                            space_delimited(tag(",")),
-                            pair(
-                                parse_string_uni,
-                                preceded(space_delimited(tag(":")), map_default_parser),
-                            ),
-                        ),
-                        |v| AvroValue::Map(HashMap::from_iter(v)).try_into(),
-                    ),
-                    tag("}"),
-                ),
-            )),
+    let (tail, defaults) = terminated(
+        opt(preceded(
+            terminated(space_or_comment_delimited(tag("=")), |input| {
+                reject_null_default(input, varname, &schema)
+            }),
+            terminated(
+                cut(context(
+                    "invalid default value",
+                    map_res(|input| parse_default(input, &schema), to_json),
+                )),
+                cond(numeric, decimal_comma_hint),
+            ),
         )),
-        tag(";"),
+        context("expected ';' after field declaration", end_of_statement),
     )(tail)?;
 
+    let Annotations {
+        order,
+        aliases,
+        mut repeated,
+        ..
+    } = annotations;
+    repeated.splice(0..0, logical.repeated.into_iter().chain(ignored));
     Ok((
         tail,
         (
-            Schema::Map(Box::new(schema)),
-            doc,
-            order,
-            aliases,
-            varname,
-            defaults,
+            (
+                schema,
+                doc.map(String::from),
+                order,
+                aliases,
+                varname,
+                defaults,
+            ),
+            repeated,
         ),
     ))
 }
 
-fn parse_union(
-    input: &str,
-) -> IResult<
-    &str,
-    (
-        Schema,
-        Option<String>,
-        Option<RecordFieldOrder>,
-        Option<Vec<String>>,
-        VarName,
-        Option<Value>,
-    ),
-> {
-    let (tail, doc) = opt(parse_doc)(input)?;
-    let (tail, schema) = map_type_to_schema(tail)?;
-
-    let boxed_schema = Box::new(schema.clone());
-    let default_parser = parse_based_on_schema(boxed_schema);
-    let (tail, ((order, aliases), varname, defaults)) = terminated(
-        tuple((
-            permutation_opt((
-                space_or_comment_delimited(parse_order),
-                space_or_comment_delimited(parse_aliases),
-            )),
-            space_or_comment_delimited(parse_var_name),
-            // default
-            opt(preceded(
-                space_or_comment_delimited(tag("=")),
-                map_res(default_parser, |value| value.try_into()),
-            )),
-        )),
-        preceded(space0, space_or_comment_delimited(tag(";"))),
-    )(tail)?;
-
-    Ok((tail, (schema, doc, order, aliases, varname, defaults)))
-}
-
 /** **************************************** */
 /**  Custom Types: Fixed, Records, Enum, etc */
 /**  These types can be declared used fields */
@@ -709,12 +1389,21 @@ fn parse_enum_default_symbol(input: &str) -> IResult<&str, AvroValue> {
 // ```
 // { COIN, NUMBER }
 // ```
-fn parse_enum_symbols(input: &str) -> IResult<&str, Vec<EnumSymbol>> {
+// or, when trailing commas are allowed
+// ```
+// { COIN, NUMBER, }
+// ```
+fn parse_enum_symbols<'a>(
+    allow_trailing_comma: bool,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Vec<EnumSymbol<'a>>> {
     delimited(
         space_or_comment_delimited(tag("{")),
-        separated_list1(tag(","), parse_enum_item),
+        terminated(
+            separated_list1(tag(","), parse_enum_item),
+            cond(allow_trailing_comma, opt(tag(","))),
+        ),
         space_or_comment_delimited(tag("}")),
-    )(input)
+    )
 }
 
 // TODO: Review this
@@ -729,13 +1418,18 @@ fn parse_enum_name(input: &str) -> IResult<&str, VarName> {
 // ```
 // = COIN;
 // ```
+// Once the `=` is seen the clause must be complete, otherwise a dangling
+// `= COIN` would be reported against the next declaration
 fn parse_enum_default(input: &str) -> IResult<&str, String> {
-    terminated(
-        preceded(
-            space_delimited(tag("=")),
-            map(parse_enum_item, |value| value.to_string()),
-        ),
-        tag(";"),
+    preceded(
+        space_delimited(tag("=")),
+        cut(terminated(
+            context(
+                "expected enum symbol after '='",
+                map(parse_enum_item, |value| value.to_string()),
+            ),
+            context("expected ';' after enum default", end_of_statement),
+        )),
     )(input)
 }
 
@@ -743,64 +1437,213 @@ fn parse_enum_default(input: &str) -> IResult<&str, String> {
 // ```
 // enum Items { COIN, NUMBER } = COIN;
 // ```
-fn parse_enum(input: &str) -> IResult<&str, Schema> {
-    let (tail, (doc, aliases, name, body, default)) = tuple((
+// The default is kept in the attributes, where `.avsc` documents have it too
+fn parse_enum_declaration_with(
+    input: &str,
+    allow_trailing_comma: bool,
+) -> IResult<&str, (Schema, Vec<IgnoredAnnotation<'_>>)> {
+    let (tail, (doc, annotations, name, body, default)) = tuple((
         opt(parse_doc),
-        opt(parse_namespaced_aliases),
+        parse_annotations(named_aliases_annotation),
         parse_enum_name,
-        parse_enum_symbols,
-        opt(parse_enum_default),
+        parse_enum_symbols(allow_trailing_comma),
+        opt(consumed(parse_enum_default)),
     ))(input)?;
-    let n = Name::new(name).unwrap();
+    let n = parse_name(input, name)?;
+    let symbols: Vec<String> = body.into_iter().map(String::from).collect();
 
-    // TODO: Check if we need to validate enum's default against one of the options
-    if default.is_some() {
-        println!("Warning: default is being ignored as of now.")
+    let mut attributes = BTreeMap::new();
+    if let Some((source, default)) = default {
+        if !symbols.contains(&default) {
+            // Point at the symbol, followed by the rest of the input
+            let start = Span::of(input, source).start + source.find('=').unwrap_or(0) + 1;
+            return Err(nom::Err::Failure(ParserError::from_external_error(
+                skip_comments(&input[start..]),
+                ErrorKind::Verify,
+                TokenError::UnknownEnumDefault(default),
+            )));
+        }
+        attributes.insert("default".to_string(), Value::String(default));
     }
-
     Ok((
         tail,
-        Schema::Enum {
-            name: n,
-            aliases: aliases,
-            doc: doc,
-            symbols: body.into_iter().map(String::from).collect::<Vec<String>>(),
-            attributes: BTreeMap::new(),
-        },
+        (
+            Schema::Enum {
+                name: n,
+                aliases: annotations.named_aliases(),
+                doc: doc.map(String::from),
+                symbols,
+                attributes,
+            },
+            annotations.repeated,
+        ),
     ))
 }
 
-// Samples
+/// Parse an enum declaration, with its doc comment and annotations.
+///
+/// The default symbol after the enum, which must be one of its symbols, is
+/// kept in the `default` attribute.
+///
+/// ```
+/// use apache_avro::schema::Schema;
+/// use avdl_parser::parser::parse_enum;
+///
+/// let (tail, schema) = parse_enum("enum Suit { SPADES, HEARTS } = SPADES;").unwrap();
+/// let Schema::Enum { name, symbols, attributes, .. } = schema else {
+///     panic!("expected an enum");
+/// };
+/// assert_eq!(name.name, "Suit");
+/// assert_eq!(symbols, ["SPADES", "HEARTS"]);
+/// assert_eq!(attributes["default"], "SPADES");
+/// assert_eq!(tail, "");
+///
+/// assert!(parse_enum("enum Suit { SPADES, HEARTS } = CLUBS;").is_err());
+/// ```
+pub fn parse_enum(input: &str) -> IResult<&str, Schema> {
+    map(
+        |i| parse_enum_declaration_with(i, false),
+        |(mut schema, _repeated)| {
+            normalize_docs(&mut schema);
+            schema
+        },
+    )(input)
+}
+
+// The annotations making a fixed a decimal, the scale being 0 when left out
+// Sample
 // ```
-// fixed MD5(16);
-// fixed @aliases(["md1"]) MD5(16);
+// @logicalType("decimal") @precision(9) @scale(2)
 // ```
-fn parse_fixed(input: &str) -> IResult<&str, Schema> {
-    let (tail, (doc, (aliases, name, size))) = tuple((
+fn parse_decimal_annotations(input: &str) -> IResult<&str, (usize, usize)> {
+    let number = |annotation| {
+        preceded(
+            tag(annotation),
+            delimited(
+                space_or_comment_delimited(tag("(")),
+                map_usize,
+                space_or_comment_delimited(tag(")")),
+            ),
+        )
+    };
+    preceded(
+        tuple((
+            tag("@logicalType"),
+            space_or_comment_delimited(tag("(")),
+            tag(r#""decimal""#),
+            space_or_comment_delimited(tag(")")),
+        )),
+        cut(pair(
+            space_or_comment_delimited(number("@precision")),
+            map(opt(space_or_comment_delimited(number("@scale"))), |scale| {
+                scale.unwrap_or(0)
+            }),
+        )),
+    )(input)
+}
+
+// The annotation making a fixed of 12 bytes hold a duration
+// Sample
+// ```
+// @logicalType("duration")
+// ```
+fn parse_duration_annotation(input: &str) -> IResult<&str, &str> {
+    recognize(tuple((
+        tag("@logicalType"),
+        space_or_comment_delimited(tag("(")),
+        tag(r#""duration""#),
+        space_or_comment_delimited(tag(")")),
+    )))(input)
+}
+
+/// Parse a fixed declaration, with its doc comment and annotations. A fixed
+/// annotated as a decimal gives a [`Schema::Decimal`] stored in it, one
+/// annotated as a duration keeps its logical type in its attributes.
+///
+/// ```
+/// use apache_avro::schema::Schema;
+/// use avdl_parser::parser::parse_fixed;
+///
+/// let (_tail, schema) = parse_fixed(r#"fixed @aliases(["Md5"]) MD5(16);"#).unwrap();
+/// assert!(matches!(schema, Schema::Fixed { size: 16, .. }));
+///
+/// let (_tail, schema) =
+///     parse_fixed(r#"@logicalType("decimal") @precision(9) @scale(2) fixed Amount(8);"#)
+///         .unwrap();
+/// assert!(matches!(schema, Schema::Decimal { precision: 9, scale: 2, .. }));
+///
+/// let (_tail, schema) = parse_fixed(r#"@logicalType("duration") fixed Span(12);"#).unwrap();
+/// let Schema::Fixed { attributes, .. } = schema else {
+///     panic!("expected a fixed");
+/// };
+/// assert_eq!(attributes["logicalType"], "duration");
+/// ```
+pub fn parse_fixed(input: &str) -> IResult<&str, Schema> {
+    map(parse_fixed_annotated, |(mut schema, _repeated)| {
+        normalize_docs(&mut schema);
+        schema
+    })(input)
+}
+
+fn parse_fixed_annotated(input: &str) -> IResult<&str, (Schema, Vec<IgnoredAnnotation<'_>>)> {
+    // The precision and scale of a decimal, or none for a duration
+    let (tail, (doc, logical, (annotations, name, size))) = tuple((
         space_delimited(opt(parse_doc)),
+        space_or_comment_delimited(opt(consumed(alt((
+            map(parse_decimal_annotations, Some),
+            value(None, parse_duration_annotation),
+        ))))),
         preceded(
             tag("fixed"),
             cut(terminated(
-                space_delimited(tuple((
-                    opt(space_delimited(parse_namespaced_aliases)),
+                space_or_comment_delimited(tuple((
+                    parse_annotations(named_aliases_annotation),
                     parse_var_name,
-                    delimited(tag("("), map_usize, tag(")")),
+                    delimited(
+                        space_or_comment_delimited(tag("(")),
+                        map_usize,
+                        space_or_comment_delimited(tag(")")),
+                    ),
                 ))),
-                char(';'),
+                end_of_statement,
             )),
         ),
     ))(input)?;
 
-    Ok((
-        tail,
-        Schema::Fixed {
-            name: name.into(),
-            aliases: aliases.clone(),
-            doc: doc,
-            size: size,
-            attributes: BTreeMap::new(),
-        },
-    ))
+    let mut fixed = Schema::Fixed {
+        name: parse_name(input, name)?,
+        aliases: annotations.named_aliases(),
+        doc: doc.map(String::from),
+        size: size,
+        attributes: BTreeMap::new(),
+    };
+    let schema = match logical {
+        Some((source, None)) => {
+            check_logical_type(&Schema::Duration, &fixed).map_err(|err| {
+                // Point at the annotation
+                let at = &input[Span::of(input, source).start..];
+                nom::Err::Failure(ParserError::from_external_error(at, ErrorKind::Verify, err))
+            })?;
+            if let Schema::Fixed { attributes, .. } = &mut fixed {
+                attributes.insert("logicalType".to_string(), Value::from("duration"));
+            }
+            fixed
+        }
+        Some((source, Some((precision, scale)))) => {
+            check_decimal(precision, scale, Some(size)).map_err(|err| {
+                // Point at the annotations
+                let at = &input[Span::of(input, source).start..];
+                nom::Err::Failure(ParserError::from_external_error(at, ErrorKind::Verify, err))
+            })?;
+            Schema::Decimal {
+                precision,
+                scale,
+                inner: Box::new(fixed),
+            }
+        }
+        None => fixed,
+    };
+    Ok((tail, (schema, annotations.repeated)))
 }
 
 // Sample
@@ -814,283 +1657,1240 @@ fn parse_record_name(input: &str) -> IResult<&str, &str> {
     )(input)
 }
 
-// Sample
-// This returns a whole schema::RecordField
-// ```
-// string @order("ignore") name = "jon";
-// ```
-fn parse_record_field(input: &str) -> IResult<&str, RecordField> {
-    preceded(
+/// Parse a field of a record, with its doc comment, annotations and default,
+/// up to its `;`.
+///
+/// The default is checked against the type of the field, unless the type is
+/// a reference. The position of the field in its record is left at 0.
+///
+/// ```
+/// use apache_avro::schema::{RecordFieldOrder, Schema};
+/// use avdl_parser::parser::parse_record_field;
+///
+/// let (_tail, field) =
+///     parse_record_field(r#"/** Shown */ string @order("ignore") name = "jon";"#).unwrap();
+/// assert_eq!(field.name, "name");
+/// assert_eq!(field.schema, Schema::String);
+/// assert_eq!(field.doc.as_deref(), Some("Shown"));
+/// assert_eq!(field.order, RecordFieldOrder::Ignore);
+/// assert_eq!(field.default, Some("jon".into()));
+///
+/// assert!(parse_record_field("int age = \"old\";").is_err());
+/// ```
+pub fn parse_record_field(input: &str) -> IResult<&str, RecordField> {
+    map(parse_record_field_annotated, |(mut field, _repeated)| {
+        normalize_optional_doc(&mut field.doc);
+        field
+    })(input)
+}
+
+fn parse_record_field_annotated(
+    input: &str,
+) -> IResult<&str, (RecordField, Vec<IgnoredAnnotation<'_>>)> {
+    let field = preceded(
         multispace0,
-        space_or_comment_delimited(alt((
-            map(
-                parse_array,
-                |(schemas, doc, order, aliases, name, default)| RecordField {
-                    name: name.to_string(),
-                    doc: doc,
-                    default: default,
-                    schema: schemas,
-                    order: order.unwrap_or(RecordFieldOrder::Ascending),
-                    aliases: aliases,
-                    position: 0,
-                    custom_attributes: BTreeMap::new(),
-                },
-            ),
-            map(
-                parse_map,
-                |(schemas, doc, order, aliases, name, default)| RecordField {
-                    name: name.to_string(),
-                    doc: doc,
-                    default: default,
-                    schema: schemas,
-                    order: order.unwrap_or(RecordFieldOrder::Ascending),
-                    aliases: aliases,
-                    position: 0,
-                    custom_attributes: BTreeMap::new(),
-                },
-            ),
-            map(
-                parse_union,
-                |(schema, doc, order, aliases, name, default)| RecordField {
+        space_or_comment_delimited(map(
+            parse_field_annotated,
+            |((schema, doc, order, aliases, name, default), repeated)| {
+                let field = RecordField {
                     name: name.to_string(),
-                    doc: doc,
-                    default: default,
-                    schema: schema,
+                    doc,
+                    default,
+                    schema,
                     order: order.unwrap_or(RecordFieldOrder::Ascending),
-                    aliases: aliases,
+                    aliases,
                     position: 0,
                     custom_attributes: BTreeMap::new(),
-                },
-            ),
-            map(
-                parse_field,
-                |(schemas, doc, order, aliases, name, default)| RecordField {
-                    name: name.to_string(),
-                    doc: doc,
-                    default: default,
-                    schema: schemas,
-                    order: order.unwrap_or(RecordFieldOrder::Ascending),
-                    aliases: aliases,
-                    position: 0,
-                    custom_attributes: BTreeMap::new(),
-                },
-            ),
-        ))),
+                };
+                (field, repeated)
+            },
+        )),
+    )(input);
+    field.map_err(|err| match err {
+        nom::Err::Failure(mut err)
+            if matches!(
+                err.cause,
+                Some(TokenError::NestedUnion | TokenError::DuplicateUnionType(_))
+            ) =>
+        {
+            if let Ok((_, name)) = skip_to_field_name(input) {
+                let field = name.to_string();
+                err.cause = err.cause.map(|cause| match cause {
+                    TokenError::DuplicateUnionType(type_name) => {
+                        TokenError::DuplicateUnionTypeInField { type_name, field }
+                    }
+                    _ => TokenError::NestedUnionInField(field),
+                });
+            }
+            nom::Err::Failure(err)
+        }
+        err => err,
+    })
+}
+
+// The name of the field declared in `input`, past a type which is not checked
+fn skip_to_field_name(input: &str) -> IResult<&str, &str> {
+    preceded(
+        pair(space_or_comment_delimited(opt(parse_doc)), skip_type),
+        preceded(
+            parse_annotations(field_annotation),
+            space_or_comment_delimited(parse_var_name),
+        ),
     )(input)
 }
 
-// Sample of record
-// ```
-// record Employee {
-//     string name;
-//     boolean active = true;
-//     long salary;
-// }
-// ```
+fn skip_type(input: &str) -> IResult<&str, ()> {
+    value(
+        (),
+        tuple((
+            parse_annotations(type_annotation),
+            space_or_comment_delimited(parse_var_name),
+            opt(alt((
+                delimited(
+                    space_or_comment_delimited(tag("<")),
+                    skip_type,
+                    space_or_comment_delimited(tag(">")),
+                ),
+                value(
+                    (),
+                    delimited(
+                        space_or_comment_delimited(tag("{")),
+                        separated_list1(space_or_comment_delimited(tag(",")), skip_type),
+                        space_or_comment_delimited(tag("}")),
+                    ),
+                ),
+                value(
+                    (),
+                    delimited(space_or_comment_delimited(tag("(")), is_not(")"), tag(")")),
+                ),
+            ))),
+        )),
+    )(input)
+}
+
+/// Parse a record declaration, with its doc comment, annotations and fields.
+///
+/// ```
+/// use apache_avro::schema::Schema;
+/// use avdl_parser::parser::parse_record;
+///
+/// let input = "record Employee {
+///     string name;
+///     boolean active = true;
+///     long salary;
+/// }";
+/// let (_tail, schema) = parse_record(input).unwrap();
+/// let Schema::Record { name, fields, .. } = schema else {
+///     panic!("expected a record");
+/// };
+/// assert_eq!(name.name, "Employee");
+/// let names: Vec<&str> = fields.iter().map(|field| field.name.as_str()).collect();
+/// assert_eq!(names, ["name", "active", "salary"]);
+/// ```
 pub fn parse_record(input: &str) -> IResult<&str, Schema> {
-    let mut used_field_names = Vec::new();
-    let (tail, (doc, (aliases, namespace), name, fields)) = tuple((
+    map(
+        |i| parse_record_with(i, None, None),
+        |(mut schema, _repeated)| {
+            normalize_docs(&mut schema);
+            schema
+        },
+    )(input)
+}
+
+// Run `parser`, and when it fails skip to the next `;` (consumed) or `}` (left
+// in the input), keeping the error so the caller can go on with what follows.
+fn recover<'a, O>(
+    input: &'a str,
+    parser: impl FnOnce(&'a str) -> IResult<&'a str, O>,
+    recovered: &mut Vec<ParserError<&'a str>>,
+) -> IResult<&'a str, Option<O>> {
+    let err = match parser(input) {
+        Ok((tail, output)) => return Ok((tail, Some(output))),
+        Err(nom::Err::Error(err)) | Err(nom::Err::Failure(err)) => err,
+        Err(err) => return Err(err),
+    };
+    match err.input.find([';', '}']) {
+        Some(i) => {
+            let tail = match &err.input[i..] {
+                rest if rest.starts_with(';') => &rest[1..],
+                rest => rest,
+            };
+            recovered.push(err);
+            Ok((tail, None))
+        }
+        None => Err(nom::Err::Failure(err)),
+    }
+}
+
+// When `recovered` is given, a field that fails to parse is skipped and its
+// error pushed there instead of failing the whole record. When
+// `field_sources` is given, the source of every field is pushed there.
+fn parse_record_with<'a>(
+    input: &'a str,
+    mut recovered: Option<&mut Vec<ParserError<&'a str>>>,
+    mut field_sources: Option<&mut Vec<&'a str>>,
+) -> IResult<&'a str, (Schema, Vec<IgnoredAnnotation<'a>>)> {
+    let mut used_field_names = HashSet::new();
+    let mut repeated = Vec::new();
+    let (tail, (doc, annotations, name, fields)) = tuple((
         opt(parse_doc),
-        permutation_opt((
-            space_or_comment_delimited(parse_namespaced_aliases),
-            space_or_comment_delimited(parse_namespace),
-        )),
+        parse_annotations(alt((
+            named_aliases_annotation,
+            map(parse_namespace, |namespace| {
+                Annotation::Namespace(namespace.to_string())
+            }),
+        ))),
         parse_record_name,
         preceded(
             multispace0,
-            delimited(
+            preceded(
                 tag("{"),
-                many1(map_res(parse_record_field, |f| {
-                    let name = f.name.clone();
-                    if used_field_names.contains(&name) {
-                        return Err("Duplicate field {name}");
-                    }
-                    used_field_names.push(name);
-                    Ok(f)
-                })),
-                preceded(multispace0, tag("}")),
+                map(
+                    many_till(
+                        |i| {
+                            let parse_field = map_res(
+                                consumed(parse_record_field_annotated),
+                                |(source, (f, field_repeated))| {
+                                    if !used_field_names.insert(f.name.clone()) {
+                                        return Err(TokenError::Duplicate(f.name));
+                                    }
+                                    repeated.extend(field_repeated);
+                                    if let Some(sources) = field_sources.as_deref_mut() {
+                                        sources.push(statement_source(source));
+                                    }
+                                    Ok(f)
+                                },
+                            );
+                            match recovered.as_deref_mut() {
+                                Some(recovered) => recover(i, parse_field, recovered),
+                                None => map(parse_field, Some)(i),
+                            }
+                        },
+                        preceded(multispace0, tag("}")),
+                    ),
+                    |(fields, _)| fields.into_iter().flatten().collect(),
+                ),
             ),
         ),
     ))(input)?;
-    let mut name = Name::new(name).unwrap();
+    let mut name = parse_name(input, name)?;
 
-    name.namespace = namespace;
+    let aliases = annotations.named_aliases();
+    name.namespace = annotations.namespace;
+    repeated.splice(0..0, annotations.repeated);
 
     Ok((
         tail,
-        Schema::Record {
-            name: name,
-            aliases: aliases,
-            doc: doc,
-            fields: fields,
-            lookup: BTreeMap::new(),
-            attributes: BTreeMap::new(),
-        },
+        (
+            Schema::Record {
+                name: name,
+                aliases,
+                doc: doc.map(String::from),
+                fields: fields,
+                lookup: BTreeMap::new(),
+                attributes: BTreeMap::new(),
+            },
+            repeated,
+        ),
     ))
 }
 
-#[derive(Error, Debug)]
-enum AvdlError {
-    #[error("Failed to import Avsc")]
-    ImportAvscError(#[from] apache_avro::Error),
+// The source of every field of the record declared in `source`
+pub(crate) fn record_field_sources(source: &str) -> Vec<&str> {
+    let mut sources = Vec::new();
+    let _ = parse_record_with(source, None, Some(&mut sources));
+    sources
+}
 
-    #[error("Failed to import Avdl")]
-    ImportIdlError,
+// `input` without the whitespace and comments it starts with, doc comments
+// excepted as they are part of the declaration that follows
+fn skip_comments(input: &str) -> &str {
+    let mut rest = input.trim_start();
+    loop {
+        rest = if let Some(line) = rest.strip_prefix("//") {
+            line.split_once('\n').map_or("", |(_, next)| next)
+        } else if rest.starts_with("/*") && !rest.starts_with("/**") {
+            rest[2..].split_once("*/").map_or("", |(_, next)| next)
+        } else {
+            return rest;
+        }
+        .trim_start();
+    }
 }
 
-#[derive(Debug, Clone, PartialEq)]
-enum Import {
-    Idl,
-    Protocol,
-    Schema,
+// The source of a field or declaration as consumed by the parser, from its
+// doc comment to its `;`, without the comments around it
+fn statement_source(consumed: &str) -> &str {
+    let source = skip_comments(consumed);
+    let end = source
+        .match_indices(';')
+        .map(|(i, _)| i + 1)
+        .find(|end| skip_comments(&source[*end..]).is_empty())
+        .unwrap_or(source.len());
+    &source[..end]
 }
 
-fn import_solver(
-    importType: Import,
-    path: String,
-    names_ref: &mut HashMap<Name, Schema>,
-) -> Result<Vec<Schema>, AvdlError> {
-    let input = fs::read_to_string(path).expect("Failed to read the file");
-    match importType {
-        Import::Idl => {
-            let (_, (schemas, _namespace)) =
-                parse_protocol(input.as_str(), names_ref).map_err(|_| AvdlError::ImportIdlError)?;
-            return Ok(schemas);
-        }
-        Import::Protocol => todo!(),
-        Import::Schema => Ok(vec![Schema::parse_str(input.as_str())?]),
-    }
+// A parameter of a message, declared like a field without its annotations.
+// The position is left at 0
+// Sample
+// ```
+// /** What to log */ string message = "hello"
+// ```
+fn parse_message_parameter(
+    input: &str,
+) -> IResult<&str, (RecordField, Vec<IgnoredAnnotation<'_>>)> {
+    let (tail, (doc, (schema, ignored))) = pair(space_delimited(opt(parse_doc)), |input| {
+        map_annotated_type(input, 0)
+    })(input)?;
+    let (tail, name) = space_or_comment_delimited(parse_var_name)(tail)?;
+    let (tail, default) = opt(preceded(
+        terminated(space_or_comment_delimited(tag("=")), |input| {
+            reject_null_default(input, name, &schema)
+        }),
+        cut(context(
+            "invalid default value",
+            map_res(|input| parse_default(input, &schema), to_json),
+        )),
+    ))(tail)?;
+    let parameter = RecordField {
+        name: name.to_string(),
+        doc: doc.map(String::from),
+        default,
+        schema,
+        order: RecordFieldOrder::Ascending,
+        aliases: None,
+        position: 0,
+        custom_attributes: BTreeMap::new(),
+    };
+    Ok((tail, (parameter, ignored)))
+}
+
+// Sample
+// ```
+// /** Say hello */
+// string hello(string greeting) throws Oops;
+// void ping() oneway;
+// ```
+#[cfg(test)]
+fn parse_message(input: &str) -> IResult<&str, Message> {
+    map(parse_message_annotated, |(message, _ignored)| message)(input)
+}
+
+fn parse_message_annotated(input: &str) -> IResult<&str, (Message, Vec<IgnoredAnnotation<'_>>)> {
+    let parse_ref = map_res(
+        space_or_comment_delimited(parse_var_name),
+        |reference_name| -> Result<Schema, TokenError> {
+            let name = Name::new(reference_name)
+                .map_err(|_| TokenError::InvalidName(reference_name.to_string()))?;
+            Ok(Schema::Ref { name })
+        },
+    );
+    map(
+        tuple((
+            opt(parse_doc),
+            alt((
+                value(
+                    (Schema::Null, Vec::new()),
+                    space_or_comment_delimited(parse_void),
+                ),
+                |input| map_annotated_type(input, 0),
+            )),
+            space_or_comment_delimited(parse_var_name),
+            preceded(
+                space_delimited(tag("(")),
+                cut(tuple((
+                    terminated(
+                        separated_list0(space_delimited(tag(",")), parse_message_parameter),
+                        context(
+                            "expected ')' after message parameters",
+                            space_delimited(tag(")")),
+                        ),
+                    ),
+                    opt(preceded(
+                        space_or_comment_delimited(tag("throws")),
+                        separated_list1(space_delimited(tag(",")), parse_ref),
+                    )),
+                    map(opt(space_or_comment_delimited(tag("oneway"))), |one_way| {
+                        one_way.is_some()
+                    }),
+                    context("expected ';' after message declaration", end_of_statement),
+                ))),
+            ),
+        )),
+        |(doc, (response, mut ignored), name, (request, errors, one_way, _))| {
+            let (request, parameters_ignored): (Vec<RecordField>, Vec<_>) =
+                request.into_iter().unzip();
+            ignored.extend(parameters_ignored.into_iter().flatten());
+            let message = Message {
+                name: name.to_string(),
+                doc: doc.map(String::from),
+                request: request
+                    .into_iter()
+                    .enumerate()
+                    .map(|(position, parameter)| RecordField {
+                        position,
+                        ..parameter
+                    })
+                    .collect(),
+                response,
+                errors: errors.unwrap_or_default(),
+                one_way,
+            };
+            (message, ignored)
+        },
+    )(input)
 }
 
-fn parse_import(input: &str) -> IResult<&str, (Import, String)> {
+fn parse_import(input: &str) -> IResult<&str, (ImportKind, String)> {
     preceded(
         space_or_comment_delimited(tag("import")),
         terminated(
             tuple((
                 space_or_comment_delimited(alt((
-                    value(Import::Idl, tag("idl")),
-                    value(Import::Protocol, tag("protocol")),
-                    value(Import::Schema, tag("schema")),
+                    value(ImportKind::Idl, tag("idl")),
+                    value(ImportKind::Protocol, tag("protocol")),
+                    value(ImportKind::Schema, tag("schema")),
                 ))),
-                parse_string_uni,
+                map(parse_string_uni, Cow::into_owned),
             )),
-            space_or_comment_delimited(tag(";")),
+            end_of_statement,
         ),
     )(input)
 }
 
-fn parse_import_into_schema(input: &str) -> IResult<&str, Vec<Schema>> {
-    map_res(
-        parse_import,
-        |(import, name)| -> Result<Vec<Schema>, String> {
-            match import {
-                Import::Idl => todo!(),
-                Import::Protocol => todo!(),
-                Import::Schema => todo!(),
-            }
-        },
+// Example:
+// ```
+// namespace org.apache.avro.examples;
+// ```
+fn parse_namespace_statement(input: &str) -> IResult<&str, &str> {
+    delimited(
+        terminated(tag("namespace"), multispace1),
+        take_while1(|c: char| c.is_alphanumeric() || c == '.' || c == '_'),
+        end_of_statement,
     )(input)
 }
 
-// Sample:
+// The main type of a schema file
 // ```
-// protocol Simple {
-//    record Simple {
-//      string name;
-//      int age;
-//    }
-// }
+// schema array<Message>;
 // ```
+fn parse_main_schema(input: &str) -> IResult<&str, (Schema, Vec<IgnoredAnnotation<'_>>)> {
+    delimited(
+        terminated(tag("schema"), multispace1),
+        |input| map_nested_type_to_schema(input, 0),
+        end_of_statement,
+    )(input)
+}
+
+/// Parse a protocol, its types and messages.
+///
+/// The named types declared are added to `names_ref` as they are parsed, and
+/// the warnings found to `warnings`. References to them are left as they
+/// are, [`parse_str`] resolves them and checks the protocol too.
+///
+/// ```
+/// use std::collections::HashMap;
+///
+/// use avdl_parser::parser::parse_protocol;
+/// use avdl_parser::Warnings;
+///
+/// let input = "protocol Simple {
+///     record Simple {
+///         string name;
+///         int age;
+///     }
+/// }";
+/// let mut names = HashMap::new();
+/// let (_tail, protocol) = parse_protocol(input, &mut names, &mut Warnings::new()).unwrap();
+/// assert_eq!(protocol.name, "Simple");
+/// assert_eq!(protocol.types.len(), 1);
+/// assert_eq!(names.len(), 1);
+/// ```
 pub fn parse_protocol<'a>(
     input: &'a str,
     names_ref: &mut HashMap<Name, Schema>,
-) -> IResult<&'a str, (Vec<Schema>, Namespace)> {
-    let (tail, (_doc, namespace, _name, schemas)) = tuple((
-        opt(parse_doc),
-        space_or_comment_delimited(opt(parse_namespace)),
-        preceded(
-            multispace0,
-            preceded(
-                space_or_comment_delimited(tag("protocol")),
-                space_delimited(parse_var_name),
-            ),
-        ),
-        delimited(
+    warnings: &mut Warnings,
+) -> IResult<&'a str, Protocol> {
+    parse_protocol_with(
+        input,
+        names_ref,
+        warnings,
+        &ParserOptions::default(),
+        None,
+        None,
+    )
+}
+
+// What a protocol is made of, in the order it is written
+pub(crate) enum Declaration {
+    Header {
+        doc: Option<Doc>,
+        namespace: Option<String>,
+        aliases: Vec<String>,
+        properties: BTreeMap<String, Value>,
+        name: String,
+    },
+    Import(Import),
+    // Along with the span of its declaration
+    Type(Schema, Span),
+    Message(Message),
+}
+
+impl Declaration {
+    // Normalizes the docs of what is declared, see `normalize_doc`
+    fn normalize_docs(&mut self) {
+        match self {
+            Declaration::Header { doc, .. } => normalize_optional_doc(doc),
+            Declaration::Import(_) => {}
+            Declaration::Type(schema, _span) => normalize_docs(schema),
+            Declaration::Message(message) => {
+                normalize_optional_doc(&mut message.doc);
+                for parameter in &mut message.request {
+                    normalize_optional_doc(&mut parameter.doc);
+                }
+            }
+        }
+    }
+}
+
+// Parses the header of a protocol and then its body, one declaration at a
+// time, handing each to `on_declaration` as soon as it is parsed instead of
+// keeping them, along with the warnings found so far. Parsing stops early
+// when `on_declaration` breaks, and an error returned by it is located at the
+// declaration it was given.
+pub(crate) fn parse_protocol_declarations<'a>(
+    input: &'a str,
+    warnings: &mut Warnings,
+    options: &ParserOptions,
+    mut recovered: Option<&mut Vec<ParserError<&'a str>>>,
+    on_declaration: &mut impl FnMut(Declaration, &mut Warnings) -> Result<ControlFlow<()>, TokenError>,
+) -> IResult<&'a str, ControlFlow<()>> {
+    let (mut rest, mut header) = parse_protocol_header(input)?;
+    if !options.preserve_docs {
+        header.normalize_docs();
+    }
+    if let ControlFlow::Break(()) = on_declaration(header, warnings).map_err(|e| {
+        nom::Err::Error(ParserError::from_external_error(
+            input,
+            ErrorKind::MapRes,
+            e,
+        ))
+    })? {
+        return Ok((rest, ControlFlow::Break(())));
+    }
+
+    loop {
+        let (tail, next) =
+            parse_declaration(input, rest, warnings, options, recovered.as_deref_mut())?;
+        let (start, declaration) = match next {
+            Some(next) => next,
+            None => return Ok((tail, ControlFlow::Continue(()))),
+        };
+        let flow = on_declaration(declaration, warnings).map_err(|e| {
+            nom::Err::Error(ParserError::from_external_error(
+                start,
+                ErrorKind::MapRes,
+                e,
+            ))
+        })?;
+        if let ControlFlow::Break(()) = flow {
+            return Ok((tail, flow));
+        }
+        rest = tail;
+    }
+}
+
+// The header of a protocol, up to the `{` opening its body
+pub(crate) fn parse_protocol_header(input: &str) -> IResult<&str, Declaration> {
+    map(
+        terminated(
+            tuple((
+                opt(parse_doc),
+                space_or_comment_delimited(parse_annotations(protocol_annotation)),
+                preceded(
+                    multispace0,
+                    preceded(
+                        space_or_comment_delimited(tag("protocol")),
+                        space_delimited(parse_var_name),
+                    ),
+                ),
+            )),
             space_delimited(tag("{")),
-            many1(space_or_comment_delimited(map_res(
-                alt((parse_record, parse_enum, parse_fixed)),
-                |mut schema| match &mut schema {
-                    Schema::Record {
-                        name,
-                        aliases: _,
-                        doc: _,
-                        fields: _,
-                        lookup: _,
-                        attributes: _,
-                    } => {
-                        // name.namespace = Some("cagon.org".to_string());
-                        let name = name.clone();
-                        if names_ref.contains_key(&name) {
-                            return Err("Duplicate field {name}");
-                        }
-                        names_ref.insert(name, schema.clone());
-                        return Ok(schema);
+        ),
+        |(doc, annotations, name)| Declaration::Header {
+            doc: doc.map(String::from),
+            namespace: annotations.namespace,
+            aliases: annotations.aliases.unwrap_or_default(),
+            properties: annotations.properties,
+            name: name.to_string(),
+        },
+    )(input)
+}
+
+// The next declaration in the body of the protocol `input`, starting at
+// `rest`, along with where it starts. None once the `}` closing the body is
+// reached, which is consumed.
+pub(crate) fn parse_declaration<'a>(
+    input: &'a str,
+    rest: &'a str,
+    warnings: &mut Warnings,
+    options: &ParserOptions,
+    mut recovered: Option<&mut Vec<ParserError<&'a str>>>,
+) -> IResult<&'a str, Option<(&'a str, Declaration)>> {
+    if let Ok((tail, _)) = preceded(multispace0, tag::<_, _, ParserError<&str>>("}"))(rest) {
+        return Ok((tail, None));
+    }
+    let (start, _) = space_delimited(opt(parse_comment))(rest)?;
+    let (tail, (mut declaration, repeated)) = terminated(
+        alt((
+            map(parse_import, |(kind, path)| {
+                (Declaration::Import(Import { kind, path }), Vec::new())
+            }),
+            map(
+                consumed(alt((
+                    |i| parse_record_with(i, recovered.as_deref_mut(), None),
+                    |i| parse_enum_declaration_with(i, options.allow_trailing_commas),
+                    parse_fixed_annotated,
+                ))),
+                |(source, (schema, repeated))| {
+                    let span = Span::of(input, statement_source(source).trim_end());
+                    (Declaration::Type(schema, span), repeated)
+                },
+            ),
+            map(parse_message_annotated, |(message, ignored)| {
+                (Declaration::Message(message), ignored)
+            }),
+        )),
+        space_delimited(opt(parse_comment)),
+    )(start)?;
+    for repeated in repeated {
+        warnings.push(repeated.warning(input));
+    }
+    if !options.preserve_docs {
+        declaration.normalize_docs();
+    }
+    Ok((tail, Some((start, declaration))))
+}
+
+// When `spans` is given, the spans of the types and fields are kept there
+fn parse_protocol_with<'a>(
+    input: &'a str,
+    names_ref: &mut HashMap<Name, Schema>,
+    warnings: &mut Warnings,
+    options: &ParserOptions,
+    recovered: Option<&mut Vec<ParserError<&'a str>>>,
+    mut spans: Option<&mut SourceMap>,
+) -> IResult<&'a str, Protocol> {
+    let mut protocol = Protocol {
+        name: String::new(),
+        namespace: None,
+        doc: None,
+        aliases: Vec::new(),
+        imports: Vec::new(),
+        types: Vec::new(),
+        messages: Vec::new(),
+        properties: BTreeMap::new(),
+    };
+    let (tail, _) = parse_protocol_declarations(
+        input,
+        warnings,
+        options,
+        recovered,
+        &mut |declaration, _warnings| {
+            match declaration {
+                Declaration::Header {
+                    doc,
+                    namespace,
+                    aliases,
+                    properties,
+                    name,
+                } => {
+                    if let Some(spans) = spans.as_deref_mut() {
+                        *spans = SourceMap::new(namespace.clone());
                     }
-                    Schema::Fixed {
-                        name,
-                        aliases: _,
-                        doc: _,
-                        size: _,
-                        attributes: _,
-                    } => {
-                        let name = name.clone();
-                        if names_ref.contains_key(&name) {
-                            return Err("Duplicate field {name}");
-                        }
-                        names_ref.insert(name, schema.clone());
-                        return Ok(schema);
+                    protocol.doc = doc;
+                    protocol.namespace = namespace;
+                    protocol.aliases = aliases;
+                    protocol.properties = properties;
+                    protocol.name = name;
+                }
+                Declaration::Import(import) => protocol.imports.push(import),
+                Declaration::Type(schema, span) => {
+                    let name = match &schema {
+                        Schema::Ref { name } => name.clone(),
+                        schema => declared_name(schema)
+                            .expect("declarations are named")
+                            .clone(),
+                    };
+                    if names_ref.contains_key(&name) {
+                        return Err(TokenError::Duplicate(name.fullname(None)));
                     }
-                    Schema::Enum {
-                        name,
-                        aliases: _,
-                        doc: _,
-                        symbols: _,
-                        attributes: _,
-                    } => {
-                        let name = name.clone();
-                        if names_ref.contains_key(&name) {
-                            return Err("Duplicate field {name}");
+                    if let Some(spans) = spans.as_deref_mut() {
+                        spans.insert_type(&name, span);
+                        if let Schema::Record { fields, .. } = &schema {
+                            let sources = record_field_sources(&input[span.start..span.end]);
+                            for (field, source) in fields.iter().zip(sources) {
+                                spans.insert_field(&name, &field.name, Span::of(input, source));
+                            }
                         }
-                        names_ref.insert(name, schema.clone());
-                        return Ok(schema);
                     }
-                    Schema::Ref { name } => {
-                        let name = name.clone();
-                        if names_ref.contains_key(&name) {
-                            return Err("Duplicate field {name}");
-                        }
-                        names_ref.insert(name, schema.clone());
-                        return Ok(schema);
+                    names_ref.insert(name, schema.clone());
+                    protocol.types.push(schema);
+                }
+                Declaration::Message(message) => protocol.messages.push(message),
+            }
+            Ok(ControlFlow::Continue(()))
+        },
+    )?;
+    Ok((tail, protocol))
+}
+
+// A file of types without a protocol around them: an optional namespace and
+// main schema, then imports and named types in any order
+fn parse_schema_file<'a>(
+    input: &'a str,
+    warnings: &mut Warnings,
+    options: &ParserOptions,
+) -> IResult<&'a str, SchemaFile> {
+    let (rest, namespace) = opt(parse_namespace_statement)(skip_comments(input))?;
+    let (mut rest, main) = opt(parse_main_schema)(skip_comments(rest))?;
+    let schema = main.map(|(schema, ignored)| {
+        for ignored in ignored {
+            warnings.push(ignored.warning(input));
+        }
+        schema
+    });
+    let mut file = SchemaFile {
+        namespace: namespace.map(String::from),
+        imports: Vec::new(),
+        types: Vec::new(),
+        schema,
+    };
+    while !skip_comments(rest).is_empty() {
+        let (tail, next) = parse_declaration(input, rest, warnings, options, None)?;
+        match next {
+            Some((_, Declaration::Import(import))) => file.imports.push(import),
+            Some((_, Declaration::Type(schema, _))) => file.types.push(schema),
+            Some((start, _)) => {
+                return Err(nom::Err::Failure(ParserError {
+                    input: start,
+                    kind: ErrorKind::Verify,
+                    context: Some("messages are only valid inside a protocol"),
+                    cause: None,
+                }))
+            }
+            None => {
+                return Err(nom::Err::Failure(ParserError {
+                    input: skip_comments(rest),
+                    kind: ErrorKind::Char,
+                    context: Some("`}` closes nothing, a schema file has no protocol"),
+                    cause: None,
+                }))
+            }
+        }
+        rest = tail;
+    }
+    Ok((rest, file))
+}
+
+pub fn parse(input: &str) -> IResult<&str, Vec<Schema>> {
+    map(parse_with_warnings, |(schemas, _warnings)| schemas)(input)
+}
+
+// Same as `parse`, but also returns the warnings found along the way
+pub fn parse_with_warnings(input: &str) -> IResult<&str, (Vec<Schema>, Warnings)> {
+    let mut names_ref = HashMap::new();
+    let mut warnings = Warnings::new();
+    let (_, mut protocol) = parse_protocol(input, &mut names_ref, &mut warnings)?;
+
+    let (names, _duplicates) = declared_names(&protocol.types, &protocol.namespace);
+    resolve(&mut protocol, &names);
+    Ok(("", (protocol.into_schemas(), warnings)))
+}
+
+// Same as `parse_with_warnings`, running the extra checks enabled in
+// `options` and returning the whole protocol. The first error found is
+// returned, already located in `input`.
+pub fn parse_protocol_with_options(
+    input: &str,
+    options: &ParserOptions,
+) -> Result<(Protocol, Warnings), AvdlError> {
+    parse_and_validate(input, options, None)
+}
+
+/// Same as [`parse_protocol_with_options`], also returning where every type
+/// and field is declared in `input`. The errors found once the protocol is
+/// parsed come with the span of the type or field they are about.
+pub fn parse_spanned(
+    input: &str,
+    options: &ParserOptions,
+) -> Result<(Protocol, Warnings, SourceMap), SpannedError> {
+    let mut spans = SourceMap::default();
+    match parse_and_validate(input, options, Some(&mut spans)) {
+        Ok((protocol, warnings)) => Ok((protocol, warnings, spans)),
+        Err(error) => Err(SpannedError {
+            span: spans.span_of(&error),
+            error: Box::new(error),
+        }),
+    }
+}
+
+fn parse_and_validate(
+    input: &str,
+    options: &ParserOptions,
+    spans: Option<&mut SourceMap>,
+) -> Result<(Protocol, Warnings), AvdlError> {
+    let mut names_ref = HashMap::new();
+    let mut warnings = Warnings::new();
+    let (tail, mut protocol) =
+        parse_protocol_with(input, &mut names_ref, &mut warnings, options, None, spans)
+            .map_err(|err| AvdlError::from_nom(input, err))?;
+    check_trailing(input, tail)?;
+    if let Some(dir) = &options.import_dir {
+        load_imports(&mut protocol, dir, options, &mut HashSet::new())?;
+    }
+    validate_and_resolve(&mut protocol, options, &mut warnings)?;
+    Ok((protocol, warnings))
+}
+
+// Reads the files imported by `protocol` from `dir`, adding their types and
+// messages in front of the ones of the protocol, which is left without
+// imports unless one fails. The files imported by an IDL file are read from its directory
+// too, each file only once, so imports going round in circles end. Warnings
+// found in imported files are not reported
+fn load_imports(
+    protocol: &mut Protocol,
+    dir: &Path,
+    options: &ParserOptions,
+    loaded: &mut HashSet<PathBuf>,
+) -> Result<(), AvdlError> {
+    let mut types = Vec::new();
+    let mut messages = Vec::new();
+    for import in &protocol.imports {
+        let path = dir.join(&import.path);
+        let imported = fs::canonicalize(&path)
+            .map_err(|source| AvdlError::Io {
+                path: path.clone(),
+                source,
+            })
+            .and_then(|canonical| {
+                if !loaded.insert(canonical) {
+                    return Ok(None);
+                }
+                let input = fs::read_to_string(&path).map_err(|source| AvdlError::Io {
+                    path: path.clone(),
+                    source,
+                })?;
+                let protocol = match import.kind {
+                    ImportKind::Idl => load_idl(&input, &path, options, loaded)
+                        .map_err(|err| err.with_file(&path))?,
+                    ImportKind::Protocol => Protocol::from_avpr_str(&input)?,
+                    ImportKind::Schema => Protocol {
+                        name: String::new(),
+                        namespace: None,
+                        doc: None,
+                        aliases: Vec::new(),
+                        imports: Vec::new(),
+                        types: vec![from_avsc_str(&input)?],
+                        messages: Vec::new(),
+                        properties: BTreeMap::new(),
+                    },
+                };
+                Ok(Some(protocol))
+            })
+            .map_err(|err| AvdlError::ImportIdlError {
+                path: path.clone(),
+                source: Box::new(err),
+            })?;
+        if let Some(mut imported) = imported {
+            // The types keep the namespace of the file declaring them
+            for schema in imported.types.iter_mut() {
+                if let Schema::Record { name, .. }
+                | Schema::Enum { name, .. }
+                | Schema::Fixed { name, .. } = schema
+                {
+                    if name.namespace.is_none() {
+                        name.namespace = imported.namespace.clone();
                     }
-                    _ => todo!(),
-                },
-            ))),
-            preceded(multispace0, tag("}")),
-        ),
-    ))(input)?;
+                }
+            }
+            types.append(&mut imported.types);
+            messages.append(&mut imported.messages);
+        }
+    }
+    // A file imported back by one of its imports is read twice
+    types.retain(|schema| !protocol.types.contains(schema));
+    messages.retain(|message| !protocol.messages.contains(message));
+    types.append(&mut protocol.types);
+    messages.append(&mut protocol.messages);
+    protocol.imports.clear();
+    protocol.types = types;
+    protocol.messages = messages;
+    Ok(())
+}
+
+// The protocol or schema file imported from `path`, with its own imports
+// read. Its references are resolved when they can be, the others are left
+// for the importing protocol, as imports going round in circles only see
+// all the types once back at the first file
+fn load_idl(
+    input: &str,
+    path: &Path,
+    options: &ParserOptions,
+    loaded: &mut HashSet<PathBuf>,
+) -> Result<Protocol, AvdlError> {
+    let mut warnings = Warnings::new();
+    let mut protocol = if declares_protocol(input) {
+        let (tail, protocol) = parse_protocol_with(
+            input,
+            &mut HashMap::new(),
+            &mut warnings,
+            options,
+            None,
+            None,
+        )
+        .map_err(|err| AvdlError::from_nom(input, err))?;
+        check_trailing(input, tail)?;
+        protocol
+    } else {
+        let (tail, file) = parse_schema_file(input, &mut warnings, options)
+            .map_err(|err| AvdlError::from_nom(input, err))?;
+        check_trailing(input, tail)?;
+        Protocol {
+            name: String::new(),
+            namespace: file.namespace,
+            doc: None,
+            aliases: Vec::new(),
+            imports: file.imports,
+            types: file.types,
+            messages: Vec::new(),
+            properties: BTreeMap::new(),
+        }
+    };
+    let dir = path.parent().unwrap_or(Path::new(""));
+    load_imports(&mut protocol, dir, options, loaded)?;
+    let options = ParserOptions {
+        allow_unresolved_refs: true,
+        ..options.clone()
+    };
+    validate_and_resolve(&mut protocol, &options, &mut warnings)?;
+    Ok(protocol)
+}
+
+// Runs the checks over the types of `protocol`, failing with the first error,
+// then resolves the references between them. Returns the named types
+fn validate_and_resolve(
+    protocol: &mut Protocol,
+    options: &ParserOptions,
+    warnings: &mut Warnings,
+) -> Result<Names, AvdlError> {
+    let (names, duplicates) = declared_names(&protocol.types, &protocol.namespace);
+    let errors = validate(protocol, &names, options, warnings);
+    match duplicates.into_iter().chain(errors).next() {
+        Some(err) => Err(err),
+        None => {
+            resolve(protocol, &names);
+            Ok(names)
+        }
+    }
+}
+
+/// Whether `input` declares a protocol, rather than the types of a schema
+/// file. Only the start of the protocol, up to the `{` opening its body, is
+/// looked at.
+pub fn declares_protocol(input: &str) -> bool {
+    parse_protocol_header(skip_comments(input)).is_ok()
+}
+
+/// Parse the protocol or the schema file in `input`, failing if anything but
+/// whitespace and comments follows it.
+///
+/// A schema file, as written since Avro 1.12, declares types without a
+/// protocol around them. It starts with an optional `namespace` statement,
+/// and an optional `schema` statement giving its main type, followed by
+/// imports and named types. They are checked and their references resolved
+/// the same way as the ones of a protocol.
+///
+/// ```
+/// use avdl_parser::{parse_idl_file, IdlFile};
+///
+/// let input = "namespace org.example;
+/// schema Message;
+///
+/// record Message {
+///     string title;
+/// }";
+/// let IdlFile::Schema(file) = parse_idl_file(input).unwrap() else {
+///     panic!("expected a schema file");
+/// };
+/// assert_eq!(file.namespace.as_deref(), Some("org.example"));
+/// assert_eq!(file.types.len(), 1);
+/// assert_eq!(file.schema.unwrap().name().unwrap().name, "Message");
+///
+/// let protocol = parse_idl_file("protocol Empty {}").unwrap();
+/// assert!(matches!(protocol, IdlFile::Protocol(_)));
+/// ```
+pub fn parse_idl_file(input: &str) -> Result<IdlFile, AvdlError> {
+    parse_idl_file_with_options(input, &ParserOptions::default()).map(|(file, _)| file)
+}
+
+/// Same as [`parse_idl_file`], running the extra checks enabled in `options`
+/// and returning the warnings too.
+pub fn parse_idl_file_with_options(
+    input: &str,
+    options: &ParserOptions,
+) -> Result<(IdlFile, Warnings), AvdlError> {
+    if declares_protocol(input) {
+        let (protocol, warnings) = parse_protocol_with_options(input, options)?;
+        return Ok((IdlFile::Protocol(protocol), warnings));
+    }
+    let mut warnings = Warnings::new();
+    let (tail, file) = parse_schema_file(input, &mut warnings, options)
+        .map_err(|err| AvdlError::from_nom(input, err))?;
+    check_trailing(input, tail)?;
+
+    // The types are checked as the ones of a protocol without messages
+    let mut protocol = Protocol {
+        name: String::new(),
+        namespace: file.namespace,
+        doc: None,
+        aliases: Vec::new(),
+        imports: file.imports,
+        types: file.types,
+        messages: Vec::new(),
+        properties: BTreeMap::new(),
+    };
+    if let Some(dir) = &options.import_dir {
+        load_imports(&mut protocol, dir, options, &mut HashSet::new())?;
+    }
+    let names = validate_and_resolve(&mut protocol, options, &mut warnings)?;
+    let schema = match file.schema {
+        Some(schema) => Some(resolve_main_schema(
+            schema,
+            &names,
+            &protocol.namespace,
+            !options.allow_unresolved_refs && protocol.imports.is_empty(),
+        )?),
+        None => None,
+    };
+    let file = SchemaFile {
+        namespace: protocol.namespace,
+        imports: protocol.imports,
+        types: protocol.types,
+        schema,
+    };
+    Ok((IdlFile::Schema(file), warnings))
+}
+
+// The main type of a schema file, which is the type it names when it is a
+// reference. The types it references must be declared when `check_refs` is
+// set, which they can't be while imports are not read
+fn resolve_main_schema(
+    mut schema: Schema,
+    names: &Names,
+    namespace: &Namespace,
+    check_refs: bool,
+) -> Result<Schema, AvdlError> {
+    let mut pending = vec![&schema];
+    while let Some(next) = pending.pop() {
+        match next {
+            Schema::Ref { name } if check_refs => {
+                let fullname = name.fully_qualified_name(namespace);
+                if !names.contains_key(&fullname) {
+                    return Err(AvdlError::UnresolvedSchema(fullname.fullname(None)));
+                }
+            }
+            Schema::Array(inner) | Schema::Map(inner) => pending.push(inner),
+            Schema::Union(union) => pending.extend(union.variants()),
+            _ => (),
+        }
+    }
+    if let Ok(Operation::Swap(found)) = schema_solver(&mut schema, names, namespace) {
+        schema = found;
+    }
+    namespace_solver(&mut schema, namespace);
+    Ok(schema)
+}
+
+// Fails unless what is left after the protocol is only whitespace and comments
+pub(crate) fn check_trailing(input: &str, tail: &str) -> Result<(), AvdlError> {
+    let mut rest = tail;
+    while let Ok((next, _)) = space_delimited(opt(parse_comment::<_, ParserError<&str>>))(rest) {
+        if next.len() == rest.len() {
+            break;
+        }
+        rest = next;
+    }
+    if rest.is_empty() {
+        return Ok(());
+    }
+    let err = ParserError {
+        input: rest,
+        kind: ErrorKind::Eof,
+        context: Some("unexpected trailing content"),
+        cause: None,
+    };
+    Err(AvdlError::from_nom(input, nom::Err::Failure(err)))
+}
 
-    Ok((tail, (schemas, namespace)))
+/// Parse the protocol in `input`, failing if anything but whitespace and
+/// comments follows it.
+pub fn parse_str(input: &str) -> Result<Protocol, AvdlError> {
+    parse_protocol_with_options(input, &ParserOptions::default()).map(|(protocol, _)| protocol)
 }
 
-pub fn parse(input: &str) -> IResult<&str, Vec<Schema>> {
+/// Parse the type in `input`, written the way a field declares it, failing
+/// if anything but whitespace and comments surrounds it. Named types are
+/// taken as references, which are left unresolved.
+///
+/// This is what `FromStr` would be, but [`Schema`] belongs to apache_avro.
+///
+/// ```
+/// use apache_avro::schema::{Schema, UnionSchema};
+/// use avdl_parser::parse_type_str;
+///
+/// let schema = parse_type_str("array<union { null, string }>").unwrap();
+/// let union = UnionSchema::new(vec![Schema::Null, Schema::String]).unwrap();
+/// assert_eq!(schema, Schema::Array(Box::new(Schema::Union(union))));
+///
+/// let schema = parse_type_str("decimal(9,2)").unwrap();
+/// assert!(matches!(schema, Schema::Decimal { precision: 9, scale: 2, .. }));
+///
+/// let schema = parse_type_str("Address").unwrap();
+/// assert!(matches!(schema, Schema::Ref { name } if name.name == "Address"));
+///
+/// // Only a type, without the name or default of a field
+/// assert!(parse_type_str("int age").is_err());
+/// assert!(parse_type_str("int = 3").is_err());
+/// assert!(parse_type_str("decimal(2, 9)").is_err());
+/// ```
+pub fn parse_type_str(input: &str) -> Result<Schema, AvdlError> {
+    let (tail, schema) =
+        map_type_to_schema(skip_comments(input)).map_err(|err| AvdlError::from_nom(input, err))?;
+    check_trailing(input, tail)?;
+    Ok(schema)
+}
+
+/// Same as [`parse_str`], reading the protocol from `reader`.
+pub fn parse_reader(mut reader: impl Read) -> Result<Protocol, AvdlError> {
+    let mut input = String::new();
+    reader.read_to_string(&mut input).map_err(AvdlError::Read)?;
+    parse_str(&input)
+}
+
+/// Same as [`parse_str`], reading the protocol from the file at `path`.
+/// Errors tell the file they were found in. Imports are read relative to
+/// the directory of `path`.
+pub fn parse_file(path: impl AsRef<Path>) -> Result<Protocol, AvdlError> {
+    parse_file_with_options(path, &ParserOptions::default()).map(|(protocol, _)| protocol)
+}
+
+/// Same as [`parse_file`], running the extra checks enabled in `options` and
+/// returning the warnings too. The directory of `path` replaces the
+/// [`ParserOptions::import_dir`] of `options`.
+pub fn parse_file_with_options(
+    path: impl AsRef<Path>,
+    options: &ParserOptions,
+) -> Result<(Protocol, Warnings), AvdlError> {
+    let path = path.as_ref();
+    let input = fs::read_to_string(path).map_err(|source| AvdlError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let options = ParserOptions {
+        import_dir: Some(path.parent().unwrap_or(Path::new("")).to_path_buf()),
+        ..options.clone()
+    };
+    parse_protocol_with_options(&input, &options).map_err(|err| err.with_file(path))
+}
+
+// Same as `parse_protocol_with_options`, but a field that fails to parse doesn't stop
+// the parser: it is skipped and its error collected, so all the problems of a
+// file are reported at once. Returns the schemas that could be parsed.
+pub fn parse_lenient(
+    input: &str,
+    options: &ParserOptions,
+) -> (Vec<Schema>, Warnings, Vec<AvdlError>) {
+    parse_recovering(input, options, None)
+}
+
+/// Every problem of the protocol in `input`, to report them all at once.
+/// Fields that fail to parse are skipped like [`parse_lenient`] does, and
+/// the errors found once the protocol is parsed come with the span of the
+/// type or field they are about, like [`parse_spanned`] does.
+pub fn check_protocol(input: &str, options: &ParserOptions) -> (Warnings, Vec<SpannedError>) {
+    let mut spans = SourceMap::default();
+    let (_schemas, warnings, errors) = parse_recovering(input, options, Some(&mut spans));
+    let errors = errors
+        .into_iter()
+        .map(|error| SpannedError {
+            span: spans.span_of(&error),
+            error: Box::new(error),
+        })
+        .collect();
+    (warnings, errors)
+}
+
+fn parse_recovering(
+    input: &str,
+    options: &ParserOptions,
+    spans: Option<&mut SourceMap>,
+) -> (Vec<Schema>, Warnings, Vec<AvdlError>) {
     let mut names_ref = HashMap::new();
-    let (_, (mut schemas, namespace)) = parse_protocol(input, &mut names_ref)?;
+    let mut warnings = Warnings::new();
+    let mut recovered = Vec::new();
+    let result = parse_protocol_with(
+        input,
+        &mut names_ref,
+        &mut warnings,
+        options,
+        Some(&mut recovered),
+        spans,
+    );
+    let mut errors: Vec<AvdlError> = recovered
+        .into_iter()
+        .map(|err| AvdlError::from_nom(input, nom::Err::Error(err)))
+        .collect();
+    let schemas = match result {
+        Ok((tail, mut protocol)) => {
+            errors.extend(check_trailing(input, tail).err());
+            if let Some(dir) = &options.import_dir {
+                errors.extend(load_imports(&mut protocol, dir, options, &mut HashSet::new()).err());
+            }
+            let (names, duplicates) = declared_names(&protocol.types, &protocol.namespace);
+            errors.extend(duplicates);
+            errors.extend(validate(&protocol, &names, options, &mut warnings));
+            resolve(&mut protocol, &names);
+            protocol.into_schemas()
+        }
+        Err(err) => {
+            errors.push(AvdlError::from_nom(input, err));
+            Vec::new()
+        }
+    };
+    (schemas, warnings, errors)
+}
 
-    for schema in schemas.iter_mut() {
-        let _ = schema_solver(schema, &mut names_ref, &None);
-        namespace_solver(schema, &namespace);
+// Replaces the references with the types they point to, looked up in `names`
+// by full name
+pub(crate) fn resolve(protocol: &mut Protocol, names: &Names) {
+    for schema in protocol.types.iter_mut() {
+        let _ = schema_solver(schema, names, &protocol.namespace);
+        namespace_solver(schema, &protocol.namespace);
+    }
+    for message in protocol.messages.iter_mut() {
+        let schemas = message
+            .request
+            .iter_mut()
+            .map(|field| &mut field.schema)
+            .chain(Some(&mut message.response))
+            .chain(message.errors.iter_mut());
+        for schema in schemas {
+            if let Ok(Operation::Swap(found)) = schema_solver(schema, names, &protocol.namespace) {
+                *schema = found;
+            }
+        }
     }
-    Ok(("", schemas))
 }
 
 enum Operation {
@@ -1098,18 +2898,19 @@ enum Operation {
     Swap(Schema),
 }
 
+// Fails with the fullname of the first reference which is not declared
 fn schema_solver(
     schema: &mut Schema,
-    names_ref: &mut HashMap<Name, Schema>,
+    names: &Names,
     enclosing_namespace: &Namespace,
-) -> Result<Operation, String> {
+) -> Result<Operation, Name> {
     match schema {
         Schema::Record { name, fields, .. } => {
             let fully_qualified_name = name.fully_qualified_name(enclosing_namespace);
 
             let record_namespace = fully_qualified_name.namespace;
             for field in fields {
-                let res = schema_solver(&mut field.schema, names_ref, &record_namespace)?;
+                let res = schema_solver(&mut field.schema, names, &record_namespace)?;
                 match res {
                     Operation::Swap(schema) => {
                         field.schema = schema;
@@ -1121,21 +2922,20 @@ fn schema_solver(
         }
         Schema::Ref { name } => {
             let fully_qualified_name = name.fully_qualified_name(enclosing_namespace);
-            let found_schema = names_ref
+            let found_schema = names
                 .get(&fully_qualified_name)
-                .ok_or("Failed to solve schema".to_string())?;
+                .ok_or(fully_qualified_name.clone())?;
             Ok(Operation::Swap(found_schema.clone()))
         }
         _ => Ok(Operation::NoOp),
     }
 }
 
-fn namespace_solver(schema: &mut Schema, enclosing_namespace: &Namespace) -> () {
-    match schema {
-        Schema::Record { name, .. } => {
+pub(crate) fn namespace_solver(schema: &mut Schema, enclosing_namespace: &Namespace) -> () {
+    if let Schema::Record { name, .. } = schema {
+        if name.namespace.is_none() {
             name.namespace = enclosing_namespace.clone();
-        },
-        _ => ()
+        }
     }
 }
 
@@ -1146,10 +2946,11 @@ mod test {
     use super::*;
     use apache_avro::schema::{Alias, Name, RecordField, RecordFieldOrder, Schema};
     use rstest::rstest;
-    use serde_json::{Map, Number, Value};
+    use serde_json::{json, Map, Number, Value};
 
     #[rstest]
     #[case("// holis\n", " holis")]
+    #[case("// at the end", " at the end")]
     #[case(
         "// TODO: Move to another place, etc.\n",
         " TODO: Move to another place, etc."
@@ -1168,10 +2969,27 @@ mod test {
     #[rstest]
     #[case(
         "/** Documentation for the enum type Kind */",
-        "Documentation for the enum type Kind"
+        " Documentation for the enum type Kind "
     )]
     fn test_parse_doc(#[case] input: &str, #[case] expected: String) {
-        assert_eq!(parse_doc(input), Ok(("", expected)))
+        assert_eq!(parse_doc(input), Ok(("", expected.as_str())))
+    }
+
+    #[rstest]
+    #[case(" person fullname ", "person fullname")]
+    #[case("\n * An example\n ", "An example")]
+    #[case("\n * Two\n *\n * paragraphs\n ", "Two\n\nparagraphs")]
+    #[case(" First line\n *   indented\n ", "First line\n  indented")]
+    #[case(
+        "\n    No stars,\n      only indentation\n ",
+        "No stars,\n  only indentation"
+    )]
+    #[case(" 2 * 3\n is 6 ", "2 * 3\nis 6")]
+    #[case("", "")]
+    #[case(r" Ends with *\/ ", "Ends with */")]
+    #[case(r" *\\ and *\a ", r"*\ and *\a")]
+    fn test_normalize_doc(#[case] doc: &str, #[case] expected: &str) {
+        assert_eq!(normalize_doc(doc), expected);
     }
 
     #[rstest]
@@ -1231,7 +3049,7 @@ mod test {
         String::from("org.apache.avro.test")
     )]
     fn test_parse_namespace(#[case] input: &str, #[case] expected: String) {
-        assert_eq!(parse_namespace(input), Ok(("", expected)));
+        assert_eq!(parse_namespace(input), Ok(("", expected.as_str())));
     }
 
     #[rstest]
@@ -1252,7 +3070,7 @@ mod test {
     #[case(r#""org.ancient.AncientRecord""#, "org.ancient.AncientRecord".to_string())]
     #[case(r#""ancientField""#, "ancientField".to_string())]
     fn test_namespace_parser(#[case] input: &str, #[case] expected: String) {
-        assert_eq!(parse_namespace_value(input), Ok(("", expected)))
+        assert_eq!(parse_namespace_value(input), Ok(("", expected.as_str())))
     }
 
     #[rstest]
@@ -1340,6 +3158,7 @@ mod test {
     #[case("int age;", (Schema::Int, None, None, None, "age", None))]
     #[case("int age = 12;", (Schema::Int, None, None, None, "age", Some(Value::Number(12.into()))))]
     #[case("int age = 0;", (Schema::Int, None, None, None, "age", Some(Value::Number(0.into()))))]
+    #[case("int age = -12;", (Schema::Int, None, None, None, "age", Some(Value::Number((-12).into()))))]
     #[case("int   age   =   123 ;", (Schema::Int, None, None, None, "age", Some(Value::Number(123.into()))))]
     fn test_parse_int_ok(
         #[case] input: &str,
@@ -1355,19 +3174,51 @@ mod test {
         assert_eq!(parse_field(input), Ok(("", expected)));
     }
 
+    #[rstest]
+    #[case(r#"@logicalType("duration") Wait wait;"#, None)]
+    #[case(r#"@logicalType("duration") Wait wait = "\u{1}\u{0}\u{0}\u{0}\u{2}\u{0}\u{0}\u{0}\u{ff}\u{0}\u{0}\u{0}";"#, Some([1, 0, 0, 0, 2, 0, 0, 0, 255, 0, 0, 0]))]
+    #[case(r#"@logicalType("duration") Wait wait = {"months": 1, "days": 2, "millis": 255};"#, Some([1, 0, 0, 0, 2, 0, 0, 0, 255, 0, 0, 0]))]
+    #[case(r#"@logicalType("duration") Wait wait = { "millis": 256 };"#, Some([0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0]))]
+    #[case(r#"@logicalType("duration") Wait wait = {};"#, Some([0; 12]))]
+    fn test_parse_duration_default(#[case] input: &str, #[case] expected: Option<[u8; 12]>) {
+        let expected = expected.map(|bytes| Value::Array(bytes.map(Value::from).to_vec()));
+        let (tail, (schema, _, _, _, _, default)) = parse_field(input).unwrap();
+        assert_eq!(tail, "");
+        assert_eq!(schema, Schema::Duration);
+        assert_eq!(default, expected);
+    }
+
+    #[rstest]
+    #[case(r#"@logicalType("duration") Wait wait = "short";"#)]
+    #[case(r#"@logicalType("duration") Wait wait = "\u{100}\u{0}\u{0}\u{0}\u{0}\u{0}\u{0}\u{0}\u{0}\u{0}\u{0}\u{0}";"#)]
+    #[case(r#"@logicalType("duration") Wait wait = {"days": 1, "days": 2};"#)]
+    #[case(r#"@logicalType("duration") Wait wait = {"weeks": 1};"#)]
+    #[case(r#"@logicalType("duration") Wait wait = {"months": -1};"#)]
+    #[case(r#"@logicalType("duration") Wait wait = {"millis": 4294967296};"#)]
+    #[case(r#"@logicalType("duration") Wait wait = 12;"#)]
+    fn test_parse_duration_default_fail(#[case] input: &str) {
+        assert!(parse_field(input).is_err());
+    }
+
     #[rstest]
     #[case("int age")] // missing semi-colon
     #[case(r#"int age = "false""#)] // wrong type
     #[case(r#"int age = 123"#)] // missing semi-colon with default
     #[case("int age = 9223372036854775807;")] // longer than i32
+    #[case("int age = - 1;")] // space after the sign
     fn test_parse_int_fail(#[case] input: &str) {
         assert!(parse_field(input).is_err());
     }
 
     #[rstest]
-    #[case("decimal(1,2) age = \"1.2\";", (Schema::Decimal { precision: 1, scale: 2, inner: Box::new(Schema::Bytes) }, None, None, None, "age", Some(AvroValue::Decimal("1.2".into()).try_into().unwrap())))]
+    #[case("decimal( 10 , 2 ) price;", (Schema::Decimal { precision: 10, scale: 2, inner: Box::new(Schema::Bytes) }, None, None, None, "price", None))]
+    #[case("decimal (10 /* digits */, 2 /* after the point */) price;", (Schema::Decimal { precision: 10, scale: 2, inner: Box::new(Schema::Bytes) }, None, None, None, "price", None))]
+    #[case("decimal(\n    10,\n    2\n) price;", (Schema::Decimal { precision: 10, scale: 2, inner: Box::new(Schema::Bytes) }, None, None, None, "price", None))]
+    #[case("decimal(4,2) price;", (Schema::Decimal { precision: 4, scale: 2, inner: Box::new(Schema::Bytes) }, None, None, None, "price", None))]
+    #[case(r#"decimal(4, 2) price = "\u{7}\u{d0}";"#, (Schema::Decimal { precision: 4, scale: 2, inner: Box::new(Schema::Bytes) }, None, None, None, "price", Some(AvroValue::Decimal(vec![0x07, 0xd0].into()).try_into().unwrap())))]
+    #[case(r#"decimal(4, 2) price = "\u{f8}0";"#, (Schema::Decimal { precision: 4, scale: 2, inner: Box::new(Schema::Bytes) }, None, None, None, "price", Some(AvroValue::Decimal(vec![0xf8, 0x30].into()).try_into().unwrap())))]
     #[case("int age;", (Schema::Int, None, None, None, "age", None))]
-    #[case("/** How old is */ int age;", (Schema::Int, Some(String::from("How old is")), None, None, "age", None))]
+    #[case("/** How old is */ int age;", (Schema::Int, Some(String::from(" How old is ")), None, None, "age", None))]
     #[case("int age = 12;", (Schema::Int, None, None, None, "age", Some(Value::Number(12.into()))))]
     #[case("int age = 0;", (Schema::Int, None, None, None, "age", Some(Value::Number(0.into()))))]
     #[case("int   age   =   123 ;", (Schema::Int, None, None, None, "age", Some(Value::Number(123.into()))))]
@@ -1378,6 +3229,9 @@ mod test {
     #[case("timestamp_ms age;", (Schema::TimestampMillis, None, None, None, "age", None))]
     #[case("timestamp_ms age = 12;", (Schema::TimestampMillis, None, None, None, "age", Some(Value::Number(12.into()))))]
     #[case("@logicalType(\"timestamp-micros\")\nlong ts = 12;", (Schema::TimestampMicros, None, None, None, "ts", Some(Value::Number(12.into()))))]
+    #[case("@logicalType(\"timestamp-millis\") long ts = 12;", (Schema::TimestampMillis, None, None, None, "ts", Some(Value::Number(12.into()))))]
+    #[case("@logicalType(\"date\") int day;", (Schema::Date, None, None, None, "day", None))]
+    #[case("@logicalType(\"uuid\") string pk;", (Schema::Uuid, None, None, None, "pk", None))]
     #[case("date age;", (Schema::Date, None, None, None, "age", None))]
     #[case("date age = 12;", (Schema::Date, None, None, None, "age", Some(Value::Number(12.into()))))]
     #[case(r#"uuid pk = "a1a2a3a4-b1b2-c1c2-d1d2-d3d4d5d6d7d8";"#, (Schema::Uuid, None, None, None, "pk", Some(Value::String("a1a2a3a4-b1b2-c1c2-d1d2-d3d4d5d6d7d8".into()))))]
@@ -1405,6 +3259,11 @@ mod test {
     #[case(r#"time_ms age = 123"#)] // missing semi-colon with default
     #[case("time_ms age = 9223372036854775807;")] // longer than i32
     #[case(r#"uuid pk = "asd";"#)] // longer than i32
+    #[case("decimal(0,0) price;")] // no digits
+    #[case("decimal(1,2) price;")] // scale larger than the precision
+    #[case(r#"decimal(3, 2) price = "\u{7}\u{d0}";"#)] // 2000 has 4 digits
+    #[case(r#"decimal(4, 2) price = "\u{100}";"#)] // not a byte
+    #[case(r#"decimal(4, 2) price = 20.00;"#)] // not bytes
     fn test_parse_logical_field_fail(#[case] input: &str) {
         assert!(parse_field(input).is_err());
     }
@@ -1415,6 +3274,7 @@ mod test {
     #[case("long stock = 9223372036854775807;", (Schema::Long, None, None, None, "stock", Some(Value::Number(Number::from(9223372036854775807 as i64)))))]
     #[case("long stock = 0;", (Schema::Long, None, None, None, "stock", Some(Value::Number(0.into()))))]
     #[case("long   stock   =   123 ;", (Schema::Long, None, None, None, "stock", Some(Value::Number(123.into()))))]
+    #[case("long stock = -9223372036854775808;", (Schema::Long, None, None, None, "stock", Some(Value::Number(i64::MIN.into()))))]
     fn test_parse_long_ok(
         #[case] input: &str,
         #[case] expected: (
@@ -1440,6 +3300,8 @@ mod test {
     #[case("float age = 3.4028234663852886e38;", (Schema::Float, None, None, None, "age", Some(Value::Number(Number::from_f64(f32::MAX.into()).unwrap()))))]
     #[case("float age = 0;", (Schema::Float, None, None, None, "age", Some(Value::Number(Number::from_f64(0.0).unwrap()))))]
     #[case("float   age   =   123 ;", (Schema::Float, None, None, None, "age", Some(Value::Number(Number::from_f64(123.0).unwrap()))))]
+    #[case("float age = -1.5;", (Schema::Float, None, None, None, "age", Some(Value::Number(Number::from_f64(-1.5).unwrap()))))]
+    #[case("float age = 1.5e-7;", (Schema::Float, None, None, None, "age", Some(Value::Number(Number::from_f64(1.5e-7).unwrap()))))]
     fn test_parse_float_ok(
         #[case] input: &str,
         #[case] expected: (
@@ -1475,6 +3337,7 @@ mod test {
     #[case("double stock = 0;", (Schema::Double, None, None, None, "stock", Some(Value::Number(Number::from_f64(0.0).unwrap()))))]
     #[case(r#"double @order("descending") stock = 0;"#, (Schema::Double, None, Some(RecordFieldOrder::Descending), None, "stock", Some(Value::Number(Number::from_f64(0.0).unwrap()))))]
     #[case("double   stock   =   123.3 ;", (Schema::Double, None, None, None, "stock", Some(Value::Number(Number::from_f64(123.3).unwrap()))))]
+    #[case("double stock = -2.5E+300;", (Schema::Double, None, None, None, "stock", Some(Value::Number(Number::from_f64(-2.5e300).unwrap()))))]
     fn test_parse_double_ok(
         #[case] input: &str,
         #[case] expected: (
@@ -1498,7 +3361,7 @@ mod test {
     }
 
     #[rstest]
-    #[case("/** Stock */ array<string> stock;", (Schema::Array(Box::new(Schema::String)), Some(String::from("Stock")), None, None, "stock", None))]
+    #[case("/** Stock */ array<string> stock;", (Schema::Array(Box::new(Schema::String)), Some(String::from(" Stock ")), None, None, "stock", None))]
     #[case(r#"array<array<string>> stock = [["cacao"]];"#, (Schema::Array(Box::new(Schema::Array(Box::new(Schema::String)))), None, None, None, "stock", Some(Value::Array(Vec::from([Value::Array(Vec::from([Value::String(String::from("cacao"))]))])))))]
     #[case(r#"array<string> stock = ["cacao"];"#, (Schema::Array(Box::new(Schema::String)), None, None, None, "stock", Some(Value::Array(Vec::from([Value::String(String::from("cacao"))])))))]
     #[case("array<string> stock;", (Schema::Array(Box::new(Schema::String)), None, None, None, "stock", None))]
@@ -1518,7 +3381,7 @@ mod test {
             Option<Value>,
         ),
     ) {
-        assert_eq!(parse_array(input), Ok(("", expected)));
+        assert_eq!(parse_field(input), Ok(("", expected)));
     }
 
     #[rstest]
@@ -1536,7 +3399,7 @@ mod test {
             Option<Value>,
         ),
     ) {
-        assert_eq!(parse_map(input), Ok(("", expected)));
+        assert_eq!(parse_field(input), Ok(("", expected)));
     }
 
     #[rstest]
@@ -1544,7 +3407,7 @@ mod test {
         r#"union { null, string } item_id = null;"#, (Schema::Union(UnionSchema::new(vec![Schema::Null, Schema::String]).unwrap()), None, None, None, "item_id", Some(Value::Null))
     )]
     #[case(
-        r#"/** Item */union { null, string } item_id = null;"#, (Schema::Union(UnionSchema::new(vec![Schema::Null, Schema::String]).unwrap()), Some(String::from("Item")), None, None, "item_id", Some(Value::Null))
+        r#"/** Item */union { null, string } item_id = null;"#, (Schema::Union(UnionSchema::new(vec![Schema::Null, Schema::String]).unwrap()), Some(String::from(" Item ")), None, None, "item_id", Some(Value::Null))
     )]
     #[case(
         r#"union { null, string } item = null;"#, (Schema::Union(UnionSchema::new(vec![Schema::Null, Schema::String]).unwrap()), None, None, None, "item", Some(Value::Null))
@@ -1566,17 +3429,87 @@ mod test {
             Option<Value>,
         ),
     ) {
-        assert_eq!(parse_union(input), Ok(("", expected)));
+        assert_eq!(parse_field(input), Ok(("", expected)));
+    }
+
+    #[rstest]
+    #[case(r#"union { null, string } item = "a";"#)] // not the first type
+    #[case(r#"union { null, string } item = nul;"#)]
+    #[case(r#"union { int, string } item = 1.5;"#)]
+    #[case(r#"union { int, string } item = ;"#)]
+    #[case(r#"union { array<int>, null } item = [1, "a"];"#)]
+    fn test_union_invalid_default(#[case] input: &str) {
+        assert!(parse_field(input).is_err());
     }
 
     #[rstest]
     #[case(r#"fixed MD5(16);"#, Schema::Fixed { name: "MD5".into(), aliases: None, doc: None, size: 16, attributes: BTreeMap::new()})]
     #[case("/** my hash */ \nfixed MD5(16);", Schema::Fixed { name: "MD5".into(), aliases: None, doc: Some("my hash".to_string()), size: 16, attributes: BTreeMap::new()})]
     #[case(r#"fixed @aliases(["md1"]) MD5(16);"#, Schema::Fixed { name: "MD5".into(), aliases: None, doc: None, size: 16, attributes: BTreeMap::new()})]
+    #[case(r#"@logicalType("decimal") @precision(9) @scale(2) fixed Amount(8);"#, Schema::Decimal { precision: 9, scale: 2, inner: Box::new(Schema::Fixed { name: "Amount".into(), aliases: None, doc: None, size: 8, attributes: BTreeMap::new()}) })]
+    #[case("/** in cents */\n@logicalType(\"decimal\")\n@precision(18)\nfixed Amount(8);", Schema::Decimal { precision: 18, scale: 0, inner: Box::new(Schema::Fixed { name: "Amount".into(), aliases: None, doc: Some("in cents".to_string()), size: 8, attributes: BTreeMap::new()}) })]
+    #[case("fixed MD5 ( 16 ) ;", Schema::Fixed { name: "MD5".into(), aliases: None, doc: None, size: 16, attributes: BTreeMap::new()})]
+    #[case("fixed MD5(/* bytes */ 16 /* of hash */) // md5\n;", Schema::Fixed { name: "MD5".into(), aliases: None, doc: None, size: 16, attributes: BTreeMap::new()})]
+    #[case("fixed\n    MD5\n    (\n        16\n    )\n;", Schema::Fixed { name: "MD5".into(), aliases: None, doc: None, size: 16, attributes: BTreeMap::new()})]
+    #[case(r#"@logicalType("duration") fixed Span(12);"#, Schema::Fixed { name: "Span".into(), aliases: None, doc: None, size: 12, attributes: BTreeMap::from([("logicalType".to_string(), "duration".into())])})]
     fn test_parse_fixed_ok(#[case] input: &str, #[case] expected: Schema) {
         assert_eq!(parse_fixed(input), Ok(("", expected)));
     }
 
+    #[rstest]
+    #[case(r#"@logicalType("decimal") fixed Amount(8);"#)] // no precision
+    #[case(r#"@logicalType("decimal") @scale(2) @precision(9) fixed Amount(8);"#)] // precision first
+    #[case(r#"@logicalType("decimal") @precision(19) fixed Amount(8);"#)] // 18 digits at most
+    #[case(r#"@logicalType("decimal") @precision(2) @scale(3) fixed Amount(8);"#)]
+    #[case(r#"@logicalType("duration") fixed Span(8);"#)] // 12 bytes only
+    fn test_parse_fixed_fail(#[case] input: &str) {
+        assert!(parse_fixed(input).is_err());
+    }
+
+    #[rstest]
+    fn test_field_end_of_statement(
+        #[values(
+            "string name",
+            "int count = 1",
+            "double ratio = 0.5",
+            "boolean on = true",
+            "uuid pk",
+            "time_ms elapsed = 12",
+            "decimal(4, 2) price",
+            r#"bytes raw = "abc""#,
+            "array<string> xs = []",
+            r#"map<int> counts = {"a": 1}"#,
+            "union { null, string } item = null",
+            "Shape shape = ROUND",
+            r#"string @order("ignore") @aliases(["label"]) title"#,
+            r#"@logicalType("timestamp-micros") long ts = 12"#
+        )]
+        field: &str,
+        #[values(";", " ;", "\n;", "/* c */;", " /* c */ // d\n ;")] end: &str,
+    ) {
+        let input = format!("{field}{end}");
+        let (tail, _field) = parse_record_field(&input).unwrap();
+        assert_eq!(tail, "");
+    }
+
+    #[rstest]
+    fn test_end_of_statement(
+        #[values(
+            "fixed MD5(16)",
+            "enum Shape { ROUND } = ROUND",
+            r#"import idl "other.avdl""#,
+            "void ping() oneway",
+            "int add(int a, int b)"
+        )]
+        statement: &str,
+        #[values(";", " ;", "\n;", "/* c */;", " /* c */ // d\n ;")] end: &str,
+    ) {
+        let input = format!("protocol P {{ {statement}{end} }}");
+        let (tail, _protocol) =
+            parse_protocol(&input, &mut HashMap::new(), &mut Warnings::new()).unwrap();
+        assert_eq!(tail, "");
+    }
+
     #[rstest]
     #[case(r#"= holis;"#, "holis")]
     #[case(r#"= holis ;"#, "holis")]
@@ -1601,7 +3534,7 @@ mod test {
     #[case("{SQUARE,TRIANGLE,CIRCLE,OVAL}")]
     fn test_enum_body(#[case] input: &str) {
         let expected = vec!["SQUARE", "TRIANGLE", "CIRCLE", "OVAL"];
-        assert_eq!(parse_enum_symbols(input), Ok(("", expected)))
+        assert_eq!(parse_enum_symbols(false)(input), Ok(("", expected)))
     }
 
     #[test]
@@ -1699,16 +3632,21 @@ mod test {
     #[case(r#"double @order("ignore") Hello;"#, RecordField{ name: String::from("Hello"), doc: None, default: None, schema: Schema::Double, order: apache_avro::schema::RecordFieldOrder::Ignore, aliases: None, position: 0, custom_attributes: BTreeMap::new() })]
     #[case("double Hello = 123;", RecordField{ name: String::from("Hello"), doc: None, default: Some(Value::Number(Number::from_f64(123.0).unwrap())), schema: Schema::Double, order: apache_avro::schema::RecordFieldOrder::Ascending, aliases: None, position: 0, custom_attributes: BTreeMap::new() })]
     #[case("double Hello = 123.0;", RecordField{ name: String::from("Hello"), doc: None, default: Some(Value::Number(Number::from_f64(123.0).unwrap())), schema: Schema::Double, order: apache_avro::schema::RecordFieldOrder::Ascending, aliases: None, position: 0, custom_attributes: BTreeMap::new() })]
+    #[case("intValue Hello;", RecordField{ name: String::from("Hello"), doc: None, default: None, schema: Schema::Ref { name: Name::new("intValue").unwrap() }, order: apache_avro::schema::RecordFieldOrder::Ascending, aliases: None, position: 0, custom_attributes: BTreeMap::new() })]
+    #[case("nullable Hello;", RecordField{ name: String::from("Hello"), doc: None, default: None, schema: Schema::Ref { name: Name::new("nullable").unwrap() }, order: apache_avro::schema::RecordFieldOrder::Ascending, aliases: None, position: 0, custom_attributes: BTreeMap::new() })]
+    #[case("array<long> /* ids */ Hello = [1];", RecordField{ name: String::from("Hello"), doc: None, default: Some(Value::Array(vec![Value::Number(1.into())])), schema: Schema::Array(Box::new(Schema::Long)), order: apache_avro::schema::RecordFieldOrder::Ascending, aliases: None, position: 0, custom_attributes: BTreeMap::new() })]
+    #[case("map<int> Hello /* empty */ = {};", RecordField{ name: String::from("Hello"), doc: None, default: Some(Value::Object(Default::default())), schema: Schema::Map(Box::new(Schema::Int)), order: apache_avro::schema::RecordFieldOrder::Ascending, aliases: None, position: 0, custom_attributes: BTreeMap::new() })]
+    #[case(r#"union { map<int>, null } Hello = {"a": 1};"#, RecordField{ name: String::from("Hello"), doc: None, default: Some(json!({"a": 1})), schema: Schema::Union(UnionSchema::new(vec![Schema::Map(Box::new(Schema::Int)), Schema::Null]).unwrap()), order: apache_avro::schema::RecordFieldOrder::Ascending, aliases: None, position: 0, custom_attributes: BTreeMap::new() })]
     fn test_parse_field(#[case] input: &str, #[case] expected: RecordField) {
         let res = parse_record_field(input);
         assert_eq!(res, Ok(("", expected)))
     }
 
     #[rstest]
-    #[case(r#"import idl "foo.avdl";"#, (Import::Idl, String::from("foo.avdl")))]
-    #[case(r#"import protocol "foo.avpr";"#, (Import::Protocol, String::from("foo.avpr")))]
-    #[case(r#"import schema "foo.avsc";"#, (Import::Schema, String::from("foo.avsc")))]
-    fn test_parse_import(#[case] input: &str, #[case] expected: (Import, String)) {
+    #[case(r#"import idl "foo.avdl";"#, (ImportKind::Idl, String::from("foo.avdl")))]
+    #[case(r#"import protocol "foo.avpr";"#, (ImportKind::Protocol, String::from("foo.avpr")))]
+    #[case(r#"import schema "foo.avsc";"#, (ImportKind::Schema, String::from("foo.avsc")))]
+    fn test_parse_import(#[case] input: &str, #[case] expected: (ImportKind, String)) {
         let res = parse_import(input);
         assert_eq!(res, Ok(("", expected)))
     }
@@ -1816,7 +3754,7 @@ mod test {
     )]
     fn test_parse_protocol(#[case] input: &str) {
         let mut names_ref = HashMap::new();
-        let r = parse_protocol(input, &mut names_ref).unwrap();
+        let r = parse_protocol(input, &mut names_ref, &mut Warnings::new()).unwrap();
         println!("{r:#?}");
     }
 
@@ -1831,7 +3769,7 @@ mod test {
     )]
     fn test_parse_protocol_duplicate_error(#[case] input: &str) {
         let mut names_ref = HashMap::new();
-        let r = parse_protocol(input, &mut names_ref);
+        let r = parse_protocol(input, &mut names_ref, &mut Warnings::new());
         // TODO: How to get proper error message?
         assert!(r.is_err());
     }
@@ -1975,4 +3913,756 @@ mod test {
         };
         assert_eq!(schema, expected);
     }
+
+    #[test]
+    fn test_parse_lenient_reports_every_field_error() {
+        let input = r#"protocol P {
+    record R {
+        string name
+        int age;
+        int count = "many";
+        boolean active;
+        long = 3;
+        string email;
+    }
+}"#;
+        let (schemas, _warnings, errors) = parse_lenient(input, &ParserOptions::default());
+        let lines: Vec<usize> = errors
+            .iter()
+            .map(|err| match err {
+                AvdlError::Parse { location, .. } => location.line,
+                err => panic!("unexpected error {err:?}"),
+            })
+            .collect();
+        assert_eq!(lines, vec![4, 5, 7]);
+        match &schemas[..] {
+            [Schema::Record { fields, .. }] => {
+                let names: Vec<&str> = fields.iter().map(|f| f.name.as_str()).collect();
+                assert_eq!(names, vec!["active", "email"]);
+            }
+            schemas => panic!("unexpected schemas {schemas:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_lenient_valid_input() {
+        let input = "protocol P {
+    record R {
+        string name;
+    }
+}";
+        let (schemas, _warnings, errors) = parse_lenient(input, &ParserOptions::default());
+        assert!(errors.is_empty());
+        assert_eq!(schemas.len(), 1);
+    }
+
+    #[test]
+    fn test_check_protocol_reports_every_error() {
+        let input = r#"protocol P {
+    record R {
+        Missing missing;
+        int count = "many";
+        string name
+        int age;
+    }
+}"#;
+        let (_warnings, errors) = check_protocol(input, &ParserOptions::default());
+        let found: Vec<(String, Option<usize>)> = errors
+            .iter()
+            .map(|err| {
+                let line = err.span.map(|span| span.location(input).line);
+                (err.error.to_string(), line)
+            })
+            .collect();
+        let expected = [
+            ("error at 4:21: invalid default value", None),
+            ("error at 6:9: expected ';' after field declaration", None),
+            (
+                "unresolved type Missing used by field missing of record R",
+                Some(3),
+            ),
+        ];
+        assert_eq!(
+            found,
+            expected.map(|(message, line)| (message.to_string(), line))
+        );
+    }
+
+    #[test]
+    fn test_docs_inside_protocol_are_kept() {
+        let input = "protocol P {
+    /** the record */
+    record R {
+        /** first */
+        string a;
+        /* not a doc */
+        /** second */
+        int b;
+    }
+}";
+        let (_tail, schemas) = parse(input).unwrap();
+        match &schemas[..] {
+            [Schema::Record { doc, fields, .. }] => {
+                assert_eq!(doc.as_deref(), Some("the record"));
+                let docs: Vec<Option<&str>> = fields.iter().map(|f| f.doc.as_deref()).collect();
+                assert_eq!(docs, vec![Some("first"), Some("second")]);
+            }
+            schemas => panic!("unexpected schemas {schemas:?}"),
+        }
+    }
+
+    #[test]
+    fn test_enum_default_followed_by_declaration_on_same_line() {
+        let input = "protocol P {
+    enum Kind { A, B } = A; record R { Kind kind; }
+}";
+        let (_tail, schemas) = parse(input).unwrap();
+        match &schemas[..] {
+            [Schema::Enum { symbols, .. }, Schema::Record { fields, .. }] => {
+                assert_eq!(symbols, &vec![String::from("A"), String::from("B")]);
+                assert_eq!(fields[0].name, "kind");
+            }
+            schemas => panic!("unexpected schemas {schemas:?}"),
+        }
+    }
+
+    #[rstest]
+    #[case("= A")]
+    #[case("= A record")]
+    fn test_parse_enum_default_without_semicolon(#[case] input: &str) {
+        assert!(matches!(
+            parse_enum_default(input),
+            Err(nom::Err::Failure(ParserError {
+                context: Some("expected ';' after enum default"),
+                ..
+            }))
+        ));
+    }
+
+    #[rstest]
+    #[case("void ping() oneway;", "ping", 0, Schema::Null, true)]
+    #[case("void ping();", "ping", 0, Schema::Null, false)]
+    #[case("string hello(string greeting);", "hello", 1, Schema::String, false)]
+    #[case(
+        "/** Add */ int add(int a, int b) throws Overflow;",
+        "add",
+        2,
+        Schema::Int,
+        false
+    )]
+    #[case(
+        r#"void log(string message = "hello", int level = 1);"#,
+        "log",
+        2,
+        Schema::Null,
+        false
+    )]
+    fn test_parse_message(
+        #[case] input: &str,
+        #[case] name: &str,
+        #[case] parameters: usize,
+        #[case] response: Schema,
+        #[case] one_way: bool,
+    ) {
+        let (tail, message) = parse_message(input).unwrap();
+        assert_eq!(tail, "");
+        assert_eq!(message.name, name);
+        assert_eq!(message.request.len(), parameters);
+        assert_eq!(message.response, response);
+        assert_eq!(message.one_way, one_way);
+    }
+
+    #[test]
+    fn test_parse_message_parameters() {
+        let input = r#"protocol P {
+    enum Level { INFO, WARN }
+    /** Logs a line */
+    void log(/** What to log */ string message = "hello", Level level = INFO, int times);
+}"#;
+        let protocol = parse_str(input).unwrap();
+        let log = &protocol.messages[0];
+        assert_eq!(log.doc.as_deref(), Some("Logs a line"));
+        let parameters: Vec<(&str, Option<&str>, Option<Value>, usize)> = log
+            .request
+            .iter()
+            .map(|parameter| {
+                (
+                    parameter.name.as_str(),
+                    parameter.doc.as_deref(),
+                    parameter.default.clone(),
+                    parameter.position,
+                )
+            })
+            .collect();
+        assert_eq!(
+            parameters,
+            [
+                (
+                    "message",
+                    Some("What to log"),
+                    Some(Value::from("hello")),
+                    0
+                ),
+                ("level", None, Some(Value::from("INFO")), 1),
+                ("times", None, None, 2),
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case(
+        "protocol P { void log(int level = \"high\"); }",
+        "invalid default value"
+    )]
+    #[case(
+        "protocol P { enum Level { INFO } void log(Level level = DEBUG); }",
+        "invalid default for parameter level of message log at /: expected Level"
+    )]
+    fn test_parse_message_parameters_fail(#[case] input: &str, #[case] message: &str) {
+        let err = parse_str(input).unwrap_err();
+        assert!(err.to_string().contains(message), "{err}");
+    }
+
+    #[test]
+    fn test_parse_protocol_with_messages() {
+        let input = "protocol P {
+    record Oops { string reason; }
+    record Greeting { string message; }
+    Greeting hello(Greeting greeting) throws Oops;
+    void ping() oneway;
+}";
+        let mut names_ref = HashMap::new();
+        let (_tail, mut protocol) =
+            parse_protocol(input, &mut names_ref, &mut Warnings::new()).unwrap();
+        resolve(&mut protocol, &names_ref);
+        assert_eq!(protocol.types.len(), 2);
+        let [hello, ping] = &protocol.messages[..] else {
+            panic!("unexpected messages {:?}", protocol.messages);
+        };
+        assert_eq!(hello.request[0].name, "greeting");
+        assert_eq!(hello.response, protocol.types[1]);
+        assert_eq!(hello.errors, vec![protocol.types[0].clone()]);
+        assert_eq!(ping.response, Schema::Null);
+        assert!(ping.one_way);
+    }
+
+    #[test]
+    fn test_repeated_aliases_are_merged() {
+        let input = r#"protocol P {
+    @aliases(["org.old.Employee", "Worker"])
+    @aliases(["Worker", "Staff"])
+    record Employee {
+        string @aliases(["fullName"]) @aliases(["full_name"]) name;
+    }
+}"#;
+        let (_tail, (schemas, warnings)) = parse_with_warnings(input).unwrap();
+        let Schema::Record {
+            aliases, fields, ..
+        } = &schemas[0]
+        else {
+            panic!("expected a record");
+        };
+        let aliases: Vec<String> = aliases
+            .iter()
+            .flatten()
+            .map(|alias| alias.fullname(None))
+            .collect();
+        assert_eq!(aliases, vec!["org.old.Employee", "Worker", "Staff"]);
+        assert_eq!(
+            fields[0].aliases,
+            Some(vec!["fullName".to_string(), "full_name".to_string()])
+        );
+        let repeated: Vec<&str> = warnings
+            .iter()
+            .map(|warning| {
+                assert_eq!(warning.code, WarningCode::RepeatedAnnotation);
+                let span = warning.span.unwrap();
+                &input[span.start..span.end]
+            })
+            .collect();
+        assert_eq!(
+            repeated,
+            vec![
+                r#"@aliases(["Worker", "Staff"])"#,
+                r#"@aliases(["full_name"])"#
+            ]
+        );
+    }
+
+    #[test]
+    fn test_repeated_namespace_with_same_value() {
+        let input = r#"protocol P {
+    @namespace("org.example") @namespace("org.example")
+    record Employee {
+        string name;
+    }
+}"#;
+        let (_tail, (schemas, warnings)) = parse_with_warnings(input).unwrap();
+        assert_eq!(
+            schemas[0].name().unwrap().fullname(None),
+            "org.example.Employee"
+        );
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(
+            warnings[0].to_string(),
+            "warning[repeated-annotation]: repeated `@namespace` annotation with the same value is ignored"
+        );
+    }
+
+    #[test]
+    fn test_parse_file_trailing_garbage() {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../tests/samples/TrailingGarbage.avdl"
+        );
+        let err = parse_file(path).unwrap_err();
+        assert!(
+            err.to_string()
+                .ends_with("TrailingGarbage.avdl:8:1: unexpected trailing content"),
+            "{err}"
+        );
+    }
+
+    #[rstest]
+    #[case("protocol P { record R { string name; } }")]
+    #[case("protocol P { record R { string name; } }\n// the end\n")]
+    #[case("protocol P { record R { string name; } } /* the end */ \n\n")]
+    fn test_parse_str(#[case] input: &str) {
+        let protocol = parse_str(input).unwrap();
+        assert_eq!(protocol.types.len(), 1);
+        assert_eq!(parse_reader(input.as_bytes()).unwrap(), protocol);
+    }
+
+    const UUID: &str = "a1a2a3a4-b1b2-c1c2-d1d2-d3d4d5d6d7d8";
+
+    // Uuid defaults are written hyphenated, wherever they are nested
+    #[rstest]
+    #[case(r#"uuid id = "A1A2A3A4-B1B2-C1C2-D1D2-D3D4D5D6D7D8";"#, json!(UUID))]
+    #[case(
+        r#"array<uuid> ids = ["a1a2a3a4b1b2c1c2d1d2d3d4d5d6d7d8", "{a1a2a3a4-b1b2-c1c2-d1d2-d3d4d5d6d7d8}", "urn:uuid:a1a2a3a4-b1b2-c1c2-d1d2-d3d4d5d6d7d8"];"#,
+        json!([UUID, UUID, UUID])
+    )]
+    #[case(r#"union { uuid, null } id = "A1A2A3A4B1B2C1C2D1D2D3D4D5D6D7D8";"#, json!(UUID))]
+    #[case(r#"map<uuid> ids = {"a": "A1A2A3A4B1B2C1C2D1D2D3D4D5D6D7D8"};"#, json!({"a": UUID}))]
+    #[case(r#"@logicalType("uuid") string id = "a1a2a3a4b1b2c1c2d1d2d3d4d5d6d7d8";"#, json!(UUID))]
+    #[case(r#"array<uuid> ids = [];"#, json!([]))]
+    fn test_uuid_default(#[case] field: &str, #[case] expected: Value) {
+        let protocol = parse_str(&format!("protocol P {{ record R {{ {field} }} }}")).unwrap();
+        let Schema::Record { fields, .. } = &protocol.types[0] else {
+            panic!("expected a record");
+        };
+        assert_eq!(fields[0].default, Some(expected));
+    }
+
+    #[rstest]
+    #[case("array< union { null, string } > tags = [null];", "array<union{null,string}>", json!([null]))]
+    #[case(r#"map<union { int, string }> scores = {"a": 1};"#, "map<union{int,string}>", json!({"a": 1}))]
+    #[case("array<array<int> > matrix = [[1, 2]];", "array<array<int>>", json!([[1, 2]]))]
+    #[case(
+        r#"map< array< /* scores */ union { null, long } // maybe
+        > > nested = {"a": [null]};"#,
+        "map<array<union{null,long}>>",
+        json!({"a": [null]})
+    )]
+    #[case(
+        "union { null, array<union { int, string }> } nested = null;",
+        "union{null,array<union{int,string}>}",
+        json!(null)
+    )]
+    #[case(
+        "union { map<union { null, int }>, array<union { null, int }> } nested = {};",
+        "union{map<union{null,int}>,array<union{null,int}>}",
+        json!({})
+    )]
+    fn test_unions_in_generics(#[case] field: &str, #[case] schema: &str, #[case] default: Value) {
+        let protocol = parse_str(&format!("protocol P {{ record R {{ {field} }} }}")).unwrap();
+        let Schema::Record { fields, .. } = &protocol.types[0] else {
+            panic!("expected a record");
+        };
+        assert_eq!(fields[0].schema, parse_type_str(schema).unwrap());
+        assert_eq!(fields[0].default, Some(default));
+    }
+
+    #[rstest]
+    #[case(
+        r#"array<@logicalType("timestamp-micros") long> timestamps = [1];"#,
+        Schema::Array(Box::new(Schema::TimestampMicros)),
+        Some(json!([1])),
+        &[]
+    )]
+    #[case(
+        r#"map<@java-class("java.math.BigDecimal") bytes> amounts;"#,
+        Schema::Map(Box::new(Schema::Bytes)),
+        None,
+        &[r#"@java-class("java.math.BigDecimal")"#]
+    )]
+    #[case(
+        r#"map< array< /* day */ @logicalType("date") int > > days;"#,
+        Schema::Map(Box::new(Schema::Array(Box::new(Schema::Date)))),
+        None,
+        &[]
+    )]
+    #[case(
+        r#"union { null, @x(1) array<@y(2) @logicalType("date") int> } days = null;"#,
+        Schema::Union(UnionSchema::new(vec![Schema::Null, Schema::Array(Box::new(Schema::Date))]).unwrap()),
+        Some(json!(null)),
+        &["@x(1)", "@y(2)"]
+    )]
+    // Other annotations are left out of the schema, with a warning at each
+    fn test_annotations_in_generics(
+        #[case] field: &str,
+        #[case] schema: Schema,
+        #[case] default: Option<Value>,
+        #[case] ignored: &[&str],
+    ) {
+        let input = format!("protocol P {{ record R {{ {field} }} }}");
+        let (protocol, warnings) =
+            parse_protocol_with_options(&input, &ParserOptions::default()).unwrap();
+        let Schema::Record { fields, .. } = &protocol.types[0] else {
+            panic!("expected a record");
+        };
+        assert_eq!(fields[0].schema, schema);
+        assert_eq!(fields[0].default, default);
+        assert!(warnings
+            .iter()
+            .all(|warning| warning.code == WarningCode::AnnotationIgnored));
+        let warned: Vec<&str> = warnings
+            .iter()
+            .filter_map(|warning| warning.span)
+            .map(|span| &input[span.start..span.end])
+            .collect();
+        assert_eq!(warned, ignored);
+    }
+
+    #[test]
+    fn test_annotations_in_messages_are_ignored() {
+        let input = r#"protocol P {
+    @x(1) long served(@y(2) long at, array<@z(3) string> tags);
+}"#;
+        let (_protocol, warnings) =
+            parse_protocol_with_options(input, &ParserOptions::default()).unwrap();
+        let warned: Vec<&str> = warnings
+            .iter()
+            .filter_map(|warning| warning.span)
+            .map(|span| &input[span.start..span.end])
+            .collect();
+        assert_eq!(warned, ["@x(1)", "@y(2)", "@z(3)"]);
+        assert_eq!(
+            warnings[0].to_string(),
+            "warning[annotation-ignored]: `@x` annotation is ignored, the type it is on keeps no properties"
+        );
+    }
+
+    #[test]
+    fn test_union_of_named_types() {
+        let input = r#"protocol P {
+    record Cat { string name; }
+    record Dog { string name; }
+    enum Size { SMALL, LARGE }
+    record Owner { union { null, Cat, Dog, Size, string } pet = null; }
+}"#;
+        let protocol = parse_str(input).unwrap();
+        let Schema::Record { fields, .. } = &protocol.types[3] else {
+            panic!("expected a record");
+        };
+        let Schema::Union(union) = &fields[0].schema else {
+            panic!("expected a union");
+        };
+        let names: Vec<String> = union.variants().iter().map(type_name).collect();
+        assert_eq!(names, ["null", "Cat", "Dog", "Size", "string"]);
+    }
+
+    #[test]
+    fn test_named_unions_in_generics() {
+        let input = r#"protocol P {
+    record Address { string street; }
+    record R { array<map<union { null, Address }>> addresses = [{"home": null}]; }
+}"#;
+        let protocol = parse_str(input).unwrap();
+        let Schema::Record { fields, .. } = &protocol.types[1] else {
+            panic!("expected a record");
+        };
+        let Schema::Array(items) = &fields[0].schema else {
+            panic!("expected an array");
+        };
+        let Schema::Map(values) = items.as_ref() else {
+            panic!("expected a map");
+        };
+        let Schema::Union(union) = values.as_ref() else {
+            panic!("expected a union");
+        };
+        assert_eq!(union.variants()[0], Schema::Null);
+        let name = union.variants()[1].name().map(|name| name.name.as_str());
+        assert_eq!(name, Some("Address"));
+        assert_eq!(fields[0].default, Some(json!([{"home": null}])));
+    }
+
+    #[rstest]
+    #[case("long", Schema::Long)]
+    #[case("  map<long>\n", Schema::Map(Box::new(Schema::Long)))]
+    #[case(
+        "/* ids */ array<int> // of users",
+        Schema::Array(Box::new(Schema::Int))
+    )]
+    fn test_parse_type_str(#[case] input: &str, #[case] expected: Schema) {
+        assert_eq!(parse_type_str(input).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case("", "unexpected end of input")]
+    #[case("int;", "unexpected trailing content")]
+    #[case("string name = \"jon\"", "unexpected trailing content")]
+    #[case("array<int", "unexpected end of input")]
+    #[case("union { null, union { int } }", "unexpected 'union'")]
+    fn test_parse_type_str_fail(#[case] input: &str, #[case] message: &str) {
+        let err = parse_type_str(input).unwrap_err();
+        assert!(err.to_string().contains(message), "{err}");
+    }
+
+    #[test]
+    fn test_parse_idl_file_schema_file() {
+        let input = r#"// Without a protocol
+namespace org.example;
+
+/** A person */
+record Person {
+    string name;
+    Address home;
+}
+
+@aliases(["Place"])
+record Address {
+    string city = "Bern";
+}
+"#;
+        let IdlFile::Schema(file) = parse_idl_file(input).unwrap() else {
+            panic!("expected a schema file");
+        };
+        assert_eq!(file.namespace.as_deref(), Some("org.example"));
+        assert_eq!(file.schema, None);
+        let names: Vec<String> = file
+            .types
+            .iter()
+            .map(|schema| schema.name().unwrap().fullname(None))
+            .collect();
+        assert_eq!(names, ["org.example.Person", "org.example.Address"]);
+        let Schema::Record { fields, .. } = &file.types[0] else {
+            panic!("expected a record");
+        };
+        assert!(matches!(&fields[1].schema, Schema::Record { name, .. } if name.name == "Address"));
+    }
+
+    #[test]
+    fn test_parse_idl_file_main_schema() {
+        let input = "schema array<Message>;
+import idl \"other.avdl\";
+record Message { string title; }";
+        let IdlFile::Schema(file) = parse_idl_file(input).unwrap() else {
+            panic!("expected a schema file");
+        };
+        assert_eq!(file.namespace, None);
+        assert_eq!(file.imports.len(), 1);
+        assert!(matches!(file.schema, Some(Schema::Array(_))));
+    }
+
+    #[rstest]
+    #[case(
+        "schema Missing; record Message { string title; }",
+        "unresolved type Missing"
+    )]
+    #[case("record R { Missing other; }", "unresolved type Missing")]
+    #[case(
+        "record R { int id; } void ping();",
+        "messages are only valid inside a protocol"
+    )]
+    #[case("record R { int id; } }", "`}` closes nothing")]
+    #[case(
+        "record R { int id; } namespace org.example;",
+        "unexpected '.example;'"
+    )]
+    #[case("record R { int id; } record R { long id; }", "duplicate name `R`")]
+    fn test_parse_idl_file_fail(#[case] input: &str, #[case] message: &str) {
+        let err = parse_idl_file(input).unwrap_err();
+        assert!(err.to_string().contains(message), "{err}");
+    }
+
+    #[test]
+    fn test_protocol_annotations() {
+        let input = r#"/** Versioned */
+@namespace("org.example")
+@aliases(["org.example.Legacy"])
+@version("1.2")
+@owner({"team": "ops", "tags": ["pii", 1, true, null], "weight": -0.5})
+protocol P { record R { int id; } }"#;
+        let protocol = parse_str(input).unwrap();
+        assert_eq!(protocol.namespace.as_deref(), Some("org.example"));
+        assert_eq!(protocol.aliases, ["org.example.Legacy"]);
+        assert_eq!(
+            protocol.properties,
+            BTreeMap::from([
+                ("version".to_string(), json!("1.2")),
+                (
+                    "owner".to_string(),
+                    json!({"team": "ops", "tags": ["pii", 1, true, null], "weight": -0.5})
+                ),
+            ])
+        );
+        let avpr = protocol.to_avpr_json().unwrap();
+        assert_eq!(avpr["version"], json!("1.2"));
+        assert_eq!(avpr["aliases"], json!(["org.example.Legacy"]));
+        assert_eq!(parse_str(&protocol.to_idl().unwrap()).unwrap(), protocol);
+    }
+
+    #[rstest]
+    #[case(
+        r#"@version("1") @version("2") protocol P {}"#,
+        "error at 1:15: annotation repeated with a different value"
+    )]
+    #[case(r#"@version(1.2.3) protocol P {}"#, "unexpected '@version(1.2.3)")]
+    #[case(r#"@aliases(["not valid"]) protocol P {}"#, "unexpected '@aliases")]
+    fn test_protocol_annotations_fail(#[case] input: &str, #[case] message: &str) {
+        let err = parse_str(input).unwrap_err();
+        assert!(err.to_string().contains(message), "{err}");
+    }
+
+    #[rstest]
+    #[case("protocol P {}", true)]
+    #[case(
+        "/** Doc */ @namespace(\"org.example\") protocol P { record R { int id; } }",
+        true
+    )]
+    #[case("// a comment\nprotocol P {}", true)]
+    #[case("namespace org.example; record R { int id; }", false)]
+    #[case("record R { int id; }", false)]
+    fn test_declares_protocol(#[case] input: &str, #[case] expected: bool) {
+        assert_eq!(declares_protocol(input), expected);
+        let file = parse_idl_file(input).unwrap();
+        assert_eq!(matches!(file, IdlFile::Protocol(_)), expected);
+    }
+
+    const DOCUMENTED: &str = r#"/**
+ * The people
+ */
+protocol People {
+    /**
+     * A person,
+     * with a name
+     */
+    record Person {
+        /** person fullname */ string name;
+    }
+    /**   Suits */ enum Suit { SPADES, HEARTS }
+    /**
+       Hash
+     */
+    fixed MD5(16);
+    /** Greets */ string greet(Person person);
+}
+"#;
+
+    #[test]
+    fn test_docs_normalized() {
+        let (protocol, _warnings) =
+            parse_protocol_with_options(DOCUMENTED, &ParserOptions::default()).unwrap();
+        assert_eq!(protocol.doc.as_deref(), Some("The people"));
+        let docs: Vec<Option<&str>> = protocol
+            .types
+            .iter()
+            .map(|schema| match schema {
+                Schema::Record { doc, .. }
+                | Schema::Enum { doc, .. }
+                | Schema::Fixed { doc, .. } => doc.as_deref(),
+                schema => panic!("unexpected {schema:?}"),
+            })
+            .collect();
+        assert_eq!(
+            docs,
+            [Some("A person,\nwith a name"), Some("Suits"), Some("Hash")]
+        );
+        let Schema::Record { fields, .. } = &protocol.types[0] else {
+            panic!("expected a record");
+        };
+        assert_eq!(fields[0].doc.as_deref(), Some("person fullname"));
+        assert_eq!(protocol.messages[0].doc.as_deref(), Some("Greets"));
+    }
+
+    #[test]
+    fn test_docs_preserved() {
+        let options = ParserOptions {
+            preserve_docs: true,
+            ..Default::default()
+        };
+        let (protocol, _warnings) = parse_protocol_with_options(DOCUMENTED, &options).unwrap();
+        assert_eq!(protocol.doc.as_deref(), Some("\n * The people\n "));
+        let Schema::Record { doc, fields, .. } = &protocol.types[0] else {
+            panic!("expected a record");
+        };
+        assert_eq!(
+            doc.as_deref(),
+            Some("\n     * A person,\n     * with a name\n     ")
+        );
+        assert_eq!(fields[0].doc.as_deref(), Some(" person fullname "));
+        assert_eq!(protocol.messages[0].doc.as_deref(), Some(" Greets "));
+    }
+
+    #[test]
+    fn test_parse_file_missing() {
+        let err = parse_file("does/not/exist.avdl").unwrap_err();
+        assert!(matches!(err, AvdlError::Io { .. }), "{err:?}");
+    }
+
+    fn sample(path: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../../tests/samples")
+            .join(path)
+    }
+
+    #[test]
+    fn test_parse_file_reads_imports() {
+        // b.avdl and c.avdl both import d.avdl, which is read once
+        let protocol = parse_file(sample("graph/a.avdl")).unwrap();
+        let names: Vec<_> = protocol
+            .types
+            .iter()
+            .map(|schema| declared_name(schema).unwrap().name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            ["Color", "Hash", "Bottom", "Left", "Right", "Top", "Oops"]
+        );
+        assert!(protocol.imports.is_empty());
+        assert_eq!(protocol.messages.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_file_circular_imports() {
+        let protocol = parse_file(sample("graph/e.avdl")).unwrap();
+        assert_eq!(protocol.types.len(), 2);
+    }
+
+    #[test]
+    fn test_import_protocol_and_missing_import() {
+        let options = ParserOptions {
+            import_dir: Some(sample("")),
+            ..Default::default()
+        };
+        let input = r#"@namespace("com.acme")
+protocol P {
+    import protocol "HelloWorld.avpr";
+    record Hello {
+        Greeting greeting;
+    }
+}"#;
+        let (protocol, _) = parse_protocol_with_options(input, &options).unwrap();
+        assert_eq!(protocol.types.len(), 3);
+        assert_eq!(protocol.messages[0].name, "hello");
+
+        let input = r#"protocol P { import schema "Missing.avsc"; }"#;
+        let err = parse_protocol_with_options(input, &options).unwrap_err();
+        let AvdlError::ImportIdlError { path, source } = err else {
+            panic!("expected an import error, got {err:?}");
+        };
+        assert!(path.ends_with("Missing.avsc"));
+        assert!(matches!(*source, AvdlError::Io { .. }));
+    }
 }
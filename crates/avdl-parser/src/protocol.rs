@@ -1,26 +1,299 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
-use crate::schema::{Documentation, Namespace, Schema};
+use apache_avro::schema::{Name, Names, Namespace, RecordField, Schema};
+use serde_json::Value;
 
-enum Types {
-    Record(Schema),
-    Enum(Schema),
-    Fixed(Schema),
-    Error(Schema),
+use crate::avpr;
+use crate::error::AvdlError;
+use crate::idl;
+use crate::validate::declared_names;
+
+/// A message of a protocol, i.e. a remote procedure call.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub name: String,
+    pub doc: Option<String>,
+    /// Parameters, in declaration order, written like the fields of a
+    /// record. Their `position` is their index.
+    pub request: Vec<RecordField>,
+    pub response: Schema,
+    pub errors: Vec<Schema>,
+    pub one_way: bool,
+}
+
+/// Kind of file an `import` statement reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportKind {
+    Idl,
+    Protocol,
+    Schema,
+}
+
+/// An `import` statement, with the path as written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Import {
+    pub kind: ImportKind,
+    pub path: String,
+}
+
+/// A parsed `protocol` declaration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Protocol {
+    pub name: String,
+    pub namespace: Namespace,
+    pub doc: Option<String>,
+    /// Set by the `@aliases` annotation of the protocol.
+    pub aliases: Vec<String>,
+    /// Imports, in declaration order. They are only read when
+    /// [`ParserOptions::import_dir`](crate::ParserOptions::import_dir) is
+    /// set, leaving none here.
+    pub imports: Vec<Import>,
+    /// Named types, in declaration order, after the ones of the imports
+    /// read.
+    pub types: Vec<Schema>,
+    pub messages: Vec<Message>,
+    /// Properties of the protocol not defined by Avro.
+    pub properties: BTreeMap<String, Value>,
+}
+
+/// A file declaring types without a protocol around them, as Avro 1.12
+/// allows.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaFile {
+    /// Set by the `namespace` statement.
+    pub namespace: Namespace,
+    /// Imports, in declaration order. They are not resolved.
+    pub imports: Vec<Import>,
+    /// Named types, in declaration order.
+    pub types: Vec<Schema>,
+    /// The main type of the file, given by the `schema` statement.
+    pub schema: Option<Schema>,
 }
 
-struct Message {
-    name: String,
-    doc: Documentation,
-    request: Vec<HashMap<String, String>>,
-    response: String,
-    errors: Vec<String>,
+/// What an IDL file declares, as returned by [`crate::parse_idl_file`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum IdlFile {
+    Protocol(Protocol),
+    Schema(SchemaFile),
+}
+
+// Schemas are compared by their canonical form, which is an equivalence
+impl Eq for Message {}
+impl Eq for Protocol {}
+impl Eq for SchemaFile {}
+impl Eq for IdlFile {}
+
+impl Protocol {
+    /// Full name of the protocol, e.g. `org.example.Shop`.
+    pub fn fullname(&self) -> String {
+        Name {
+            name: self.name.clone(),
+            namespace: self.namespace.clone(),
+        }
+        .fullname(None)
+    }
+
+    /// Read a protocol from an `.avpr` document.
+    pub fn from_avpr_str(input: &str) -> Result<Protocol, AvdlError> {
+        avpr::from_avpr_str(input)
+    }
+
+    /// Write the protocol as an `.avpr` JSON value.
+    pub fn to_avpr_json(&self) -> Result<Value, AvdlError> {
+        avpr::to_avpr_json(self)
+    }
+
+    /// Write the protocol as Avro IDL.
+    pub fn to_idl(&self) -> Result<String, AvdlError> {
+        idl::to_idl(self)
+    }
+
+    /// Every named type of the protocol, including the ones declared inside
+    /// others, by full name. Fails when two types with the same full name are
+    /// defined differently.
+    pub fn names(&self) -> Result<Names, AvdlError> {
+        let (names, duplicates) = declared_names(&self.types, &self.namespace);
+        match duplicates.into_iter().next() {
+            Some(err) => Err(err),
+            None => Ok(names),
+        }
+    }
+
+    /// The named types, as returned by [`crate::parse`].
+    pub fn into_schemas(self) -> Vec<Schema> {
+        self.types
+    }
 }
 
-struct Protocol {
-    name: String,
-    namespace: Namespace,
-    doc: Documentation,
-    types: Vec<Types>,
-    messages: Vec<HashMap<String, Message>>
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::parser::parse_protocol;
+    use crate::warning::Warnings;
+    use crate::ParserOptions;
+    use apache_avro::schema::{RecordField, RecordFieldOrder};
+    use rstest::rstest;
+
+    fn field(name: &str, schema: Schema, default: Option<Value>, position: usize) -> RecordField {
+        RecordField {
+            name: name.to_string(),
+            doc: None,
+            default,
+            schema,
+            order: RecordFieldOrder::Ascending,
+            aliases: None,
+            position,
+            custom_attributes: BTreeMap::new(),
+        }
+    }
+
+    fn record(name: &str, fields: Vec<RecordField>) -> Schema {
+        Schema::Record {
+            name: Name::new(name).unwrap(),
+            aliases: None,
+            doc: None,
+            lookup: fields
+                .iter()
+                .map(|field| (field.name.clone(), field.position))
+                .collect(),
+            fields,
+            attributes: BTreeMap::new(),
+        }
+    }
+
+    #[rstest]
+    #[case(
+        include_str!("../../../tests/samples/MinimalProtocol.avdl"),
+        Protocol {
+            name: "MinimalProtocol".to_string(),
+            namespace: None,
+            doc: None,
+            aliases: vec![],
+            imports: vec![],
+            types: vec![],
+            messages: vec![],
+            properties: BTreeMap::new(),
+        }
+    )]
+    #[case(
+        include_str!("../../../tests/samples/simple.avdl"),
+        Protocol {
+            name: "MyProtocol".to_string(),
+            namespace: None,
+            doc: None,
+            aliases: vec![],
+            imports: vec![],
+            types: vec![record("Hello", vec![field("name", Schema::String, None, 0)])],
+            messages: vec![],
+            properties: BTreeMap::new(),
+        }
+    )]
+    #[case(
+        include_str!("../../../tests/samples/HelloArray.avdl"),
+        Protocol {
+            name: "MyProtocol".to_string(),
+            namespace: None,
+            doc: None,
+            aliases: vec![],
+            imports: vec![],
+            types: vec![record(
+                "HelloArray",
+                vec![
+                    field("name", Schema::String, Some(Value::String("".to_string())), 0),
+                    field(
+                        "stock",
+                        Schema::Array(Box::new(Schema::String)),
+                        Some(Value::Array(vec![Value::String("".to_string())])),
+                        1,
+                    ),
+                ],
+            )],
+            messages: vec![],
+            properties: BTreeMap::new(),
+        }
+    )]
+    fn test_parse_protocol_fixtures(#[case] input: &str, #[case] expected: Protocol) {
+        let (_tail, protocol) =
+            parse_protocol(input, &mut HashMap::new(), &mut Warnings::new()).unwrap();
+        assert_eq!(protocol, expected);
+    }
+
+    #[test]
+    fn test_parse_protocol_keeps_name_namespace_and_doc() {
+        let input = include_str!("../../../tests/samples/Docs.avdl");
+        let (protocol, _warnings) =
+            crate::parse_protocol_with_options(input, &ParserOptions::default()).unwrap();
+        assert_eq!(protocol.name, "MyProtocol");
+        assert_eq!(protocol.namespace.as_deref(), Some("org.apache.avro.test"));
+        assert_eq!(protocol.fullname(), "org.apache.avro.test.MyProtocol");
+        assert!(protocol
+            .doc
+            .as_deref()
+            .unwrap()
+            .ends_with("An example protocol in Avro IDL"));
+        assert_eq!(protocol.types.len(), 1);
+        assert_eq!(protocol.into_schemas().len(), 1);
+    }
+
+    #[test]
+    fn test_names() {
+        // Nested types can only be declared in JSON
+        let input = r#"{
+  "protocol": "Shop",
+  "namespace": "org.example",
+  "types": [
+    {"type": "enum", "name": "Status", "symbols": ["ACTIVE", "CLOSED"]},
+    {"type": "record", "name": "Invoice", "namespace": "org.billing", "fields": [
+      {"name": "id", "type": "string"}
+    ]},
+    {"type": "record", "name": "Order", "fields": [
+      {"name": "status", "type": "Status"},
+      {"name": "line", "type": {"type": "record", "name": "Line", "fields": [
+        {"name": "sku", "type": "string"}
+      ]}},
+      {"name": "parcel", "type": {"type": "record", "name": "Parcel", "namespace": "org.shipping", "fields": [
+        {"name": "id", "type": "string"}
+      ]}}
+    ]}
+  ],
+  "messages": {}
+}"#;
+        let protocol = Protocol::from_avpr_str(input).unwrap();
+        let names = protocol.names().unwrap();
+        assert_eq!(names.len(), 5);
+        for (name, namespace) in [
+            ("Status", "org.example"),
+            ("Invoice", "org.billing"),
+            ("Order", "org.example"),
+            ("Line", "org.example"),
+            ("Parcel", "org.shipping"),
+        ] {
+            let fullname = Name::new(&format!("{namespace}.{name}")).unwrap();
+            let by_parts = Name {
+                name: name.to_string(),
+                namespace: Some(namespace.to_string()),
+            };
+            let found = &names[&fullname];
+            assert_eq!(found.name().unwrap().name, name);
+            assert_eq!(names.get(&by_parts), Some(found));
+        }
+    }
+
+    #[test]
+    fn test_names_fail_on_duplicates() {
+        let mut protocol = crate::parse_str("protocol P { enum Status { ACTIVE } }").unwrap();
+        let mut redefined = protocol.types[0].clone();
+        if let Schema::Enum { symbols, .. } = &mut redefined {
+            symbols.push("CLOSED".to_string());
+        }
+        protocol
+            .types
+            .push(record("Holder", vec![field("status", redefined, None, 0)]));
+        assert!(matches!(
+            protocol.names(),
+            Err(AvdlError::Duplicate(name)) if name == "Status"
+        ));
+    }
 }
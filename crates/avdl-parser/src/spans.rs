@@ -0,0 +1,126 @@
+//! Where the types and fields of a protocol are declared in its source.
+
+use std::collections::HashMap;
+
+use apache_avro::schema::{Name, Namespace};
+
+use crate::error::{AvdlError, Span};
+
+/// Spans of the declarations of a protocol, as returned by
+/// [`crate::parse_spanned`]. A declaration starts at its doc comment or
+/// annotations, and ends with its closing `}` or `;`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    namespace: Namespace,
+    types: HashMap<Name, Span>,
+    fields: HashMap<(Name, String), Span>,
+}
+
+impl SourceMap {
+    pub(crate) fn new(namespace: Namespace) -> SourceMap {
+        SourceMap {
+            namespace,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn insert_type(&mut self, name: &Name, span: Span) {
+        self.types.insert(self.fullname(name), span);
+    }
+
+    pub(crate) fn insert_field(&mut self, record: &Name, field: &str, span: Span) {
+        self.fields
+            .insert((self.fullname(record), field.to_string()), span);
+    }
+
+    // Names without a namespace belong to the one of the protocol
+    fn fullname(&self, name: &Name) -> Name {
+        name.fully_qualified_name(&self.namespace)
+    }
+
+    /// Span of the declaration of the type named `name`.
+    pub fn type_span(&self, name: &Name) -> Option<Span> {
+        self.types.get(&self.fullname(name)).copied()
+    }
+
+    /// Span of the declaration of `field` in the record named `record`.
+    pub fn field_span(&self, record: &Name, field: &str) -> Option<Span> {
+        self.fields
+            .get(&(self.fullname(record), field.to_string()))
+            .copied()
+    }
+
+    /// Span of the type or field an error found once the protocol is parsed
+    /// is about.
+    pub fn span_of(&self, error: &AvdlError) -> Option<Span> {
+        match error {
+            AvdlError::UnresolvedRef { record, field, .. }
+            | AvdlError::InvalidDefault { record, field, .. } => {
+                self.field_span(&Name::new(record).ok()?, field)
+            }
+            AvdlError::Duplicate(name) => self.type_span(&Name::new(name).ok()?),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error::AvdlError;
+    use crate::options::ParserOptions;
+    use crate::parser::parse_spanned;
+    use apache_avro::schema::Name;
+
+    const INPUT: &str = r#"@namespace("org.example")
+protocol Shop {
+    // Statuses
+    enum Status { ACTIVE, CLOSED }
+
+    record Order {
+        /** When the order was placed */
+        long placed_at; // in milliseconds
+        Status @order("descending") status;
+    }
+}"#;
+
+    #[test]
+    fn test_spans_of_types_and_fields() {
+        let (_protocol, _warnings, spans) =
+            parse_spanned(INPUT, &ParserOptions::default()).unwrap();
+        let status = spans.type_span(&Name::new("Status").unwrap()).unwrap();
+        assert_eq!(
+            &INPUT[status.start..status.end],
+            "enum Status { ACTIVE, CLOSED }"
+        );
+        let order = Name::new("org.example.Order").unwrap();
+        let placed_at = spans.field_span(&order, "placed_at").unwrap();
+        assert_eq!(placed_at.location(INPUT).line, 7);
+        assert_eq!(
+            &INPUT[placed_at.start..placed_at.end],
+            "/** When the order was placed */\n        long placed_at;"
+        );
+        let status = spans.field_span(&order, "status").unwrap();
+        assert_eq!(status.location(INPUT).line, 9);
+        assert_eq!(status.location(INPUT).column, 9);
+        assert_eq!(spans.field_span(&order, "missing"), None);
+    }
+
+    #[test]
+    fn test_validation_errors_are_spanned() {
+        let input = "protocol P {
+    enum Status { ACTIVE, CLOSED }
+    record User {
+        string name;
+        int age = 30;
+
+        Missing status;
+    }
+}";
+        let err = parse_spanned(input, &ParserOptions::default()).unwrap_err();
+        assert!(
+            matches!(*err.error, AvdlError::UnresolvedRef { .. }),
+            "{err:?}"
+        );
+        assert_eq!(err.span.unwrap().location(input).line, 7);
+    }
+}
@@ -0,0 +1,321 @@
+//! Numbers summarizing a set of protocols, to see where schemas need work.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+use apache_avro::schema::{Name, Namespace, RecordField, Schema};
+use serde::Serialize;
+
+use crate::protocol::Protocol;
+use crate::visit::{SchemaVisitor, Walk};
+
+/// How many times a named type is used by the fields of records.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Referenced {
+    /// Full name of the type.
+    pub name: String,
+    pub count: usize,
+}
+
+/// What [`stats`] found in a set of protocols.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Stats {
+    pub protocols: usize,
+    pub messages: usize,
+    /// Records and errors, including the ones declared inside others.
+    pub records: usize,
+    pub enums: usize,
+    pub fixed: usize,
+    pub fields: usize,
+    /// How many records have a given number of fields, by number of fields.
+    pub fields_per_record: BTreeMap<usize, usize>,
+    pub fields_without_doc: usize,
+    pub unions: usize,
+    /// Unions whose first branch is not `null`, which is then no valid
+    /// default.
+    pub unions_without_null_first: usize,
+    /// Records, arrays, maps and unions nested in each other at the deepest,
+    /// counting the named type they are in. References are not followed.
+    pub max_depth: usize,
+    /// Full name of the first type that nests that deep.
+    pub deepest_type: Option<String>,
+    /// The named types used by record fields, most used first.
+    pub most_referenced: Vec<Referenced>,
+}
+
+/// Count the types, fields and unions of `protocols`, and how they nest and
+/// reference each other.
+pub fn stats(protocols: &[Protocol]) -> Stats {
+    let mut counter = Counter::default();
+    for protocol in protocols {
+        counter.stats.protocols += 1;
+        counter.stats.messages += protocol.messages.len();
+        for schema in &protocol.types {
+            counter.namespace = protocol.namespace.clone();
+            counter.root = schema
+                .name()
+                .map(|name| fullname(name, &protocol.namespace));
+            schema.walk(&mut counter);
+        }
+    }
+    let mut stats = counter.stats;
+    let mut referenced: Vec<Referenced> = counter
+        .references
+        .into_iter()
+        .map(|(name, count)| Referenced { name, count })
+        .collect();
+    referenced.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+    stats.most_referenced = referenced;
+    stats
+}
+
+// Types listed by the text summary, the JSON one has them all
+const MOST_REFERENCED_SHOWN: usize = 10;
+
+/// A table of the numbers, meant to be read.
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows = [
+            ("protocols", self.protocols),
+            ("messages", self.messages),
+            ("records", self.records),
+            ("enums", self.enums),
+            ("fixed", self.fixed),
+            ("fields", self.fields),
+            ("fields without doc", self.fields_without_doc),
+            ("unions", self.unions),
+            ("unions without null first", self.unions_without_null_first),
+        ];
+        for (label, count) in rows {
+            writeln!(f, "{label:<28}{count}")?;
+        }
+        write!(f, "{:<28}{}", "deepest nesting", self.max_depth)?;
+        match &self.deepest_type {
+            Some(name) => writeln!(f, " ({name})")?,
+            None => writeln!(f)?,
+        }
+        if !self.fields_per_record.is_empty() {
+            writeln!(f, "\nrecords by number of fields")?;
+            for (fields, records) in &self.fields_per_record {
+                writeln!(f, "  {:<26}{records}", format!("{fields} fields"))?;
+            }
+        }
+        if !self.most_referenced.is_empty() {
+            writeln!(f, "\nmost referenced types")?;
+            let width = self
+                .most_referenced
+                .iter()
+                .take(MOST_REFERENCED_SHOWN)
+                .map(|referenced| referenced.name.len())
+                .max()
+                .unwrap_or_default()
+                .max(24);
+            for referenced in self.most_referenced.iter().take(MOST_REFERENCED_SHOWN) {
+                writeln!(f, "  {:<width$}  {}", referenced.name, referenced.count)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct Counter {
+    stats: Stats,
+    namespace: Namespace,
+    // The top level type being walked
+    root: Option<String>,
+    depth: usize,
+    references: HashMap<String, usize>,
+    declared: HashSet<String>,
+    // A named type used again after its declaration, whose insides are not
+    // counted again
+    repeated: Option<*const Schema>,
+}
+
+impl SchemaVisitor for Counter {
+    fn enter(&mut self, schema: &Schema) {
+        if self.repeated.is_some() {
+            return;
+        }
+        let name = match schema {
+            Schema::Record { name, .. }
+            | Schema::Enum { name, .. }
+            | Schema::Fixed { name, .. }
+            | Schema::Ref { name } => Some(fullname(name, &self.namespace)),
+            _ => None,
+        };
+        // Top level types are declared, not referenced. Only records nest others
+        if let (Some(name), true) = (&name, self.depth > 0) {
+            *self.references.entry(name.clone()).or_default() += 1;
+        }
+        // Parsed references may hold the whole type they reference
+        if let Some(name) = name {
+            if matches!(schema, Schema::Ref { .. }) || !self.declared.insert(name) {
+                self.repeated = Some(schema);
+                return;
+            }
+        }
+        let stats = &mut self.stats;
+        match schema {
+            Schema::Record { fields, .. } => {
+                stats.records += 1;
+                *stats.fields_per_record.entry(fields.len()).or_default() += 1;
+            }
+            Schema::Enum { .. } => stats.enums += 1,
+            Schema::Fixed { .. } => stats.fixed += 1,
+            Schema::Union(union) => {
+                stats.unions += 1;
+                if !matches!(union.variants().first(), Some(Schema::Null)) {
+                    stats.unions_without_null_first += 1;
+                }
+            }
+            _ => (),
+        }
+        if nests(schema) {
+            self.depth += 1;
+            if self.depth > self.stats.max_depth {
+                self.stats.max_depth = self.depth;
+                self.stats.deepest_type = self.root.clone();
+            }
+        }
+    }
+
+    fn leave(&mut self, schema: &Schema) {
+        match self.repeated {
+            Some(repeated) if std::ptr::eq(repeated, schema) => self.repeated = None,
+            Some(_) => (),
+            None if nests(schema) => self.depth -= 1,
+            None => (),
+        }
+    }
+
+    fn field(&mut self, field: &RecordField) {
+        self.stats.fields += 1;
+        if field.doc.is_none() {
+            self.stats.fields_without_doc += 1;
+        }
+    }
+}
+
+fn nests(schema: &Schema) -> bool {
+    matches!(
+        schema,
+        Schema::Record { .. } | Schema::Array(_) | Schema::Map(_) | Schema::Union(_)
+    )
+}
+
+fn fullname(name: &Name, namespace: &Namespace) -> String {
+    name.fully_qualified_name(namespace).fullname(None)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::options::ParserOptions;
+    use crate::parser::parse_protocol_with_options;
+
+    fn protocols() -> Vec<Protocol> {
+        [
+            r#"@namespace("org.example")
+protocol Shop {
+    enum Kind { BOOK, FOOD }
+    fixed Hash(16);
+
+    /** Something we sell */
+    record Item {
+        /** Unique */
+        string id;
+        Kind kind;
+        union { Hash, null } hash;
+    }
+
+    record Order {
+        array<Item> items;
+        map<array<union { null, Item }>> by_shop;
+        Kind kind;
+    }
+
+    Order find(string id);
+}"#,
+            r#"@namespace("org.other")
+protocol Empty {
+    record Nothing {}
+}"#,
+        ]
+        .into_iter()
+        .map(|input| {
+            let (protocol, _warnings) =
+                parse_protocol_with_options(input, &ParserOptions::default()).unwrap();
+            protocol
+        })
+        .collect()
+    }
+
+    #[test]
+    fn test_stats() {
+        let stats = stats(&protocols());
+        assert_eq!(
+            stats,
+            Stats {
+                protocols: 2,
+                messages: 1,
+                records: 3,
+                enums: 1,
+                fixed: 1,
+                fields: 6,
+                fields_per_record: BTreeMap::from([(0, 1), (3, 2)]),
+                fields_without_doc: 5,
+                unions: 2,
+                unions_without_null_first: 1,
+                max_depth: 4,
+                deepest_type: Some("org.example.Order".to_string()),
+                most_referenced: vec![
+                    Referenced {
+                        name: "org.example.Item".to_string(),
+                        count: 2
+                    },
+                    Referenced {
+                        name: "org.example.Kind".to_string(),
+                        count: 2
+                    },
+                    Referenced {
+                        name: "org.example.Hash".to_string(),
+                        count: 1
+                    },
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn test_stats_display() {
+        assert_eq!(
+            stats(&protocols()).to_string(),
+            "protocols                   2
+messages                    1
+records                     3
+enums                       1
+fixed                       1
+fields                      6
+fields without doc          5
+unions                      2
+unions without null first   1
+deepest nesting             4 (org.example.Order)
+
+records by number of fields
+  0 fields                  1
+  3 fields                  2
+
+most referenced types
+  org.example.Item          2
+  org.example.Kind          2
+  org.example.Hash          1
+"
+        );
+    }
+
+    #[test]
+    fn test_stats_empty() {
+        assert_eq!(stats(&[]), Stats::default());
+    }
+}
@@ -10,6 +10,8 @@
 //! - an escape followed by whitespace consumes all whitespace between the
 //!   escape and the next non-whitespace character
 
+use std::borrow::Cow;
+
 use nom::branch::alt;
 use nom::bytes::streaming::{is_not, take_while_m_n};
 use nom::character::streaming::{char, multispace1};
@@ -131,8 +133,9 @@ where
 }
 
 /// Parse a string. Use a loop of parse_fragment and push all of the fragments
-/// into an output string.
-pub fn parse_string<'a, E>(input: &'a str) -> IResult<&'a str, String, E>
+/// into an output string. A string without escapes borrows from `input`,
+/// only escapes make it owned.
+pub fn parse_string<'a, E>(input: &'a str) -> IResult<&'a str, Cow<'a, str>, E>
 where
   E: ParseError<&'a str> + FromExternalError<&'a str, std::num::ParseIntError>,
 {
@@ -142,14 +145,16 @@ where
     // 0..,
     // Our parser function– parses a single string fragment
     parse_fragment,
-    // Our init value, an empty string
-    String::new,
+    // Our init value, an empty string, which allocates nothing
+    || Cow::Borrowed(""),
     // Our folding function. For each fragment, append the fragment to the
-    // string.
-    |mut string, fragment| {
+    // string. The first literal is borrowed as is, anything appended to it
+    // turns the string into an owned one.
+    |mut string: Cow<'a, str>, fragment| {
       match fragment {
-        StringFragment::Literal(s) => string.push_str(s),
-        StringFragment::EscapedChar(c) => string.push(c),
+        StringFragment::Literal(s) if string.is_empty() => string = Cow::Borrowed(s),
+        StringFragment::Literal(s) => string.to_mut().push_str(s),
+        StringFragment::EscapedChar(c) => string.to_mut().push(c),
         StringFragment::EscapedWS => {}
       }
       string
@@ -162,3 +167,30 @@ where
   // loop won't accidentally match your closing delimiter!
   delimited(char('"'), build_string, char('"'))(input)
 }
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use nom::error::Error;
+
+  fn parse(input: &str) -> Cow<'_, str> {
+    let (tail, string) = parse_string::<Error<&str>>(input).unwrap();
+    assert_eq!(tail, "");
+    string
+  }
+
+  #[test]
+  fn test_parse_string_without_escapes_borrows() {
+    assert!(matches!(parse(r#""org.example""#), Cow::Borrowed("org.example")));
+    assert!(matches!(parse(r#""""#), Cow::Borrowed("")));
+  }
+
+  #[test]
+  fn test_parse_string_with_escapes_owns() {
+    assert!(matches!(parse(r#""tab\there""#), Cow::Owned(s) if s == "tab\there"));
+    assert!(matches!(parse(r#""\n""#), Cow::Owned(s) if s == "\n"));
+    assert!(matches!(parse(r#""a\u{41}""#), Cow::Owned(s) if s == "aA"));
+    // Escaped whitespace is dropped, joining the literals around it
+    assert!(matches!(parse("\"a\\   b\""), Cow::Owned(s) if s == "ab"));
+  }
+}
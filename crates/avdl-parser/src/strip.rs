@@ -0,0 +1,152 @@
+//! Removing metadata which does not change how data is encoded.
+
+use std::collections::BTreeMap;
+
+use apache_avro::schema::{Alias, RecordField, Schema, UnionSchema};
+use serde_json::Value;
+
+/// What [`strip`] removes. Nothing is removed by default.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StripOptions {
+    /// Docs of named types and fields.
+    pub docs: bool,
+    /// Aliases of named types and fields.
+    pub aliases: bool,
+    /// Properties of named types not defined by Avro, e.g. `connect.name`.
+    pub attributes: bool,
+    /// Properties of record fields not defined by Avro, e.g. `x-pii`.
+    pub custom_attributes: bool,
+}
+
+/// Remove the metadata selected in `options` from `schema` and every type
+/// defined inside it.
+pub fn strip(schema: &mut Schema, options: &StripOptions) {
+    match schema {
+        Schema::Record {
+            doc,
+            aliases,
+            attributes,
+            fields,
+            ..
+        } => {
+            strip_named(doc, aliases, attributes, options);
+            for field in fields.iter_mut() {
+                strip_field(field, options);
+            }
+        }
+        Schema::Enum {
+            doc,
+            aliases,
+            attributes,
+            ..
+        }
+        | Schema::Fixed {
+            doc,
+            aliases,
+            attributes,
+            ..
+        } => strip_named(doc, aliases, attributes, options),
+        Schema::Array(items) => strip(items, options),
+        Schema::Map(values) => strip(values, options),
+        Schema::Union(union) => {
+            let mut variants = union.variants().to_vec();
+            for variant in variants.iter_mut() {
+                strip(variant, options);
+            }
+            // The variants were valid before, and stripping keeps their kinds
+            *union = UnionSchema::new(variants).expect("stripped union is valid");
+        }
+        _ => (),
+    }
+}
+
+fn strip_named(
+    doc: &mut Option<String>,
+    aliases: &mut Option<Vec<Alias>>,
+    attributes: &mut BTreeMap<String, Value>,
+    options: &StripOptions,
+) {
+    if options.docs {
+        *doc = None;
+    }
+    if options.aliases {
+        *aliases = None;
+    }
+    if options.attributes {
+        attributes.clear();
+    }
+}
+
+fn strip_field(field: &mut RecordField, options: &StripOptions) {
+    if options.docs {
+        field.doc = None;
+    }
+    if options.aliases {
+        field.aliases = None;
+    }
+    if options.custom_attributes {
+        field.custom_attributes.clear();
+    }
+    strip(&mut field.schema, options);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::avsc::{from_avsc_str, to_avsc_string};
+
+    const INPUT: &str = r#"{
+  "type": "record",
+  "name": "User",
+  "doc": "A user",
+  "fields": [
+    {
+      "name": "email",
+      "type": "string",
+      "doc": "Contact address",
+      "x-owner": "accounts",
+      "x-pii": true,
+      "aliases": [
+        "mail"
+      ]
+    }
+  ],
+  "connect.name": "User"
+}"#;
+
+    #[test]
+    fn test_strip_custom_attributes_keeps_aliases() {
+        let mut schema = from_avsc_str(INPUT).unwrap();
+        let options = StripOptions {
+            custom_attributes: true,
+            ..StripOptions::default()
+        };
+        strip(&mut schema, &options);
+        assert_eq!(
+            to_avsc_string(&schema).unwrap(),
+            r#"{
+  "type": "record",
+  "name": "User",
+  "doc": "A user",
+  "fields": [
+    {
+      "name": "email",
+      "type": "string",
+      "doc": "Contact address",
+      "aliases": [
+        "mail"
+      ]
+    }
+  ],
+  "connect.name": "User"
+}"#
+        );
+    }
+
+    #[test]
+    fn test_strip_nothing_by_default() {
+        let mut schema = from_avsc_str(INPUT).unwrap();
+        strip(&mut schema, &StripOptions::default());
+        assert_eq!(to_avsc_string(&schema).unwrap(), INPUT);
+    }
+}
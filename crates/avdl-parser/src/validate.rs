@@ -0,0 +1,1072 @@
+//! Checks run once the whole protocol is parsed and every declared name is
+//! known.
+
+use std::collections::{HashMap, HashSet};
+
+use apache_avro::schema::{Name, Names, Namespace, RecordField, Schema};
+use serde_json::Value;
+
+use crate::error::{AvdlError, DefaultValue};
+use crate::options::ParserOptions;
+use crate::protocol::{Message, Protocol};
+use crate::visit::{SchemaVisitor, Walk};
+use crate::warning::{Warning, WarningCode, Warnings};
+
+/// Run the checks enabled in `options` over the types of a protocol,
+/// returning the errors found.
+///
+/// `names` holds the named types declared in the protocol, by full name, as
+/// returned by [`Protocol::names`]. References must not be solved yet.
+pub fn validate(
+    protocol: &Protocol,
+    names: &Names,
+    options: &ParserOptions,
+    warnings: &mut Warnings,
+) -> Vec<AvdlError> {
+    let schemas = &protocol.types;
+    let namespace = &protocol.namespace;
+    let mut errors = Vec::new();
+    if options.strict {
+        errors.extend(invalid_names(schemas));
+    }
+    // Types read by imports are not in `names`, so references can only be
+    // checked once the protocol has no imports left
+    if !options.allow_unresolved_refs && protocol.imports.is_empty() {
+        errors.extend(unresolved_refs(schemas, names, namespace));
+        errors.extend(unresolved_message_refs(
+            &protocol.messages,
+            names,
+            namespace,
+        ));
+    }
+    errors.extend(check_defaults(schemas, names, namespace, options.max_depth));
+    errors.extend(check_parameter_defaults(
+        &protocol.messages,
+        names,
+        namespace,
+        options.max_depth,
+    ));
+    if options.lint_union_null_order {
+        lint_union_null_order(schemas, warnings);
+    }
+    if options.lint_field_shadowing {
+        lint_field_shadowing(
+            schemas,
+            names,
+            namespace,
+            &options.allow_field_shadowing,
+            warnings,
+        );
+    }
+    errors
+}
+
+/// Report the names of types, fields and enum symbols which are not valid
+/// according to the specification.
+///
+/// The parser accepts any alphanumeric character, while the specification
+/// only allows ASCII ones.
+pub fn invalid_names(schemas: &[Schema]) -> Vec<AvdlError> {
+    let mut names = Vec::new();
+    for schema in schemas {
+        match schema {
+            Schema::Record { name, fields, .. } => {
+                names.push(&name.name);
+                names.extend(fields.iter().map(|field| &field.name));
+            }
+            Schema::Enum { name, symbols, .. } => {
+                names.push(&name.name);
+                names.extend(symbols);
+            }
+            Schema::Fixed { name, .. } => names.push(&name.name),
+            _ => (),
+        }
+    }
+    names
+        .into_iter()
+        .filter(|name| !is_valid_name(name))
+        .map(|name| AvdlError::InvalidName(name.clone()))
+        .collect()
+}
+
+pub(crate) fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .filter(|c| c.is_ascii_alphabetic() || *c == '_')
+        .is_some()
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Report the references used by record fields to types which are not
+/// declared in `names`.
+///
+/// Names without a namespace belong to the one of the enclosing record, or
+/// to the protocol `namespace`.
+pub fn unresolved_refs(
+    schemas: &[Schema],
+    names: &HashMap<Name, Schema>,
+    namespace: &Namespace,
+) -> Vec<AvdlError> {
+    let declared = declared_fullnames(names, namespace);
+    let mut errors = Vec::new();
+    for schema in schemas {
+        let (record, fields) = match schema {
+            Schema::Record { name, fields, .. } => (name.fully_qualified_name(namespace), fields),
+            _ => continue,
+        };
+        for field in fields {
+            for name in undeclared(&field.schema, &declared, &record.namespace) {
+                errors.push(AvdlError::UnresolvedRef {
+                    record: record.fullname(None),
+                    field: field.name.clone(),
+                    name: name.fullname(None),
+                });
+            }
+        }
+    }
+    errors
+}
+
+/// Report the references used by the parameters, responses and errors of
+/// messages to types which are not declared in `names`.
+///
+/// Names without a namespace belong to the protocol `namespace`.
+pub fn unresolved_message_refs(
+    messages: &[Message],
+    names: &HashMap<Name, Schema>,
+    namespace: &Namespace,
+) -> Vec<AvdlError> {
+    let declared = declared_fullnames(names, namespace);
+    let mut errors = Vec::new();
+    for message in messages {
+        let schemas = message
+            .request
+            .iter()
+            .map(|parameter| &parameter.schema)
+            .chain(Some(&message.response))
+            .chain(&message.errors);
+        for schema in schemas {
+            for name in undeclared(schema, &declared, namespace) {
+                errors.push(AvdlError::UnresolvedMessageRef {
+                    message: message.name.clone(),
+                    name: name.fullname(None),
+                });
+            }
+        }
+    }
+    errors
+}
+
+fn declared_fullnames(names: &HashMap<Name, Schema>, namespace: &Namespace) -> HashSet<Name> {
+    names
+        .keys()
+        .map(|name| name.fully_qualified_name(namespace))
+        .collect()
+}
+
+// References in `schema`, through arrays, maps and unions, to names which
+// are not in `declared`
+fn undeclared<'a>(
+    schema: &'a Schema,
+    declared: &HashSet<Name>,
+    namespace: &Namespace,
+) -> Vec<&'a Name> {
+    let mut found = Vec::new();
+    let mut pending = vec![schema];
+    while let Some(schema) = pending.pop() {
+        match schema {
+            Schema::Ref { name } if !declared.contains(&name.fully_qualified_name(namespace)) => {
+                found.push(name)
+            }
+            Schema::Array(inner) | Schema::Map(inner) => pending.push(inner),
+            Schema::Union(union) => pending.extend(union.variants()),
+            _ => (),
+        }
+    }
+    found
+}
+
+/// Check the default of every record field matches the field schema,
+/// including the items of arrays and maps and the first branch of unions.
+///
+/// Can be used on schemas built by hand. References to types which are not
+/// part of `schemas` are not checked.
+pub fn validate_defaults(schemas: &[Schema], max_depth: usize) -> Vec<AvdlError> {
+    let (names, _duplicates) = declared_names(schemas, &None);
+    check_defaults(schemas, &names, &None, max_depth)
+}
+
+// Same as `validate_defaults`, looking references up in `names`
+fn check_defaults(
+    schemas: &[Schema],
+    names: &Names,
+    namespace: &Namespace,
+    max_depth: usize,
+) -> Vec<AvdlError> {
+    let mut errors = Vec::new();
+    for schema in schemas {
+        let (record, fields) = match schema {
+            Schema::Record { name, fields, .. } => (name, fields),
+            _ => continue,
+        };
+        let record_namespace = record.fully_qualified_name(namespace).namespace;
+        for field in fields {
+            let default = match &field.default {
+                Some(default) => default,
+                None => continue,
+            };
+            let site = format!("{}.{}", record.name, field.name);
+            if let Err(err) = check_default_depth(&site, default, max_depth) {
+                errors.push(err);
+                continue;
+            }
+            if let Err((path, expected, found)) = check_default(
+                &field.schema,
+                default,
+                names,
+                &record_namespace,
+                String::new(),
+            ) {
+                errors.push(AvdlError::InvalidDefault {
+                    record: record.fullname(None),
+                    field: field.name.clone(),
+                    path: if path.is_empty() {
+                        "/".to_string()
+                    } else {
+                        path
+                    },
+                    expected,
+                    found: Box::new(DefaultValue(found)),
+                });
+            }
+        }
+    }
+    errors
+}
+
+// Defaults of the parameters of messages are checked like those of fields
+fn check_parameter_defaults(
+    messages: &[Message],
+    names: &Names,
+    namespace: &Namespace,
+    max_depth: usize,
+) -> Vec<AvdlError> {
+    let mut errors = Vec::new();
+    for message in messages {
+        for parameter in &message.request {
+            let default = match &parameter.default {
+                Some(default) => default,
+                None => continue,
+            };
+            let site = format!("{}.{}", message.name, parameter.name);
+            if let Err(err) = check_default_depth(&site, default, max_depth) {
+                errors.push(err);
+                continue;
+            }
+            if let Err((path, expected, found)) =
+                check_default(&parameter.schema, default, names, namespace, String::new())
+            {
+                errors.push(AvdlError::InvalidParameterDefault {
+                    message: message.name.clone(),
+                    parameter: parameter.name.clone(),
+                    path: if path.is_empty() {
+                        "/".to_string()
+                    } else {
+                        path
+                    },
+                    expected,
+                    found: Box::new(DefaultValue(found)),
+                });
+            }
+        }
+    }
+    errors
+}
+
+// The name `schema` declares, a decimal stored in a fixed taking the name of
+// the fixed
+pub(crate) fn declared_name(schema: &Schema) -> Option<&Name> {
+    match schema {
+        Schema::Record { name, .. } | Schema::Enum { name, .. } | Schema::Fixed { name, .. } => {
+            Some(name)
+        }
+        Schema::Decimal { inner, .. } => declared_name(inner),
+        _ => None,
+    }
+}
+
+pub(crate) fn collect_names<'a>(schema: &'a Schema, names: &mut HashMap<Name, &'a Schema>) {
+    match schema {
+        Schema::Record { name, fields, .. } => {
+            names.insert(name.clone(), schema);
+            for field in fields {
+                collect_names(&field.schema, names);
+            }
+        }
+        Schema::Enum { name, .. } | Schema::Fixed { name, .. } => {
+            names.insert(name.clone(), schema);
+        }
+        Schema::Decimal { inner, .. } => {
+            if let Some(name) = declared_name(inner) {
+                names.insert(name.clone(), schema);
+            }
+        }
+        Schema::Array(inner) | Schema::Map(inner) => collect_names(inner, names),
+        Schema::Union(union) => {
+            for variant in union.variants() {
+                collect_names(variant, names);
+            }
+        }
+        _ => (),
+    }
+}
+
+// The named types of `schemas`, including the ones declared inside others, by
+// full name, the ones without a namespace taking the one they are declared in.
+// A name declared again with another definition is reported, and the first
+// definition kept.
+pub(crate) fn declared_names(schemas: &[Schema], namespace: &Namespace) -> (Names, Vec<AvdlError>) {
+    let mut collector = NameCollector {
+        enclosing: vec![namespace.clone()],
+        names: HashMap::new(),
+        duplicates: Vec::new(),
+    };
+    for schema in schemas {
+        schema.walk(&mut collector);
+    }
+    (collector.names, collector.duplicates)
+}
+
+struct NameCollector {
+    enclosing: Vec<Namespace>,
+    names: Names,
+    duplicates: Vec<AvdlError>,
+}
+
+impl SchemaVisitor for NameCollector {
+    fn enter(&mut self, schema: &Schema) {
+        let Some(name) = declared_name(schema) else {
+            return;
+        };
+        let enclosing = self.enclosing.last().cloned().flatten();
+        let fullname = name.fully_qualified_name(&enclosing);
+        self.enclosing.push(fullname.namespace.clone());
+        match self.names.get(&fullname) {
+            // Solved references are copies of the definition, and the fixed
+            // of a decimal is entered after the decimal
+            Some(declared) if declared == schema => (),
+            Some(Schema::Decimal { inner, .. }) if **inner == *schema => (),
+            Some(_) => self
+                .duplicates
+                .push(AvdlError::Duplicate(fullname.fullname(None))),
+            None => {
+                self.names.insert(fullname, schema.clone());
+            }
+        }
+    }
+
+    fn leave(&mut self, schema: &Schema) {
+        if declared_name(schema).is_some() {
+            self.enclosing.pop();
+        }
+    }
+}
+
+// On mismatch, returns the path of the offending value, the expected type and
+// the value itself
+fn check_default(
+    schema: &Schema,
+    value: &Value,
+    names: &Names,
+    namespace: &Namespace,
+    path: String,
+) -> Result<(), (String, String, Value)> {
+    let valid = match (schema, value) {
+        (Schema::Null, Value::Null) => true,
+        (Schema::Boolean, Value::Bool(_)) => true,
+        (Schema::Int | Schema::Date | Schema::TimeMillis, Value::Number(n)) => n
+            .as_i64()
+            .map(|n| i32::try_from(n).is_ok())
+            .unwrap_or(false),
+        (
+            Schema::Long
+            | Schema::TimeMicros
+            | Schema::TimestampMillis
+            | Schema::TimestampMicros
+            | Schema::LocalTimestampMillis
+            | Schema::LocalTimestampMicros,
+            Value::Number(n),
+        ) => n.is_i64(),
+        (Schema::Float | Schema::Double, Value::Number(_)) => true,
+        (Schema::Bytes | Schema::String, Value::String(_)) => true,
+        // Bytes parsed from IDL are converted to JSON as an array of numbers
+        (Schema::Bytes, Value::Array(bytes)) => is_bytes(bytes),
+        (
+            Schema::Decimal {
+                precision, inner, ..
+            },
+            value,
+        ) => match default_bytes(value) {
+            Some(bytes) => {
+                unscaled_digits(&bytes) <= *precision
+                    && match inner.as_ref() {
+                        Schema::Fixed { size, .. } => bytes.len() == *size,
+                        _ => true,
+                    }
+            }
+            None => false,
+        },
+        (Schema::Fixed { size, .. }, Value::Array(bytes)) => {
+            bytes.len() == *size && is_bytes(bytes)
+        }
+        (Schema::Uuid, Value::String(s)) => uuid::Uuid::parse_str(s).is_ok(),
+        (Schema::Enum { symbols, .. }, Value::String(s)) => symbols.contains(s),
+        (Schema::Fixed { size, .. }, Value::String(s)) => s.chars().count() == *size,
+        (Schema::Duration, Value::String(s)) => s.chars().count() == 12,
+        (Schema::Duration, Value::Array(bytes)) => bytes.len() == 12 && is_bytes(bytes),
+        (Schema::Array(items), Value::Array(values)) => {
+            for (i, value) in values.iter().enumerate() {
+                check_default(items, value, names, namespace, format!("{path}/{i}"))?;
+            }
+            true
+        }
+        (Schema::Map(inner), Value::Object(entries)) => {
+            for (key, value) in entries {
+                check_default(inner, value, names, namespace, format!("{path}/{key}"))?;
+            }
+            true
+        }
+        (Schema::Union(union), value) => match union.variants().first() {
+            Some(first) => return check_default(first, value, names, namespace, path),
+            None => false,
+        },
+        (Schema::Record { name, fields, .. }, Value::Object(entries)) => {
+            let namespace = name.fully_qualified_name(namespace).namespace;
+            for field in fields {
+                match entries.get(&field.name) {
+                    Some(value) => check_default(
+                        &field.schema,
+                        value,
+                        names,
+                        &namespace,
+                        format!("{path}/{}", field.name),
+                    )?,
+                    None if field.default.is_some() => (),
+                    None => {
+                        return Err((
+                            format!("{path}/{}", field.name),
+                            type_name(&field.schema),
+                            value.clone(),
+                        ))
+                    }
+                }
+            }
+            true
+        }
+        (Schema::Ref { name }, value) => match names.get(&name.fully_qualified_name(namespace)) {
+            Some(schema) => return check_default(schema, value, names, namespace, path),
+            None => true,
+        },
+        _ => false,
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err((path, type_name(schema), value.clone()))
+    }
+}
+
+fn is_bytes(values: &[Value]) -> bool {
+    values
+        .iter()
+        .all(|value| value.as_u64().map(|n| n <= 255).unwrap_or(false))
+}
+
+// The bytes of a default written as a string of characters up to `\u00FF`,
+// or as the array of numbers the parser stores
+fn default_bytes(value: &Value) -> Option<Vec<u8>> {
+    match value {
+        Value::String(s) => s.chars().map(|c| u8::try_from(c).ok()).collect(),
+        Value::Array(bytes) => bytes
+            .iter()
+            .map(|byte| byte.as_u64().and_then(|byte| u8::try_from(byte).ok()))
+            .collect(),
+        _ => None,
+    }
+}
+
+// Number of digits of the unscaled value of a decimal, the two's complement
+// big-endian integer in `bytes`
+pub(crate) fn unscaled_digits(bytes: &[u8]) -> usize {
+    let mut magnitude = bytes.to_vec();
+    if bytes.first().is_some_and(|byte| byte & 0x80 != 0) {
+        for byte in magnitude.iter_mut() {
+            *byte = !*byte;
+        }
+        for byte in magnitude.iter_mut().rev() {
+            let (sum, carry) = byte.overflowing_add(1);
+            *byte = sum;
+            if !carry {
+                break;
+            }
+        }
+    }
+    let mut digits = 0;
+    while magnitude.iter().any(|&byte| byte != 0) {
+        let mut remainder = 0;
+        for byte in magnitude.iter_mut() {
+            let value = remainder * 256 + u32::from(*byte);
+            *byte = (value / 10) as u8;
+            remainder = value % 10;
+        }
+        digits += 1;
+    }
+    digits
+}
+
+// Most digits a decimal stored in a fixed of `size` bytes can have
+pub(crate) fn max_decimal_digits(size: usize) -> usize {
+    match size {
+        0 => 0,
+        size => ((8 * size - 1) as f64 * 2f64.log10()).floor() as usize,
+    }
+}
+
+/// Name of a schema as written in IDL, e.g. `array<int>`.
+pub fn type_name(schema: &Schema) -> String {
+    match schema {
+        Schema::Null => "null".to_string(),
+        Schema::Boolean => "boolean".to_string(),
+        Schema::Int => "int".to_string(),
+        Schema::Long => "long".to_string(),
+        Schema::Float => "float".to_string(),
+        Schema::Double => "double".to_string(),
+        Schema::Bytes => "bytes".to_string(),
+        Schema::String => "string".to_string(),
+        Schema::Array(items) => format!("array<{}>", type_name(items)),
+        Schema::Map(values) => format!("map<{}>", type_name(values)),
+        Schema::Union(union) => {
+            let variants: Vec<String> = union.variants().iter().map(type_name).collect();
+            format!("union {{ {} }}", variants.join(", "))
+        }
+        Schema::Record { name, .. }
+        | Schema::Enum { name, .. }
+        | Schema::Fixed { name, .. }
+        | Schema::Ref { name } => name.fullname(None),
+        Schema::Decimal { inner, .. } if matches!(**inner, Schema::Fixed { .. }) => {
+            type_name(inner)
+        }
+        Schema::Decimal {
+            precision, scale, ..
+        } => format!("decimal({precision},{scale})"),
+        Schema::Uuid => "uuid".to_string(),
+        Schema::Date => "date".to_string(),
+        Schema::TimeMillis => "time_ms".to_string(),
+        Schema::TimestampMillis => "timestamp_ms".to_string(),
+        schema => format!("{:?}", apache_avro::schema::SchemaKind::from(schema)).to_lowercase(),
+    }
+}
+
+/// Fail if `default` nests deeper than `max_depth` arrays or objects.
+///
+/// The value is walked without recursion, so any depth can be checked.
+pub fn check_default_depth(
+    field: &str,
+    default: &Value,
+    max_depth: usize,
+) -> Result<(), AvdlError> {
+    let mut pending = vec![(default, String::new(), 0)];
+    while let Some((value, path, depth)) = pending.pop() {
+        let children: Vec<(&Value, String)> = match value {
+            Value::Array(items) => items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| (item, format!("{path}/{i}")))
+                .collect(),
+            Value::Object(entries) => entries
+                .iter()
+                .map(|(key, item)| (item, format!("{path}/{key}")))
+                .collect(),
+            _ => continue,
+        };
+        if depth == max_depth {
+            return Err(AvdlError::DefaultTooDeep {
+                field: field.to_string(),
+                path: if path.is_empty() {
+                    "/".to_string()
+                } else {
+                    path
+                },
+                max_depth,
+            });
+        }
+        pending.extend(
+            children
+                .into_iter()
+                .map(|(item, path)| (item, path, depth + 1)),
+        );
+    }
+    Ok(())
+}
+
+/// Warn about record fields named exactly like a named type, which is legal
+/// but confusing and collides in the code generated from the schema.
+pub fn lint_field_shadowing(
+    schemas: &[Schema],
+    names: &HashMap<Name, Schema>,
+    namespace: &Namespace,
+    allowed: &[String],
+    warnings: &mut Warnings,
+) {
+    for schema in schemas {
+        let (record, fields) = match schema {
+            Schema::Record { name, fields, .. } => (name.fully_qualified_name(namespace), fields),
+            _ => continue,
+        };
+        for field in fields {
+            let shadowed = names.keys().find(|name| name.name == field.name);
+            let shadowed = match shadowed {
+                Some(shadowed) => shadowed,
+                None => continue,
+            };
+            let is_allowed = allowed.iter().any(|site| {
+                *site == format!("{}.{}", record.name, field.name)
+                    || *site == format!("{}.{}", record.fullname(None), field.name)
+            });
+            if is_allowed {
+                continue;
+            }
+            warnings.push(Warning::new(
+                WarningCode::FieldShadowsType,
+                format!(
+                    "field `{}` of record `{}` has the same name as type `{}`, consider renaming it",
+                    field.name,
+                    record.name,
+                    shadowed.fullname(None)
+                ),
+                None,
+            ));
+        }
+    }
+}
+
+/// Fields whose union has `null` after another type while their default is
+/// `null` or absent, along with the name of their record.
+///
+/// `null` usually goes first, so a `null` default is allowed.
+pub fn union_null_not_first(schemas: &[Schema]) -> Vec<(&Name, &RecordField)> {
+    let mut sites = Vec::new();
+    for schema in schemas {
+        let (record, fields) = match schema {
+            Schema::Record { name, fields, .. } => (name, fields),
+            _ => continue,
+        };
+        for field in fields {
+            let null_position = match &field.schema {
+                Schema::Union(union) => union
+                    .variants()
+                    .iter()
+                    .position(|variant| *variant == Schema::Null),
+                _ => None,
+            };
+            let default_is_null = matches!(field.default, None | Some(Value::Null));
+            if matches!(null_position, Some(position) if position > 0) && default_is_null {
+                sites.push((record, field));
+            }
+        }
+    }
+    sites
+}
+
+/// Warn about the fields found by [`union_null_not_first`].
+pub fn lint_union_null_order(schemas: &[Schema], warnings: &mut Warnings) {
+    for (record, field) in union_null_not_first(schemas) {
+        warnings.push(Warning::new(
+            WarningCode::UnionNullNotFirst,
+            format!(
+                "field `{}` of record `{}` has null after another type in `{}`, consider putting it first",
+                field.name,
+                record.name,
+                type_name(&field.schema)
+            ),
+            None,
+        ));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::options::DEFAULT_MAX_DEPTH;
+    use crate::parser::parse_protocol_with_options;
+    use apache_avro::schema::{RecordField, RecordFieldOrder, UnionSchema};
+    use rstest::rstest;
+    use serde_json::json;
+
+    const INPUT: &str = "protocol P {
+    enum Status {
+        ACTIVE, INACTIVE
+    }
+    record Event {
+        string Status;
+        string status;
+    }
+}";
+
+    fn lint(options: &ParserOptions) -> Warnings {
+        let (_protocol, warnings) = parse_protocol_with_options(INPUT, options).unwrap();
+        warnings
+    }
+
+    #[test]
+    fn test_field_shadowing_warns() {
+        let warnings = lint(&ParserOptions {
+            lint_field_shadowing: true,
+            ..Default::default()
+        });
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code, WarningCode::FieldShadowsType);
+        assert_eq!(
+            warnings[0].message,
+            "field `Status` of record `Event` has the same name as type `Status`, consider renaming it"
+        );
+    }
+
+    #[rstest]
+    #[case("Event.Status")]
+    #[case("P.Event.Status")]
+    fn test_field_shadowing_allowed(#[case] site: &str) {
+        let input = INPUT.replace("protocol P", "@namespace(\"P\")\nprotocol P");
+        let options = ParserOptions {
+            lint_field_shadowing: true,
+            allow_field_shadowing: vec![site.to_string()],
+            ..Default::default()
+        };
+        let (_protocol, warnings) = parse_protocol_with_options(&input, &options).unwrap();
+        assert_eq!(warnings, vec![]);
+    }
+
+    #[test]
+    fn test_field_shadowing_off_by_default() {
+        assert_eq!(lint(&ParserOptions::default()), vec![]);
+    }
+
+    #[rstest]
+    #[case("[[[1]]]", 3)]
+    #[case("[[], [[1]]]", 3)]
+    #[case("1", 0)]
+    fn test_default_at_depth_limit(#[case] default: &str, #[case] max_depth: usize) {
+        let default: Value = serde_json::from_str(default).unwrap();
+        assert!(check_default_depth("R.f", &default, max_depth).is_ok());
+    }
+
+    #[test]
+    fn test_default_too_deep() {
+        let input = "protocol P {
+    record R {
+        array<array<array<array<int>>>> values = [[[[1]]]];
+    }
+}";
+        let options = ParserOptions {
+            max_depth: 3,
+            ..Default::default()
+        };
+        match parse_protocol_with_options(input, &options) {
+            Err(err @ AvdlError::DefaultTooDeep { .. }) => {
+                assert_eq!(
+                    err.to_string(),
+                    "default of R.values nests deeper than 3 levels at /0/0/0"
+                );
+            }
+            result => panic!("unexpected result {result:?}"),
+        }
+        let options = ParserOptions {
+            max_depth: 4,
+            ..Default::default()
+        };
+        assert!(parse_protocol_with_options(input, &options).is_ok());
+    }
+
+    fn record(schema: Schema, default: Value) -> Schema {
+        Schema::Record {
+            name: Name::new("R").unwrap(),
+            aliases: None,
+            doc: None,
+            fields: vec![RecordField {
+                name: "f".to_string(),
+                doc: None,
+                aliases: None,
+                default: Some(default),
+                schema,
+                order: RecordFieldOrder::Ascending,
+                position: 0,
+                custom_attributes: BTreeMap::new(),
+            }],
+            lookup: BTreeMap::new(),
+            attributes: BTreeMap::new(),
+        }
+    }
+
+    fn union(variants: Vec<Schema>) -> Schema {
+        Schema::Union(UnionSchema::new(variants).unwrap())
+    }
+
+    fn decimal(precision: usize, scale: usize) -> Schema {
+        Schema::Decimal {
+            precision,
+            scale,
+            inner: Box::new(Schema::Bytes),
+        }
+    }
+
+    #[rstest]
+    #[case(Schema::String, json!("jon"))]
+    #[case(Schema::Int, json!(42))]
+    #[case(Schema::Long, json!(9_000_000_000i64))]
+    #[case(Schema::LocalTimestampMicros, json!(-9_000_000_000i64))]
+    #[case(Schema::Double, json!(1.5))]
+    #[case(Schema::Array(Box::new(Schema::Int)), json!([1, 2]))]
+    #[case(Schema::Map(Box::new(Schema::Boolean)), json!({"a": true}))]
+    #[case(union(vec![Schema::Null, Schema::String]), json!(null))]
+    #[case(Schema::Uuid, json!("123e4567-e89b-12d3-a456-426614174000"))]
+    #[case(Schema::Bytes, json!([104, 105]))]
+    #[case(decimal(4, 2), json!([7, 208]))]
+    #[case(decimal(4, 2), json!("\u{7}\u{d0}"))]
+    fn test_valid_default(#[case] schema: Schema, #[case] default: Value) {
+        let errors = validate_defaults(&[record(schema, default)], DEFAULT_MAX_DEPTH);
+        assert!(errors.is_empty(), "{errors:?}");
+    }
+
+    #[rstest]
+    #[case(Schema::String, json!(null), "/", "string")]
+    #[case(Schema::Int, json!(1.5), "/", "int")]
+    #[case(Schema::Int, json!(3_000_000_000i64), "/", "int")]
+    #[case(Schema::Array(Box::new(Schema::Int)), json!([1, "a"]), "/1", "int")]
+    #[case(Schema::Map(Box::new(Schema::Long)), json!({"a": "b"}), "/a", "long")]
+    #[case(union(vec![Schema::String, Schema::Null]), json!(null), "/", "string")]
+    #[case(Schema::Uuid, json!("not-a-uuid"), "/", "uuid")]
+    #[case(Schema::Bytes, json!([1, 256]), "/", "bytes")]
+    #[case(decimal(3, 2), json!([7, 208]), "/", "decimal(3,2)")]
+    #[case(decimal(3, 2), json!("\u{100}"), "/", "decimal(3,2)")]
+    fn test_invalid_default(
+        #[case] schema: Schema,
+        #[case] default: Value,
+        #[case] expected_path: &str,
+        #[case] expected_type: &str,
+    ) {
+        let errors = validate_defaults(&[record(schema, default)], DEFAULT_MAX_DEPTH);
+        match &errors[..] {
+            [AvdlError::InvalidDefault {
+                record,
+                field,
+                path,
+                expected,
+                ..
+            }] => {
+                assert_eq!(record, "R");
+                assert_eq!(field, "f");
+                assert_eq!(path, expected_path);
+                assert_eq!(expected, expected_type);
+            }
+            errors => panic!("unexpected errors {errors:?}"),
+        }
+    }
+
+    #[rstest]
+    #[case(&[], 0)]
+    #[case(&[0x00], 0)]
+    #[case(&[0x07, 0xd0], 4)]
+    #[case(&[0xf8, 0x30], 4)]
+    #[case(&[0x80], 3)]
+    #[case(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff], 1)]
+    fn test_unscaled_digits(#[case] bytes: &[u8], #[case] digits: usize) {
+        assert_eq!(unscaled_digits(bytes), digits);
+    }
+
+    #[rstest]
+    #[case(1, 2)]
+    #[case(4, 9)]
+    #[case(8, 18)]
+    #[case(16, 38)]
+    fn test_max_decimal_digits(#[case] size: usize, #[case] digits: usize) {
+        assert_eq!(max_decimal_digits(size), digits);
+    }
+
+    #[test]
+    fn test_invalid_enum_default() {
+        let status = Schema::Enum {
+            name: Name::new("Status").unwrap(),
+            aliases: None,
+            doc: None,
+            symbols: vec!["ACTIVE".to_string()],
+            attributes: BTreeMap::new(),
+        };
+        let reference = Schema::Ref {
+            name: Name::new("Status").unwrap(),
+        };
+        let errors = validate_defaults(&[status, record(reference, json!("GONE"))], 8);
+        assert_eq!(
+            errors[0].to_string(),
+            "invalid default for field f of record R at /: expected Status"
+        );
+    }
+
+    #[rstest]
+    #[case("string s = null;")]
+    #[case("int n = 1.5;")]
+    fn test_invalid_default_is_rejected(#[case] field: &str) {
+        let input = format!("protocol P {{\n    record R {{\n        {field}\n    }}\n}}");
+        assert!(parse_protocol_with_options(&input, &ParserOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_unresolved_ref() {
+        let input = "protocol P {
+    record Address {
+        string street;
+    }
+    record User {
+        Addres home;
+    }
+}";
+        let err = parse_protocol_with_options(input, &ParserOptions::default()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unresolved type Addres used by field home of record User"
+        );
+        let options = ParserOptions {
+            allow_unresolved_refs: true,
+            ..Default::default()
+        };
+        assert!(parse_protocol_with_options(input, &options).is_ok());
+    }
+
+    #[rstest]
+    #[case("Status get(Statu id);", "Statu")]
+    #[case("Statu get(Status id);", "Statu")]
+    #[case("array<Statu> list();", "Statu")]
+    #[case("void get(Status id) throws Eror;", "Eror")]
+    fn test_unresolved_message_ref(#[case] message: &str, #[case] name: &str) {
+        let input = format!(
+            "protocol P {{
+    enum Status {{ ON, OFF }}
+    record Error {{ string reason; }}
+    {message}
+}}"
+        );
+        let err = parse_protocol_with_options(&input, &ParserOptions::default()).unwrap_err();
+        assert!(matches!(
+            err,
+            AvdlError::UnresolvedMessageRef { name: found, .. } if found == name
+        ));
+    }
+
+    #[test]
+    fn test_refs_to_imported_types() {
+        let input = include_str!("../../../tests/samples/Import.avdl");
+        assert!(parse_protocol_with_options(input, &ParserOptions::default()).is_ok());
+    }
+
+    #[test]
+    fn test_forward_ref() {
+        let input = "@namespace(\"org.example\")
+protocol P {
+    record User {
+        array<Address> addresses;
+        union { null, Address } home;
+    }
+    record Address {
+        string street;
+    }
+}";
+        assert!(parse_protocol_with_options(input, &ParserOptions::default()).is_ok());
+    }
+
+    #[test]
+    fn test_cross_namespace_ref() {
+        let input = "@namespace(\"org.example\")
+protocol P {
+    @namespace(\"org.other\")
+    record Address {
+        string street;
+    }
+    @namespace(\"org.other\")
+    record Company {
+        Address office;
+    }
+    record User {
+        Address home;
+    }
+}";
+        let err = parse_protocol_with_options(input, &ParserOptions::default()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unresolved type Address used by field home of record org.example.User"
+        );
+    }
+
+    #[test]
+    fn test_strict_rejects_non_ascii_names() {
+        let input = "protocol P {
+    record Coffee {
+        string naïve;
+    }
+}";
+        assert!(parse_protocol_with_options(input, &ParserOptions::default()).is_ok());
+        let options = ParserOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let err = parse_protocol_with_options(input, &options).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid name `naïve`, names must start with [A-Za-z_] and contain only [A-Za-z0-9_]"
+        );
+    }
+
+    #[test]
+    fn test_trailing_commas() {
+        let input = "protocol P {
+    enum Kind {
+        BOOK, FOOD,
+    }
+}";
+        let err = parse_protocol_with_options(input, &ParserOptions::default()).unwrap_err();
+        assert!(matches!(err, AvdlError::Parse { .. }));
+        let options = ParserOptions {
+            allow_trailing_commas: true,
+            ..Default::default()
+        };
+        let (protocol, _warnings) = parse_protocol_with_options(input, &options).unwrap();
+        match &protocol.types[..] {
+            [Schema::Enum { symbols, .. }] => assert_eq!(symbols, &vec!["BOOK", "FOOD"]),
+            schemas => panic!("unexpected schemas {schemas:?}"),
+        }
+    }
+
+    #[test]
+    fn test_union_null_order_warns() {
+        let input = r#"protocol P {
+    record User {
+        union { string, null } nickname;
+        union { null, string } email = null;
+        union { string, null } country = "CH";
+        union { int, string } code;
+    }
+}"#;
+        let options = ParserOptions {
+            lint_union_null_order: true,
+            ..Default::default()
+        };
+        let (_protocol, warnings) = parse_protocol_with_options(input, &options).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code, WarningCode::UnionNullNotFirst);
+        assert_eq!(
+            warnings[0].message,
+            "field `nickname` of record `User` has null after another type in `union { string, null }`, consider putting it first"
+        );
+    }
+}
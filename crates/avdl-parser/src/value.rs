@@ -0,0 +1,472 @@
+//! Avro values of schemas: the default ones, built for tests and fixtures,
+//! and the ones JSON holds.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use apache_avro::schema::{Name, Names, Namespace, RecordField, Schema};
+use apache_avro::types::Value;
+use apache_avro::{Days, Decimal, Duration, Millis, Months};
+use serde_json::Value as Json;
+use uuid::Uuid;
+
+use crate::validate::type_name;
+
+/// The default value of a schema: the default of a field where it has one,
+/// and a zero value elsewhere.
+pub trait DefaultValue {
+    /// The default value of a schema that references no other types, or an
+    /// error at the first one.
+    fn default_value(&self) -> Result<Value, Mismatch>;
+
+    /// The default value of a schema whose names without a namespace belong
+    /// to `namespace`, finding the types it references by name in `names`.
+    fn default_value_in(&self, namespace: &Namespace, names: &Names) -> Result<Value, Mismatch>;
+}
+
+impl DefaultValue for Schema {
+    fn default_value(&self) -> Result<Value, Mismatch> {
+        self.default_value_in(&None, &Names::new())
+    }
+
+    fn default_value_in(&self, namespace: &Namespace, names: &Names) -> Result<Value, Mismatch> {
+        Defaults {
+            names,
+            records: HashSet::new(),
+        }
+        .value(self, namespace, "")
+    }
+}
+
+// Builds default values, keeping the records being built so that a record
+// needing itself fails instead of recursing forever
+struct Defaults<'a> {
+    names: &'a Names,
+    records: HashSet<Name>,
+}
+
+impl Defaults<'_> {
+    // Zero values are false, 0, empty strings, bytes, arrays and maps, the
+    // first symbol of an enum and the null branch of a union. A fixed or a
+    // union without null has none that would not be a guess
+    fn value(
+        &mut self,
+        schema: &Schema,
+        namespace: &Namespace,
+        path: &str,
+    ) -> Result<Value, Mismatch> {
+        let none = || Mismatch {
+            path: path.to_string(),
+            message: format!("`{}` has no default value", type_name(schema)),
+        };
+        Ok(match schema {
+            Schema::Null => Value::Null,
+            Schema::Boolean => Value::Boolean(false),
+            Schema::Int => Value::Int(0),
+            Schema::Long => Value::Long(0),
+            Schema::Float => Value::Float(0.0),
+            Schema::Double => Value::Double(0.0),
+            Schema::Bytes => Value::Bytes(Vec::new()),
+            Schema::String => Value::String(String::new()),
+            Schema::Fixed { .. } => return Err(none()),
+            Schema::Enum { symbols, .. } => match symbols.first() {
+                Some(symbol) => Value::Enum(0, symbol.clone()),
+                None => return Err(none()),
+            },
+            Schema::Uuid => Value::Uuid(Uuid::nil()),
+            Schema::Date => Value::Date(0),
+            Schema::TimeMillis => Value::TimeMillis(0),
+            Schema::TimeMicros => Value::TimeMicros(0),
+            Schema::TimestampMillis => Value::TimestampMillis(0),
+            Schema::TimestampMicros => Value::TimestampMicros(0),
+            Schema::LocalTimestampMillis => Value::LocalTimestampMillis(0),
+            Schema::LocalTimestampMicros => Value::LocalTimestampMicros(0),
+            Schema::Duration => {
+                Value::Duration(Duration::new(Months::new(0), Days::new(0), Millis::new(0)))
+            }
+            // Zero, in as many bytes as the fixed it is stored in
+            Schema::Decimal { inner, .. } => {
+                let size = match inner.as_ref() {
+                    Schema::Fixed { size, .. } => *size,
+                    _ => 1,
+                };
+                Value::Decimal(Decimal::from(vec![0; size]))
+            }
+            Schema::Array(_) => Value::Array(Vec::new()),
+            Schema::Map(_) => Value::Map(Default::default()),
+            Schema::Union(union) => {
+                let variants = union.variants();
+                match variants.iter().position(|variant| *variant == Schema::Null) {
+                    Some(index) => Value::Union(index as u32, Box::new(Value::Null)),
+                    None => return Err(none()),
+                }
+            }
+            Schema::Record { name, fields, .. } => {
+                let fullname = name.fully_qualified_name(namespace);
+                if !self.records.insert(fullname.clone()) {
+                    let message = format!("record {} needs a value of itself", name.name);
+                    return Err(Mismatch {
+                        path: path.to_string(),
+                        message,
+                    });
+                }
+                let fields = fields
+                    .iter()
+                    .map(|field| self.field(field, &fullname.namespace, path))
+                    .collect::<Result<_, _>>();
+                self.records.remove(&fullname);
+                Value::Record(fields?)
+            }
+            Schema::Ref { name } => match resolve(self.names, name, namespace) {
+                Some(schema) => self.value(schema, namespace, path)?,
+                None => {
+                    return Err(Mismatch {
+                        path: path.to_string(),
+                        message: format!("unknown type `{}`", name.name),
+                    })
+                }
+            },
+        })
+    }
+
+    fn field(
+        &mut self,
+        field: &RecordField,
+        namespace: &Namespace,
+        path: &str,
+    ) -> Result<(String, Value), Mismatch> {
+        let path = format!("{path}/{}", escape(&field.name));
+        let value = match &field.default {
+            Some(default) => from_json(&field.schema, namespace, self.names, default, &path)?,
+            None => self.value(&field.schema, namespace, &path)?,
+        };
+        Ok((field.name.clone(), value))
+    }
+}
+
+/// Where a datum doesn't match its schema, with the path to the value
+/// within the datum, e.g. `/tags/0`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            f.write_str(&self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+/// The value of `schema` that `json` holds, finding the types it references
+/// by name in `names`, or where it doesn't match first.
+///
+/// Unions take their first branch that matches, and bytes are either a
+/// string of code points up to 255 or an array of numbers.
+pub fn from_json(
+    schema: &Schema,
+    namespace: &Namespace,
+    names: &Names,
+    json: &Json,
+    path: &str,
+) -> Result<Value, Mismatch> {
+    let mismatch = |message: String| Mismatch {
+        path: path.to_string(),
+        message,
+    };
+    let expected = |what: &str| mismatch(format!("expected {what}, found {}", kind(json)));
+    let int = || {
+        json.as_i64()
+            .and_then(|n| i32::try_from(n).ok())
+            .ok_or_else(|| expected("an int"))
+    };
+    let long = || json.as_i64().ok_or_else(|| expected("a long"));
+    let string = || json.as_str().ok_or_else(|| expected("a string"));
+    let sized_bytes = |size: Option<usize>| match (bytes(json), size) {
+        (Some(bytes), Some(size)) if bytes.len() != size => Err(expected(&format!("{size} bytes"))),
+        (Some(bytes), _) => Ok(bytes),
+        (None, _) => Err(expected("bytes")),
+    };
+    Ok(match schema {
+        Schema::Null if json.is_null() => Value::Null,
+        Schema::Null => return Err(expected("null")),
+        Schema::Boolean => Value::Boolean(json.as_bool().ok_or_else(|| expected("a boolean"))?),
+        Schema::Int => Value::Int(int()?),
+        Schema::Long => Value::Long(long()?),
+        Schema::Float => Value::Float(json.as_f64().ok_or_else(|| expected("a float"))? as f32),
+        Schema::Double => Value::Double(json.as_f64().ok_or_else(|| expected("a double"))?),
+        Schema::Bytes => Value::Bytes(sized_bytes(None)?),
+        Schema::String => Value::String(string()?.to_string()),
+        Schema::Fixed { size, .. } => Value::Fixed(*size, sized_bytes(Some(*size))?),
+        Schema::Enum { name, symbols, .. } => {
+            let symbol = string()?;
+            let Some(index) = symbols.iter().position(|known| known == symbol) else {
+                let message = format!("`{symbol}` is not a symbol of enum {}", name.name);
+                return Err(mismatch(message));
+            };
+            Value::Enum(index as u32, symbol.to_string())
+        }
+        Schema::Uuid => {
+            let uuid = string()?;
+            let uuid =
+                Uuid::parse_str(uuid).map_err(|_| mismatch(format!("`{uuid}` is not a uuid")))?;
+            Value::Uuid(uuid)
+        }
+        Schema::Date => Value::Date(int()?),
+        Schema::TimeMillis => Value::TimeMillis(int()?),
+        Schema::TimeMicros => Value::TimeMicros(long()?),
+        Schema::TimestampMillis => Value::TimestampMillis(long()?),
+        Schema::TimestampMicros => Value::TimestampMicros(long()?),
+        Schema::LocalTimestampMillis => Value::LocalTimestampMillis(long()?),
+        Schema::LocalTimestampMicros => Value::LocalTimestampMicros(long()?),
+        Schema::Duration => {
+            let bytes: [u8; 12] = sized_bytes(Some(12))?.try_into().unwrap();
+            Value::Duration(Duration::from(bytes))
+        }
+        Schema::Decimal { inner, .. } => {
+            let size = match inner.as_ref() {
+                Schema::Fixed { size, .. } => Some(*size),
+                _ => None,
+            };
+            Value::Decimal(Decimal::from(sized_bytes(size)?))
+        }
+        Schema::Array(items) => {
+            let values = json.as_array().ok_or_else(|| expected("an array"))?;
+            let values = values
+                .iter()
+                .enumerate()
+                .map(|(index, value)| {
+                    from_json(items, namespace, names, value, &format!("{path}/{index}"))
+                })
+                .collect::<Result<_, _>>()?;
+            Value::Array(values)
+        }
+        Schema::Map(values) => {
+            let entries = json.as_object().ok_or_else(|| expected("an object"))?;
+            let entries = entries
+                .iter()
+                .map(|(key, value)| {
+                    let path = format!("{path}/{}", escape(key));
+                    Ok((
+                        key.clone(),
+                        from_json(values, namespace, names, value, &path)?,
+                    ))
+                })
+                .collect::<Result<_, _>>()?;
+            Value::Map(entries)
+        }
+        Schema::Union(union) => {
+            let found = union
+                .variants()
+                .iter()
+                .enumerate()
+                .find_map(|(index, variant)| {
+                    let value = from_json(variant, namespace, names, json, path).ok()?;
+                    Some(Value::Union(index as u32, Box::new(value)))
+                });
+            let Some(value) = found else {
+                let message = format!(
+                    "{} matches no branch of `{}`",
+                    kind(json),
+                    type_name(schema)
+                );
+                return Err(mismatch(message));
+            };
+            value
+        }
+        Schema::Record { name, fields, .. } => {
+            let object = json.as_object().ok_or_else(|| expected("an object"))?;
+            let namespace = name.fully_qualified_name(namespace).namespace;
+            let fields = fields
+                .iter()
+                .map(|field| {
+                    let path = format!("{path}/{}", escape(&field.name));
+                    // Missing fields take their default
+                    let value = match (object.get(&field.name), &field.default) {
+                        (Some(value), _) | (None, Some(value)) => value,
+                        (None, None) => {
+                            let message = format!("missing required field `{}`", field.name);
+                            return Err(mismatch(message));
+                        }
+                    };
+                    let value = from_json(&field.schema, &namespace, names, value, &path)?;
+                    Ok((field.name.clone(), value))
+                })
+                .collect::<Result<_, _>>()?;
+            Value::Record(fields)
+        }
+        Schema::Ref { name } => match resolve(names, name, namespace) {
+            Some(schema) => from_json(schema, namespace, names, json, path)?,
+            None => return Err(mismatch(format!("unknown type `{}`", name.name))),
+        },
+    })
+}
+
+/// The type `name` refers to from within `namespace`, found in `names`.
+pub fn resolve<'a>(names: &'a Names, name: &Name, namespace: &Namespace) -> Option<&'a Schema> {
+    let fullname = name.fully_qualified_name(namespace);
+    names.get(&fullname).or_else(|| names.get(name))
+}
+
+// Bytes as a string of code points up to 255, the way Avro's JSON encoding
+// writes them, or as an array of numbers
+fn bytes(json: &Json) -> Option<Vec<u8>> {
+    match json {
+        Json::String(string) => string.chars().map(|c| u8::try_from(c).ok()).collect(),
+        Json::Array(values) => values
+            .iter()
+            .map(|value| u8::try_from(value.as_u64()?).ok())
+            .collect(),
+        _ => None,
+    }
+}
+
+// A key of a JSON pointer, with `~` and `/` escaped
+fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+fn kind(json: &Json) -> &'static str {
+    match json {
+        Json::Null => "null",
+        Json::Bool(_) => "a boolean",
+        Json::Number(_) => "a number",
+        Json::String(_) => "a string",
+        Json::Array(_) => "an array",
+        Json::Object(_) => "an object",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::expand::{expand_refs, registry};
+    use crate::parser::parse;
+    use rstest::rstest;
+
+    // The default value of the last type of `input`, self-contained so that
+    // apache_avro can validate it
+    fn last_default(input: &str) -> (Schema, Result<Value, Mismatch>) {
+        let (_tail, schemas) = parse(input).unwrap();
+        let names = registry(&schemas);
+        let schema = expand_refs(schemas.last().unwrap(), &names);
+        let value = schema.default_value_in(&None, &names);
+        (schema, value)
+    }
+
+    fn fields(value: Value) -> Vec<(String, Value)> {
+        match value {
+            Value::Record(fields) => fields,
+            _ => panic!("expected a record, got {value:?}"),
+        }
+    }
+
+    #[test]
+    fn test_default_value_zero_values() {
+        let (schema, value) = last_default(
+            "protocol P {
+    enum Suit { SPADES, HEARTS }
+    record Card {
+        string name;
+        long number;
+        boolean shown;
+        Suit suit;
+        array<int> marks;
+        map<string> tags;
+        union { string, null } note;
+        decimal(9, 2) price;
+        date played;
+    }
+}",
+        );
+        let value = value.unwrap();
+        assert!(value.validate(&schema));
+        assert_eq!(
+            fields(value),
+            [
+                ("name".into(), Value::String(String::new())),
+                ("number".into(), Value::Long(0)),
+                ("shown".into(), Value::Boolean(false)),
+                ("suit".into(), Value::Enum(0, "SPADES".into())),
+                ("marks".into(), Value::Array(Vec::new())),
+                ("tags".into(), Value::Map(Default::default())),
+                ("note".into(), Value::Union(1, Box::new(Value::Null))),
+                ("price".into(), Value::Decimal(Decimal::from(vec![0]))),
+                ("played".into(), Value::Date(0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_default_value_field_defaults() {
+        let (schema, value) = last_default(
+            r#"protocol P {
+    enum Suit { SPADES, HEARTS }
+    record Address { string city = "Bern"; }
+    record Person {
+        int age = 3;
+        union { string, null } name = "jon";
+        array<long> scores = [1, 2];
+        Suit suit = HEARTS;
+        Address home;
+    }
+}"#,
+        );
+        let value = value.unwrap();
+        assert!(value.validate(&schema));
+        let home = Value::Record(vec![("city".into(), Value::String("Bern".into()))]);
+        assert_eq!(
+            fields(value),
+            [
+                ("age".into(), Value::Int(3)),
+                (
+                    "name".into(),
+                    Value::Union(0, Box::new(Value::String("jon".into())))
+                ),
+                (
+                    "scores".into(),
+                    Value::Array(vec![Value::Long(1), Value::Long(2)])
+                ),
+                ("suit".into(), Value::Enum(1, "HEARTS".into())),
+                ("home".into(), home),
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case(
+        "fixed MD5(16); record R { MD5 hash; }",
+        "/hash: `MD5` has no default value"
+    )]
+    #[case(
+        "record R { union { int, string } id; }",
+        "/id: `union { int, string }` has no default value"
+    )]
+    #[case(
+        "fixed MD5(16); record S { MD5 hash; } record R { S inner; }",
+        "/inner/hash: `MD5` has no default value"
+    )]
+    #[case(
+        "record Node { Node next; }",
+        "/next: record Node needs a value of itself"
+    )]
+    fn test_default_value_rejected(#[case] types: &str, #[case] expected: &str) {
+        let (_schema, value) = last_default(&format!("protocol P {{ {types} }}"));
+        assert_eq!(value.unwrap_err().to_string(), expected);
+    }
+
+    #[test]
+    fn test_default_value_unknown_type() {
+        let schema = Schema::Ref {
+            name: Name::new("Missing").unwrap(),
+        };
+        assert_eq!(
+            schema.default_value().unwrap_err().to_string(),
+            "unknown type `Missing`"
+        );
+    }
+}
@@ -0,0 +1,185 @@
+//! Walking through every schema nested in another one.
+
+use apache_avro::schema::{RecordField, Schema, UnionSchema};
+
+/// Callbacks for [`Walk::walk`], all doing nothing unless implemented.
+pub trait SchemaVisitor {
+    /// Called for every schema, before the ones nested in it.
+    fn enter(&mut self, _schema: &Schema) {}
+
+    /// Called for every schema, after the ones nested in it.
+    fn leave(&mut self, _schema: &Schema) {}
+
+    /// Called for every field of a record, before its schema is entered.
+    fn field(&mut self, _field: &RecordField) {}
+}
+
+/// Callbacks for [`Walk::walk_mut`], all doing nothing unless implemented.
+pub trait SchemaVisitorMut {
+    /// Called for every schema, before the ones nested in it. The schemas
+    /// nested in what is left after the call are walked next.
+    fn enter(&mut self, _schema: &mut Schema) {}
+
+    /// Called for every schema, after the ones nested in it.
+    fn leave(&mut self, _schema: &mut Schema) {}
+
+    /// Called for every field of a record, before its schema is entered.
+    fn field(&mut self, _field: &mut RecordField) {}
+}
+
+/// Walking a schema and the ones nested in it: array items, map values,
+/// union variants, record fields and the type annotated by a decimal.
+///
+/// References are not followed.
+pub trait Walk {
+    fn walk(&self, visitor: &mut impl SchemaVisitor);
+
+    /// Like [`Walk::walk`], letting the visitor change the schemas.
+    ///
+    /// A union whose variants stop being a valid union, e.g. two `bytes`
+    /// after replacing a `string`, is left as it was.
+    fn walk_mut(&mut self, visitor: &mut impl SchemaVisitorMut);
+}
+
+impl Walk for Schema {
+    fn walk(&self, visitor: &mut impl SchemaVisitor) {
+        visitor.enter(self);
+        match self {
+            Schema::Array(inner) | Schema::Map(inner) | Schema::Decimal { inner, .. } => {
+                inner.walk(visitor)
+            }
+            Schema::Union(union) => {
+                for variant in union.variants() {
+                    variant.walk(visitor);
+                }
+            }
+            Schema::Record { fields, .. } => {
+                for field in fields {
+                    visitor.field(field);
+                    field.schema.walk(visitor);
+                }
+            }
+            _ => (),
+        }
+        visitor.leave(self);
+    }
+
+    fn walk_mut(&mut self, visitor: &mut impl SchemaVisitorMut) {
+        visitor.enter(self);
+        match self {
+            Schema::Array(inner) | Schema::Map(inner) | Schema::Decimal { inner, .. } => {
+                inner.walk_mut(visitor)
+            }
+            Schema::Union(union) => {
+                let mut variants = union.variants().to_vec();
+                for variant in variants.iter_mut() {
+                    variant.walk_mut(visitor);
+                }
+                if let Ok(walked) = UnionSchema::new(variants) {
+                    *union = walked;
+                }
+            }
+            Schema::Record { fields, .. } => {
+                for field in fields.iter_mut() {
+                    visitor.field(field);
+                    field.schema.walk_mut(visitor);
+                }
+            }
+            _ => (),
+        }
+        visitor.leave(self);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::avsc::from_avsc_str;
+    use apache_avro::schema::SchemaKind;
+
+    const NESTED: &str = r#"{
+  "type": "record",
+  "name": "Order",
+  "fields": [
+    {"name": "id", "type": "string"},
+    {"name": "lines", "type": {"type": "array", "items": {
+      "type": "record",
+      "name": "Line",
+      "fields": [
+        {"name": "sku", "type": "string"},
+        {"name": "price", "type": {"type": "bytes", "logicalType": "decimal", "precision": 9, "scale": 2}}
+      ]
+    }}},
+    {"name": "notes", "type": ["null", "string", {"type": "map", "values": "string"}]}
+  ]
+}"#;
+
+    #[derive(Default)]
+    struct Counter {
+        entered: usize,
+        left: usize,
+        fields: Vec<String>,
+    }
+
+    impl SchemaVisitor for Counter {
+        fn enter(&mut self, _schema: &Schema) {
+            self.entered += 1;
+        }
+
+        fn leave(&mut self, _schema: &Schema) {
+            self.left += 1;
+        }
+
+        fn field(&mut self, field: &RecordField) {
+            self.fields.push(field.name.clone());
+        }
+    }
+
+    #[test]
+    fn test_walk_counts_nodes() {
+        let schema = from_avsc_str(NESTED).unwrap();
+        let mut counter = Counter::default();
+        schema.walk(&mut counter);
+        // Order, string, array, Line, string, decimal, bytes, union, null,
+        // string, map, string
+        assert_eq!(counter.entered, 12);
+        assert_eq!(counter.left, 12);
+        assert_eq!(counter.fields, vec!["id", "lines", "sku", "price", "notes"]);
+    }
+
+    struct StringsToBytes;
+
+    impl SchemaVisitorMut for StringsToBytes {
+        fn enter(&mut self, schema: &mut Schema) {
+            if let Schema::String = schema {
+                *schema = Schema::Bytes;
+            }
+        }
+    }
+
+    #[derive(Default)]
+    struct Kinds(Vec<SchemaKind>);
+
+    impl SchemaVisitor for Kinds {
+        fn enter(&mut self, schema: &Schema) {
+            self.0.push(SchemaKind::from(schema));
+        }
+    }
+
+    #[test]
+    fn test_walk_mut_strings_to_bytes() {
+        let mut schema = from_avsc_str(NESTED).unwrap();
+        schema.walk_mut(&mut StringsToBytes);
+        let mut kinds = Kinds::default();
+        schema.walk(&mut kinds);
+        assert!(!kinds.0.contains(&SchemaKind::String));
+        assert_eq!(
+            kinds
+                .0
+                .iter()
+                .filter(|kind| **kind == SchemaKind::Bytes)
+                .count(),
+            5
+        );
+    }
+}
@@ -0,0 +1,93 @@
+use std::fmt;
+
+use crate::error::Span;
+
+/// Kind of warning, rendered as a short kebab-case code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum_macros::Display, strum_macros::AsRefStr)]
+#[strum(serialize_all = "kebab-case")]
+pub enum WarningCode {
+    /// A record field has the same name as a named type.
+    FieldShadowsType,
+    /// A union has `null` after another type although the field default is
+    /// `null` or absent.
+    UnionNullNotFirst,
+    /// An annotation is given twice on one declaration, e.g. two `@aliases`
+    /// left over from a merge.
+    RepeatedAnnotation,
+    /// An annotation is put on a type which keeps no properties, e.g. the
+    /// items of an array, and is left out of the schema.
+    AnnotationIgnored,
+}
+
+/// Something suspicious found while parsing that does not prevent producing
+/// the schemas.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    pub code: WarningCode,
+    pub message: String,
+    pub span: Option<Span>,
+}
+
+pub type Warnings = Vec<Warning>;
+
+impl Warning {
+    pub fn new(code: WarningCode, message: impl Into<String>, span: Option<Span>) -> Warning {
+        Warning {
+            code,
+            message: message.into(),
+            span,
+        }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "warning[{}]: {}", self.code, self.message)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::error::Location;
+    use crate::parser::parse_with_warnings;
+
+    #[test]
+    fn test_repeated_annotation_warning() {
+        let input = r#"protocol P {
+    @aliases(["Form"])
+    @aliases(["Figure"])
+    enum Shapes {
+        SQUARE, CIRCLE
+    } = SQUARE;
+}"#;
+        let (_tail, (_schemas, warnings)) = parse_with_warnings(input).unwrap();
+        assert_eq!(warnings.len(), 1);
+        let warning = &warnings[0];
+        assert_eq!(warning.code, WarningCode::RepeatedAnnotation);
+        let span = warning.span.expect("warning should have a span");
+        assert_eq!(&input[span.start..span.end], r#"@aliases(["Figure"])"#);
+        assert_eq!(span.location(input), Location { line: 3, column: 5 });
+    }
+
+    #[test]
+    fn test_no_warnings() {
+        let input = "protocol P {
+    enum Shapes {
+        SQUARE, CIRCLE
+    } = SQUARE;
+    record R {
+        string name;
+    }
+}";
+        let (_tail, (_schemas, warnings)) = parse_with_warnings(input).unwrap();
+        assert_eq!(warnings, vec![]);
+    }
+
+    #[test]
+    fn test_warning_code_rendering() {
+        let warning = Warning::new(WarningCode::RepeatedAnnotation, "ignored", None);
+        assert_eq!(warning.code.as_ref(), "repeated-annotation");
+        assert_eq!(warning.to_string(), "warning[repeated-annotation]: ignored");
+    }
+}
@@ -0,0 +1,327 @@
+//! Compares the parser against the reference implementation: each `.avdl`
+//! fixture in `tests/conformance` comes with the `.avpr` the Java
+//! `avro-tools idl` writes for it, or the `.avsc` for a schema file.
+//! `scripts/conformance.java.sh` writes them again, and records the version
+//! of avro-tools in `tests/conformance/VERSION`.
+//!
+//! Both sides are normalized before comparing them, since Avro allows
+//! different ways to write the same schema: names are written in full and
+//! the keys of the objects sorted. Fixtures using what the parser does not
+//! support yet are listed in [`EXPECTED_FAILURES`], and the test fails once
+//! they pass so the list stays up to date.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::{Map, Value};
+
+use avdl_parser::avsc::Avsc;
+use avdl_parser::expand::{expand_refs, registry};
+use avdl_parser::{parse_idl_file, IdlFile};
+
+// Fixture names, with why they do not match the reference output
+const EXPECTED_FAILURES: [(&str, &str); 3] = [
+    (
+        "bytes_defaults",
+        "bytes defaults are written as arrays of numbers",
+    ),
+    ("enum_defaults", "enum field defaults are not parsed"),
+    (
+        "simple",
+        "error declarations, backquoted names and docs after a field type are not parsed",
+    ),
+];
+
+const PRIMITIVES: [&str; 8] = [
+    "null", "boolean", "int", "long", "float", "double", "bytes", "string",
+];
+
+fn fixtures() -> Vec<PathBuf> {
+    let folder = Path::new(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../../tests/conformance"
+    ));
+    let mut fixtures: Vec<PathBuf> = fs::read_dir(folder)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "avdl"))
+        .collect();
+    fixtures.sort();
+    fixtures
+}
+
+fn read_json(path: &Path) -> Value {
+    let json = fs::read_to_string(path).unwrap();
+    serde_json::from_str(&json).unwrap_or_else(|err| panic!("{}: {err}", path.display()))
+}
+
+fn qualify(name: &str, namespace: Option<&str>) -> String {
+    match namespace {
+        Some(namespace) if !namespace.is_empty() && !name.contains('.') => {
+            format!("{namespace}.{name}")
+        }
+        _ => name.to_string(),
+    }
+}
+
+fn sorted(object: Map<String, Value>) -> Value {
+    let object: BTreeMap<String, Value> = object.into_iter().collect();
+    Value::Object(object.into_iter().collect())
+}
+
+// Parameters of a message are written as the fields of a record
+fn normalize_fields(fields: &Value, namespace: Option<&str>) -> Value {
+    let Value::Array(fields) = fields else {
+        return fields.clone();
+    };
+    let fields = fields.iter().map(|field| match field {
+        Value::Object(field) => {
+            let mut field = field.clone();
+            if let Some(schema) = field.get("type") {
+                field.insert("type".into(), normalize_schema(schema, namespace));
+            }
+            sorted(field)
+        }
+        field => field.clone(),
+    });
+    Value::Array(fields.collect())
+}
+
+// Names are written in full, without the `namespace` keys a writer may
+// leave out when the enclosing one applies
+fn normalize_schema(schema: &Value, namespace: Option<&str>) -> Value {
+    match schema {
+        Value::String(name) if PRIMITIVES.contains(&name.as_str()) => schema.clone(),
+        Value::String(name) => Value::String(qualify(name, namespace)),
+        Value::Array(branches) => Value::Array(
+            branches
+                .iter()
+                .map(|branch| normalize_schema(branch, namespace))
+                .collect(),
+        ),
+        Value::Object(object) => {
+            let mut object = object.clone();
+            let mut namespace = namespace.map(str::to_string);
+            if let Some(Value::String(name)) = object.get("name").cloned() {
+                let fullname = match object.remove("namespace") {
+                    Some(Value::String(own)) => qualify(&name, Some(&own)),
+                    _ => qualify(&name, namespace.as_deref()),
+                };
+                namespace = fullname
+                    .rsplit_once('.')
+                    .map(|(space, _)| space.to_string());
+                object.insert("name".into(), Value::String(fullname));
+            }
+            if let Some(Value::Array(aliases)) = object.get("aliases") {
+                let aliases = aliases
+                    .iter()
+                    .map(|alias| match alias {
+                        Value::String(alias) => Value::String(qualify(alias, namespace.as_deref())),
+                        alias => alias.clone(),
+                    })
+                    .collect();
+                object.insert("aliases".into(), Value::Array(aliases));
+            }
+            if let Some(fields) = object.get("fields") {
+                let fields = normalize_fields(fields, namespace.as_deref());
+                object.insert("fields".into(), fields);
+            }
+            for key in ["items", "values"] {
+                if let Some(schema) = object.get(key) {
+                    let schema = normalize_schema(schema, namespace.as_deref());
+                    object.insert(key.into(), schema);
+                }
+            }
+            sorted(object)
+        }
+        schema => schema.clone(),
+    }
+}
+
+fn normalize_protocol(protocol: &Value) -> Value {
+    let Value::Object(protocol) = protocol else {
+        return protocol.clone();
+    };
+    let mut protocol = protocol.clone();
+    let namespace = match protocol.get("namespace") {
+        Some(Value::String(namespace)) => Some(namespace.clone()),
+        _ => None,
+    };
+    let namespace = namespace.as_deref();
+    if let Some(types) = protocol.get("types") {
+        let types = normalize_schema(types, namespace);
+        protocol.insert("types".into(), types);
+    }
+    if let Some(Value::Object(messages)) = protocol.get("messages") {
+        let messages = messages
+            .iter()
+            .map(|(name, message)| {
+                let Value::Object(message) = message else {
+                    return (name.clone(), message.clone());
+                };
+                let mut message = message.clone();
+                if let Some(request) = message.get("request") {
+                    let request = normalize_fields(request, namespace);
+                    message.insert("request".into(), request);
+                }
+                for key in ["response", "errors"] {
+                    if let Some(schema) = message.get(key) {
+                        let schema = normalize_schema(schema, namespace);
+                        message.insert(key.into(), schema);
+                    }
+                }
+                (name.clone(), sorted(message))
+            })
+            .collect();
+        protocol.insert("messages".into(), sorted(messages));
+    }
+    sorted(protocol)
+}
+
+// Where `found` differs from `expected`, as JSON pointers
+fn differences(expected: &Value, found: &Value, path: &str, out: &mut Vec<String>) {
+    match (expected, found) {
+        (Value::Object(expected), Value::Object(found)) => {
+            for (key, value) in expected {
+                let path = format!("{path}/{key}");
+                match found.get(key) {
+                    Some(found) => differences(value, found, &path, out),
+                    None => out.push(format!("{path}: missing, expected {value}")),
+                }
+            }
+            for (key, value) in found {
+                if !expected.contains_key(key) {
+                    out.push(format!("{path}/{key}: unexpected {value}"));
+                }
+            }
+        }
+        (Value::Array(expected), Value::Array(found)) => {
+            for (i, value) in expected.iter().enumerate() {
+                let path = format!("{path}/{i}");
+                match found.get(i) {
+                    Some(found) => differences(value, found, &path, out),
+                    None => out.push(format!("{path}: missing, expected {value}")),
+                }
+            }
+            for (i, value) in found.iter().enumerate().skip(expected.len()) {
+                out.push(format!("{path}/{i}: unexpected {value}"));
+            }
+        }
+        (expected, found) if expected != found => {
+            let path = if path.is_empty() { "/" } else { path };
+            out.push(format!("{path}: expected {expected}, found {found}"));
+        }
+        _ => {}
+    }
+}
+
+// How the output for a fixture differs from the reference one
+fn check(avdl: &Path) -> Vec<String> {
+    let input = fs::read_to_string(avdl).unwrap();
+    let avpr = avdl.with_extension("avpr");
+    let avsc = avdl.with_extension("avsc");
+    let mut out = Vec::new();
+    match (parse_idl_file(&input), avsc.exists()) {
+        (Err(err), _) => out.push(format!("failed to parse: {err}")),
+        (Ok(IdlFile::Protocol(protocol)), false) => {
+            assert!(avpr.exists(), "{} has no reference output", avdl.display());
+            let found = match protocol.to_avpr_json() {
+                Ok(found) => found,
+                Err(err) => return vec![format!("failed to write: {err}")],
+            };
+            differences(
+                &normalize_protocol(&read_json(&avpr)),
+                &normalize_protocol(&found),
+                "",
+                &mut out,
+            );
+        }
+        (Ok(IdlFile::Protocol(protocol)), true) => out.push(format!(
+            "expected a schema, parsed protocol {}",
+            protocol.name
+        )),
+        (Ok(IdlFile::Schema(file)), true) => {
+            let Some(schema) = file.schema else {
+                return vec!["expected a schema statement".to_string()];
+            };
+            let schema = expand_refs(&schema, &registry(&file.types));
+            let found = serde_json::to_value(Avsc(&schema)).unwrap();
+            differences(
+                &normalize_schema(&read_json(&avsc), None),
+                &normalize_schema(&found, None),
+                "",
+                &mut out,
+            );
+        }
+        (Ok(IdlFile::Schema(_)), false) => {
+            out.push("expected a protocol, parsed a schema file".to_string())
+        }
+    }
+    out
+}
+
+#[test]
+fn test_conformance() {
+    let fixtures = fixtures();
+    let names: Vec<String> = fixtures
+        .iter()
+        .map(|avdl| avdl.file_stem().unwrap().to_string_lossy().into_owned())
+        .collect();
+    for (name, _) in EXPECTED_FAILURES {
+        assert!(names.iter().any(|n| n == name), "no fixture named {name}");
+    }
+
+    let mut unexpected = Vec::new();
+    for (avdl, name) in fixtures.iter().zip(&names) {
+        let expected = EXPECTED_FAILURES.iter().find(|(n, _)| n == name);
+        let differences = check(avdl);
+        match (differences.is_empty(), expected) {
+            (true, None) => println!("{name} ... ok"),
+            (true, Some(_)) => {
+                println!("{name} ... passed, remove it from the expected failures");
+                unexpected.push(name.as_str());
+            }
+            (false, Some((_, reason))) => println!("{name} ... expected failure, {reason}"),
+            (false, None) => {
+                println!("{name} ... FAILED");
+                unexpected.push(name.as_str());
+            }
+        }
+        for difference in &differences {
+            println!("    {difference}");
+        }
+    }
+    assert!(
+        unexpected.is_empty(),
+        "unexpected conformance results for {}",
+        unexpected.join(", ")
+    );
+}
+
+#[test]
+fn test_normalize_namespaces() {
+    let written = serde_json::json!({
+        "protocol": "P",
+        "namespace": "a",
+        "types": [
+            {"type": "record", "name": "R", "namespace": "a", "aliases": ["Old"], "fields": [
+                {"name": "f", "type": {"type": "enum", "name": "b.E", "symbols": ["X"]}},
+                {"name": "g", "type": ["null", "E"]},
+            ]},
+        ],
+        "messages": {"m": {"request": [{"name": "r", "type": "R"}], "response": "a.R"}},
+    });
+    let expected = serde_json::json!({
+        "protocol": "P",
+        "namespace": "a",
+        "types": [
+            {"type": "record", "name": "a.R", "aliases": ["a.Old"], "fields": [
+                {"name": "f", "type": {"type": "enum", "name": "b.E", "symbols": ["X"]}},
+                {"name": "g", "type": ["null", "a.E"]},
+            ]},
+        ],
+        "messages": {"m": {"request": [{"name": "r", "type": "a.R"}], "response": "a.R"}},
+    });
+    assert_eq!(normalize_protocol(&written), expected);
+}
@@ -0,0 +1,81 @@
+//! Memory used by `parse_events`, measured with a global allocator counting
+//! the bytes in use. It is in a test binary of its own so the allocator
+//! doesn't replace the one of the other tests.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::ops::ControlFlow;
+
+use avdl_parser::events::{parse_events, ParseEvent};
+use avdl_parser::parse;
+
+// Counts the bytes allocated by the current thread, so tests running
+// alongside don't get in the way
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATED: Cell<usize> = const { Cell::new(0) };
+    static PEAK: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATED.try_with(|allocated| {
+            let total = allocated.get() + layout.size();
+            allocated.set(total);
+            let _ = PEAK.try_with(|peak| peak.set(peak.get().max(total)));
+        });
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let _ = ALLOCATED
+            .try_with(|allocated| allocated.set(allocated.get().saturating_sub(layout.size())));
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+// Bytes allocated at most while running `f`, on top of what was already
+fn peak_allocation(f: impl FnOnce()) -> usize {
+    let before = ALLOCATED.with(Cell::get);
+    PEAK.with(|peak| peak.set(before));
+    f();
+    PEAK.with(Cell::get) - before
+}
+
+fn large_protocol(records: usize) -> String {
+    let mut input = String::from("protocol Large {\n");
+    for i in 0..records {
+        input.push_str(&format!(
+            "    record Record{i} {{ string name; int age; long id; boolean active; }}\n"
+        ));
+    }
+    input.push('}');
+    input
+}
+
+#[test]
+fn test_parse_events_counts_fields_in_bounded_memory() {
+    let input = large_protocol(10_000);
+    let mut fields = 0;
+    let peak = peak_allocation(|| {
+        parse_events(&input, &mut |event| {
+            if let ParseEvent::Field(_) = event {
+                fields += 1;
+            }
+            ControlFlow::Continue(())
+        })
+        .unwrap();
+    });
+    assert_eq!(fields, 40_000);
+    assert!(peak < 64 * 1024, "peak allocation was {peak} bytes");
+
+    // Keeping the whole tree takes more than the input itself
+    let peak = peak_allocation(|| {
+        parse(&input).unwrap();
+    });
+    assert!(peak > input.len(), "peak allocation was {peak} bytes");
+}
@@ -0,0 +1,28 @@
+//! Inputs that crashed the fuzz target, kept in
+//! `fuzz/regressions/fuzz_parse_protocol`. Each goes through what the target
+//! does, which must return instead of panicking or overflowing the stack.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use avdl_parser::parser::parse_protocol;
+use avdl_parser::{parse_str, Warnings};
+
+#[test]
+fn test_fuzz_regressions() {
+    let folder =
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("../../fuzz/regressions/fuzz_parse_protocol");
+    let mut inputs = 0;
+    for entry in fs::read_dir(&folder).unwrap() {
+        let path = entry.unwrap().path();
+        let data = fs::read(&path).unwrap();
+        let input = String::from_utf8_lossy(&data);
+        let mut names_ref = HashMap::new();
+        let mut warnings = Warnings::new();
+        let _ = parse_protocol(&input, &mut names_ref, &mut warnings);
+        let _ = parse_str(&input);
+        inputs += 1;
+    }
+    assert!(inputs > 0, "no inputs in {}", folder.display());
+}
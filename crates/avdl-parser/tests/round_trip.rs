@@ -0,0 +1,641 @@
+//! Properties of random protocols: writing one as IDL and parsing it back
+//! gives the same protocol, and each of its types is a valid `.avsc` schema.
+//!
+//! The generators only produce what the parser supports, with defaults that
+//! match their field. There is one strategy per kind of schema, and types
+//! refer to the ones declared before them by position, so they are generated
+//! independently of each other and a failure shrinks to a small protocol with
+//! the offending type in it.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use apache_avro::schema::{Name, RecordField, RecordFieldOrder, Schema, UnionSchema};
+use proptest::collection::{btree_map, vec};
+use proptest::prelude::*;
+use proptest::sample::Index;
+use serde_json::Value;
+
+use avdl_parser::avsc::to_avsc_string;
+use avdl_parser::builder::{EnumBuilder, FieldBuilder, FixedBuilder, RecordBuilder};
+use avdl_parser::canonical::canonical_form;
+use avdl_parser::expand::{expand_refs, registry};
+use avdl_parser::options::DEFAULT_MAX_DEPTH;
+use avdl_parser::validate::validate_defaults;
+use avdl_parser::visit::{SchemaVisitorMut, Walk};
+use avdl_parser::{parse_str, Message, Protocol};
+
+// Words the parser reads as something other than a name
+const KEYWORDS: [&str; 29] = [
+    "array",
+    "boolean",
+    "bytes",
+    "date",
+    "decimal",
+    "double",
+    "enum",
+    "error",
+    "false",
+    "fixed",
+    "float",
+    "idl",
+    "import",
+    "int",
+    "long",
+    "map",
+    "null",
+    "oneway",
+    "protocol",
+    "record",
+    "schema",
+    "string",
+    "throws",
+    "time_ms",
+    "timestamp_ms",
+    "true",
+    "union",
+    "uuid",
+    "void",
+];
+
+fn name() -> impl Strategy<Value = String> {
+    "[A-Za-z_][A-Za-z0-9_]{0,7}".prop_filter("keywords are not names", |name| {
+        !KEYWORDS.contains(&name.as_str())
+    })
+}
+
+fn namespace() -> impl Strategy<Value = String> {
+    vec(name(), 1..3).prop_map(|parts| parts.join("."))
+}
+
+// Docs are trimmed when parsed
+fn doc() -> impl Strategy<Value = Option<String>> {
+    proptest::option::of("[A-Za-z0-9,.'\"(){}@*/\\\\-]+( [A-Za-z0-9,.'\"(){}@*/\\\\-]+){0,4}")
+}
+
+// Keeps the first of the items with the same key
+fn unique_by<T, K: Ord>(items: Vec<T>, key: impl Fn(&T) -> K) -> Vec<T> {
+    let mut seen = BTreeSet::new();
+    items
+        .into_iter()
+        .filter(|item| seen.insert(key(item)))
+        .collect()
+}
+
+/// A type as generated, before the references are resolved.
+#[derive(Debug, Clone)]
+enum Type {
+    Schema(Schema),
+    Array(Box<Type>),
+    Map(Box<Type>),
+    Union(Vec<Type>),
+    /// One of the named types declared before.
+    Ref(Index),
+}
+
+fn primitive() -> impl Strategy<Value = Schema> {
+    prop_oneof![
+        Just(Schema::Null),
+        Just(Schema::Boolean),
+        Just(Schema::Int),
+        Just(Schema::Long),
+        Just(Schema::Float),
+        Just(Schema::Double),
+        Just(Schema::Bytes),
+        Just(Schema::String),
+    ]
+}
+
+// Logical types with a keyword of their own
+fn logical() -> impl Strategy<Value = Schema> {
+    prop_oneof![
+        Just(Schema::Uuid),
+        Just(Schema::Date),
+        Just(Schema::TimeMillis),
+        Just(Schema::TimestampMillis),
+        (1..39usize)
+            .prop_flat_map(|precision| (Just(precision), 0..=precision))
+            .prop_map(|(precision, scale)| Schema::Decimal {
+                precision,
+                scale,
+                inner: Box::new(Schema::Bytes),
+            }),
+    ]
+}
+
+// Logical types written with `@logicalType`
+fn annotated() -> impl Strategy<Value = Schema> {
+    prop_oneof![
+        Just(Schema::TimeMicros),
+        Just(Schema::TimestampMicros),
+        Just(Schema::LocalTimestampMillis),
+        Just(Schema::LocalTimestampMicros),
+        Just(Schema::Duration),
+    ]
+}
+
+// Types of fields, message parameters and responses, and of what is inside
+// arrays, maps and unions
+fn value_type() -> impl Strategy<Value = Type> {
+    let leaf = prop_oneof![
+        3 => primitive().prop_map(Type::Schema),
+        1 => logical().prop_map(Type::Schema),
+        1 => annotated().prop_map(Type::Schema),
+        1 => any::<Index>().prop_map(Type::Ref),
+    ];
+    leaf.prop_recursive(3, 16, 3, |inner| {
+        prop_oneof![
+            inner.clone().prop_map(|items| Type::Array(Box::new(items))),
+            inner.clone().prop_map(|values| Type::Map(Box::new(values))),
+            vec(inner, 1..4).prop_map(Type::Union),
+        ]
+    })
+}
+
+// A reference when nothing is declared yet is left out of unions, and is
+// `null` anywhere else
+fn resolve(ty: &Type, declared: &[Schema]) -> Schema {
+    match ty {
+        Type::Schema(schema) => schema.clone(),
+        Type::Array(items) => Schema::Array(Box::new(resolve(items, declared))),
+        Type::Map(values) => Schema::Map(Box::new(resolve(values, declared))),
+        Type::Union(variants) => {
+            let variants: Vec<Schema> = variants
+                .iter()
+                .filter(|variant| !matches!(variant, Type::Ref(_)) || !declared.is_empty())
+                .map(|variant| resolve(variant, declared))
+                .filter(|variant| !matches!(variant, Schema::Union(_)))
+                .collect();
+            // Unions hold at most one branch of each type
+            let variants = unique_by(variants, |variant| branch_kind(variant, declared));
+            match UnionSchema::new(variants) {
+                Ok(union) if !union.variants().is_empty() => Schema::Union(union),
+                _ => Schema::Null,
+            }
+        }
+        Type::Ref(_) if declared.is_empty() => Schema::Null,
+        Type::Ref(index) => Schema::Ref {
+            name: declared_name(&declared[index.index(declared.len())]).clone(),
+        },
+    }
+}
+
+// Decimals stored in a fixed are named after it
+fn declared_name(schema: &Schema) -> &Name {
+    match schema {
+        Schema::Decimal { inner, .. } => declared_name(inner),
+        schema => schema.name().unwrap(),
+    }
+}
+
+// A union holds a single decimal, so the ones stored in a fixed are taken
+// for bytes like the others
+fn branch_kind(schema: &Schema, declared: &[Schema]) -> String {
+    match schema {
+        Schema::Ref { name } => {
+            match declared.iter().find(|schema| declared_name(schema) == name) {
+                Some(Schema::Decimal { .. }) => canonical_form(&Schema::Bytes),
+                _ => "named".to_string(),
+            }
+        }
+        schema => canonical_form(schema),
+    }
+}
+
+fn order() -> impl Strategy<Value = RecordFieldOrder> {
+    prop_oneof![
+        4 => Just(RecordFieldOrder::Ascending),
+        1 => Just(RecordFieldOrder::Descending),
+        1 => Just(RecordFieldOrder::Ignore),
+    ]
+}
+
+#[derive(Debug, Clone)]
+struct Field {
+    name: String,
+    ty: Type,
+    doc: Option<String>,
+    order: RecordFieldOrder,
+    aliases: Vec<String>,
+}
+
+fn field() -> impl Strategy<Value = Field> {
+    (name(), value_type(), doc(), order(), vec(name(), 0..2)).prop_map(
+        |(name, ty, doc, order, aliases)| Field {
+            name,
+            ty,
+            doc,
+            order,
+            aliases: unique_by(aliases, String::clone),
+        },
+    )
+}
+
+/// A named type as generated, before it is given a name.
+#[derive(Debug, Clone)]
+enum Declaration {
+    Record {
+        doc: Option<String>,
+        fields: Vec<Field>,
+    },
+    Enum {
+        doc: Option<String>,
+        symbols: Vec<String>,
+        default: Option<Index>,
+    },
+    Fixed {
+        size: usize,
+    },
+    Decimal {
+        precision: usize,
+        scale: usize,
+        size: usize,
+    },
+}
+
+fn record() -> impl Strategy<Value = Declaration> {
+    (doc(), vec(field(), 1..5)).prop_map(|(doc, fields)| Declaration::Record {
+        doc,
+        fields: unique_by(fields, |field| field.name.clone()),
+    })
+}
+
+fn enumeration() -> impl Strategy<Value = Declaration> {
+    (
+        doc(),
+        vec(name(), 1..5),
+        proptest::option::of(any::<Index>()),
+    )
+        .prop_map(|(doc, symbols, default)| Declaration::Enum {
+            doc,
+            symbols: unique_by(symbols, String::clone),
+            default,
+        })
+}
+
+fn fixed() -> impl Strategy<Value = Declaration> {
+    (0..64usize).prop_map(|size| Declaration::Fixed { size })
+}
+
+// A fixed of n bytes holds decimals of at least 2n digits
+fn decimal() -> impl Strategy<Value = Declaration> {
+    (1..17usize)
+        .prop_flat_map(|size| (Just(size), 1..=2 * size))
+        .prop_flat_map(|(size, precision)| (Just(size), Just(precision), 0..=precision))
+        .prop_map(|(size, precision, scale)| Declaration::Decimal {
+            precision,
+            scale,
+            size,
+        })
+}
+
+fn declaration() -> impl Strategy<Value = Declaration> {
+    prop_oneof![2 => record(), 1 => enumeration(), 1 => fixed(), 1 => decimal()]
+}
+
+fn declare(name: &str, declaration: &Declaration, declared: &[Schema]) -> Schema {
+    match declaration {
+        Declaration::Record { doc, fields } => {
+            let mut record = RecordBuilder::new(name);
+            if let Some(doc) = doc {
+                record = record.doc(doc);
+            }
+            for field in fields {
+                let mut builder = FieldBuilder::new(&field.name, resolve(&field.ty, declared))
+                    .order(field.order.clone());
+                if let Some(doc) = &field.doc {
+                    builder = builder.doc(doc);
+                }
+                for alias in &field.aliases {
+                    builder = builder.alias(alias);
+                }
+                record = record.field(builder);
+            }
+            record.build().unwrap()
+        }
+        Declaration::Enum {
+            doc,
+            symbols,
+            default,
+        } => {
+            let mut enumeration = EnumBuilder::new(name).symbols(symbols);
+            if let Some(doc) = doc {
+                enumeration = enumeration.doc(doc);
+            }
+            if let Some(default) = default {
+                let symbol = default.get(symbols);
+                enumeration = enumeration.attribute("default", Value::from(symbol.as_str()));
+            }
+            enumeration.build().unwrap()
+        }
+        Declaration::Fixed { size } => FixedBuilder::new(name, *size).build().unwrap(),
+        Declaration::Decimal {
+            precision,
+            scale,
+            size,
+        } => Schema::Decimal {
+            precision: *precision,
+            scale: *scale,
+            inner: Box::new(FixedBuilder::new(name, *size).build().unwrap()),
+        },
+    }
+}
+
+/// A message as generated, before the references are resolved.
+#[derive(Debug, Clone)]
+struct Signature {
+    name: String,
+    doc: Option<String>,
+    request: Vec<(String, Option<String>, Type)>,
+    response: Type,
+    one_way: bool,
+}
+
+fn signature() -> impl Strategy<Value = Signature> {
+    (
+        name(),
+        doc(),
+        vec((name(), doc(), value_type()), 0..3),
+        value_type(),
+        any::<bool>(),
+    )
+        .prop_map(|(name, doc, request, response, one_way)| Signature {
+            name,
+            doc,
+            request: unique_by(request, |(name, _, _)| name.clone()),
+            response,
+            one_way,
+        })
+}
+
+fn message(signature: &Signature, types: &[Schema]) -> Message {
+    let response = resolve(&signature.response, types);
+    Message {
+        name: signature.name.clone(),
+        doc: signature.doc.clone(),
+        request: signature
+            .request
+            .iter()
+            .enumerate()
+            .map(|(position, (name, doc, ty))| RecordField {
+                name: name.clone(),
+                doc: doc.clone(),
+                default: None,
+                schema: resolve(ty, types),
+                order: RecordFieldOrder::Ascending,
+                aliases: None,
+                position,
+                custom_attributes: BTreeMap::new(),
+            })
+            .collect(),
+        // Only messages without a response can be one-way
+        one_way: signature.one_way && response == Schema::Null,
+        response,
+        errors: Vec::new(),
+    }
+}
+
+// Like in the parser, the types of the protocol are in its namespace without
+// naming it
+fn protocol_without_defaults() -> impl Strategy<Value = Protocol> {
+    (
+        name(),
+        proptest::option::of(namespace()),
+        doc(),
+        vec((name(), declaration()), 0..5),
+        vec(signature(), 0..3),
+        btree_map(property_key(), json(), 0..3),
+    )
+        .prop_map(
+            |(name, namespace, doc, declarations, signatures, properties)| {
+                let mut types = Vec::new();
+                for (name, declaration) in unique_by(declarations, |(name, _)| name.clone()) {
+                    let schema = declare(&name, &declaration, &types);
+                    types.push(schema);
+                }
+                let messages = unique_by(signatures, |signature| signature.name.clone())
+                    .iter()
+                    .map(|signature| message(signature, &types))
+                    .collect();
+                Protocol {
+                    name,
+                    namespace,
+                    doc,
+                    aliases: Vec::new(),
+                    imports: Vec::new(),
+                    types,
+                    messages,
+                    properties,
+                }
+            },
+        )
+}
+
+fn property_key() -> impl Strategy<Value = String> {
+    "[a-z][a-zA-Z0-9_.-]{0,8}".prop_filter("not an annotation of Avro", |key| {
+        !matches!(
+            key.as_str(),
+            "namespace" | "aliases" | "order" | "logicalType"
+        )
+    })
+}
+
+// The value of a custom annotation
+fn json() -> impl Strategy<Value = Value> {
+    let leaf = prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::from),
+        any::<i64>().prop_map(Value::from),
+        double().prop_map(Value::from),
+        text().prop_map(Value::from),
+    ];
+    leaf.prop_recursive(3, 12, 3, |inner| {
+        prop_oneof![
+            vec(inner.clone(), 0..3).prop_map(Value::from),
+            btree_map(text(), inner, 0..3)
+                .prop_map(|entries| Value::Object(entries.into_iter().collect())),
+        ]
+    })
+}
+
+fn text() -> impl Strategy<Value = String> {
+    any::<String>()
+}
+
+fn float() -> impl Strategy<Value = f64> {
+    use proptest::num::f32;
+    (f32::POSITIVE | f32::NEGATIVE | f32::NORMAL | f32::SUBNORMAL | f32::ZERO).prop_map(f64::from)
+}
+
+fn double() -> impl Strategy<Value = f64> {
+    use proptest::num::f64;
+    f64::POSITIVE | f64::NEGATIVE | f64::NORMAL | f64::SUBNORMAL | f64::ZERO
+}
+
+// A default for a field of type `schema`, as the parser stores it, if IDL
+// has a way to write one
+fn default(schema: &Schema, types: &[Schema]) -> Option<BoxedStrategy<Value>> {
+    let default = match schema {
+        Schema::Null => Just(Value::Null).boxed(),
+        Schema::Boolean => any::<bool>().prop_map(Value::from).boxed(),
+        Schema::Int | Schema::Date | Schema::TimeMillis => {
+            any::<i32>().prop_map(Value::from).boxed()
+        }
+        Schema::Long
+        | Schema::TimeMicros
+        | Schema::TimestampMillis
+        | Schema::TimestampMicros
+        | Schema::LocalTimestampMillis
+        | Schema::LocalTimestampMicros => any::<i64>().prop_map(Value::from).boxed(),
+        Schema::Float => float().prop_map(Value::from).boxed(),
+        Schema::Double => double().prop_map(Value::from).boxed(),
+        // The parser keeps the bytes of the string
+        Schema::Bytes => text()
+            .prop_map(|text| Value::from(text.into_bytes()))
+            .boxed(),
+        Schema::String => text().prop_map(Value::from).boxed(),
+        Schema::Duration => any::<[u8; 12]>()
+            .prop_map(|bytes| Value::from(bytes.to_vec()))
+            .boxed(),
+        Schema::Decimal {
+            precision, inner, ..
+        } if **inner == Schema::Bytes => {
+            let max = 10i128.pow(*precision as u32) - 1;
+            (-max..=max)
+                .prop_map(|unscaled| Value::from(unscaled_bytes(unscaled)))
+                .boxed()
+        }
+        Schema::Uuid => any::<u128>()
+            .prop_map(|uuid| Value::from(uuid::Uuid::from_u128(uuid).to_string()))
+            .boxed(),
+        Schema::Array(items) => vec(default(items, types)?, 0..3)
+            .prop_map(Value::from)
+            .boxed(),
+        Schema::Map(values) => btree_map(text(), default(values, types)?, 0..3)
+            .prop_map(|entries| Value::Object(entries.into_iter().collect()))
+            .boxed(),
+        Schema::Union(union) => default(union.variants().first()?, types)?,
+        // Enums take one of their symbols, other named types no default
+        Schema::Ref { name } => match types.iter().find(|schema| declared_name(schema) == name)? {
+            Schema::Enum { symbols, .. } => proptest::sample::select(symbols.clone())
+                .prop_map(Value::from)
+                .boxed(),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(default)
+}
+
+// The two's complement big-endian bytes of `value`, without the leading
+// bytes only repeating the sign
+fn unscaled_bytes(value: i128) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let skip = bytes
+        .windows(2)
+        .take_while(|pair| matches!(pair, [0x00, 0x00..=0x7f] | [0xff, 0x80..=0xff]))
+        .count();
+    bytes[skip..].to_vec()
+}
+
+// The defaults are generated once the types of the fields and parameters are
+// known, in a single step so the protocol still shrinks on its own
+fn protocol() -> impl Strategy<Value = Protocol> {
+    protocol_without_defaults().prop_flat_map(|protocol| {
+        let defaults: Vec<BoxedStrategy<Option<Value>>> = protocol
+            .types
+            .iter()
+            .flat_map(|schema| match schema {
+                Schema::Record { fields, .. } => fields.as_slice(),
+                _ => &[],
+            })
+            .chain(
+                protocol
+                    .messages
+                    .iter()
+                    .flat_map(|message| &message.request),
+            )
+            .map(|field| match default(&field.schema, &protocol.types) {
+                Some(default) => proptest::option::of(default).boxed(),
+                None => Just(None).boxed(),
+            })
+            .collect();
+        (Just(protocol), defaults).prop_map(|(mut protocol, defaults)| {
+            let fields = protocol
+                .types
+                .iter_mut()
+                .flat_map(|schema| match schema {
+                    Schema::Record { fields, .. } => fields.as_mut_slice(),
+                    _ => &mut [],
+                })
+                .chain(
+                    protocol
+                        .messages
+                        .iter_mut()
+                        .flat_map(|message| &mut message.request),
+                );
+            for (field, default) in fields.zip(defaults) {
+                field.default = default;
+            }
+            protocol
+        })
+    })
+}
+
+// Puts the names without a namespace in the given one
+struct InNamespace(String);
+
+impl SchemaVisitorMut for InNamespace {
+    fn enter(&mut self, schema: &mut Schema) {
+        if let Schema::Record { name, .. }
+        | Schema::Enum { name, .. }
+        | Schema::Fixed { name, .. }
+        | Schema::Ref { name } = schema
+        {
+            name.namespace.get_or_insert_with(|| self.0.clone());
+        }
+    }
+}
+
+// Canonical forms of the types of `protocol`. The parser qualifies the names
+// of records only and replaces some references with their type, so names are
+// qualified and references expanded first.
+fn canonical_forms(protocol: &Protocol) -> Vec<String> {
+    let mut types = protocol.types.clone();
+    if let Some(namespace) = &protocol.namespace {
+        for schema in &mut types {
+            schema.walk_mut(&mut InNamespace(namespace.clone()));
+        }
+    }
+    let registry = registry(&types);
+    types
+        .iter()
+        .map(|schema| canonical_form(&expand_refs(schema, &registry)))
+        .collect()
+}
+
+proptest! {
+    #[test]
+    fn idl_round_trip(protocol in protocol()) {
+        prop_assert!(validate_defaults(&protocol.types, DEFAULT_MAX_DEPTH).is_empty());
+        let written = protocol.to_idl().unwrap();
+        let parsed = parse_str(&written).map_err(|err| {
+            TestCaseError::fail(format!("{err}\n{written}"))
+        })?;
+        prop_assert_eq!(canonical_forms(&parsed), canonical_forms(&protocol));
+        prop_assert_eq!(&parsed.properties, &protocol.properties);
+        // Messages, docs, defaults and everything else left out of the
+        // canonical form are written the same way again
+        prop_assert_eq!(parsed.to_idl().unwrap(), written);
+    }
+
+    #[test]
+    fn types_are_valid_avsc(protocol in protocol()) {
+        let registry = registry(&protocol.types);
+        for schema in &protocol.types {
+            let avsc = to_avsc_string(&expand_refs(schema, &registry)).unwrap();
+            if let Err(err) = Schema::parse_str(&avsc) {
+                return Err(TestCaseError::fail(format!("{err}\n{avsc}")));
+            }
+        }
+    }
+}
@@ -0,0 +1,85 @@
+//! Python bindings, built into the `avdl` module with `maturin build` (or
+//! `maturin develop`) from this folder.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use avdl_parser::error::SpannedError;
+use avdl_parser::output::{plan_outputs, FileNaming};
+use avdl_parser::{parse_spanned, AvdlError, ParserOptions, Protocol};
+use pyo3::create_exception;
+use pyo3::exceptions::{PyOSError, PyValueError};
+use pyo3::prelude::*;
+
+create_exception!(
+    avdl,
+    IdlError,
+    PyValueError,
+    "Invalid AVDL, with the `line` and `column` it was found at, None when unknown."
+);
+
+// Parses `input`, read from `path` if given, raising an `IdlError` on failure
+fn parse(py: Python<'_>, input: &str, path: Option<&Path>) -> PyResult<Protocol> {
+    let SpannedError { error, span } = match parse_spanned(input, &ParserOptions::default()) {
+        Ok((protocol, _warnings, _spans)) => return Ok(protocol),
+        Err(err) => err,
+    };
+    let location = match error.as_ref() {
+        AvdlError::Parse { location, .. } => Some(location.clone()),
+        _ => span.map(|span| span.location(input)),
+    };
+    let error = match path {
+        Some(path) => error.with_file(path),
+        None => *error,
+    };
+    let err = IdlError::new_err(error.to_string());
+    let value = err.value(py);
+    value.setattr("line", location.as_ref().map(|location| location.line))?;
+    value.setattr("column", location.as_ref().map(|location| location.column))?;
+    Err(err)
+}
+
+/// Parse the AVDL protocol in `text`, returning its named types as dicts
+/// shaped like their AVSC schemas.
+#[pyfunction]
+fn parse_idl(py: Python<'_>, text: &str) -> PyResult<Vec<PyObject>> {
+    let protocol = parse(py, text, None)?;
+    let json = py.import("json")?;
+    protocol
+        .into_schemas()
+        .iter()
+        .map(|schema| {
+            let schema = serde_json::to_string(schema).expect("a schema is valid JSON");
+            Ok(json.call_method1("loads", (schema,))?.unbind())
+        })
+        .collect()
+}
+
+/// Write the named types of the AVDL file at `path` as AVSC files in `out_dir`,
+/// like `avrokit convert schema` does, returning the paths written.
+#[pyfunction]
+fn idl_to_avsc_files(py: Python<'_>, path: PathBuf, out_dir: PathBuf) -> PyResult<Vec<PathBuf>> {
+    let input = fs::read_to_string(&path).map_err(|err| PyOSError::new_err(err.to_string()))?;
+    let protocol = parse(py, &input, Some(&path))?;
+    let outputs = plan_outputs(&protocol.types, &FileNaming::Name, &protocol.name, false)
+        .map_err(|err| IdlError::new_err(err.to_string()))?;
+    let mut written = Vec::new();
+    for (file, schema) in outputs {
+        let file = out_dir.join(file);
+        if let Some(folder) = file.parent() {
+            fs::create_dir_all(folder).map_err(|err| PyOSError::new_err(err.to_string()))?;
+        }
+        let json = serde_json::to_string_pretty(schema).expect("a schema is valid JSON");
+        fs::write(&file, json).map_err(|err| PyOSError::new_err(err.to_string()))?;
+        written.push(file);
+    }
+    Ok(written)
+}
+
+#[pymodule]
+pub fn avdl(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("IdlError", m.py().get_type::<IdlError>())?;
+    m.add_function(wrap_pyfunction!(parse_idl, m)?)?;
+    m.add_function(wrap_pyfunction!(idl_to_avsc_files, m)?)?;
+    Ok(())
+}
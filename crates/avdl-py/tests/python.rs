@@ -0,0 +1,30 @@
+//! Runs the tests of `test_avdl.py` against the module, embedding Python.
+
+use std::ffi::CString;
+
+use avdl::avdl as module;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+#[test]
+fn test_python_smoke() {
+    pyo3::append_to_inittab!(module);
+    Python::with_gil(|py| {
+        let globals = PyDict::new(py);
+        py.run(
+            &CString::new(include_str!("test_avdl.py")).unwrap(),
+            Some(&globals),
+            None,
+        )
+        .unwrap();
+        for (name, test) in globals.iter() {
+            let name: String = name.extract().unwrap();
+            if name.starts_with("test_") {
+                if let Err(err) = test.call0() {
+                    err.display(py);
+                    panic!("{name} failed");
+                }
+            }
+        }
+    });
+}
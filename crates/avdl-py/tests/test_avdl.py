@@ -0,0 +1,56 @@
+"""Smoke tests of the avdl module.
+
+Run with pytest once the module is installed with `maturin develop`, or with
+`cargo test -p avdl-py`.
+"""
+
+import json
+import os
+import tempfile
+
+import avdl
+
+IDL = """@namespace("org.example")
+protocol Shop {
+    record Order {
+        string id;
+        union { null, string } note = null;
+        timestamp_ms placed_at;
+    }
+}
+"""
+
+
+def test_parse_idl():
+    [order] = avdl.parse_idl(IDL)
+    assert order["name"] == "Order"
+    assert order["namespace"] == "org.example"
+    fields = {field["name"]: field for field in order["fields"]}
+    assert fields["note"]["type"] == ["null", "string"]
+    assert fields["note"]["default"] is None
+    assert fields["placed_at"]["type"] == {
+        "type": "long",
+        "logicalType": "timestamp-millis",
+    }
+
+
+def test_parse_idl_raises_with_location():
+    try:
+        avdl.parse_idl("protocol Shop {\n    record Order { string id }\n}")
+    except avdl.IdlError as err:
+        assert err.line == 2
+        assert err.column is not None
+        assert "expected ';'" in str(err)
+    else:
+        raise AssertionError("expected an IdlError")
+
+
+def test_idl_to_avsc_files():
+    with tempfile.TemporaryDirectory() as folder:
+        idl = os.path.join(folder, "shop.avdl")
+        with open(idl, "w") as f:
+            f.write(IDL)
+        written = avdl.idl_to_avsc_files(idl, os.path.join(folder, "out"))
+        assert [os.path.basename(path) for path in written] == ["Order.avsc"]
+        with open(written[0]) as f:
+            assert json.load(f)["name"] == "Order"
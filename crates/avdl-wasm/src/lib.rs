@@ -0,0 +1,48 @@
+//! Converting AVDL to AVSC schemas from the browser.
+//!
+//! Build with `wasm-pack build crates/avdl-wasm --target web`. Nothing here
+//! reads files or prints, imports are not followed.
+
+use apache_avro::schema::Schema;
+use avdl_parser::output::{plan_outputs, FileNaming};
+use avdl_parser::{parse_protocol_with_options, AvdlError, ParserOptions};
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+/// A named type of the protocol, written as an AVSC schema.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConvertedSchema {
+    /// Full name of the type.
+    pub name: String,
+    pub json: String,
+}
+
+/// Parse the protocol in `input` and write its records, enums and fixed
+/// types as AVSC schemas, like `avrokit convert schema` does.
+pub fn convert(input: &str) -> Result<Vec<ConvertedSchema>, AvdlError> {
+    let (protocol, _warnings) = parse_protocol_with_options(input, &ParserOptions::default())?;
+    // Checked like the files `avrokit convert schema` writes
+    let outputs = plan_outputs(&protocol.types, &FileNaming::Name, &protocol.name, false)?;
+    let converted = outputs
+        .into_iter()
+        .filter_map(|(_file, schema)| {
+            let name = match schema {
+                Schema::Decimal { inner, .. } => inner.name(),
+                schema => schema.name(),
+            }?;
+            Some(ConvertedSchema {
+                name: name.fullname(protocol.namespace.clone()),
+                json: serde_json::to_string_pretty(schema).expect("a schema is valid JSON"),
+            })
+        })
+        .collect();
+    Ok(converted)
+}
+
+/// [`convert`] for JavaScript: returns an array of `{ name, json }` objects,
+/// or throws the error message.
+#[wasm_bindgen]
+pub fn convert_idl_to_schemas(input: &str) -> Result<JsValue, JsValue> {
+    let converted = convert(input).map_err(|err| JsValue::from_str(&err.to_string()))?;
+    serde_wasm_bindgen::to_value(&converted).map_err(JsValue::from)
+}
@@ -0,0 +1,39 @@
+//! Run with `wasm-pack test --node crates/avdl-wasm`.
+#![cfg(target_arch = "wasm32")]
+
+use avdl_wasm::convert_idl_to_schemas;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+#[derive(serde::Deserialize)]
+struct Converted {
+    name: String,
+    json: String,
+}
+
+#[wasm_bindgen_test]
+fn test_convert_idl_to_schemas() {
+    let input = r#"@namespace("org.example")
+protocol Shop {
+    enum Kind { BOOK, FOOD }
+    record Item { string id; Kind kind; }
+    record Order { array<Item> items; }
+}"#;
+    let converted = convert_idl_to_schemas(input).unwrap();
+    let converted: Vec<Converted> = serde_wasm_bindgen::from_value(converted).unwrap();
+    let names: Vec<&str> = converted.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(
+        names,
+        vec!["org.example.Kind", "org.example.Item", "org.example.Order"]
+    );
+    let kind: serde_json::Value = serde_json::from_str(&converted[0].json).unwrap();
+    assert_eq!(kind["symbols"], serde_json::json!(["BOOK", "FOOD"]));
+    let item: serde_json::Value = serde_json::from_str(&converted[1].json).unwrap();
+    assert_eq!(item["fields"][1]["type"]["name"], "Kind");
+}
+
+#[wasm_bindgen_test]
+fn test_convert_idl_to_schemas_throws_errors() {
+    let err = convert_idl_to_schemas("protocol Shop { record Item { string id } }").unwrap_err();
+    let message = err.as_string().unwrap();
+    assert!(message.starts_with("error at 1:"), "{message}");
+}
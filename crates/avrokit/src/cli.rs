@@ -0,0 +1,98 @@
+//! The command line of avrokit, with a module per command holding its arguments and running it.
+
+pub mod check_compat;
+pub mod completions;
+pub mod convert;
+pub mod diff;
+pub mod doc;
+pub mod fingerprint;
+pub mod fix;
+pub mod fmt;
+pub mod generate;
+pub mod graph;
+pub mod lint;
+#[cfg(feature = "registry")]
+pub mod registry;
+pub mod stats;
+pub mod validate;
+pub mod validate_data;
+
+use clap::{Parser, Subcommand, ValueEnum};
+
+#[derive(Parser, Debug)]
+#[command(author, version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Commands,
+
+    /// Only print errors, and the outputs of the command
+    #[arg(long, short, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Also print what is being read and written
+    #[arg(long, short, global = true)]
+    pub verbose: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Commands {
+    /// Convert from AVDL to JSON AVSC schemas
+    #[command(arg_required_else_help = true)]
+    Convert(convert::ConvertArgs),
+    /// Generate documentation pages from an AVDL file
+    #[command(arg_required_else_help = true)]
+    Doc(doc::DocArgs),
+    /// Generate code for the types of an AVDL file
+    #[command(arg_required_else_help = true)]
+    Generate(generate::GenerateArgs),
+    /// Rewrite AVDL files with the layout `convert idl` writes
+    #[command(arg_required_else_help = true)]
+    Fmt(fmt::FmtArgs),
+    /// Rewrite an AVDL file fixing common mistakes, printing what changed
+    #[command(arg_required_else_help = true)]
+    Fix(fix::FixArgs),
+    /// Write the dependencies between AVDL files or their named types as a Graphviz DOT graph
+    #[command(arg_required_else_help = true)]
+    Graph(graph::GraphArgs),
+    /// Print the fingerprint of the Parsing Canonical Form of each type of an AVDL file
+    #[command(arg_required_else_help = true)]
+    Fingerprint(fingerprint::FingerprintArgs),
+    /// Check that AVDL files parse and pass validation, without writing anything
+    #[command(arg_required_else_help = true)]
+    Validate(validate::ValidateArgs),
+    /// Print numbers summarizing the types of AVDL files: how many of each kind, fields without
+    /// doc, unions without null first, the deepest nesting and the most referenced types
+    #[command(arg_required_else_help = true)]
+    Stats(stats::StatsArgs),
+    /// Check that AVDL files follow the naming and documentation conventions, exiting with an
+    /// error when one doesn't
+    #[command(arg_required_else_help = true)]
+    Lint(lint::LintArgs),
+    /// Check that the values of a JSON, newline delimited JSON or .avro file are values of a
+    /// record of an AVDL file
+    #[command(arg_required_else_help = true)]
+    ValidateData(validate_data::ValidateDataArgs),
+    /// Publish the records of an AVDL file to a Confluent compatible schema registry, or check
+    /// that they are compatible with the versions it has
+    #[cfg(feature = "registry")]
+    #[command(subcommand)]
+    Registry(registry::RegistryCommand),
+    /// Print the changes to the types between two versions of an AVDL file
+    #[command(arg_required_else_help = true)]
+    Diff(diff::DiffArgs),
+    /// Check that data written with the types of one AVDL file can be read with the other,
+    /// exiting with an error when it can't
+    #[command(arg_required_else_help = true)]
+    CheckCompat(check_compat::CheckCompatArgs),
+    /// Print the completion script of a shell
+    #[command(arg_required_else_help = true)]
+    Completions(completions::CompletionsArgs),
+}
+
+#[derive(Debug, Clone, PartialEq, ValueEnum)]
+pub enum ReportFormat {
+    /// Lines of text, meant to be read
+    Text,
+    /// A JSON array, meant for tools
+    Json,
+}
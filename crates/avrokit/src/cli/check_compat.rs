@@ -0,0 +1,68 @@
+//! `avrokit check-compat`, checking that data written with the types of one AVDL file can be read
+//! with the other.
+
+use std::path::PathBuf;
+
+use avdl_parser::compat::check_reader_writer;
+use avdl_parser::expand::{expand_refs, registry};
+use avdl_parser::ParserOptions;
+use clap::ValueEnum;
+
+use crate::{read_and_parse, Invalid};
+
+#[derive(Debug, clap::Args)]
+pub struct CheckCompatArgs {
+    /// Path to the previous AVDL file
+    #[arg(required = true)]
+    pub old: PathBuf,
+
+    /// Path to the new AVDL file
+    #[arg(required = true)]
+    pub new: PathBuf,
+
+    /// Which direction data has to be readable in
+    #[arg(long, value_enum, default_value = "backward")]
+    pub mode: CompatMode,
+}
+
+#[derive(Debug, Clone, PartialEq, ValueEnum)]
+pub enum CompatMode {
+    /// The new types read data written with the old ones
+    Backward,
+    /// The old types read data written with the new ones
+    Forward,
+    /// Both backward and forward
+    Full,
+}
+
+pub fn run(args: CheckCompatArgs) -> anyhow::Result<()> {
+    let old = read_and_parse(&args.old, &ParserOptions::default())?;
+    let new = read_and_parse(&args.new, &ParserOptions::default())?;
+    let (old_registry, new_registry) = (registry(&old.types), registry(&new.types));
+    let mut compatible = true;
+    // Types are matched by full name, the ones in only one file are not checked
+    for old_schema in &old.types {
+        let Some(name) = old_schema.name() else { continue };
+        let fullname = name.fullname(old.namespace.clone());
+        let Some(new_schema) = new.types.iter().find(|schema| {
+            schema.name().is_some_and(|other| other.fullname(new.namespace.clone()) == fullname)
+        }) else { continue };
+        let old_schema = expand_refs(old_schema, &old_registry);
+        let new_schema = expand_refs(new_schema, &new_registry);
+        let mut found = Vec::new();
+        if args.mode != CompatMode::Forward {
+            found.extend(check_reader_writer(&new_schema, &old_schema).into_iter().map(|found| ("backward", found)));
+        }
+        if args.mode != CompatMode::Backward {
+            found.extend(check_reader_writer(&old_schema, &new_schema).into_iter().map(|found| ("forward", found)));
+        }
+        for (direction, incompatibility) in found {
+            compatible = false;
+            println!("{direction}: {incompatibility}");
+        }
+    }
+    if !compatible {
+        return Err(Invalid(format!("{} is not compatible with {}", args.new.display(), args.old.display())).into());
+    }
+    Ok(())
+}
@@ -0,0 +1,19 @@
+//! `avrokit completions`, printing the completion script of a shell.
+
+use std::io;
+
+use clap::CommandFactory;
+use clap_complete::Shell;
+
+use crate::cli::Cli;
+
+#[derive(Debug, clap::Args)]
+pub struct CompletionsArgs {
+    #[arg(value_enum)]
+    pub shell: Shell,
+}
+
+pub fn run(args: CompletionsArgs) -> anyhow::Result<()> {
+    clap_complete::generate(args.shell, &mut Cli::command(), "avrokit", &mut io::stdout());
+    Ok(())
+}
@@ -0,0 +1,612 @@
+//! `avrokit convert`, writing the schemas or the protocol of AVDL files as JSON, or AVDL from JSON.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
+use std::fs;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use anyhow::Context;
+use apache_avro::schema::Schema;
+use avdl_parser::avpr::to_avpr_string;
+use avdl_parser::avsc::{from_avsc_str, AvscList};
+use avdl_parser::canonical::canonical_form;
+use avdl_parser::expand::{expand_refs, registry};
+use avdl_parser::graph::topological_order;
+use avdl_parser::idl::{schemas_to_idl, to_idl};
+use avdl_parser::namespace::{rewrite_namespace, rewrite_schema_namespace};
+use avdl_parser::output::{plan_outputs, FileNaming, FilenameTemplate};
+use avdl_parser::{declares_protocol, parse_idl_file_with_options, parse_lenient, parse_spanned, AvdlError, IdlFile, ParserOptions, Protocol};
+use clap::error::ErrorKind;
+use clap::{CommandFactory, ValueEnum};
+use rayon::prelude::*;
+use tracing::{info, Level};
+
+use crate::cli::Cli;
+use crate::inputs::{expand_inputs, find_files, matches};
+use crate::watch::{self, timestamp};
+use crate::{describe, exit_code, is_stdio, logging, parse_error, read, read_and_parse, render_schema, report, with_imports, write, Invalid, JsonStyle};
+
+#[derive(Debug, clap::Args)]
+pub struct ConvertArgs {
+    /// Type of conversion
+    #[arg(required = true)]
+    pub target: ConvertTarget,
+
+    /// AVDL files, folders searched for them or glob patterns, or the .avpr file or .avsc
+    /// files to convert to AVDL, followed by the target folder when more than one path is
+    /// given (the current folder otherwise). `-` reads the AVDL from stdin, or writes the
+    /// output to stdout
+    #[arg(required = true, num_args = 1.., value_name = "PATHS")]
+    pub paths: Vec<PathBuf>,
+
+    /// Write the outputs of the files found in folders, or by glob patterns, to the same
+    /// subfolders of the target folder
+    #[arg(long)]
+    pub mirror_dirs: bool,
+
+    /// Stop at the first file that fails, instead of reporting it and going on
+    #[arg(long)]
+    pub fail_fast: bool,
+
+    /// Keep running, converting the files again whenever an AVDL file changes, and only
+    /// rewriting the outputs whose contents changed
+    #[arg(long, conflicts_with = "fail_fast")]
+    pub watch: bool,
+
+    /// How many files are converted at once, the number of CPUs by default
+    #[arg(long, short, value_name = "N")]
+    pub jobs: Option<NonZeroUsize>,
+
+    /// Write nothing, printing the files that would be written instead, with the number of
+    /// lines added and removed when they exist
+    #[arg(long, conflicts_with = "watch")]
+    pub dry_run: bool,
+
+    /// Fail when a file to write already exists with different contents
+    #[arg(long)]
+    pub no_overwrite: bool,
+
+    /// Remove the .avsc files of the target folder no type was written to, e.g. after a
+    /// record is renamed
+    #[arg(long)]
+    pub prune: bool,
+
+    /// How the schemas are written to stdout
+    #[arg(long, value_enum, default_value = "json")]
+    pub format: StdoutFormat,
+
+    /// Name of the protocol wrapping .avsc files converted to AVDL, the name of the first
+    /// file by default
+    #[arg(long, value_name = "NAME")]
+    pub protocol_name: Option<String>,
+
+    /// Exit with an error if the parser reports any warning
+    #[arg(long)]
+    pub fail_on_warnings: bool,
+
+    /// Reject anything the specification does not allow
+    #[arg(long)]
+    pub strict: bool,
+
+    /// Accept trailing commas in enum symbols
+    #[arg(long)]
+    pub allow_trailing_commas: bool,
+
+    /// Keep parsing after a field fails and report every error found
+    #[arg(long)]
+    pub keep_going: bool,
+
+    /// Enable an extra lint, can be repeated
+    #[arg(long = "lint", value_enum)]
+    pub lints: Vec<Lint>,
+
+    /// Do not report the field-shadowing lint for a field, given as `Record.field`
+    #[arg(long, value_name = "RECORD.FIELD")]
+    pub allow_field_shadowing: Vec<String>,
+
+    /// Accept references to types not declared in the protocol
+    #[arg(long)]
+    pub allow_unresolved_refs: bool,
+
+    /// How the schema files are named
+    #[arg(long, value_enum, default_value = "name")]
+    pub naming: FileNamingArg,
+
+    /// Where the schema files go: `flat` is `--naming fullname`, and `namespaced` is
+    /// `--naming dirs`
+    #[arg(long, value_enum, conflicts_with = "naming")]
+    pub layout: Option<LayoutArg>,
+
+    /// How the schema files are named, with the placeholders {name}, {namespace},
+    /// {fullname}, {protocol} and {ext}, e.g. `{namespace}.{name}.avsc`
+    #[arg(long, value_name = "TEMPLATE", value_parser = FilenameTemplate::parse, conflicts_with_all = ["naming", "layout"])]
+    pub filename_template: Option<FilenameTemplate>,
+
+    /// Write files whose paths differ only by case, which overwrite each other on
+    /// case-insensitive filesystems
+    #[arg(long)]
+    pub allow_case_collisions: bool,
+
+    /// Write the schemas in Parsing Canonical Form, for fingerprints and registries
+    #[arg(long, conflicts_with = "compact")]
+    pub canonical: bool,
+
+    /// Write every schema on a single line
+    #[arg(long)]
+    pub compact: bool,
+
+    /// Define the referenced types inside each file, so every file can be read on its own
+    #[arg(long)]
+    pub inline_refs: bool,
+
+    /// Move the types of the protocol namespace to this namespace
+    #[arg(long, value_name = "NAMESPACE", conflicts_with = "keep_going")]
+    pub set_namespace: Option<String>,
+
+    /// Move only the types of this namespace, and the ones nested in it, with --set-namespace
+    #[arg(long, value_name = "NAMESPACE", requires = "set_namespace")]
+    pub from_namespace: Option<String>,
+
+    /// Only write the types whose full name matches this name or glob pattern, e.g.
+    /// `org.example.*` (repeatable). The types they reference are written too, unless
+    /// inlined with --inline-refs
+    #[arg(long, value_name = "NAME_OR_GLOB", conflicts_with = "bundle")]
+    pub select: Vec<String>,
+
+    /// Don't write the types whose full name matches this name or glob pattern (repeatable),
+    /// unless a type written references them
+    #[arg(long, value_name = "NAME_OR_GLOB", conflicts_with = "bundle")]
+    pub exclude: Vec<String>,
+
+    /// Write every named type of the protocol to this single file instead, each one after
+    /// the types it references
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["watch", "prune", "mirror_dirs", "inline_refs", "keep_going", "canonical"])]
+    pub bundle: Option<PathBuf>,
+
+    /// What the bundle holds
+    #[arg(long, value_enum, default_value = "array", requires = "bundle")]
+    pub bundle_format: BundleFormat,
+}
+
+#[derive(Debug, Clone, PartialEq, ValueEnum)]
+pub enum ConvertTarget {
+    /// A formatted `.avdl` file, from an `.avpr` file or `.avsc` files
+    Idl,
+    /// A single `.avpr` file named after the protocol
+    Protocol,
+    /// An `.avsc` file for every record, enum and fixed
+    Schema,
+}
+
+#[derive(Debug, Clone, PartialEq, ValueEnum)]
+pub enum StdoutFormat {
+    /// A JSON array of the schemas
+    Json,
+    /// A schema per line
+    Ndjson,
+}
+
+#[derive(Debug, Clone, PartialEq, ValueEnum)]
+pub enum BundleFormat {
+    /// A JSON array of the schemas
+    Array,
+    /// An .avpr protocol document
+    Avpr,
+}
+
+#[derive(Debug, Clone, PartialEq, ValueEnum)]
+pub enum FileNamingArg {
+    /// `Event.avsc`
+    Name,
+    /// `org.example.Event.avsc`
+    Fullname,
+    /// `org/example/Event.avsc`
+    Dirs,
+}
+
+#[derive(Debug, Clone, PartialEq, ValueEnum)]
+pub enum LayoutArg {
+    /// `org.example.Event.avsc`
+    Flat,
+    /// `org/example/Event.avsc`
+    Namespaced,
+}
+
+#[derive(Debug, Clone, PartialEq, ValueEnum)]
+pub enum Lint {
+    /// Record fields named exactly like a type of the protocol
+    FieldShadowing,
+    /// Unions with null after another type while the default is null or absent
+    UnionNullOrder,
+}
+
+pub fn run(args: ConvertArgs) -> anyhow::Result<()> {
+    // Like `cp`, the last of several paths is the target
+    let (inputs, out) = match args.paths.as_slice() {
+        [input] => (vec![input.clone()], PathBuf::from(".")),
+        [inputs @ .., out] => (inputs.to_vec(), out.clone()),
+        [] => unreachable!("at least one path is required"),
+    };
+    if args.watch && (args.target == ConvertTarget::Idl || inputs.iter().any(|input| is_stdio(input))) {
+        Cli::command().error(ErrorKind::ArgumentConflict, "--watch only converts AVDL files, and can't read stdin").exit();
+    }
+    if args.prune && (args.target != ConvertTarget::Schema || is_stdio(&out)) {
+        Cli::command().error(ErrorKind::ArgumentConflict, "--prune only removes .avsc files, written by `convert schema` to a folder").exit();
+    }
+    if (!args.select.is_empty() || !args.exclude.is_empty()) && args.target != ConvertTarget::Schema {
+        Cli::command().error(ErrorKind::ArgumentConflict, "--select and --exclude only apply to `convert schema`").exit();
+    }
+    let mode = WriteMode { dry_run: args.dry_run, no_overwrite: args.no_overwrite };
+    if args.target == ConvertTarget::Idl {
+        return convert_to_idl(&inputs, &out, args.protocol_name.as_deref(), mode);
+    }
+    if args.keep_going && args.target == ConvertTarget::Protocol {
+        Cli::command().error(ErrorKind::ArgumentConflict, "--keep-going cannot be used to write a protocol, since the types that fail are left out").exit();
+    }
+    let options = ParserOptions {
+        strict: args.strict,
+        allow_trailing_commas: args.allow_trailing_commas,
+        lint_field_shadowing: args.lints.contains(&Lint::FieldShadowing),
+        allow_field_shadowing: args.allow_field_shadowing.clone(),
+        lint_union_null_order: args.lints.contains(&Lint::UnionNullOrder),
+        allow_unresolved_refs: args.allow_unresolved_refs,
+        ..Default::default()
+    };
+    let style = match (args.canonical, args.compact) {
+        (true, _) => JsonStyle::Canonical,
+        (_, true) => JsonStyle::Compact,
+        _ => JsonStyle::Pretty,
+    };
+    if let Some(bundle) = &args.bundle {
+        return write_bundle(&args, bundle, &options, mode);
+    }
+    let naming = match (args.filename_template, args.layout, args.naming) {
+        (Some(template), _, _) => FileNaming::Template(template),
+        (None, Some(LayoutArg::Flat), _) => FileNaming::Fullname,
+        (None, Some(LayoutArg::Namespaced), _) => FileNaming::Dirs,
+        (None, None, FileNamingArg::Name) => FileNaming::Name,
+        (None, None, FileNamingArg::Fullname) => FileNaming::Fullname,
+        (None, None, FileNamingArg::Dirs) => FileNaming::Dirs,
+    };
+    // Nothing is printed or written here, since inputs are converted in parallel
+    let convert = |idl: &Path, out: &Path, converted: &mut Converted| -> anyhow::Result<()> {
+        let input = read(idl)?;
+        let options = with_imports(idl, &options);
+        let idl = if is_stdio(idl) { Path::new("<stdin>") } else { idl };
+        let (schemas, protocol, warnings) = if args.keep_going {
+            let (schemas, warnings, errors) = parse_lenient(&input, &options);
+            // All the errors are reported, the last one as the failure of the file
+            let mut errors = errors.into_iter().map(|err| anyhow::Error::from(err.with_file(idl)));
+            if let Some(last) = errors.next_back() {
+                for err in errors {
+                    converted.messages.push((Level::ERROR, describe(&err).trim_end().to_string()));
+                }
+                return Err(last);
+            }
+            (schemas, None, warnings)
+        } else if !declares_protocol(&input) {
+            // Types declared without a protocol, which the file name stands for as with --keep-going
+            if args.target == ConvertTarget::Protocol {
+                return Err(Invalid(format!("{} declares no protocol, convert its types with `convert schema`", idl.display())).into());
+            }
+            let (file, warnings) = parse_idl_file_with_options(&input, &options).map_err(|err| anyhow::Error::from(err.with_file(idl)))?;
+            let IdlFile::Schema(mut file) = file else {
+                unreachable!("the input declares no protocol");
+            };
+            if let Some(to) = &args.set_namespace {
+                let from = args.from_namespace.clone().or_else(|| file.namespace.clone());
+                for schema in &mut file.types {
+                    rewrite_schema_namespace(schema, from.as_deref(), to);
+                }
+            }
+            (file.types, None, warnings)
+        } else {
+            let (mut protocol, warnings, _spans) = parse_spanned(&input, &options).map_err(|err| parse_error(err, &input, idl))?;
+            if let Some(to) = &args.set_namespace {
+                let from = args.from_namespace.clone().or_else(|| protocol.namespace.clone());
+                rewrite_namespace(&mut protocol, from.as_deref(), to);
+            }
+            (protocol.types.clone(), Some(protocol), warnings)
+        };
+        for warning in &warnings {
+            match warning.span {
+                Some(span) => converted.messages.push((Level::WARN, format!("{warning} at {}:{}", idl.display(), span.location(&input)))),
+                None => converted.messages.push((Level::WARN, warning.to_string())),
+            }
+        }
+        if args.fail_on_warnings && !warnings.is_empty() {
+            return Err(Invalid(format!("{} has warnings, and --fail-on-warnings is set", idl.display())).into());
+        }
+        if let (ConvertTarget::Protocol, Some(protocol)) = (&args.target, &protocol) {
+            // Fails on types or messages the avpr format can't represent
+            let json = to_avpr_string(protocol).with_context(|| idl.display().to_string())?;
+            if is_stdio(out) {
+                converted.stdout = json + "\n";
+            } else {
+                converted.files.push((out.join(format!("{}.avpr", protocol.name)), json + "\n"));
+            }
+            return Ok(());
+        }
+        // Types of a schema file, or of a file parsed with --keep-going, have no protocol. Its file name stands for it
+        let protocol_name = match &protocol {
+            Some(protocol) => protocol.name.clone(),
+            None => idl.file_stem().unwrap_or_default().to_string_lossy().into_owned(),
+        };
+        let outputs = plan_outputs(&schemas, &naming, &protocol_name, args.allow_case_collisions).map_err(|err| {
+            let hint = match (&naming, &err) {
+                (FileNaming::Template(_), AvdlError::OutputCollision { .. }) => "add {namespace} or {fullname} to `--filename-template`, or rename one of the types",
+                (FileNaming::Template(_), _) => "change `--filename-template`, rename one of the types, or pass `--allow-case-collisions`",
+                (_, AvdlError::OutputCollision { .. }) => "use `--layout flat` or `--layout namespaced`, or rename one of the types",
+                _ => "use `--layout flat` or `--layout namespaced`, rename one of the types, or pass `--allow-case-collisions`",
+            };
+            anyhow::Error::new(err).context(format!("{} (hint: {hint})", idl.display()))
+        })?;
+        for schema in schemas.iter().filter(|schema| !outputs.iter().any(|(_, output)| std::ptr::eq(*output, *schema))) {
+            converted.messages.push((Level::WARN, format!("warning: skipping {}, which has no name to write it under", serde_json::to_string(schema).unwrap())));
+        }
+        let mut outputs = outputs;
+        if !args.select.is_empty() || !args.exclude.is_empty() {
+            let fullname = |schema: &Schema| schema.name().map(|name| name.fullname(None)).unwrap_or_default();
+            converted.types = outputs.iter().map(|(_, schema)| fullname(schema)).collect();
+            let mut kept: HashSet<String> = converted.types.iter()
+                .filter(|name| args.select.is_empty() || args.select.iter().any(|pattern| matches(pattern, name)))
+                .filter(|name| !args.exclude.iter().any(|pattern| matches(pattern, name)))
+                .cloned()
+                .collect();
+            // The types the kept ones reference are written too, unless they get inlined
+            if !args.inline_refs {
+                let names = registry(&schemas);
+                let referenced: Vec<String> = outputs.iter()
+                    .filter(|(_, schema)| kept.contains(&fullname(schema)))
+                    .flat_map(|(_, schema)| registry(&[expand_refs(schema, &names)]).into_keys())
+                    .map(|name| name.fullname(None))
+                    .collect();
+                kept.extend(referenced);
+            }
+            outputs.retain(|(_, schema)| kept.contains(&fullname(schema)));
+        }
+        let registry = if args.inline_refs { Some(registry(&schemas)) } else { None };
+        if is_stdio(out) {
+            let schemas: Vec<Schema> = outputs.into_iter().map(|(_, schema)| match &registry {
+                Some(registry) => expand_refs(schema, registry),
+                None => schema.clone(),
+            }).collect();
+            let stdout = &mut converted.stdout;
+            match (&args.format, style) {
+                (StdoutFormat::Json, JsonStyle::Pretty) => writeln!(stdout, "{}", serde_json::to_string_pretty(&schemas).unwrap())?,
+                (StdoutFormat::Json, style) => {
+                    let schemas: Vec<String> = schemas.iter().map(|schema| render_schema(schema, style)).collect();
+                    writeln!(stdout, "[{}]", schemas.join(","))?;
+                }
+                (StdoutFormat::Ndjson, JsonStyle::Canonical) => {
+                    for schema in &schemas {
+                        writeln!(stdout, "{}", canonical_form(schema))?;
+                    }
+                }
+                (StdoutFormat::Ndjson, _) => {
+                    for schema in &schemas {
+                        writeln!(stdout, "{}", serde_json::to_string(schema).unwrap())?;
+                    }
+                }
+            }
+            return Ok(());
+        }
+        converted.files = outputs.into_iter().map(|(path, schema)| {
+            let json = match &registry {
+                Some(registry) => render_schema(&expand_refs(schema, registry), style),
+                None => render_schema(schema, style),
+            };
+            (out.join(path), json + "\n")
+        }).collect();
+        Ok(())
+    };
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(args.jobs.map_or(0, NonZeroUsize::get)).build()?;
+    // Inputs are converted in parallel, then their output printed and written in order.
+    // Failures are reported as they come, returning the exit code of the first one
+    let rebuild = || -> anyhow::Result<Option<i32>> {
+        let inputs = expand_inputs(&inputs)?;
+        let results: Vec<(Converted, anyhow::Result<()>)> = pool.install(|| inputs.par_iter().map(|input| {
+            let out = match (args.mirror_dirs, input.mirrored_dir()) {
+                (true, Some(dir)) if !is_stdio(&out) => out.join(dir),
+                _ => out.clone(),
+            };
+            let mut converted = Converted::default();
+            let result = convert(&input.path, &out, &mut converted);
+            (converted, result)
+        }).collect());
+        // Patterns that match nothing are most likely typos
+        if results.iter().all(|(_, result)| result.is_ok()) {
+            let flags = args.select.iter().map(|pattern| ("--select", pattern)).chain(args.exclude.iter().map(|pattern| ("--exclude", pattern)));
+            for (flag, pattern) in flags {
+                if !results.iter().any(|(converted, _)| converted.types.iter().any(|name| matches(pattern, name))) {
+                    return Err(Invalid(format!("{flag} `{pattern}` matches no type of the inputs")).into());
+                }
+            }
+        }
+        let mut code = None;
+        // The input each file was written from, and its contents
+        let mut written: HashMap<PathBuf, (&Path, String)> = HashMap::new();
+        for (input, (converted, result)) in inputs.iter().zip(results) {
+            for (level, message) in &converted.messages {
+                logging::log(*level, message);
+            }
+            print!("{}", converted.stdout);
+            let result = result.and_then(|()| {
+                for (path, contents) in &converted.files {
+                    match written.get(path) {
+                        Some((first, first_contents)) if first_contents != contents => return Err(AvdlError::OutputCollision {
+                            first: first.display().to_string(),
+                            second: input.path.display().to_string(),
+                            path: path.clone(),
+                        }.into()),
+                        _ => (),
+                    }
+                }
+                write_outputs(converted.files, mode)
+            });
+            match result {
+                Ok(files) => written.extend(files.into_iter().map(|(path, contents)| (path, (input.path.as_path(), contents)))),
+                Err(err) => {
+                    report(&err);
+                    code.get_or_insert(exit_code(&err));
+                    if args.fail_fast {
+                        break;
+                    }
+                }
+            }
+        }
+        // The files of the inputs that failed would be taken as stale
+        if args.prune && code.is_none() {
+            prune_outputs(&out, &written.into_keys().collect(), args.dry_run)?;
+        }
+        Ok(code)
+    };
+    if args.watch {
+        return watch::watch(&inputs, || match rebuild() {
+            Ok(None) => info!("[{}] converted, watching for changes", timestamp()),
+            Ok(Some(_)) => info!("[{}] failed, watching for changes", timestamp()),
+            Err(err) => {
+                report(&err);
+                info!("[{}] failed, watching for changes", timestamp());
+            }
+        });
+    }
+    if let Some(code) = rebuild()? {
+        process::exit(code);
+    }
+    Ok(())
+}
+
+// Writes every named type of the protocol to the single file `bundle`
+fn write_bundle(args: &ConvertArgs, bundle: &Path, options: &ParserOptions, mode: WriteMode) -> anyhow::Result<()> {
+    let [input] = args.paths.as_slice() else {
+        Cli::command().error(ErrorKind::ArgumentConflict, "--bundle writes the types of a single AVDL file, with no target folder").exit();
+    };
+    if args.target != ConvertTarget::Schema {
+        Cli::command().error(ErrorKind::ArgumentConflict, "--bundle only applies to `convert schema`").exit();
+    }
+    let mut protocol = read_and_parse(input, options)?;
+    if let Some(to) = &args.set_namespace {
+        let from = args.from_namespace.clone().or_else(|| protocol.namespace.clone());
+        rewrite_namespace(&mut protocol, from.as_deref(), to);
+    }
+    // Later types reference the earlier ones by name
+    let types = topological_order(&protocol.types, &protocol.namespace).with_context(|| input.display().to_string())?;
+    let json = match args.bundle_format {
+        BundleFormat::Array => {
+            let types = AvscList(&types, &protocol.namespace);
+            if args.compact { serde_json::to_string(&types)? } else { serde_json::to_string_pretty(&types)? }
+        }
+        BundleFormat::Avpr => {
+            protocol.types = types.into_iter().cloned().collect();
+            to_avpr_string(&protocol).with_context(|| input.display().to_string())?
+        }
+    };
+    write_outputs(vec![(bundle.to_path_buf(), json + "\n")], mode)?;
+    Ok(())
+}
+
+// Write the protocol of an .avpr file, or the schemas of .avsc files, as AVDL
+fn convert_to_idl(inputs: &[PathBuf], out: &Path, protocol_name: Option<&str>, mode: WriteMode) -> anyhow::Result<()> {
+    let is = |path: &Path, extension: &str| path.extension().is_some_and(|e| e == extension);
+    let (name, idl) = match inputs {
+        [avpr] if is(avpr, "avpr") => {
+            let mut protocol = Protocol::from_avpr_str(&read(avpr)?).with_context(|| avpr.display().to_string())?;
+            if let Some(name) = protocol_name {
+                protocol.name = name.to_string();
+            }
+            (protocol.name.clone(), to_idl(&protocol).with_context(|| avpr.display().to_string())?)
+        },
+        _ if inputs.iter().all(|input| is(input, "avsc")) => {
+            let mut schemas = Vec::new();
+            for input in inputs {
+                schemas.push(from_avsc_str(&read(input)?).with_context(|| input.display().to_string())?);
+            }
+            let name = match protocol_name {
+                Some(name) => name.to_string(),
+                None => inputs[0].file_stem().unwrap_or_default().to_string_lossy().into_owned(),
+            };
+            let idl = schemas_to_idl(&name, &schemas);
+            (name, idl)
+        }
+        _ => Cli::command().error(ErrorKind::InvalidValue, "convert one .avpr file, or one or more .avsc files, to AVDL").exit(),
+    };
+    if is_stdio(out) {
+        print!("{idl}");
+        return Ok(());
+    }
+    write_outputs(vec![(out.join(format!("{name}.avdl")), idl)], mode)?;
+    Ok(())
+}
+
+// What converting an input gives, printed and written once every input is converted
+#[derive(Debug, Default)]
+struct Converted {
+    messages: Vec<(Level, String)>,
+    // The full names of the types of the input, when filtered with --select or --exclude
+    types: Vec<String>,
+    stdout: String,
+    files: Vec<(PathBuf, String)>,
+}
+
+// How convert treats the files it writes
+#[derive(Debug, Clone, Copy)]
+struct WriteMode {
+    dry_run: bool,
+    no_overwrite: bool,
+}
+
+// Writes the outputs of an input, all or none of them, and returns them. A dry run prints the
+// ones that would change instead
+fn write_outputs(files: Vec<(PathBuf, String)>, mode: WriteMode) -> anyhow::Result<Vec<(PathBuf, String)>> {
+    if mode.no_overwrite {
+        if let Some((path, _)) = files.iter().find(|(path, contents)| fs::read(path).is_ok_and(|current| current != contents.as_bytes())) {
+            return Err(Invalid(format!("{} already exists with different contents, and --no-overwrite is set", path.display())).into());
+        }
+    }
+    for (path, contents) in &files {
+        if !mode.dry_run {
+            write(path, contents)?;
+            continue;
+        }
+        match fs::read_to_string(path) {
+            Ok(current) if current == *contents => (),
+            Ok(current) => println!("{} ({})", path.display(), line_changes(&current, contents)),
+            Err(_) => println!("{} (new)", path.display()),
+        }
+    }
+    Ok(files)
+}
+
+// `+added -removed lines`, counting the lines of one text missing from the other
+fn line_changes(old: &str, new: &str) -> String {
+    let mut counts: HashMap<&str, isize> = HashMap::new();
+    for line in old.lines() {
+        *counts.entry(line).or_default() -= 1;
+    }
+    for line in new.lines() {
+        *counts.entry(line).or_default() += 1;
+    }
+    let added: isize = counts.values().filter(|count| **count > 0).sum();
+    let removed: isize = counts.values().filter(|count| **count < 0).map(|count| -count).sum();
+    format!("+{added} -{removed} lines")
+}
+
+// Removes the .avsc files under `out` that are not in `written`, left by types renamed or removed
+fn prune_outputs(out: &Path, written: &HashSet<PathBuf>, dry_run: bool) -> anyhow::Result<()> {
+    if !out.is_dir() {
+        return Ok(());
+    }
+    let files = find_files(out, "avsc").with_context(|| format!("failed to read {}", out.display()))?;
+    for file in files.into_iter().filter(|file| !written.contains(file)) {
+        if dry_run {
+            println!("{} (removed)", file.display());
+        } else {
+            fs::remove_file(&file).with_context(|| format!("failed to remove {}", file.display()))?;
+            info!("removed {}", file.display());
+        }
+    }
+    Ok(())
+}
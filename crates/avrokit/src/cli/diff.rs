@@ -0,0 +1,47 @@
+//! `avrokit diff`, listing the changes to the types between two versions of an AVDL file.
+
+use std::path::PathBuf;
+
+use avdl_parser::diff::{diff, Change, ChangeKind};
+use avdl_parser::ParserOptions;
+
+use crate::cli::ReportFormat;
+use crate::read_and_parse;
+
+#[derive(Debug, clap::Args)]
+pub struct DiffArgs {
+    /// Path to the previous AVDL file
+    #[arg(required = true)]
+    pub old: PathBuf,
+
+    /// Path to the new AVDL file
+    #[arg(required = true)]
+    pub new: PathBuf,
+
+    /// How the changes are printed
+    #[arg(long, value_enum, default_value = "text")]
+    pub format: ReportFormat,
+}
+
+pub fn run(args: DiffArgs) -> anyhow::Result<()> {
+    let old = read_and_parse(&args.old, &ParserOptions::default())?;
+    let new = read_and_parse(&args.new, &ParserOptions::default())?;
+    let changes = diff(&old.types, &new.types);
+    match args.format {
+        ReportFormat::Text => {
+            // Doc changes don't matter to the data, so they are listed apart
+            let (docs, changes): (Vec<&Change>, Vec<&Change>) = changes.iter().partition(|change| change.kind == ChangeKind::DocChanged);
+            for change in changes {
+                println!("{change}");
+            }
+            if !docs.is_empty() {
+                println!("\ndoc changes:");
+                for change in docs {
+                    println!("  {}", change.path);
+                }
+            }
+        }
+        ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&changes).unwrap()),
+    }
+    Ok(())
+}
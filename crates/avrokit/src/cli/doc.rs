@@ -0,0 +1,48 @@
+//! `avrokit doc`, writing the documentation pages of an AVDL file.
+
+use std::path::PathBuf;
+
+use avdl_parser::docs::{render_docs, DocFormat, DocLayout};
+use avdl_parser::ParserOptions;
+use clap::ValueEnum;
+
+use crate::{read_and_parse, write};
+
+#[derive(Debug, clap::Args)]
+pub struct DocArgs {
+    /// Path to AVDL file
+    #[arg(required = true)]
+    pub idl_file: PathBuf,
+
+    /// Target folder to place the pages
+    #[arg(long, default_value = ".")]
+    pub out: PathBuf,
+
+    /// Format of the pages
+    #[arg(long, value_enum, default_value = "markdown")]
+    pub format: DocFormatArg,
+
+    /// Render the whole protocol on one page with a table of contents,
+    /// instead of a page per named type
+    #[arg(long)]
+    pub single_page: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, ValueEnum)]
+pub enum DocFormatArg {
+    Markdown,
+    Html,
+}
+
+pub fn run(args: DocArgs) -> anyhow::Result<()> {
+    let protocol = read_and_parse(&args.idl_file, &ParserOptions::default())?;
+    let format = match args.format {
+        DocFormatArg::Markdown => DocFormat::Markdown,
+        DocFormatArg::Html => DocFormat::Html,
+    };
+    let layout = if args.single_page { DocLayout::SinglePage } else { DocLayout::PagePerType };
+    for (path, page) in render_docs(&protocol, format, layout) {
+        write(&args.out.join(path), page)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,48 @@
+//! `avrokit fingerprint`, printing the fingerprint of every type of an AVDL file.
+
+use std::path::PathBuf;
+
+use avdl_parser::expand::{expand_refs, registry};
+use avdl_parser::fingerprint::Fingerprint;
+use avdl_parser::ParserOptions;
+use clap::ValueEnum;
+use md5::Md5;
+use sha2::Sha256;
+
+use crate::read_and_parse;
+
+#[derive(Debug, clap::Args)]
+pub struct FingerprintArgs {
+    /// Path to AVDL file
+    #[arg(required = true)]
+    pub idl_file: PathBuf,
+
+    /// Fingerprinting algorithm
+    #[arg(long, value_enum, default_value = "rabin")]
+    pub algorithm: FingerprintAlgorithm,
+}
+
+#[derive(Debug, Clone, PartialEq, ValueEnum)]
+pub enum FingerprintAlgorithm {
+    /// 64-bit Rabin fingerprint (CRC-64-AVRO)
+    Rabin,
+    Sha256,
+    Md5,
+}
+
+pub fn run(args: FingerprintArgs) -> anyhow::Result<()> {
+    let protocol = read_and_parse(&args.idl_file, &ParserOptions::default())?;
+    let registry = registry(&protocol.types);
+    for schema in &protocol.types {
+        let Some(name) = schema.name() else { continue };
+        // Each fingerprint covers the types the schema references
+        let schema = expand_refs(schema, &registry);
+        let fingerprint = match args.algorithm {
+            FingerprintAlgorithm::Rabin => format!("{:016x}", schema.fingerprint_rabin()),
+            FingerprintAlgorithm::Sha256 => schema.fingerprint_digest::<Sha256>().to_string(),
+            FingerprintAlgorithm::Md5 => schema.fingerprint_digest::<Md5>().to_string(),
+        };
+        println!("{} {fingerprint}", name.fullname(protocol.namespace.clone()));
+    }
+    Ok(())
+}
@@ -0,0 +1,34 @@
+//! `avrokit fix`, rewriting an AVDL file to fix common mistakes.
+
+use std::path::PathBuf;
+
+use avdl_parser::fix::fix_union_null_order;
+use tracing::warn;
+
+use crate::{read, write};
+
+#[derive(Debug, clap::Args)]
+pub struct FixArgs {
+    /// Path to AVDL file
+    #[arg(required = true)]
+    pub idl_file: PathBuf,
+
+    /// Overwrite the file instead of printing the result
+    #[arg(long)]
+    pub in_place: bool,
+}
+
+pub fn run(args: FixArgs) -> anyhow::Result<()> {
+    let idl = &args.idl_file;
+    let input = read(idl)?;
+    let (output, notes) = fix_union_null_order(&input).map_err(|err| anyhow::Error::from(err.with_file(idl)))?;
+    for note in &notes {
+        warn!("{note} at {}:{}", idl.display(), note.span.location(&input));
+    }
+    if args.in_place {
+        write(idl, output)?;
+    } else {
+        print!("{output}");
+    }
+    Ok(())
+}
@@ -0,0 +1,51 @@
+//! `avrokit fmt`, rewriting AVDL files with the layout `convert idl` writes.
+
+use std::path::PathBuf;
+
+use avdl_parser::idl::format_idl;
+use avdl_parser::ParserOptions;
+use tracing::warn;
+
+use crate::inputs::expand_inputs;
+use crate::{read, write, Invalid};
+
+#[derive(Debug, clap::Args)]
+pub struct FmtArgs {
+    /// AVDL files, folders searched recursively for them, or glob patterns
+    #[arg(required = true)]
+    pub paths: Vec<PathBuf>,
+
+    /// Write nothing, and fail if any file is not formatted, printing its path
+    #[arg(long)]
+    pub check: bool,
+
+    /// Format files with comments other than doc comments too, dropping those comments
+    #[arg(long)]
+    pub drop_comments: bool,
+}
+
+pub fn run(args: FmtArgs) -> anyhow::Result<()> {
+    let mut unformatted = 0;
+    for input in expand_inputs(&args.paths)? {
+        let path = &input.path;
+        let text = read(path)?;
+        let (formatted, comments) = format_idl(&text, &ParserOptions::default()).map_err(|err| anyhow::Error::from(err.with_file(path)))?;
+        if let (Some(comment), false) = (comments.first(), args.drop_comments) {
+            warn!("warning: {}:{}: only doc comments are kept when formatting, skipping the file (pass --drop-comments to format it anyway)", path.display(), comment.location(&text));
+            continue;
+        }
+        if formatted == text {
+            continue;
+        }
+        if args.check {
+            println!("{}", path.display());
+            unformatted += 1;
+        } else {
+            write(path, formatted)?;
+        }
+    }
+    if unformatted > 0 {
+        return Err(Invalid(format!("{unformatted} files are not formatted")).into());
+    }
+    Ok(())
+}
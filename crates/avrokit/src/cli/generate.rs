@@ -0,0 +1,120 @@
+//! `avrokit generate`, writing Rust code for the types of an AVDL file or random values of one of
+//! its records.
+
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use anyhow::Context;
+use apache_avro::Writer;
+use avdl_parser::codegen::{to_rust, RustOptions};
+use avdl_parser::expand::{expand_refs, registry};
+use avdl_parser::{ParserOptions, Protocol};
+use clap::ValueEnum;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::data::Generator;
+use crate::{find_record, read_and_parse, write};
+
+#[derive(Debug, clap::Args)]
+pub struct GenerateArgs {
+    #[arg(value_enum)]
+    pub target: GenerateTarget,
+
+    /// Path to AVDL file
+    #[arg(required = true)]
+    pub idl_file: PathBuf,
+
+    /// File to write the code to, printed when missing
+    #[arg(long)]
+    pub out: Option<PathBuf>,
+
+    /// Use chrono types for timestamps
+    #[arg(long)]
+    pub chrono: bool,
+
+    /// Use uuid::Uuid for uuids
+    #[arg(long)]
+    pub uuid: bool,
+
+    /// Record to generate values of, with `data`
+    #[arg(long, required_if_eq("target", "data"))]
+    pub record: Option<String>,
+
+    /// Number of values to generate, with `data`
+    #[arg(long, default_value_t = 10)]
+    pub count: usize,
+
+    /// How the values are written, with `data`
+    #[arg(long, value_enum, default_value = "json")]
+    pub format: DataFormat,
+
+    /// Seed of the random values, for the same ones on every run
+    #[arg(long)]
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, ValueEnum)]
+pub enum GenerateTarget {
+    /// Structs and enums deriving serde's traits
+    Rust,
+    /// Random values of a record
+    Data,
+}
+
+#[derive(Debug, Clone, PartialEq, ValueEnum)]
+pub enum DataFormat {
+    /// One JSON value per line
+    Json,
+    /// An Avro object container file
+    Avro,
+}
+
+pub fn run(args: GenerateArgs) -> anyhow::Result<()> {
+    let protocol = read_and_parse(&args.idl_file, &ParserOptions::default())?;
+    let contents = match args.target {
+        GenerateTarget::Rust => to_rust(&protocol, &RustOptions { chrono: args.chrono, uuid: args.uuid }).into_bytes(),
+        GenerateTarget::Data => {
+            let record = args.record.expect("--record is required with data");
+            generate_data(&protocol, &record, args.count, args.format, args.seed)?
+        },
+    };
+    match args.out {
+        Some(out) => write(&out, contents)?,
+        None => io::stdout().write_all(&contents).context("failed to write to stdout")?,
+    }
+    Ok(())
+}
+
+// `count` random values of the record named `record`, one JSON value per line or in an object
+// container file
+fn generate_data(protocol: &Protocol, record: &str, count: usize, format: DataFormat, seed: Option<u64>) -> anyhow::Result<Vec<u8>> {
+    let schema = find_record(protocol, record)?;
+    let names = registry(&protocol.types);
+    // The container file holds the schema, with the types it references
+    let schema = expand_refs(schema, &names);
+    let rng = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    let mut generator = Generator::new(rng, &names);
+    let values = (0..count).map(|_| generator.value(&schema, &protocol.namespace));
+    match format {
+        DataFormat::Json => {
+            let mut lines = String::new();
+            for value in values {
+                let json = serde_json::Value::try_from(value).context("failed to convert a value to JSON")?;
+                lines.push_str(&json.to_string());
+                lines.push('\n');
+            }
+            Ok(lines.into_bytes())
+        },
+        DataFormat::Avro => {
+            let mut writer = Writer::new(&schema, Vec::new());
+            for value in values {
+                writer.append(value).context("failed to write a value")?;
+            }
+            writer.into_inner().context("failed to write the container file")
+        },
+    }
+}
@@ -0,0 +1,68 @@
+//! `avrokit graph`, drawing the imports between AVDL files or the references between their types.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use avdl_parser::graph::{file_graph, type_graph};
+use avdl_parser::{parse_spanned, ParserOptions, Protocol};
+
+use crate::inputs::find_idl_files;
+use crate::{parse_error, read, write};
+
+#[derive(Debug, clap::Args)]
+pub struct GraphArgs {
+    /// Folder with the AVDL files, searched recursively, or a single AVDL file
+    #[arg(required = true)]
+    pub path: PathBuf,
+
+    /// File to write the graph to, instead of printing it
+    #[arg(long)]
+    pub out: Option<PathBuf>,
+
+    /// One node per named type, with an edge for each reference (default for a single file)
+    #[arg(long, conflicts_with = "files")]
+    pub types: bool,
+
+    /// One node per file, with an edge for each import (default for a folder)
+    #[arg(long)]
+    pub files: bool,
+
+    /// Group the named types of each namespace in a cluster
+    #[arg(long, conflicts_with = "files")]
+    pub namespace_clusters: bool,
+}
+
+pub fn run(args: GraphArgs) -> anyhow::Result<()> {
+    // Imports are kept to draw the files, and may point to types of files not parsed yet
+    let options = ParserOptions {
+        allow_unresolved_refs: true,
+        ..Default::default()
+    };
+    let (dir, paths) = if args.path.is_file() {
+        (args.path.parent().unwrap_or(Path::new("")).to_path_buf(), vec![args.path.clone()])
+    } else {
+        let paths = find_idl_files(&args.path).with_context(|| format!("failed to read {}", args.path.display()))?;
+        (args.path.clone(), paths)
+    };
+    let files = paths
+        .into_iter()
+        .map(|path| {
+            let input = read(&path)?;
+            let (protocol, _warnings, _spans) = parse_spanned(&input, &options).map_err(|err| parse_error(err, &input, &path))?;
+            let relative = path.strip_prefix(&dir).unwrap_or(&path).to_path_buf();
+            Ok((relative, protocol))
+        })
+        .collect::<anyhow::Result<Vec<(PathBuf, Protocol)>>>()?;
+    let dot = if args.types || args.namespace_clusters || (args.path.is_file() && !args.files) {
+        let protocols: Vec<Protocol> = files.into_iter().map(|(_, protocol)| protocol).collect();
+        let graph = type_graph(&protocols);
+        if args.namespace_clusters { graph.to_dot_with_namespace_clusters("types") } else { graph.to_dot("types") }
+    } else {
+        file_graph(&files).to_dot("files")
+    };
+    match args.out {
+        Some(out) => write(&out, dot)?,
+        None => print!("{dot}"),
+    }
+    Ok(())
+}
@@ -0,0 +1,49 @@
+//! `avrokit lint`, checking the naming and documentation conventions of AVDL files.
+
+use std::path::PathBuf;
+
+use avdl_parser::lint::Rule;
+use avdl_parser::ParserOptions;
+
+use crate::cli::ReportFormat;
+use crate::diagnostics::{lint_file, Diagnostic};
+use crate::inputs::expand_inputs;
+use crate::Invalid;
+
+#[derive(Debug, clap::Args)]
+pub struct LintArgs {
+    /// AVDL files, folders searched recursively for them, or glob patterns
+    #[arg(required = true)]
+    pub paths: Vec<PathBuf>,
+
+    /// Code of a rule not to check, e.g. AVDL005 (repeatable). The rules are AVDL001 record
+    /// names in PascalCase, AVDL002 field names in camelCase, AVDL003 enum symbols in
+    /// UPPER_SNAKE_CASE, AVDL004 records with a namespace, AVDL005 records with a doc comment,
+    /// and AVDL006 null first in unions
+    #[arg(long, value_name = "CODE")]
+    pub allow: Vec<Rule>,
+
+    /// How the problems found are printed
+    #[arg(long, value_enum, default_value = "text")]
+    pub format: ReportFormat,
+}
+
+pub fn run(args: LintArgs) -> anyhow::Result<()> {
+    let inputs = expand_inputs(&args.paths)?;
+    let found: Vec<Diagnostic> = inputs.iter().flat_map(|input| lint_file(&input.path, &ParserOptions::default(), &args.allow)).collect();
+    match args.format {
+        ReportFormat::Text => {
+            for diagnostic in &found {
+                println!("{diagnostic}");
+            }
+        }
+        ReportFormat::Json => {
+            let found: Vec<serde_json::Value> = found.iter().map(Diagnostic::to_json).collect();
+            println!("{}", serde_json::to_string_pretty(&found).unwrap());
+        }
+    }
+    if !found.is_empty() {
+        return Err(Invalid(format!("{} problems found", found.len())).into());
+    }
+    Ok(())
+}
@@ -0,0 +1,92 @@
+//! `avrokit registry`, publishing the records of an AVDL file to a schema registry or checking them
+//! against it.
+
+use std::path::PathBuf;
+
+use apache_avro::schema::Schema;
+use avdl_parser::expand::{expand_refs, registry};
+use avdl_parser::ParserOptions;
+use clap::Subcommand;
+
+use crate::schema_registry::{payload, Client, RegistryError};
+use crate::{read_and_parse, render_schema, Invalid, JsonStyle};
+
+#[derive(Debug, Subcommand)]
+pub enum RegistryCommand {
+    /// Register the schema of every record under its subject, printing the ids
+    #[command(arg_required_else_help = true)]
+    Publish(RegistryArgs),
+    /// Check the schema of every record against the latest version of its subject, exiting with
+    /// an error when one is not compatible
+    #[command(arg_required_else_help = true)]
+    Check(RegistryArgs),
+}
+
+/// The credentials of basic authentication are read from the AVROKIT_REGISTRY_USER and
+/// AVROKIT_REGISTRY_PASSWORD environment variables
+#[derive(Debug, clap::Args)]
+pub struct RegistryArgs {
+    /// Path to AVDL file
+    #[arg(required = true)]
+    pub idl_file: PathBuf,
+
+    /// URL of the schema registry
+    #[arg(long, required_unless_present = "dry_run")]
+    pub url: Option<String>,
+
+    /// Subject of each record, where `{namespace}`, `{name}` and `{fullname}` stand for the ones
+    /// of the record
+    #[arg(long, default_value = "{fullname}-value")]
+    pub subject_template: String,
+
+    /// Print the subjects and the bodies of the requests instead of sending them
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+// Register the records of an AVDL file with a schema registry, or check them against it
+pub fn run(command: RegistryCommand) -> anyhow::Result<()> {
+    let (args, publish) = match command {
+        RegistryCommand::Publish(args) => (args, true),
+        RegistryCommand::Check(args) => (args, false),
+    };
+    let protocol = read_and_parse(&args.idl_file, &ParserOptions::default())?;
+    let names = registry(&protocol.types);
+    let client = match &args.url {
+        Some(url) if !args.dry_run => Some(Client::from_env(url)),
+        _ => None,
+    };
+    let mut incompatible = 0;
+    for schema in &protocol.types {
+        let Schema::Record { name, .. } = schema else { continue };
+        let fullname = name.fully_qualified_name(&protocol.namespace);
+        let subject = args.subject_template
+            .replace("{namespace}", fullname.namespace.as_deref().unwrap_or_default())
+            .replace("{name}", &fullname.name)
+            .replace("{fullname}", &fullname.fullname(None));
+        // The registry takes schemas with the types they reference
+        let schema = render_schema(&expand_refs(schema, &names), JsonStyle::Compact);
+        let Some(client) = &client else {
+            println!("{subject} {}", payload(&schema));
+            continue;
+        };
+        // Schemas the registry refuses fail the check, the network failing is an I/O error
+        let failed = |err: RegistryError| match err {
+            RegistryError::Rejected { .. } => anyhow::Error::new(Invalid(format!("{subject}: {err}"))),
+            RegistryError::Network(_) => anyhow::Error::new(err).context(subject.clone()),
+        };
+        if publish {
+            let id = client.publish(&subject, &schema).map_err(failed)?;
+            println!("{subject}: id {id}");
+        } else if client.check(&subject, &schema).map_err(failed)? {
+            println!("{subject}: compatible");
+        } else {
+            println!("{subject}: not compatible");
+            incompatible += 1;
+        }
+    }
+    if incompatible > 0 {
+        return Err(Invalid(format!("{incompatible} schemas are not compatible")).into());
+    }
+    Ok(())
+}
@@ -0,0 +1,42 @@
+//! `avrokit stats`, summing up the types of AVDL files.
+
+use std::path::PathBuf;
+
+use avdl_parser::stats::stats;
+use avdl_parser::{ParserOptions, Protocol};
+use clap::ValueEnum;
+
+use crate::inputs::expand_inputs;
+use crate::read_and_parse;
+
+#[derive(Debug, clap::Args)]
+pub struct StatsArgs {
+    /// AVDL files, folders searched recursively for them, or glob patterns
+    #[arg(required = true)]
+    pub paths: Vec<PathBuf>,
+
+    /// How the numbers are printed
+    #[arg(long, value_enum, default_value = "table")]
+    pub format: StatsFormat,
+}
+
+#[derive(Debug, Clone, PartialEq, ValueEnum)]
+pub enum StatsFormat {
+    /// A table, meant to be read
+    Table,
+    /// A JSON object, meant for tools
+    Json,
+}
+
+pub fn run(args: StatsArgs) -> anyhow::Result<()> {
+    let protocols = expand_inputs(&args.paths)?
+        .iter()
+        .map(|input| read_and_parse(&input.path, &ParserOptions::default()))
+        .collect::<anyhow::Result<Vec<Protocol>>>()?;
+    let stats = stats(&protocols);
+    match args.format {
+        StatsFormat::Table => print!("{stats}"),
+        StatsFormat::Json => println!("{}", serde_json::to_string_pretty(&stats).unwrap()),
+    }
+    Ok(())
+}
@@ -0,0 +1,44 @@
+//! `avrokit validate`, reporting the problems of AVDL files.
+
+use std::path::PathBuf;
+
+use avdl_parser::ParserOptions;
+
+use crate::cli::ReportFormat;
+use crate::diagnostics::{diagnose, Diagnostic, Severity};
+use crate::inputs::expand_inputs;
+use crate::Invalid;
+
+#[derive(Debug, clap::Args)]
+pub struct ValidateArgs {
+    /// AVDL files, folders searched recursively for them, or glob patterns
+    #[arg(required = true)]
+    pub paths: Vec<PathBuf>,
+
+    /// How the problems found are printed
+    #[arg(long, value_enum, default_value = "text")]
+    pub format: ReportFormat,
+}
+
+pub fn run(args: ValidateArgs) -> anyhow::Result<()> {
+    let inputs = expand_inputs(&args.paths)?;
+    let found: Vec<Diagnostic> = inputs.iter().flat_map(|input| diagnose(&input.path, &ParserOptions::default())).collect();
+    match args.format {
+        ReportFormat::Text => {
+            for diagnostic in &found {
+                println!("{diagnostic}");
+            }
+        }
+        ReportFormat::Json => {
+            let found: Vec<serde_json::Value> = found.iter().map(Diagnostic::to_json).collect();
+            println!("{}", serde_json::to_string_pretty(&found).unwrap());
+        }
+    }
+    let failed = inputs.iter().filter(|input| {
+        found.iter().any(|diagnostic| diagnostic.file == input.path && diagnostic.severity == Severity::Error)
+    }).count();
+    if failed > 0 {
+        return Err(Invalid(format!("{failed} of {} files failed validation", inputs.len())).into());
+    }
+    Ok(())
+}
@@ -0,0 +1,77 @@
+//! `avrokit validate-data`, checking values against a record of an AVDL file.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use apache_avro::Reader;
+use avdl_parser::expand::registry;
+use avdl_parser::value::from_json;
+use avdl_parser::ParserOptions;
+
+use crate::{find_record, read, read_and_parse, Invalid};
+
+#[derive(Debug, clap::Args)]
+pub struct ValidateDataArgs {
+    /// Path to AVDL file
+    #[arg(required = true)]
+    pub idl_file: PathBuf,
+
+    /// Record the values should be values of
+    #[arg(long, required = true)]
+    pub record: String,
+
+    /// A JSON array, JSON values one per line, or an .avro object container file
+    #[arg(required = true)]
+    pub data: PathBuf,
+
+    /// Number of invalid values reported, the first problem of each
+    #[arg(long, default_value_t = 10)]
+    pub max_errors: usize,
+}
+
+pub fn run(args: ValidateDataArgs) -> anyhow::Result<()> {
+    let protocol = read_and_parse(&args.idl_file, &ParserOptions::default())?;
+    let schema = find_record(&protocol, &args.record)?;
+    let names = registry(&protocol.types);
+    let values = read_data(&args.data)?;
+    let mut failed = 0;
+    for (index, value) in values.iter().enumerate() {
+        if let Err(mismatch) = from_json(schema, &protocol.namespace, &names, value, "") {
+            if failed < args.max_errors {
+                println!("{}: value {}: {mismatch}", args.data.display(), index + 1);
+            }
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        return Err(Invalid(format!("{failed} of {} values are not {} records", values.len(), args.record)).into());
+    }
+    Ok(())
+}
+
+// The values of a JSON array, of newline delimited JSON values, or of an .avro object container
+// file, as JSON
+fn read_data(path: &Path) -> anyhow::Result<Vec<serde_json::Value>> {
+    if path.extension().is_some_and(|extension| extension == "avro") {
+        let file = fs::File::open(path).with_context(|| format!("failed to read {}", path.display()))?;
+        let reader = Reader::new(io::BufReader::new(file)).with_context(|| format!("failed to read {}", path.display()))?;
+        return reader
+            .map(|value| {
+                let value = value.with_context(|| format!("failed to read {}", path.display()))?;
+                serde_json::Value::try_from(value).with_context(|| format!("failed to convert a value of {} to JSON", path.display()))
+            })
+            .collect();
+    }
+    let contents = read(path)?;
+    let mut values = serde_json::Deserializer::from_str(&contents)
+        .into_iter()
+        .collect::<Result<Vec<serde_json::Value>, _>>()
+        .map_err(|err| Invalid(format!("{} is not JSON: {err}", path.display())))?;
+    // A single array holds the values
+    if let [serde_json::Value::Array(items)] = values.as_mut_slice() {
+        values = std::mem::take(items);
+    }
+    Ok(values)
+}
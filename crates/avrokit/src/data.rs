@@ -0,0 +1,167 @@
+//! Random values of the types of a protocol, for `generate data`.
+
+use apache_avro::schema::{Names, Namespace, RecordField, Schema};
+use apache_avro::types::Value;
+use apache_avro::{Days, Decimal, Duration, Millis, Months};
+use avdl_parser::value::{from_json, resolve};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+
+// Records nested deeper than this take no more optional values, so that
+// recursive types end
+const MAX_DEPTH: usize = 4;
+
+// Timestamps are between 2000-01-01 and 2030-01-01
+const START_MILLIS: i64 = 946_684_800_000;
+const END_MILLIS: i64 = 1_893_456_000_000;
+const MILLIS_PER_DAY: i64 = 86_400_000;
+
+/// Builds random values of schemas, finding the types they reference by
+/// name in `names`.
+pub struct Generator<'a, R> {
+    rng: R,
+    names: &'a Names,
+    depth: usize,
+}
+
+impl<'a, R: Rng> Generator<'a, R> {
+    pub fn new(rng: R, names: &'a Names) -> Self {
+        Generator {
+            rng,
+            names,
+            depth: 0,
+        }
+    }
+
+    /// A random value of `schema`, whose names without a namespace belong to
+    /// `namespace`. Fields with a default take it now and then.
+    pub fn value(&mut self, schema: &Schema, namespace: &Namespace) -> Value {
+        match schema {
+            Schema::Null => Value::Null,
+            Schema::Boolean => Value::Boolean(self.rng.gen()),
+            Schema::Int => Value::Int(self.rng.gen_range(-1000..1000)),
+            Schema::Long => Value::Long(self.rng.gen_range(-1_000_000..1_000_000)),
+            Schema::Float => Value::Float(self.rng.gen_range(-1000.0..1000.0)),
+            Schema::Double => Value::Double(self.rng.gen_range(-1000.0..1000.0)),
+            Schema::Bytes => {
+                let size = self.rng.gen_range(0..16);
+                Value::Bytes(self.bytes(size))
+            }
+            Schema::String => Value::String(self.word()),
+            Schema::Fixed { size, .. } => Value::Fixed(*size, self.bytes(*size)),
+            Schema::Enum { symbols, .. } => {
+                let index = self.rng.gen_range(0..symbols.len());
+                Value::Enum(index as u32, symbols[index].clone())
+            }
+            Schema::Uuid => {
+                let uuid = uuid::Builder::from_random_bytes(self.rng.gen()).into_uuid();
+                Value::Uuid(uuid)
+            }
+            Schema::Date => Value::Date((self.timestamp() / MILLIS_PER_DAY) as i32),
+            Schema::TimeMillis => Value::TimeMillis(self.rng.gen_range(0..MILLIS_PER_DAY) as i32),
+            Schema::TimeMicros => Value::TimeMicros(self.rng.gen_range(0..MILLIS_PER_DAY * 1000)),
+            Schema::TimestampMillis => Value::TimestampMillis(self.timestamp()),
+            Schema::TimestampMicros => Value::TimestampMicros(self.timestamp() * 1000),
+            Schema::LocalTimestampMillis => Value::LocalTimestampMillis(self.timestamp()),
+            Schema::LocalTimestampMicros => Value::LocalTimestampMicros(self.timestamp() * 1000),
+            Schema::Duration => Value::Duration(Duration::new(
+                Months::new(self.rng.gen_range(0..24)),
+                Days::new(self.rng.gen_range(0..31)),
+                Millis::new(self.rng.gen_range(0..MILLIS_PER_DAY as u32)),
+            )),
+            Schema::Decimal {
+                precision, inner, ..
+            } => {
+                // An unscaled value with at most `precision` digits
+                let limit = 10_i64.pow((*precision).clamp(1, 18) as u32);
+                let unscaled = self.rng.gen_range(-limit + 1..limit).to_be_bytes();
+                let size = match inner.as_ref() {
+                    Schema::Fixed { size, .. } => *size,
+                    _ => unscaled.len(),
+                };
+                // Sign extended, or cut to the size of the fixed, which fits
+                // the precision
+                let sign = if unscaled[0] & 0x80 == 0 { 0 } else { 0xff };
+                let mut bytes = vec![sign; size.saturating_sub(unscaled.len())];
+                bytes.extend(&unscaled[unscaled.len().saturating_sub(size)..]);
+                Value::Decimal(Decimal::from(bytes))
+            }
+            Schema::Array(items) => {
+                let count = self.count();
+                Value::Array((0..count).map(|_| self.value(items, namespace)).collect())
+            }
+            Schema::Map(values) => {
+                let count = self.count();
+                Value::Map(
+                    (0..count)
+                        .map(|_| (self.word(), self.value(values, namespace)))
+                        .collect(),
+                )
+            }
+            Schema::Union(union) => {
+                let variants = union.variants();
+                let index = if self.depth < MAX_DEPTH {
+                    self.rng.gen_range(0..variants.len())
+                } else {
+                    variants
+                        .iter()
+                        .position(|variant| *variant == Schema::Null)
+                        .unwrap_or(0)
+                };
+                let value = self.value(&variants[index], namespace);
+                Value::Union(index as u32, Box::new(value))
+            }
+            Schema::Record { name, fields, .. } => {
+                let namespace = name.fully_qualified_name(namespace).namespace;
+                self.depth += 1;
+                let fields = fields
+                    .iter()
+                    .map(|field| (field.name.clone(), self.field(field, &namespace)))
+                    .collect();
+                self.depth -= 1;
+                Value::Record(fields)
+            }
+            Schema::Ref { name } => match resolve(self.names, name, namespace) {
+                Some(schema) => self.value(schema, namespace),
+                None => Value::Null,
+            },
+        }
+    }
+
+    // One time in four, a field takes its default when it has one
+    fn field(&mut self, field: &RecordField, namespace: &Namespace) -> Value {
+        let default = field
+            .default
+            .as_ref()
+            .filter(|_| self.rng.gen_ratio(1, 4))
+            .and_then(|default| from_json(&field.schema, namespace, self.names, default, "").ok());
+        default.unwrap_or_else(|| self.value(&field.schema, namespace))
+    }
+
+    fn bytes(&mut self, size: usize) -> Vec<u8> {
+        (0..size).map(|_| self.rng.gen()).collect()
+    }
+
+    fn word(&mut self) -> String {
+        let size = self.rng.gen_range(3..12);
+        (&mut self.rng)
+            .sample_iter(Alphanumeric)
+            .take(size)
+            .map(char::from)
+            .collect()
+    }
+
+    // Items of arrays and maps, none once deep in a recursive type
+    fn count(&mut self) -> usize {
+        if self.depth < MAX_DEPTH {
+            self.rng.gen_range(0..=3)
+        } else {
+            0
+        }
+    }
+
+    // Milliseconds since the epoch
+    fn timestamp(&mut self) -> i64 {
+        self.rng.gen_range(START_MILLIS..END_MILLIS)
+    }
+}
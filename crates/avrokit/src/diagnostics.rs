@@ -0,0 +1,142 @@
+//! The problems `validate` and `lint` report, as text or JSON.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use avdl_parser::error::{Location, SpannedError};
+use avdl_parser::lint::{lint, Rule};
+use avdl_parser::{check_protocol, parse_spanned, AvdlError, ParserOptions};
+use serde_json::{json, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    fn as_str(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// A problem of a file, at the line and column it was found at when known.
+pub struct Diagnostic {
+    pub file: PathBuf,
+    pub location: Option<Location>,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "file": self.file.display().to_string(),
+            "line": self.location.as_ref().map(|location| location.line),
+            "column": self.location.as_ref().map(|location| location.column),
+            "severity": self.severity.as_str(),
+            "message": self.message,
+        })
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:", self.file.display())?;
+        if let Some(location) = &self.location {
+            write!(f, "{location}:")?;
+        }
+        write!(f, " {}: {}", self.severity.as_str(), self.message)
+    }
+}
+
+/// Every problem of the AVDL file at `path`, errors and warnings.
+pub fn diagnose(path: &Path, options: &ParserOptions) -> Vec<Diagnostic> {
+    let diagnostic = |location, severity, message| Diagnostic {
+        file: path.to_path_buf(),
+        location,
+        severity,
+        message,
+    };
+    let input = match fs::read_to_string(path) {
+        Ok(input) => input,
+        Err(err) => {
+            let message = format!("failed to read the file: {err}");
+            return vec![diagnostic(None, Severity::Error, message)];
+        }
+    };
+    let (warnings, errors) = check_protocol(&input, &with_imports(path, options));
+    let mut found: Vec<Diagnostic> = errors
+        .into_iter()
+        .map(|SpannedError { error, span }| match *error {
+            // Parse errors know their location, and have it in their message
+            AvdlError::Parse {
+                location,
+                message,
+                cause,
+                ..
+            } => {
+                let message = match cause {
+                    Some(cause) => format!("{message}: {cause}"),
+                    None => message,
+                };
+                diagnostic(Some(location), Severity::Error, message)
+            }
+            // What went wrong in the imported file is in the source
+            AvdlError::ImportIdlError { path, source } => {
+                let message = format!("failed to import {}: {source}", path.display());
+                diagnostic(None, Severity::Error, message)
+            }
+            error => {
+                let location = span.map(|span| span.location(&input));
+                diagnostic(location, Severity::Error, error.to_string())
+            }
+        })
+        .collect();
+    found.extend(warnings.into_iter().map(|warning| {
+        let location = warning.span.map(|span| span.location(&input));
+        let message = format!("{} [{}]", warning.message, warning.code);
+        diagnostic(location, Severity::Warning, message)
+    }));
+    // In the order of the file, the ones without a location last
+    found.sort_by_key(|diagnostic| match &diagnostic.location {
+        Some(location) => (false, location.line, location.column),
+        None => (true, 0, 0),
+    });
+    found
+}
+
+/// The findings of the lint rules but the `allowed` ones in the AVDL file at
+/// `path`, as warnings, or its errors when it doesn't parse.
+pub fn lint_file(path: &Path, options: &ParserOptions, allowed: &[Rule]) -> Vec<Diagnostic> {
+    let options = with_imports(path, options);
+    let parsed = fs::read_to_string(path)
+        .ok()
+        .and_then(|input| Some((parse_spanned(&input, &options).ok()?, input)));
+    let Some(((protocol, _warnings, spans), input)) = parsed else {
+        let mut found = diagnose(path, &options);
+        found.retain(|diagnostic| diagnostic.severity == Severity::Error);
+        return found;
+    };
+    lint(&protocol, &spans, allowed)
+        .into_iter()
+        .map(|finding| Diagnostic {
+            file: path.to_path_buf(),
+            location: finding.span.map(|span| span.location(&input)),
+            severity: Severity::Warning,
+            message: finding.to_string(),
+        })
+        .collect()
+}
+
+// Imports are read from the directory of the file
+fn with_imports(path: &Path, options: &ParserOptions) -> ParserOptions {
+    ParserOptions {
+        import_dir: Some(path.parent().unwrap_or(Path::new("")).to_path_buf()),
+        ..options.clone()
+    }
+}
@@ -0,0 +1,198 @@
+//! Finding the files given to a command as folders or glob patterns.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A file to convert, and the path it was found from.
+pub struct Input {
+    pub path: PathBuf,
+    // The folder given, or the part of a glob pattern before its wildcards
+    root: PathBuf,
+}
+
+impl Input {
+    /// Folder of the file, relative to the one it was found in.
+    pub fn mirrored_dir(&self) -> Option<&Path> {
+        self.path
+            .parent()?
+            .strip_prefix(&self.root)
+            .ok()
+            .filter(|dir| !dir.as_os_str().is_empty())
+    }
+}
+
+/// The files `paths` stand for. Folders are searched for `.avdl` files and
+/// glob patterns, with `*`, `?` and `**`, are expanded here since shells
+/// don't on Windows. Other paths are kept as they are, `-` included.
+pub fn expand_inputs(paths: &[PathBuf]) -> io::Result<Vec<Input>> {
+    let mut inputs = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            inputs.extend(find_idl_files(path)?.into_iter().map(|file| Input {
+                path: file,
+                root: path.clone(),
+            }));
+        } else if !path.exists() && is_glob(path) {
+            let (root, pattern) = split_glob(path);
+            let mut found = Vec::new();
+            glob(&root, &pattern, &mut found)?;
+            if found.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("no file matches {}", path.display()),
+                ));
+            }
+            found.sort();
+            inputs.extend(found.into_iter().map(|file| Input {
+                path: file,
+                root: root.clone(),
+            }));
+        } else {
+            let root = path.parent().map(Path::to_path_buf).unwrap_or_default();
+            inputs.push(Input {
+                path: path.clone(),
+                root,
+            });
+        }
+    }
+    Ok(inputs)
+}
+
+/// The folder where the files `path` stands for are, for watching them, and
+/// whether its subfolders have some too.
+pub fn watched_dir(path: &Path) -> (PathBuf, bool) {
+    let (dir, recursive) = if path.is_dir() {
+        (path.to_path_buf(), true)
+    } else if !path.exists() && is_glob(path) {
+        (split_glob(path).0, true)
+    } else {
+        (
+            path.parent().map(Path::to_path_buf).unwrap_or_default(),
+            false,
+        )
+    };
+    if dir.as_os_str().is_empty() {
+        (PathBuf::from("."), recursive)
+    } else {
+        (dir, recursive)
+    }
+}
+
+/// The `.avdl` files under `dir`, sorted.
+pub fn find_idl_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    find_files(dir, "avdl")
+}
+
+/// The files with `extension` under `dir`, sorted.
+pub fn find_files(dir: &Path, extension: &str) -> io::Result<Vec<PathBuf>> {
+    let mut found = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            found.extend(find_files(&path, extension)?);
+        } else if path.extension().is_some_and(|found| found == extension) {
+            found.push(path);
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+fn is_glob(path: &Path) -> bool {
+    path.to_string_lossy().contains(['*', '?'])
+}
+
+// The folder before the first component with wildcards, and the components
+// from there
+fn split_glob(path: &Path) -> (PathBuf, Vec<String>) {
+    let mut root = PathBuf::new();
+    let mut pattern = Vec::new();
+    for component in path.components() {
+        let text = component.as_os_str().to_string_lossy();
+        if pattern.is_empty() && !text.contains(['*', '?']) {
+            root.push(component);
+        } else {
+            pattern.push(text.into_owned());
+        }
+    }
+    (root, pattern)
+}
+
+// Adds the files under `dir` matching the components of `pattern` to `found`.
+// Folders that can't be read have no matches.
+fn glob(dir: &Path, pattern: &[String], found: &mut Vec<PathBuf>) -> io::Result<()> {
+    let Some((first, rest)) = pattern.split_first() else {
+        return Ok(());
+    };
+    // An empty folder is the current one, left out of the paths found
+    let read = if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    };
+    let Ok(entries) = fs::read_dir(read) else {
+        return Ok(());
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .map(|entry| dir.join(entry.file_name()))
+        .collect();
+    paths.sort();
+    if first == "**" {
+        // Any number of folders, none included
+        if rest.is_empty() {
+            found.extend(find_idl_files(read)?.into_iter().map(|file| {
+                file.strip_prefix(".")
+                    .map(Path::to_path_buf)
+                    .unwrap_or(file)
+            }));
+            return Ok(());
+        }
+        glob(dir, rest, found)?;
+        for path in paths.iter().filter(|path| path.is_dir()) {
+            glob(path, pattern, found)?;
+        }
+        return Ok(());
+    }
+    for path in paths {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if !matches(first, &name) {
+            continue;
+        }
+        if rest.is_empty() {
+            if path.is_file() {
+                found.push(path);
+            }
+        } else if path.is_dir() {
+            glob(&path, rest, found)?;
+        }
+    }
+    Ok(())
+}
+
+/// Whether `name` matches `pattern`, where `*` is any text and `?` any
+/// character.
+pub fn matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Where the last `*` is, and how much of the name it takes so far
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            p = star_p + 1;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
@@ -1,74 +1,182 @@
-use apache_avro::Schema;
-use clap::{Parser, Subcommand, ValueEnum};
-use std::path::{PathBuf, Path};
-use avdl_parser::parse;
+mod cli;
+mod data;
+mod diagnostics;
+mod inputs;
+mod logging;
+#[cfg(feature = "registry")]
+mod schema_registry;
+mod watch;
+
+use apache_avro::schema::Schema;
+use anyhow::Context;
+use clap::Parser;
+use std::path::Path;
+use avdl_parser::canonical::canonical_form;
+use avdl_parser::error::SpannedError;
+use avdl_parser::{parse_spanned, AvdlError, ParserOptions, Protocol};
+use std::fmt;
 use std::fs;
+use std::io::{self, Read};
+use std::process;
+use tracing::debug;
+use cli::{Cli, Commands};
 
-#[derive(Parser, Debug)]
-#[command(author, version, about)]
-struct Cli {
-    #[command(subcommand)]
-    command: Commands,
-}
+// Exit codes: the input is not valid AVDL, a file can't be read or written, or the input is
+// valid but fails a check
+const EXIT_PARSE: i32 = 1;
+const EXIT_IO: i32 = 2;
+const EXIT_VALIDATION: i32 = 3;
+
+// A check the input failed, exiting with EXIT_VALIDATION
+#[derive(Debug)]
+struct Invalid(String);
 
-#[derive(Debug, Clone, PartialEq, ValueEnum)]
-enum ConvertTarget {
-    // Idl,
-    // Protocol,
-    Schema,
+impl fmt::Display for Invalid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
 }
 
-#[derive(Debug, Subcommand)]
-enum Commands {
-    /// Convert from AVDL to JSON AVSC schemas
-    #[command(arg_required_else_help = true)]
-    Convert {
-        /// Type of conversion
-        #[arg(required = true)]
-        target: ConvertTarget,
-
-        /// Path to AVDL file
-        #[arg(required = true)]
-        idl_file: PathBuf,
-
-        /// Target folder to place the avsc schemas
-        #[arg(required = false, value_parser, default_value = ".")]
-        out: PathBuf
-    },
+impl std::error::Error for Invalid {}
+
+fn exit_code(err: &anyhow::Error) -> i32 {
+    let invalid = |cause: &(dyn std::error::Error + 'static)| {
+        cause.is::<Invalid>() || matches!(cause.downcast_ref(), Some(AvdlError::CaseCollision { .. } | AvdlError::OutputCollision { .. }))
+    };
+    if err.chain().any(|cause| cause.is::<io::Error>()) {
+        EXIT_IO
+    } else if err.chain().any(invalid) {
+        EXIT_VALIDATION
+    } else {
+        EXIT_PARSE
+    }
 }
 
-fn main() {
-    let args = Cli::parse();
-    match args.command {
-        Commands::Convert { target, idl_file: idl, out } => {
-            let input = fs::read_to_string(idl)
-            .expect("Should have been able to read the file");
-        let (_tail, schemas) = parse(&input).expect("failed to parse");
-        fs::create_dir_all(&out).expect("failed to create outdir");
-        for schema in schemas {
-            if let Schema::Record { name, aliases, doc, fields, lookup, attributes } = &schema {
-                let filename = &name.name;
-                let filename = format!("{filename}.avsc");
-                let outpath = Path::new(&out).join(filename);
-                // let contents = schema.canonical_form();
-                let json = serde_json::to_string_pretty(&schema).unwrap();
-                fs::write(outpath, json).expect("Failed to write to file");
+// Prints `error: <context>: <cause>`, and the line a parse error was found in
+fn report(err: &anyhow::Error) {
+    tracing::error!("{}", describe(err).trim_end());
+}
 
+// The lines `report` prints
+fn describe(err: &anyhow::Error) -> String {
+    match err.downcast_ref::<AvdlError>() {
+        Some(AvdlError::Parse { file, location, line_text, message, cause }) => {
+            let file = file.as_ref().map(|file| format!("{}:", file.display())).unwrap_or_default();
+            match cause {
+                Some(cause) => format!("error: {file}{location}: {message}: {cause}\n  | {line_text}\n"),
+                None => format!("error: {file}{location}: {message}\n  | {line_text}\n"),
             }
-            // match &schema {
+        }
+        _ => format!("error: {err:#}\n"),
+    }
+}
+
+// The error of parsing `input`, read from `file`, with the line and column of the type or field
+// it is about
+fn parse_error(err: SpannedError, input: &str, file: &Path) -> anyhow::Error {
+    let SpannedError { error, span } = err;
+    match (*error, span) {
+        (error @ AvdlError::Parse { .. }, _) => error.with_file(file).into(),
+        (error, Some(span)) => anyhow::Error::new(error).context(format!("{}:{}", file.display(), span.location(input))),
+        (error, None) => anyhow::Error::new(error).context(file.display().to_string()),
+    }
+}
 
-            //     Schema::Record { name, aliases, doc, fields, lookup, attributes } => {
-            //         let filename = format!("{name}.avsc");
-            //         let outpath = Path::new(&out).join(filename);
-            //         // let contents = schema.canonical_form();
-            //         let json = serde_json::to_string_pretty(&schema).unwrap();
-            //         fs::write(outpath, json).expect("Failed to write to file");
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum JsonStyle {
+    Pretty,
+    Compact,
+    Canonical,
+}
 
-            //     },
-            //     _ => panic!("Invalid")
-            // }
+// The JSON of `schema`, keys always in the same order
+fn render_schema(schema: &Schema, style: JsonStyle) -> String {
+    match style {
+        JsonStyle::Pretty => serde_json::to_string_pretty(schema).unwrap(),
+        JsonStyle::Compact => serde_json::to_string(schema).unwrap(),
+        JsonStyle::Canonical => canonical_form(schema),
+    }
+}
 
-        }
-        },
+// `-` stands for stdin or stdout
+fn is_stdio(path: &Path) -> bool {
+    path.as_os_str() == "-"
+}
+
+// Reads `path`, or stdin for `-`
+fn read(path: &Path) -> anyhow::Result<String> {
+    if is_stdio(path) {
+        let mut input = String::new();
+        io::stdin().read_to_string(&mut input).context("failed to read stdin")?;
+        return Ok(input);
+    }
+    debug!("reading {}", path.display());
+    fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))
+}
+
+// Writes `path`, creating its folder, unless it already holds `contents`
+fn write(path: &Path, contents: impl AsRef<[u8]>) -> anyhow::Result<()> {
+    // Whatever watches the file is not woken up for nothing
+    if fs::read(path).is_ok_and(|current| current == contents.as_ref()) {
+        debug!("{} is unchanged", path.display());
+        return Ok(());
+    }
+    if let Some(folder) = path.parent() {
+        fs::create_dir_all(folder).with_context(|| format!("failed to create {}", folder.display()))?;
+    }
+    debug!("writing {}", path.display());
+    fs::write(path, contents).with_context(|| format!("failed to write {}", path.display()))
+}
+
+// The record of `protocol` named `record`, with or without its namespace
+fn find_record<'a>(protocol: &'a Protocol, record: &str) -> anyhow::Result<&'a Schema> {
+    let found = protocol.types.iter().find(|schema| match schema {
+        Schema::Record { name, .. } => name.name == record || name.fullname(protocol.namespace.clone()) == record,
+        _ => false,
+    });
+    found.ok_or_else(|| Invalid(format!("no record named `{record}`")).into())
+}
+
+// Imports are read from the directory of the file, stdin has none to read
+fn with_imports(idl: &Path, options: &ParserOptions) -> ParserOptions {
+    let import_dir = if is_stdio(idl) { None } else { Some(idl.parent().unwrap_or(Path::new("")).to_path_buf()) };
+    ParserOptions { import_dir, ..options.clone() }
+}
+
+fn read_and_parse(idl: &Path, options: &ParserOptions) -> anyhow::Result<Protocol> {
+    let input = read(idl)?;
+    match parse_spanned(&input, &with_imports(idl, options)) {
+        Ok((protocol, _warnings, _spans)) => Ok(protocol),
+        Err(err) => Err(parse_error(err, &input, idl)),
+    }
+}
+
+fn main() {
+    let args = Cli::parse();
+    logging::init(args.quiet, args.verbose);
+    if let Err(err) = run(args) {
+        report(&err);
+        process::exit(exit_code(&err));
+    }
+}
+
+fn run(args: Cli) -> anyhow::Result<()> {
+    match args.command {
+        Commands::Convert(args) => cli::convert::run(args),
+        Commands::Doc(args) => cli::doc::run(args),
+        Commands::Generate(args) => cli::generate::run(args),
+        Commands::Graph(args) => cli::graph::run(args),
+        Commands::Fingerprint(args) => cli::fingerprint::run(args),
+        Commands::Validate(args) => cli::validate::run(args),
+        Commands::Stats(args) => cli::stats::run(args),
+        Commands::Lint(args) => cli::lint::run(args),
+        Commands::ValidateData(args) => cli::validate_data::run(args),
+        #[cfg(feature = "registry")]
+        Commands::Registry(command) => cli::registry::run(command),
+        Commands::Diff(args) => cli::diff::run(args),
+        Commands::CheckCompat(args) => cli::check_compat::run(args),
+        Commands::Fmt(args) => cli::fmt::run(args),
+        Commands::Completions(args) => cli::completions::run(args),
+        Commands::Fix(args) => cli::fix::run(args),
     }
 }
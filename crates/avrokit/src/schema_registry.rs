@@ -0,0 +1,145 @@
+//! A client of a Confluent compatible schema registry, for the `registry`
+//! subcommands.
+
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde_json::{json, Value};
+
+/// Environment variables holding the credentials of basic authentication.
+pub const USER_VAR: &str = "AVROKIT_REGISTRY_USER";
+pub const PASSWORD_VAR: &str = "AVROKIT_REGISTRY_PASSWORD";
+
+const CONTENT_TYPE: &str = "application/vnd.schemaregistry.v1+json";
+
+// The error code of the registry when a subject has no version yet
+const SUBJECT_NOT_FOUND: i64 = 40401;
+
+/// Why a request to the registry failed: it couldn't be sent or answered, or
+/// the registry refused it.
+#[derive(Debug)]
+pub enum RegistryError {
+    Network(io::Error),
+    Rejected {
+        status: u16,
+        code: Option<i64>,
+        message: String,
+    },
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegistryError::Network(_) => f.write_str("failed to reach the registry"),
+            RegistryError::Rejected {
+                status,
+                code: Some(code),
+                message,
+            } => write!(
+                f,
+                "the registry rejected the request ({status}, error {code}): {message}"
+            ),
+            RegistryError::Rejected {
+                status, message, ..
+            } => write!(f, "the registry rejected the request ({status}): {message}"),
+        }
+    }
+}
+
+impl Error for RegistryError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RegistryError::Network(err) => Some(err),
+            RegistryError::Rejected { .. } => None,
+        }
+    }
+}
+
+/// The body of the requests registering or checking `schema`, the JSON of an
+/// Avro schema.
+pub fn payload(schema: &str) -> Value {
+    json!({ "schema": schema })
+}
+
+pub struct Client {
+    url: String,
+    authorization: Option<String>,
+}
+
+impl Client {
+    /// A client of the registry at `url`, authenticating with the
+    /// credentials of [`USER_VAR`] and [`PASSWORD_VAR`] when set.
+    pub fn from_env(url: &str) -> Client {
+        let authorization = env::var(USER_VAR).ok().map(|user| {
+            let password = env::var(PASSWORD_VAR).unwrap_or_default();
+            format!("Basic {}", STANDARD.encode(format!("{user}:{password}")))
+        });
+        Client {
+            url: url.trim_end_matches('/').to_string(),
+            authorization,
+        }
+    }
+
+    /// Register `schema` under `subject`, returning its id.
+    pub fn publish(&self, subject: &str, schema: &str) -> Result<u64, RegistryError> {
+        let response = self.post(&format!("/subjects/{subject}/versions"), schema)?;
+        id(&response).ok_or_else(|| unexpected(&response))
+    }
+
+    /// Whether `schema` is compatible with the latest version of `subject`,
+    /// following the compatibility level of the subject. A subject without
+    /// versions takes any schema.
+    pub fn check(&self, subject: &str, schema: &str) -> Result<bool, RegistryError> {
+        let path = format!("/compatibility/subjects/{subject}/versions/latest");
+        match self.post(&path, schema) {
+            Ok(response) => response["is_compatible"]
+                .as_bool()
+                .ok_or_else(|| unexpected(&response)),
+            Err(RegistryError::Rejected {
+                code: Some(SUBJECT_NOT_FOUND),
+                ..
+            }) => Ok(true),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn post(&self, path: &str, schema: &str) -> Result<Value, RegistryError> {
+        let mut request = ureq::post(&format!("{}{path}", self.url))
+            .set("Content-Type", CONTENT_TYPE)
+            .set("Accept", CONTENT_TYPE);
+        if let Some(authorization) = &self.authorization {
+            request = request.set("Authorization", authorization);
+        }
+        match request.send_json(payload(schema)) {
+            Ok(response) => response.into_json().map_err(RegistryError::Network),
+            Err(ureq::Error::Status(status, response)) => {
+                // Errors have a body like `{"error_code": 42201, "message": "..."}`
+                let body: Value = response.into_json().unwrap_or_default();
+                Err(RegistryError::Rejected {
+                    status,
+                    code: body["error_code"].as_i64(),
+                    message: body["message"].as_str().unwrap_or("no message").to_string(),
+                })
+            }
+            Err(ureq::Error::Transport(transport)) => Err(RegistryError::Network(
+                io::Error::other(transport.to_string()),
+            )),
+        }
+    }
+}
+
+fn id(response: &Value) -> Option<u64> {
+    response["id"].as_u64()
+}
+
+// An answer the registry shouldn't give, reported like a failure of the network
+fn unexpected(response: &Value) -> RegistryError {
+    RegistryError::Network(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("unexpected response: {response}"),
+    ))
+}
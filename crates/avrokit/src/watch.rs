@@ -0,0 +1,67 @@
+//! Converting files again whenever they change, for `convert --watch`.
+
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+use notify_debouncer_mini::new_debouncer;
+use notify_debouncer_mini::notify::RecursiveMode;
+use tracing::warn;
+
+use crate::inputs::watched_dir;
+
+// Editors often write a file several times when saving it
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Call `rebuild` now, and then every time an `.avdl` file that `paths`
+/// stand for changes, until the process is killed.
+pub fn watch(paths: &[PathBuf], mut rebuild: impl FnMut()) -> anyhow::Result<()> {
+    let (sender, receiver) = mpsc::channel();
+    let mut debouncer = new_debouncer(DEBOUNCE, sender).context("failed to watch for changes")?;
+    for path in paths {
+        let (dir, recursive) = watched_dir(path);
+        let mode = if recursive {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+        debouncer
+            .watcher()
+            .watch(&dir, mode)
+            .with_context(|| format!("failed to watch {}", dir.display()))?;
+    }
+    rebuild();
+    for events in receiver {
+        match events {
+            // Outputs written next to the inputs change too, and are ignored
+            Ok(events) => {
+                let changed = events.iter().any(|event| {
+                    event
+                        .path
+                        .extension()
+                        .is_some_and(|extension| extension == "avdl")
+                });
+                if changed {
+                    rebuild();
+                }
+            }
+            Err(err) => warn!("[{}] warning: {err}", timestamp()),
+        }
+    }
+    Ok(())
+}
+
+/// The current time of the day in UTC, as `HH:MM:SS`.
+pub fn timestamp() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+        % 86_400;
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
@@ -0,0 +1,90 @@
+//! Runs `convert schema --bundle`, which writes every type to one file.
+
+use std::fs;
+
+use apache_avro::Schema;
+use assert_cmd::Command;
+use serde_json::Value;
+
+// Declared before the types they use
+const SHOP: &str = r#"@namespace("org.example")
+protocol Shop {
+    record Order { array<Item> items; union { null, Order } previous = null; }
+    record Item { string id; Kind kind; }
+    enum Kind { BOOK, FOOD }
+}"#;
+
+fn bundle(args: &[&str]) -> Value {
+    let tmp = tempfile::tempdir().unwrap();
+    fs::write(tmp.path().join("Shop.avdl"), SHOP).unwrap();
+    Command::cargo_bin("avrokit")
+        .unwrap()
+        .args([
+            "convert",
+            "schema",
+            "Shop.avdl",
+            "--bundle",
+            "out/shop.json",
+        ])
+        .args(args)
+        .current_dir(tmp.path())
+        .assert()
+        .success();
+    let bundle = fs::read_to_string(tmp.path().join("out/shop.json")).unwrap();
+    assert!(bundle.ends_with("]\n") || bundle.ends_with("}\n"));
+    serde_json::from_str(&bundle).unwrap()
+}
+
+fn names(schemas: &Value) -> Vec<&str> {
+    schemas
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|schema| schema["name"].as_str().unwrap())
+        .collect()
+}
+
+#[test]
+fn test_bundle_array() {
+    let schemas = bundle(&[]);
+    assert_eq!(names(&schemas), ["Kind", "Item", "Order"]);
+    // Types are defined once, and referenced by name afterwards
+    assert_eq!(schemas[1]["fields"][1]["type"], "Kind");
+    let schemas: Vec<String> = schemas
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(Value::to_string)
+        .collect();
+    let schemas: Vec<&str> = schemas.iter().map(String::as_str).collect();
+    assert_eq!(Schema::parse_list(&schemas).unwrap().len(), 3);
+
+    assert_eq!(bundle(&["--compact"]), bundle(&[]));
+}
+
+#[test]
+fn test_bundle_avpr() {
+    let avpr = bundle(&["--bundle-format", "avpr"]);
+    assert_eq!(avpr["protocol"], "Shop");
+    assert_eq!(names(&avpr["types"]), ["Kind", "Item", "Order"]);
+}
+
+#[test]
+fn test_bundle_single_input() {
+    let tmp = tempfile::tempdir().unwrap();
+    fs::write(tmp.path().join("Shop.avdl"), SHOP).unwrap();
+    Command::cargo_bin("avrokit")
+        .unwrap()
+        .args([
+            "convert",
+            "schema",
+            "Shop.avdl",
+            "out",
+            "--bundle",
+            "shop.json",
+        ])
+        .current_dir(tmp.path())
+        .assert()
+        .code(2);
+    assert!(!tmp.path().join("shop.json").exists());
+}
@@ -0,0 +1,99 @@
+//! The help of every subcommand, the completion scripts, and the messages
+//! `--quiet` and `--verbose` let through.
+
+use std::fs;
+
+use assert_cmd::Command;
+
+const SUBCOMMANDS: [&str; 14] = [
+    "convert",
+    "doc",
+    "generate",
+    "fmt",
+    "fix",
+    "graph",
+    "fingerprint",
+    "validate",
+    "stats",
+    "lint",
+    "validate-data",
+    "diff",
+    "check-compat",
+    "completions",
+];
+
+fn avrokit() -> Command {
+    Command::cargo_bin("avrokit").unwrap()
+}
+
+#[test]
+fn test_help() {
+    let output = avrokit().arg("--help").output().unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let help = String::from_utf8(output.stdout).unwrap();
+    for subcommand in SUBCOMMANDS {
+        assert!(help.contains(&format!("\n  {subcommand} ")), "{help}");
+        let output = avrokit().args([subcommand, "--help"]).output().unwrap();
+        assert!(output.status.success(), "{subcommand}: {output:?}");
+        let help = String::from_utf8(output.stdout).unwrap();
+        assert!(
+            help.contains(&format!("Usage: avrokit {subcommand} ")),
+            "{help}"
+        );
+        assert!(help.contains("--quiet"), "{help}");
+    }
+}
+
+#[test]
+fn test_completions() {
+    for shell in ["bash", "zsh", "fish", "powershell", "elvish"] {
+        let output = avrokit().args(["completions", shell]).output().unwrap();
+        assert!(output.status.success(), "{shell}: {output:?}");
+        let script = String::from_utf8(output.stdout).unwrap();
+        assert!(script.contains("validate-data"), "{shell}: {script}");
+    }
+    let output = avrokit().args(["completions", "tcsh"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_quiet_and_verbose() {
+    let tmp = tempfile::tempdir().unwrap();
+    // Unions with null last give a warning with this lint
+    fs::write(
+        tmp.path().join("Shop.avdl"),
+        "protocol Shop {\n    record Item { union { string, null } note; }\n}",
+    )
+    .unwrap();
+    let convert = |flag: Option<&str>| {
+        let output = avrokit()
+            .args(["convert", "schema", "Shop.avdl", "out"])
+            .args(["--lint", "union-null-order"])
+            .args(flag)
+            .current_dir(tmp.path())
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{output:?}");
+        String::from_utf8(output.stderr).unwrap()
+    };
+
+    let stderr = convert(None);
+    assert!(stderr.contains("warning"), "{stderr}");
+    assert!(!stderr.contains("writing"), "{stderr}");
+    assert_eq!(convert(Some("--quiet")), "");
+    fs::remove_dir_all(tmp.path().join("out")).unwrap();
+    let stderr = convert(Some("--verbose"));
+    assert!(stderr.contains("warning"), "{stderr}");
+    assert!(stderr.contains("reading Shop.avdl\n"), "{stderr}");
+    assert!(stderr.contains("writing out/Item.avsc\n"), "{stderr}");
+
+    let output = avrokit()
+        .args(["convert", "schema", "Missing.avdl", "--quiet"])
+        .current_dir(tmp.path())
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    assert!(
+        String::from_utf8_lossy(&output.stderr).starts_with("error: failed to read Missing.avdl")
+    );
+}
@@ -0,0 +1,747 @@
+//! Runs the `convert` command against the samples of the repository.
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::time::{Duration, SystemTime};
+
+use avdl_parser::avsc::from_avsc_str;
+use avdl_parser::canonical::canonical_form;
+use avdl_parser::parse_str;
+use serde_json::Value;
+
+fn samples() -> &'static Path {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/../../tests"))
+}
+
+fn avrokit(args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_avrokit"))
+        .args(args)
+        .output()
+        .expect("avrokit should run")
+}
+
+#[test]
+fn test_convert_protocol() {
+    let out = tempfile::tempdir().unwrap();
+    let idl = samples().join("samples/Library.avdl");
+    let output = avrokit(&[
+        "convert",
+        "protocol",
+        idl.to_str().unwrap(),
+        out.path().to_str().unwrap(),
+    ]);
+    assert!(output.status.success(), "{output:?}");
+
+    let written = fs::read_to_string(out.path().join("Library.avpr")).unwrap();
+    let written: Value = serde_json::from_str(&written).unwrap();
+    let golden = fs::read_to_string(samples().join("golden/Library.avpr")).unwrap();
+    let golden: Value = serde_json::from_str(&golden).unwrap();
+    assert_eq!(written, golden);
+}
+
+#[test]
+fn test_convert_protocol_rejects_keep_going() {
+    let out = tempfile::tempdir().unwrap();
+    let idl = samples().join("samples/Library.avdl");
+    let output = avrokit(&[
+        "convert",
+        "protocol",
+        idl.to_str().unwrap(),
+        out.path().to_str().unwrap(),
+        "--keep-going",
+    ]);
+    assert!(!output.status.success());
+    assert!(!out.path().join("Library.avpr").exists());
+}
+
+fn path(path: &Path) -> &str {
+    path.to_str().unwrap()
+}
+
+// Canonical forms of the schemas in `folder`, by file name
+fn canonical_forms(folder: &Path) -> Vec<(String, String)> {
+    let mut forms: Vec<(String, String)> = fs::read_dir(folder)
+        .unwrap()
+        .map(|entry| {
+            let file = entry.unwrap().path();
+            let schema = from_avsc_str(&fs::read_to_string(&file).unwrap()).unwrap();
+            let name = file.file_name().unwrap().to_string_lossy().into_owned();
+            (name, canonical_form(&schema))
+        })
+        .collect();
+    forms.sort();
+    forms
+}
+
+#[test]
+fn test_convert_idl_round_trip() {
+    let out = tempfile::tempdir().unwrap();
+    let first = out.path().join("first");
+    let idl = out.path().join("idl");
+    let second = out.path().join("second");
+    let shop = samples().join("samples/Shop.avdl");
+    // Every file defines the types it uses, so each can be read on its own
+    let output = avrokit(&[
+        "convert",
+        "schema",
+        path(&shop),
+        path(&first),
+        "--inline-refs",
+    ]);
+    assert!(output.status.success(), "{output:?}");
+
+    let output = avrokit(&[
+        "convert",
+        "idl",
+        path(&first.join("Item.avsc")),
+        path(&first.join("Order.avsc")),
+        path(&idl),
+        "--protocol-name",
+        "Shop",
+    ]);
+    assert!(output.status.success(), "{output:?}");
+    let output = avrokit(&[
+        "convert",
+        "schema",
+        path(&idl.join("Shop.avdl")),
+        path(&second),
+        "--inline-refs",
+    ]);
+    assert!(output.status.success(), "{output:?}");
+
+    let forms = canonical_forms(&first);
+    assert_eq!(forms.len(), 4);
+    assert_eq!(canonical_forms(&second), forms);
+}
+
+#[test]
+fn test_convert_idl_from_avpr() {
+    let out = tempfile::tempdir().unwrap();
+    let avpr = samples().join("golden/Library.avpr");
+    let output = avrokit(&["convert", "idl", path(&avpr), path(out.path())]);
+    assert!(output.status.success(), "{output:?}");
+
+    let written = fs::read_to_string(out.path().join("Library.avdl")).unwrap();
+    let original = fs::read_to_string(samples().join("samples/Library.avdl")).unwrap();
+    assert_eq!(parse_str(&written).unwrap(), parse_str(&original).unwrap());
+}
+
+// A tree of protocols, `a/Broken.avdl` failing to parse
+fn write_tree(root: &Path) {
+    let protocols = [
+        ("a/One.avdl", "protocol One { record One { string id; } }"),
+        (
+            "a/Broken.avdl",
+            "protocol Broken { record Broken { string id } }",
+        ),
+        ("b/c/Two.avdl", "protocol Two { record Two { int id; } }"),
+        ("b/notes.txt", "not a protocol"),
+    ];
+    for (file, text) in protocols {
+        let file = root.join(file);
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        fs::write(file, text).unwrap();
+    }
+}
+
+#[test]
+fn test_convert_folders() {
+    let tmp = tempfile::tempdir().unwrap();
+    let src = tmp.path().join("src");
+    let out = tmp.path().join("out");
+    write_tree(&src);
+    fs::remove_file(src.join("a/Broken.avdl")).unwrap();
+    let output = avrokit(&["convert", "schema", path(&src), path(&out), "--mirror-dirs"]);
+    assert!(output.status.success(), "{output:?}");
+    assert!(out.join("a/One.avsc").exists());
+    assert!(out.join("b/c/Two.avsc").exists());
+
+    let flat = tmp.path().join("flat");
+    let output = avrokit(&["convert", "schema", path(&src), path(&flat)]);
+    assert!(output.status.success(), "{output:?}");
+    assert!(flat.join("One.avsc").exists());
+    assert!(flat.join("Two.avsc").exists());
+}
+
+#[test]
+fn test_convert_glob() {
+    let tmp = tempfile::tempdir().unwrap();
+    let src = tmp.path().join("src");
+    let out = tmp.path().join("out");
+    write_tree(&src);
+    // Expanded by avrokit, like it is on Windows
+    let pattern = src.join("**").join("T?o.avdl");
+    let output = avrokit(&[
+        "convert",
+        "schema",
+        path(&pattern),
+        path(&out),
+        "--mirror-dirs",
+    ]);
+    assert!(output.status.success(), "{output:?}");
+    assert!(out.join("b/c/Two.avsc").exists());
+    assert!(!out.join("a/One.avsc").exists());
+
+    let pattern = src.join("*").join("Missing*.avdl");
+    let output = avrokit(&["convert", "schema", path(&pattern), path(&out)]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("no file matches"));
+}
+
+#[test]
+fn test_convert_failures() {
+    let tmp = tempfile::tempdir().unwrap();
+    let src = tmp.path().join("src");
+    write_tree(&src);
+
+    // Broken.avdl comes first, and the others are converted anyway
+    let out = tmp.path().join("out");
+    let output = avrokit(&["convert", "schema", path(&src), path(&out)]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Broken.avdl"));
+    assert!(out.join("One.avsc").exists());
+    assert!(out.join("Two.avsc").exists());
+
+    let out = tmp.path().join("fail-fast");
+    let output = avrokit(&["convert", "schema", path(&src), path(&out), "--fail-fast"]);
+    assert!(!output.status.success());
+    assert!(!out.join("One.avsc").exists());
+    assert!(!out.join("Two.avsc").exists());
+}
+
+#[test]
+fn test_convert_named_types() {
+    let tmp = tempfile::tempdir().unwrap();
+    let idl = tmp.path().join("Cards.avdl");
+    fs::write(
+        &idl,
+        "protocol Cards {
+    enum Suit { HEARTS, SPADES }
+    fixed Hash(16);
+    record Card { Suit suit; int rank; }
+    record Deck { array<Card> cards; Hash hash; }
+}",
+    )
+    .unwrap();
+    let out = tmp.path().join("out");
+    let output = avrokit(&["convert", "schema", path(&idl), path(&out)]);
+    assert!(output.status.success(), "{output:?}");
+    for (file, kind) in [
+        ("Suit.avsc", "enum"),
+        ("Hash.avsc", "fixed"),
+        ("Card.avsc", "record"),
+        ("Deck.avsc", "record"),
+    ] {
+        let json = fs::read_to_string(out.join(file)).unwrap();
+        let json: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(json["type"], kind, "{file}");
+    }
+}
+
+#[test]
+fn test_convert_reads_imports() {
+    let out = tempfile::tempdir().unwrap();
+    // a.avdl imports the types of b.avdl and c.avdl, which both import d.avdl
+    let idl = samples().join("samples/graph/a.avdl");
+    let output = avrokit(&["convert", "schema", path(&idl), path(out.path())]);
+    assert!(output.status.success(), "{output:?}");
+    let mut written: Vec<_> = fs::read_dir(out.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    written.sort();
+    assert_eq!(
+        written,
+        [
+            "Bottom.avsc",
+            "Color.avsc",
+            "Hash.avsc",
+            "Left.avsc",
+            "Oops.avsc",
+            "Right.avsc",
+            "Top.avsc"
+        ]
+    );
+}
+
+#[test]
+fn test_convert_schema_file() {
+    let tmp = tempfile::tempdir().unwrap();
+    let idl = tmp.path().join("Messages.avdl");
+    fs::write(
+        &idl,
+        "namespace org.example;
+schema Message;
+
+record Author { string name; }
+record Message { string title; Author author; }
+",
+    )
+    .unwrap();
+    let out = tmp.path().join("out");
+    let output = avrokit(&["convert", "schema", path(&idl), path(&out)]);
+    assert!(output.status.success(), "{output:?}");
+    for file in ["Author.avsc", "Message.avsc"] {
+        let json = fs::read_to_string(out.join(file)).unwrap();
+        let json: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(json["namespace"], "org.example", "{file}");
+    }
+
+    let output = avrokit(&["convert", "protocol", path(&idl), path(&out)]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("declares no protocol"), "{stderr}");
+}
+
+const EVENTS: &str = r#"protocol Events {
+    @namespace("org.acme.billing") record Event { string id; }
+    @namespace("org.acme.shipping") record Event { string id; }
+}"#;
+
+#[test]
+fn test_convert_namespaced_layout() {
+    let tmp = tempfile::tempdir().unwrap();
+    let idl = tmp.path().join("Events.avdl");
+    fs::write(&idl, EVENTS).unwrap();
+
+    let out = tmp.path().join("namespaced");
+    let output = avrokit(&[
+        "convert",
+        "schema",
+        path(&idl),
+        path(&out),
+        "--layout",
+        "namespaced",
+    ]);
+    assert!(output.status.success(), "{output:?}");
+    assert!(out.join("org/acme/billing/Event.avsc").exists());
+    assert!(out.join("org/acme/shipping/Event.avsc").exists());
+
+    let out = tmp.path().join("flat");
+    let output = avrokit(&[
+        "convert",
+        "schema",
+        path(&idl),
+        path(&out),
+        "--layout",
+        "flat",
+    ]);
+    assert!(output.status.success(), "{output:?}");
+    assert!(out.join("org.acme.billing.Event.avsc").exists());
+    assert!(out.join("org.acme.shipping.Event.avsc").exists());
+}
+
+#[test]
+fn test_convert_collision() {
+    let tmp = tempfile::tempdir().unwrap();
+    let idl = tmp.path().join("Events.avdl");
+    fs::write(&idl, EVENTS).unwrap();
+    let out = tmp.path().join("out");
+    let output = avrokit(&["convert", "schema", path(&idl), path(&out)]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("would both be written to Event.avsc"),
+        "{stderr}"
+    );
+    // Nothing is written when any file collides
+    assert!(!out.exists());
+}
+
+#[test]
+fn test_convert_filename_template() {
+    let tmp = tempfile::tempdir().unwrap();
+    let idl = tmp.path().join("Events.avdl");
+    fs::write(&idl, EVENTS).unwrap();
+    let out = tmp.path().join("out");
+    let output = avrokit(&[
+        "convert",
+        "schema",
+        path(&idl),
+        path(&out),
+        "--filename-template",
+        "{protocol}/{namespace}.{name}.schema.json",
+    ]);
+    assert!(output.status.success(), "{output:?}");
+    assert!(out
+        .join("Events/org.acme.billing.Event.schema.json")
+        .exists());
+    assert!(out
+        .join("Events/org.acme.shipping.Event.schema.json")
+        .exists());
+
+    let output = avrokit(&[
+        "convert",
+        "schema",
+        path(&idl),
+        path(&out),
+        "--filename-template",
+        "{version}.avsc",
+    ]);
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("unknown placeholder `{version}`"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn test_convert_filename_template_collision() {
+    let tmp = tempfile::tempdir().unwrap();
+    let idl = tmp.path().join("Events.avdl");
+    fs::write(&idl, EVENTS).unwrap();
+    let out = tmp.path().join("out");
+    let output = avrokit(&[
+        "convert",
+        "schema",
+        path(&idl),
+        path(&out),
+        "--filename-template",
+        "{name}.{ext}",
+    ]);
+    assert_eq!(output.status.code(), Some(3));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("would both be written to Event.avsc"),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains("add {namespace} or {fullname} to `--filename-template`"),
+        "{stderr}"
+    );
+    assert!(!out.exists());
+}
+
+const CARDS: &str = r#"@namespace("org.example")
+protocol Cards {
+    enum Suit { HEARTS, SPADES }
+    /** A card */
+    record Card { Suit suit; int rank = 1; }
+}"#;
+
+// The `Card.avsc` written from `CARDS` with `flags`
+fn convert_card(flags: &[&str]) -> String {
+    let tmp = tempfile::tempdir().unwrap();
+    let idl = tmp.path().join("Cards.avdl");
+    fs::write(&idl, CARDS).unwrap();
+    let out = tmp.path().join("out");
+    let mut args = vec!["convert", "schema", path(&idl), path(&out)];
+    args.extend(flags);
+    let output = avrokit(&args);
+    assert!(output.status.success(), "{output:?}");
+    fs::read_to_string(out.join("Card.avsc")).unwrap()
+}
+
+#[test]
+fn test_convert_pretty() {
+    assert_eq!(
+        convert_card(&[]),
+        r#"{
+  "type": "record",
+  "namespace": "org.example",
+  "name": "Card",
+  "doc": "A card",
+  "fields": [
+    {
+      "name": "suit",
+      "type": {
+        "type": "enum",
+        "name": "Suit",
+        "symbols": [
+          "HEARTS",
+          "SPADES"
+        ]
+      }
+    },
+    {
+      "name": "rank",
+      "type": "int",
+      "default": 1
+    }
+  ]
+}
+"#
+    );
+}
+
+#[test]
+fn test_convert_compact() {
+    assert_eq!(
+        convert_card(&["--compact"]),
+        r#"{"type":"record","namespace":"org.example","name":"Card","doc":"A card","fields":[{"name":"suit","type":{"type":"enum","name":"Suit","symbols":["HEARTS","SPADES"]}},{"name":"rank","type":"int","default":1}]}
+"#
+    );
+}
+
+#[test]
+fn test_convert_canonical() {
+    assert_eq!(
+        convert_card(&["--canonical"]),
+        r#"{"name":"org.example.Card","type":"record","fields":[{"name":"suit","type":{"name":"org.example.Suit","type":"enum","symbols":["HEARTS","SPADES"]}},{"name":"rank","type":"int"}]}
+"#
+    );
+}
+
+#[test]
+fn test_convert_canonical_conflicts_with_compact() {
+    let output = avrokit(&["convert", "schema", "-", "-", "--canonical", "--compact"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_convert_keeps_unchanged_outputs() {
+    let tmp = tempfile::tempdir().unwrap();
+    let idl = tmp.path().join("Cards.avdl");
+    fs::write(&idl, CARDS).unwrap();
+    let out = tmp.path().join("out");
+    let output = avrokit(&["convert", "schema", path(&idl), path(&out)]);
+    assert!(output.status.success(), "{output:?}");
+
+    // Back-dated, so that a rewrite shows
+    let long_ago = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+    for name in ["Suit.avsc", "Card.avsc"] {
+        let file = fs::File::options()
+            .write(true)
+            .open(out.join(name))
+            .unwrap();
+        file.set_modified(long_ago).unwrap();
+    }
+    fs::write(&idl, CARDS.replace("int rank = 1", "int rank = 2")).unwrap();
+    let output = avrokit(&["convert", "schema", path(&idl), path(&out)]);
+    assert!(output.status.success(), "{output:?}");
+    let modified = |name: &str| fs::metadata(out.join(name)).unwrap().modified().unwrap();
+    assert_eq!(modified("Suit.avsc"), long_ago);
+    assert_ne!(modified("Card.avsc"), long_ago);
+}
+
+#[test]
+fn test_convert_dry_run() {
+    let tmp = tempfile::tempdir().unwrap();
+    let idl = tmp.path().join("Cards.avdl");
+    fs::write(&idl, CARDS).unwrap();
+    let out = tmp.path().join("out");
+    fs::create_dir(&out).unwrap();
+    fs::write(out.join("Card.avsc"), "{\n  \"type\": \"record\"\n}\n").unwrap();
+    let output = avrokit(&["convert", "schema", "--dry-run", path(&idl), path(&out)]);
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        format!(
+            "{} (new)\n{} (+22 -1 lines)\n",
+            out.join("Suit.avsc").display(),
+            out.join("Card.avsc").display()
+        )
+    );
+    assert!(!out.join("Suit.avsc").exists());
+    assert_eq!(
+        fs::read_to_string(out.join("Card.avsc")).unwrap(),
+        "{\n  \"type\": \"record\"\n}\n"
+    );
+}
+
+#[test]
+fn test_convert_no_overwrite() {
+    let tmp = tempfile::tempdir().unwrap();
+    let idl = tmp.path().join("Cards.avdl");
+    fs::write(&idl, CARDS).unwrap();
+    let out = tmp.path().join("out");
+    let args = [
+        "convert",
+        "schema",
+        "--no-overwrite",
+        path(&idl),
+        path(&out),
+    ];
+    // Files holding the same contents are fine
+    assert!(avrokit(&args).status.success());
+    assert!(avrokit(&args).status.success());
+
+    fs::write(&idl, CARDS.replace("int rank = 1", "int rank = 2")).unwrap();
+    fs::remove_file(out.join("Suit.avsc")).unwrap();
+    let output = avrokit(&args);
+    assert_eq!(output.status.code(), Some(3));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("Card.avsc already exists with different contents"),
+        "{stderr}"
+    );
+    // Nothing of the input is written
+    assert!(!out.join("Suit.avsc").exists());
+}
+
+#[test]
+fn test_convert_prune() {
+    let tmp = tempfile::tempdir().unwrap();
+    let idl = tmp.path().join("Cards.avdl");
+    fs::write(&idl, CARDS).unwrap();
+    let out = tmp.path().join("out");
+    let output = avrokit(&["convert", "schema", path(&idl), path(&out)]);
+    assert!(output.status.success(), "{output:?}");
+    fs::write(out.join("notes.txt"), "kept").unwrap();
+
+    fs::write(&idl, CARDS.replace("Card", "PlayingCard")).unwrap();
+    let args = ["convert", "schema", "--prune", path(&idl), path(&out)];
+    let output = avrokit(&[&args[..3], &["--dry-run"], &args[3..]].concat());
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.ends_with(&format!("{} (removed)\n", out.join("Card.avsc").display())),
+        "{stdout}"
+    );
+    assert!(out.join("Card.avsc").exists());
+
+    let output = avrokit(&args);
+    assert!(output.status.success(), "{output:?}");
+    let mut files: Vec<String> = fs::read_dir(&out)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    files.sort();
+    assert_eq!(files, ["PlayingCard.avsc", "Suit.avsc", "notes.txt"]);
+
+    let output = avrokit(&["convert", "protocol", "--prune", path(&idl), path(&out)]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+// The files under `folder`, with their contents
+fn read_tree(folder: &Path) -> Vec<(String, String)> {
+    let mut files = Vec::new();
+    let mut pending = vec![folder.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                pending.push(path);
+            } else {
+                let name = path.strip_prefix(folder).unwrap().display().to_string();
+                files.push((name, fs::read_to_string(path).unwrap()));
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+#[test]
+fn test_convert_jobs() {
+    let tmp = tempfile::tempdir().unwrap();
+    let convert = |jobs: &str| {
+        let out = tmp.path().join(format!("jobs-{jobs}"));
+        let output = avrokit(&[
+            "convert",
+            "schema",
+            "--layout",
+            "namespaced",
+            "--jobs",
+            jobs,
+            path(&samples().join("samples")),
+            path(&out),
+        ]);
+        // Some samples fail on purpose
+        let stderr = String::from_utf8(output.stderr)
+            .unwrap()
+            .replace(path(&out), "<out>");
+        (output.status.code(), stderr, read_tree(&out))
+    };
+    let sequential = convert("1");
+    assert!(sequential.2.len() > 20, "{sequential:?}");
+    assert_eq!(convert("4"), sequential);
+}
+
+#[test]
+fn test_convert_files_colliding() {
+    let tmp = tempfile::tempdir().unwrap();
+    let one = tmp.path().join("One.avdl");
+    fs::write(&one, "protocol One { record Item { string id; } }").unwrap();
+    let two = tmp.path().join("Two.avdl");
+    fs::write(&two, "protocol Two { record Item { int id; } }").unwrap();
+    let out = tmp.path().join("out");
+    let output = avrokit(&["convert", "schema", path(&one), path(&two), path(&out)]);
+    assert_eq!(output.status.code(), Some(3));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains(&format!(
+            "`{}` and `{}` would both be written to {}",
+            one.display(),
+            two.display(),
+            out.join("Item.avsc").display()
+        )),
+        "{stderr}"
+    );
+    assert!(fs::read_to_string(out.join("Item.avsc"))
+        .unwrap()
+        .contains("\"string\""));
+
+    // Both can declare the same type
+    fs::write(&two, "protocol Two { record Item { string id; } }").unwrap();
+    let output = avrokit(&["convert", "schema", path(&one), path(&two), path(&out)]);
+    assert!(output.status.success(), "{output:?}");
+}
+
+const SHOP: &str = r#"@namespace("org.shop")
+protocol Shop {
+    @namespace("org.shop.internal") record Audit { string by; }
+    @namespace("org.shop.internal") record Cursor { string after; Audit audit; }
+    record Item { string id; }
+    record Order { array<Item> items; }
+}"#;
+
+// The files `convert schema` writes of SHOP with `flags`
+fn convert_shop(flags: &[&str]) -> (std::process::Output, Vec<String>) {
+    let tmp = tempfile::tempdir().unwrap();
+    let idl = tmp.path().join("Shop.avdl");
+    fs::write(&idl, SHOP).unwrap();
+    let out = tmp.path().join("out");
+    let mut args = vec!["convert", "schema", path(&idl), path(&out)];
+    args.extend(flags);
+    let output = avrokit(&args);
+    let written = match out.exists() {
+        true => read_tree(&out).into_iter().map(|(name, _)| name).collect(),
+        false => Vec::new(),
+    };
+    (output, written)
+}
+
+#[test]
+fn test_convert_select() {
+    let (output, written) = convert_shop(&["--select", "org.shop.Item"]);
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(written, ["Item.avsc"]);
+
+    // Referenced types are written too, unless inlined
+    let (output, written) = convert_shop(&["--select", "org.shop.Order"]);
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(written, ["Item.avsc", "Order.avsc"]);
+    let (output, written) = convert_shop(&["--select", "*.Order", "--inline-refs"]);
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(written, ["Order.avsc"]);
+}
+
+#[test]
+fn test_convert_exclude() {
+    let (output, written) = convert_shop(&["--exclude", "org.shop.internal.*"]);
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(written, ["Item.avsc", "Order.avsc"]);
+
+    // Unless a type written references them
+    let (output, written) = convert_shop(&["--exclude", "*.Audit"]);
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        written,
+        ["Audit.avsc", "Cursor.avsc", "Item.avsc", "Order.avsc"]
+    );
+}
+
+#[test]
+fn test_convert_select_unknown() {
+    let (output, written) = convert_shop(&["--select", "org.shop.Iteem"]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(written.is_empty());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("--select `org.shop.Iteem` matches no type of the inputs"),
+        "{stderr}"
+    );
+}
@@ -0,0 +1,75 @@
+//! Runs the `diff` command between two versions of a protocol.
+
+use std::fs;
+
+use assert_cmd::Command;
+use serde_json::{json, Value};
+
+const OLD: &str = r#"protocol Bank {
+    /** An account */
+    record Account { string id; string owner; int balance = 0; }
+}"#;
+
+const NEW: &str = r#"protocol Bank {
+    /** A bank account */
+    record Account { string id; string @aliases(["owner"]) holder; long balance = 0; }
+    enum Currency { EUR, USD }
+}"#;
+
+fn diff(format: &str) -> String {
+    let tmp = tempfile::tempdir().unwrap();
+    fs::write(tmp.path().join("old.avdl"), OLD).unwrap();
+    fs::write(tmp.path().join("new.avdl"), NEW).unwrap();
+    let output = Command::cargo_bin("avrokit")
+        .unwrap()
+        .args(["diff", "old.avdl", "new.avdl", "--format", format])
+        .current_dir(tmp.path())
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_diff_text() {
+    assert_eq!(
+        diff("text"),
+        "info: Account.owner: renamed to holder
+breaking: Account.balance: type changed from int to long
+info: Currency: type added
+
+doc changes:
+  Account
+"
+    );
+}
+
+#[test]
+fn test_diff_json() {
+    let changes: Value = serde_json::from_str(&diff("json")).unwrap();
+    assert_eq!(
+        changes[1],
+        json!({
+            "severity": "info",
+            "kind": "field_renamed",
+            "path": "Account.owner",
+            "message": "renamed to holder",
+        })
+    );
+    let kinds: Vec<&str> = changes
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|change| change["kind"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            "doc_changed",
+            "field_renamed",
+            "field_retyped",
+            "type_added"
+        ]
+    );
+}
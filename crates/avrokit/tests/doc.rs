@@ -0,0 +1,58 @@
+//! Runs `doc`, which renders documentation pages from an AVDL file.
+
+use std::fs;
+use std::path::Path;
+
+use assert_cmd::Command;
+
+const SHOP: &str = include_str!("../../../tests/samples/Shop.avdl");
+
+fn doc(args: &[&str], dir: &Path) {
+    fs::write(dir.join("Shop.avdl"), SHOP).unwrap();
+    let output = Command::cargo_bin("avrokit")
+        .unwrap()
+        .args(["doc", "Shop.avdl", "--out", "docs"])
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+}
+
+// The files of `dir`, sorted
+fn files(dir: &Path) -> Vec<String> {
+    let mut files: Vec<String> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    files.sort();
+    files
+}
+
+#[test]
+fn test_page_per_type() {
+    let tmp = tempfile::tempdir().unwrap();
+    doc(&[], tmp.path());
+    let docs = tmp.path().join("docs");
+    assert_eq!(files(&docs), ["Hash.md", "Item.md", "Kind.md", "Order.md"]);
+    assert_eq!(
+        fs::read_to_string(docs.join("Kind.md")).unwrap(),
+        include_str!("../../../tests/golden/Shop/Kind.md")
+    );
+    assert_eq!(
+        fs::read_to_string(docs.join("Item.md")).unwrap(),
+        include_str!("../../../tests/golden/Shop/Item.md")
+    );
+}
+
+#[test]
+fn test_single_page() {
+    let tmp = tempfile::tempdir().unwrap();
+    doc(&["--single-page", "--format", "html"], tmp.path());
+    let docs = tmp.path().join("docs");
+    assert_eq!(files(&docs), ["Shop.html"]);
+    assert_eq!(
+        fs::read_to_string(docs.join("Shop.html")).unwrap(),
+        include_str!("../../../tests/golden/Shop.html")
+    );
+}
@@ -0,0 +1,153 @@
+//! The messages and exit codes of failing commands.
+
+use std::fs;
+
+use assert_cmd::Command;
+
+fn avrokit() -> Command {
+    Command::cargo_bin("avrokit").unwrap()
+}
+
+#[test]
+fn test_missing_file() {
+    let tmp = tempfile::tempdir().unwrap();
+    let missing = tmp.path().join("Missing.avdl");
+    let output = avrokit()
+        .args(["convert", "schema"])
+        .arg(&missing)
+        .arg(tmp.path())
+        .assert()
+        .code(2)
+        .get_output()
+        .clone();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.starts_with(&format!("error: failed to read {}: ", missing.display())),
+        "{stderr}"
+    );
+    assert!(!stderr.contains("panicked"), "{stderr}");
+}
+
+#[test]
+fn test_broken_file() {
+    let tmp = tempfile::tempdir().unwrap();
+    let idl = tmp.path().join("Broken.avdl");
+    fs::write(&idl, "protocol Shop {\n    record Item { string id }\n}").unwrap();
+    let output = avrokit()
+        .args(["convert", "schema"])
+        .arg(&idl)
+        .arg(tmp.path())
+        .assert()
+        .code(1)
+        .get_output()
+        .clone();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.starts_with(&format!("error: {}:2:", idl.display())),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains("  |     record Item { string id }"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn test_unknown_type() {
+    let tmp = tempfile::tempdir().unwrap();
+    let idl = tmp.path().join("Shop.avdl");
+    fs::write(
+        &idl,
+        "protocol Shop {\n    record Item { Missing kind; }\n}",
+    )
+    .unwrap();
+    let output = avrokit()
+        .args(["convert", "schema"])
+        .arg(&idl)
+        .arg(tmp.path())
+        .assert()
+        .code(1)
+        .get_output()
+        .clone();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.starts_with(&format!("error: {}:2:19: ", idl.display())),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn test_unwritable_output() {
+    let tmp = tempfile::tempdir().unwrap();
+    let idl = tmp.path().join("Shop.avdl");
+    fs::write(&idl, "protocol Shop { record Item { string id; } }").unwrap();
+    // A file where the output folder should be
+    let out = tmp.path().join("out");
+    fs::write(&out, "").unwrap();
+    let output = avrokit()
+        .args(["convert", "schema"])
+        .arg(&idl)
+        .arg(out.join("schemas"))
+        .assert()
+        .code(2)
+        .get_output()
+        .clone();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("error: failed to create "), "{stderr}");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_read_only_output() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let tmp = tempfile::tempdir().unwrap();
+    let idl = tmp.path().join("Shop.avdl");
+    fs::write(&idl, "protocol Shop { record Item { string id; } }").unwrap();
+    let out = tmp.path().join("out");
+    fs::create_dir(&out).unwrap();
+    fs::set_permissions(&out, fs::Permissions::from_mode(0o555)).unwrap();
+    // Permissions don't stop root
+    if fs::write(out.join("probe"), "").is_ok() {
+        return;
+    }
+    let output = avrokit()
+        .args(["convert", "schema"])
+        .arg(&idl)
+        .arg(&out)
+        .assert()
+        .code(2)
+        .get_output()
+        .clone();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.starts_with(&format!(
+            "error: failed to write {}: ",
+            out.join("Item.avsc").display()
+        )),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn test_validation_failure() {
+    let tmp = tempfile::tempdir().unwrap();
+    let old = tmp.path().join("Old.avdl");
+    let new = tmp.path().join("New.avdl");
+    fs::write(&old, "protocol Shop { record Item { string id; } }").unwrap();
+    fs::write(
+        &new,
+        "protocol Shop { record Item { string id; int count; } }",
+    )
+    .unwrap();
+    let output = avrokit()
+        .arg("check-compat")
+        .arg(&old)
+        .arg(&new)
+        .assert()
+        .code(3)
+        .get_output()
+        .clone();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("is not compatible with"), "{stderr}");
+}
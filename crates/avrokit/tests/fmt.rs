@@ -0,0 +1,53 @@
+//! Runs the `fmt` command on copies of the samples.
+
+use std::fs;
+use std::path::Path;
+
+use assert_cmd::Command;
+
+fn sample(name: &str) -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../tests");
+    fs::read_to_string(path.join(name)).unwrap()
+}
+
+fn fmt(args: &[&str], dir: &Path) -> std::process::Output {
+    Command::cargo_bin("avrokit")
+        .unwrap()
+        .arg("fmt")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_fmt() {
+    let tmp = tempfile::tempdir().unwrap();
+    let file = tmp.path().join("Shop.avdl");
+    let unformatted = sample("samples/Unformatted.avdl");
+    fs::write(&file, &unformatted).unwrap();
+
+    // The file has plain comments, which would be lost
+    let output = fmt(&["Shop.avdl"], tmp.path());
+    assert!(output.status.success(), "{output:?}");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("warning: Shop.avdl:5:43: "), "{stderr}");
+    assert_eq!(fs::read_to_string(&file).unwrap(), unformatted);
+
+    let output = fmt(&["--check", "--drop-comments", "Shop.avdl"], tmp.path());
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "Shop.avdl\n");
+    assert_eq!(fs::read_to_string(&file).unwrap(), unformatted);
+
+    let output = fmt(&["--drop-comments", "."], tmp.path());
+    assert!(output.status.success(), "{output:?}");
+    let formatted = fs::read_to_string(&file).unwrap();
+    assert_eq!(formatted, sample("golden/Unformatted.avdl"));
+
+    // Formatting twice changes nothing
+    let output = fmt(&["--check", "Shop.avdl"], tmp.path());
+    assert!(output.status.success(), "{output:?}");
+    let output = fmt(&["Shop.avdl"], tmp.path());
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(fs::read_to_string(&file).unwrap(), formatted);
+}
@@ -0,0 +1,89 @@
+//! Runs `generate data`, which writes random values of a record.
+
+use std::fs;
+
+use apache_avro::types::Value;
+use apache_avro::Reader;
+use assert_cmd::Command;
+
+const PEOPLE: &str = r#"@namespace("org.example")
+protocol People {
+    enum Role { ADMIN, MEMBER }
+    record Person {
+        uuid id;
+        string name;
+        Role role;
+        int level = 1;
+        union { null, Person } manager = null;
+        array<string> tags = [];
+        timestamp_ms joined;
+        decimal(9, 2) balance;
+    }
+}"#;
+
+fn generate(args: &[&str]) -> Vec<u8> {
+    let tmp = tempfile::tempdir().unwrap();
+    fs::write(tmp.path().join("People.avdl"), PEOPLE).unwrap();
+    let output = Command::cargo_bin("avrokit")
+        .unwrap()
+        .args(["generate", "data", "People.avdl", "--record", "Person"])
+        .args(args)
+        .current_dir(tmp.path())
+        .assert()
+        .success();
+    output.get_output().stdout.clone()
+}
+
+#[test]
+fn test_generate_avro() {
+    let avro = generate(&["--format", "avro", "--count", "5", "--seed", "7"]);
+    let reader = Reader::new(&avro[..]).unwrap();
+    let values: Vec<Value> = reader.map(Result::unwrap).collect();
+    assert_eq!(values.len(), 5);
+    for value in values {
+        let Value::Record(fields) = value else {
+            panic!("not a record: {value:?}")
+        };
+        let names: Vec<&str> = fields.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["id", "name", "role", "level", "manager", "tags", "joined", "balance"]
+        );
+    }
+}
+
+#[test]
+fn test_generate_json() {
+    let json = String::from_utf8(generate(&["--seed", "7"])).unwrap();
+    let values: Vec<serde_json::Value> = json
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(values.len(), 10);
+    for value in &values {
+        assert!(["ADMIN", "MEMBER"].contains(&value["role"].as_str().unwrap()));
+        // Between 2000 and 2030
+        let joined = value["joined"].as_i64().unwrap();
+        assert!((946_684_800_000..1_893_456_000_000).contains(&joined));
+    }
+}
+
+#[test]
+fn test_generate_same_seed() {
+    assert_eq!(generate(&["--seed", "42"]), generate(&["--seed", "42"]));
+    assert_ne!(generate(&["--seed", "42"]), generate(&["--seed", "43"]));
+}
+
+#[test]
+fn test_generate_unknown_record() {
+    let tmp = tempfile::tempdir().unwrap();
+    fs::write(tmp.path().join("People.avdl"), PEOPLE).unwrap();
+    let output = Command::cargo_bin("avrokit")
+        .unwrap()
+        .args(["generate", "data", "People.avdl", "--record", "Team"])
+        .current_dir(tmp.path())
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("no record named `Team`"));
+}
@@ -0,0 +1,52 @@
+//! Runs `graph` on a single AVDL file, which graphs its named types.
+
+use std::fs;
+
+use assert_cmd::Command;
+
+const PEOPLE: &str = r#"@namespace("org.example")
+protocol People {
+    enum Role { ADMIN, MEMBER }
+    record Person { string name; array<Role> roles; }
+    record Team { array<Person> members; union { null, Person } lead = null; }
+}"#;
+
+fn graph(args: &[&str]) -> String {
+    let tmp = tempfile::tempdir().unwrap();
+    fs::write(tmp.path().join("People.avdl"), PEOPLE).unwrap();
+    let output = Command::cargo_bin("avrokit")
+        .unwrap()
+        .args(["graph", "People.avdl"])
+        .args(args)
+        .current_dir(tmp.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_single_file() {
+    assert_eq!(
+        graph(&[]),
+        r#"digraph "types" {
+    "org.example.Person" [shape=box];
+    "org.example.Role" [shape=ellipse];
+    "org.example.Team" [shape=box];
+    "org.example.Person" -> "org.example.Role" [label="element of"];
+    "org.example.Team" -> "org.example.Person" [label="union member of, element of"];
+}
+"#
+    );
+    assert_eq!(
+        graph(&["--files"]),
+        "digraph \"files\" {\n    \"People.avdl\" [shape=note];\n}\n"
+    );
+}
+
+#[test]
+fn test_namespace_clusters() {
+    assert!(graph(&["--namespace-clusters"]).contains(
+        "    subgraph \"cluster_org.example\" {\n        label=\"org.example\";\n        \"org.example.Person\" [shape=box];\n"
+    ));
+}
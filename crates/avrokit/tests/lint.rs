@@ -0,0 +1,80 @@
+//! Runs the `lint` command over files breaking the conventions.
+
+use std::fs;
+use std::path::Path;
+
+use assert_cmd::Command;
+use serde_json::Value;
+
+const SHOP: &str = r#"protocol Shop {
+    enum Status { ACTIVE, onHold }
+    record order_line {
+        string item_id;
+    }
+}"#;
+
+fn lint(args: &[&str], dir: &Path) -> (Option<i32>, String) {
+    let output = Command::cargo_bin("avrokit")
+        .unwrap()
+        .arg("lint")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap();
+    (
+        output.status.code(),
+        String::from_utf8(output.stdout).unwrap(),
+    )
+}
+
+#[test]
+fn test_lint() {
+    let tmp = tempfile::tempdir().unwrap();
+    fs::write(tmp.path().join("Shop.avdl"), SHOP).unwrap();
+    let (code, stdout) = lint(&["Shop.avdl"], tmp.path());
+    assert_eq!(code, Some(3));
+    assert_eq!(
+        stdout,
+        "Shop.avdl:2:5: warning: symbol `onHold` of enum `Status` should be UPPER_SNAKE_CASE [AVDL003]
+Shop.avdl:3:5: warning: record `order_line` should be PascalCase [AVDL001]
+Shop.avdl:3:5: warning: record `order_line` has no namespace [AVDL004]
+Shop.avdl:3:5: warning: record `order_line` has no doc comment [AVDL005]
+Shop.avdl:4:9: warning: field `item_id` of record `order_line` should be camelCase [AVDL002]
+"
+    );
+}
+
+#[test]
+fn test_lint_allow() {
+    let tmp = tempfile::tempdir().unwrap();
+    fs::write(tmp.path().join("Shop.avdl"), SHOP).unwrap();
+    let args = [
+        "--allow",
+        "AVDL001",
+        "--allow",
+        "AVDL002",
+        "--allow",
+        "AVDL003",
+        "--allow",
+        "AVDL004",
+        "--format",
+        "json",
+        "Shop.avdl",
+    ];
+    let (code, stdout) = lint(&args, tmp.path());
+    assert_eq!(code, Some(3));
+    let found: Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(found.as_array().unwrap().len(), 1);
+    assert_eq!(
+        found[0]["message"],
+        "record `order_line` has no doc comment [AVDL005]"
+    );
+
+    let args = [&args[..8], &["--allow", "avdl005", "Shop.avdl"]].concat();
+    let (code, stdout) = lint(&args, tmp.path());
+    assert_eq!(code, Some(0));
+    assert_eq!(stdout, "");
+
+    let (code, _stdout) = lint(&["--allow", "AVDL999", "Shop.avdl"], tmp.path());
+    assert_eq!(code, Some(2));
+}
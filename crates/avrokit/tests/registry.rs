@@ -0,0 +1,154 @@
+//! Runs the `registry` subcommands against a mock schema registry.
+#![cfg(feature = "registry")]
+
+use std::fs;
+use std::net::TcpListener;
+use std::process::Output;
+
+use assert_cmd::Command;
+use httpmock::prelude::*;
+use serde_json::json;
+
+const PEOPLE: &str = r#"@namespace("org.example")
+protocol People {
+    enum Role { ADMIN, MEMBER }
+    record Person { string name; Role role; }
+}"#;
+
+const PERSON: &str = r#"{"type":"record","namespace":"org.example","name":"Person","fields":[{"name":"name","type":"string"},{"name":"role","type":{"type":"enum","name":"Role","symbols":["ADMIN","MEMBER"]}}]}"#;
+
+fn registry(args: &[&str]) -> Output {
+    let tmp = tempfile::tempdir().unwrap();
+    fs::write(tmp.path().join("People.avdl"), PEOPLE).unwrap();
+    Command::cargo_bin("avrokit")
+        .unwrap()
+        .arg("registry")
+        .args(args)
+        .env_remove("AVROKIT_REGISTRY_USER")
+        .env_remove("AVROKIT_REGISTRY_PASSWORD")
+        .current_dir(tmp.path())
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_dry_run() {
+    let output = registry(&[
+        "publish",
+        "People.avdl",
+        "--dry-run",
+        "--subject-template",
+        "{namespace}.{name}-value",
+    ]);
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        format!("org.example.Person-value {}\n", json!({ "schema": PERSON }))
+    );
+}
+
+#[test]
+fn test_publish() {
+    let server = MockServer::start();
+    let mock = server.mock(|when, then| {
+        when.method(POST)
+            .path("/subjects/org.example.Person-value/versions")
+            .header("Authorization", "Basic dXNlcjpzZWNyZXQ=")
+            .json_body(json!({ "schema": PERSON }));
+        then.status(200).json_body(json!({ "id": 7 }));
+    });
+    let tmp = tempfile::tempdir().unwrap();
+    fs::write(tmp.path().join("People.avdl"), PEOPLE).unwrap();
+    let output = Command::cargo_bin("avrokit")
+        .unwrap()
+        .args([
+            "registry",
+            "publish",
+            "People.avdl",
+            "--url",
+            &server.base_url(),
+        ])
+        .env("AVROKIT_REGISTRY_USER", "user")
+        .env("AVROKIT_REGISTRY_PASSWORD", "secret")
+        .current_dir(tmp.path())
+        .output()
+        .unwrap();
+    mock.assert();
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "org.example.Person-value: id 7\n"
+    );
+}
+
+#[test]
+fn test_check() {
+    let server = MockServer::start();
+    let path = "/compatibility/subjects/org.example.Person-value/versions/latest";
+    let mut mock = server.mock(|when, then| {
+        when.method(POST).path(path);
+        then.status(200).json_body(json!({ "is_compatible": true }));
+    });
+    let output = registry(&["check", "People.avdl", "--url", &server.base_url()]);
+    mock.assert();
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "org.example.Person-value: compatible\n"
+    );
+
+    mock.delete();
+    let mock = server.mock(|when, then| {
+        when.method(POST).path(path);
+        then.status(200)
+            .json_body(json!({ "is_compatible": false }));
+    });
+    let output = registry(&["check", "People.avdl", "--url", &server.base_url()]);
+    mock.assert();
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "org.example.Person-value: not compatible\n"
+    );
+}
+
+#[test]
+fn test_check_new_subject() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(POST);
+        then.status(404)
+            .json_body(json!({ "error_code": 40401, "message": "Subject not found." }));
+    });
+    let output = registry(&["check", "People.avdl", "--url", &server.base_url()]);
+    assert!(output.status.success(), "{output:?}");
+}
+
+#[test]
+fn test_rejected() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(POST);
+        then.status(422)
+            .json_body(json!({ "error_code": 42201, "message": "Invalid schema" }));
+    });
+    let output = registry(&["publish", "People.avdl", "--url", &server.base_url()]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&output.stderr).contains(
+        "org.example.Person-value: the registry rejected the request (422, error 42201): Invalid schema"
+    ));
+}
+
+#[test]
+fn test_network_failure() {
+    // Nothing listens on a port once its listener is dropped
+    let port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let url = format!("http://127.0.0.1:{port}");
+    let output = registry(&["publish", "People.avdl", "--url", &url]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("failed to reach the registry"));
+}
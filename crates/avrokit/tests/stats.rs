@@ -0,0 +1,78 @@
+//! Runs `stats` over a folder of AVDL files.
+
+use std::fs;
+
+use assert_cmd::Command;
+use serde_json::{json, Value};
+
+#[test]
+fn test_stats() {
+    let tmp = tempfile::tempdir().unwrap();
+    fs::create_dir(tmp.path().join("schemas")).unwrap();
+    fs::write(
+        tmp.path().join("schemas/shop.avdl"),
+        r#"@namespace("org.shop")
+protocol Shop {
+    enum Kind { BOOK, FOOD }
+    record Item { /** Unique */ string id; Kind kind; }
+    record Order { array<union { null, Item }> items; union { Kind, null } kind; }
+}"#,
+    )
+    .unwrap();
+    fs::write(
+        tmp.path().join("schemas/people.avdl"),
+        r#"@namespace("org.people")
+protocol People {
+    fixed Hash(16);
+    record Person { string name; union { null, Hash } hash = null; }
+}"#,
+    )
+    .unwrap();
+    let output = Command::cargo_bin("avrokit")
+        .unwrap()
+        .args(["stats", "schemas", "--format", "json"])
+        .current_dir(tmp.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let stats: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        stats,
+        json!({
+            "protocols": 2,
+            "messages": 0,
+            "records": 3,
+            "enums": 1,
+            "fixed": 1,
+            "fields": 6,
+            "fields_per_record": { "2": 3 },
+            "fields_without_doc": 5,
+            "unions": 3,
+            "unions_without_null_first": 1,
+            "max_depth": 3,
+            "deepest_type": "org.shop.Order",
+            "most_referenced": [
+                { "name": "org.shop.Kind", "count": 2 },
+                { "name": "org.people.Hash", "count": 1 },
+                { "name": "org.shop.Item", "count": 1 },
+            ],
+        })
+    );
+
+    let output = Command::cargo_bin("avrokit")
+        .unwrap()
+        .args(["stats", "schemas/shop.avdl"])
+        .current_dir(tmp.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let table = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        table.starts_with("protocols                   1\n"),
+        "{table}"
+    );
+    assert!(
+        table.contains("deepest nesting             3 (org.shop.Order)\n"),
+        "{table}"
+    );
+}
@@ -0,0 +1,63 @@
+//! Piping protocols through `convert` with `-` as input and output.
+
+use assert_cmd::Command;
+use serde_json::Value;
+
+const SHOP: &str = include_str!("../../../tests/samples/Shop.avdl");
+
+fn convert(args: &[&str], input: &str) -> (String, String) {
+    let output = Command::cargo_bin("avrokit")
+        .unwrap()
+        .arg("convert")
+        .args(args)
+        .write_stdin(input)
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+    (
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+fn names(schemas: &[Value]) -> Vec<&str> {
+    schemas
+        .iter()
+        .map(|schema| schema["name"].as_str().unwrap())
+        .collect()
+}
+
+#[test]
+fn test_schemas_to_stdout_as_json() {
+    let (stdout, stderr) = convert(&["schema", "-", "-"], SHOP);
+    let schemas: Vec<Value> = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(names(&schemas), vec!["Kind", "Hash", "Item", "Order"]);
+    assert_eq!(stderr, "");
+}
+
+#[test]
+fn test_schemas_to_stdout_as_ndjson() {
+    let (stdout, _stderr) = convert(&["schema", "-", "-", "--format", "ndjson"], SHOP);
+    let schemas: Vec<Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(names(&schemas), vec!["Kind", "Hash", "Item", "Order"]);
+}
+
+#[test]
+fn test_warnings_go_to_stderr() {
+    let input = "protocol P { record R { union { string, null } note; } }";
+    let (stdout, stderr) = convert(&["schema", "-", "-", "--lint", "union-null-order"], input);
+    let schemas: Vec<Value> = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(names(&schemas), vec!["R"]);
+    assert!(stderr.contains("union-null-not-first"), "{stderr}");
+}
+
+#[test]
+fn test_protocol_to_stdout() {
+    let (stdout, _stderr) = convert(&["protocol", "-", "-"], SHOP);
+    let protocol: Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(protocol["protocol"], "Shop");
+}
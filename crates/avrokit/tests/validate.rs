@@ -0,0 +1,81 @@
+//! Runs the `validate` command, which writes no files.
+
+use std::fs;
+use std::path::Path;
+
+use assert_cmd::Command;
+use serde_json::Value;
+
+const BROKEN: &str = r#"protocol Shop {
+    record Item {
+        Missing kind;
+        int count = "many";
+    }
+}"#;
+
+fn validate(args: &[&str], dir: &Path) -> (Option<i32>, String) {
+    let output = Command::cargo_bin("avrokit")
+        .unwrap()
+        .arg("validate")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap();
+    (
+        output.status.code(),
+        String::from_utf8(output.stdout).unwrap(),
+    )
+}
+
+#[test]
+fn test_validate_passing_file() {
+    let tmp = tempfile::tempdir().unwrap();
+    fs::write(
+        tmp.path().join("Shop.avdl"),
+        "protocol Shop { record Item { string id; } }",
+    )
+    .unwrap();
+    let (code, stdout) = validate(&["Shop.avdl"], tmp.path());
+    assert_eq!(code, Some(0));
+    assert_eq!(stdout, "");
+    assert_eq!(fs::read_dir(tmp.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn test_validate_reports_every_error() {
+    let tmp = tempfile::tempdir().unwrap();
+    fs::write(tmp.path().join("Shop.avdl"), BROKEN).unwrap();
+    fs::write(
+        tmp.path().join("Valid.avdl"),
+        "protocol Valid { record Item { string id; } }",
+    )
+    .unwrap();
+    let (code, stdout) = validate(&["."], tmp.path());
+    assert_eq!(code, Some(3));
+    assert_eq!(
+        stdout,
+        "./Shop.avdl:3:9: error: unresolved type Missing used by field kind of record Item
+./Shop.avdl:4:21: error: invalid default value
+"
+    );
+}
+
+#[test]
+fn test_validate_json() {
+    let tmp = tempfile::tempdir().unwrap();
+    fs::write(tmp.path().join("Shop.avdl"), BROKEN).unwrap();
+    let (code, stdout) = validate(&["Shop.avdl", "--format", "json"], tmp.path());
+    assert_eq!(code, Some(3));
+    let found: Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(
+        found[0],
+        serde_json::json!({
+            "file": "Shop.avdl",
+            "line": 3,
+            "column": 9,
+            "severity": "error",
+            "message": "unresolved type Missing used by field kind of record Item",
+        })
+    );
+    assert_eq!(found.as_array().unwrap().len(), 2);
+}
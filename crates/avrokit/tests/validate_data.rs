@@ -0,0 +1,93 @@
+//! Runs `validate-data`, which checks data files against a record.
+
+use std::fs;
+use std::process::Output;
+
+use assert_cmd::Command;
+
+const PEOPLE: &str = r#"@namespace("org.example")
+protocol People {
+    enum Role { ADMIN, MEMBER }
+    record Person {
+        string name;
+        Role role;
+        union { null, int } age = null;
+        array<string> tags = [];
+    }
+}"#;
+
+// Validates `data`, written to a file named `file`
+fn validate_data(file: &str, data: &[u8], args: &[&str]) -> Output {
+    let tmp = tempfile::tempdir().unwrap();
+    fs::write(tmp.path().join("People.avdl"), PEOPLE).unwrap();
+    fs::write(tmp.path().join(file), data).unwrap();
+    Command::cargo_bin("avrokit")
+        .unwrap()
+        .args(["validate-data", "People.avdl", "--record", "Person", file])
+        .args(args)
+        .current_dir(tmp.path())
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_valid_json() {
+    let array = r#"[
+        {"name": "Ada", "role": "ADMIN", "age": 36, "tags": ["math"]},
+        {"name": "Alan", "role": "MEMBER"}
+    ]"#;
+    let output = validate_data("people.json", array.as_bytes(), &[]);
+    assert!(output.status.success(), "{output:?}");
+
+    let lines = "{\"name\": \"Ada\", \"role\": \"ADMIN\"}\n{\"name\": \"Alan\", \"role\": \"MEMBER\", \"age\": null}\n";
+    let output = validate_data("people.ndjson", lines.as_bytes(), &[]);
+    assert!(output.status.success(), "{output:?}");
+}
+
+#[test]
+fn test_valid_avro() {
+    let tmp = tempfile::tempdir().unwrap();
+    fs::write(tmp.path().join("People.avdl"), PEOPLE).unwrap();
+    let output = Command::cargo_bin("avrokit")
+        .unwrap()
+        .args(["generate", "data", "People.avdl", "--record", "Person"])
+        .args(["--format", "avro", "--seed", "1"])
+        .current_dir(tmp.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let output = validate_data("people.avro", &output.stdout, &[]);
+    assert!(output.status.success(), "{output:?}");
+}
+
+#[test]
+fn test_invalid_values() {
+    let lines = [
+        r#"{"role": "ADMIN"}"#,
+        r#"{"name": "Ada", "role": "ADMIN", "age": "36"}"#,
+        r#"{"name": "Ada", "role": "OWNER"}"#,
+        r#"{"name": "Ada", "role": "ADMIN", "tags": ["math", 1]}"#,
+        r#"{"name": "Ada", "role": "ADMIN"}"#,
+    ];
+    let output = validate_data("people.json", lines.join("\n").as_bytes(), &[]);
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "people.json: value 1: missing required field `name`
+people.json: value 2: /age: a string matches no branch of `union { null, int }`
+people.json: value 3: /role: `OWNER` is not a symbol of enum Role
+people.json: value 4: /tags/1: expected a string, found a number
+"
+    );
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("4 of 5 values are not Person records")
+    );
+
+    let output = validate_data(
+        "people.json",
+        lines.join("\n").as_bytes(),
+        &["--max-errors", "1"],
+    );
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(String::from_utf8_lossy(&output.stdout).lines().count(), 1);
+}
@@ -0,0 +1,61 @@
+//! Runs `convert --watch`, editing the input while it watches.
+
+use std::fs;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const SHOP: &str = "@namespace(\"org.example\")
+protocol Shop {
+    record Item { string id; }
+}";
+
+// Kills the watching process however the test ends
+struct Watching(Child);
+
+impl Drop for Watching {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+// Waits for `path` to hold something `check` accepts
+fn wait_for(path: &Path, check: impl Fn(&str) -> bool) {
+    let start = Instant::now();
+    loop {
+        if fs::read_to_string(path).is_ok_and(|contents| check(&contents)) {
+            return;
+        }
+        assert!(
+            start.elapsed() < Duration::from_secs(20),
+            "{} was not written",
+            path.display()
+        );
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+#[test]
+fn test_convert_watch() {
+    let tmp = tempfile::tempdir().unwrap();
+    let idl = tmp.path().join("Shop.avdl");
+    fs::write(&idl, SHOP).unwrap();
+    let out = tmp.path().join("out");
+    let child = Command::new(env!("CARGO_BIN_EXE_avrokit"))
+        .args(["convert", "schema", "--watch"])
+        .args([&idl, &out])
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let _watching = Watching(child);
+    let item = out.join("Item.avsc");
+    wait_for(&item, |json| json.contains("\"id\""));
+
+    // A broken file is reported, and the watch goes on
+    fs::write(&idl, "protocol Shop {").unwrap();
+    thread::sleep(Duration::from_millis(500));
+    fs::write(&idl, SHOP.replace("string id;", "string id; int count;")).unwrap();
+    wait_for(&item, |json| json.contains("\"count\""));
+}
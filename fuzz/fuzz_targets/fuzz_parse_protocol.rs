@@ -0,0 +1,19 @@
+//! Parses arbitrary bytes as a protocol, the parser must fail instead of
+//! panicking or overflowing the stack.
+
+#![no_main]
+
+use std::collections::HashMap;
+
+use avdl_parser::parser::parse_protocol;
+use avdl_parser::{parse_str, Warnings};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let input = String::from_utf8_lossy(data);
+    let mut names_ref = HashMap::new();
+    let mut warnings = Warnings::new();
+    let _ = parse_protocol(&input, &mut names_ref, &mut warnings);
+    // Validating and resolving what parsed must not panic either
+    let _ = parse_str(&input);
+});
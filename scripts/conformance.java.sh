@@ -0,0 +1,30 @@
+#!/bin/sh -e
+#
+# Usage
+# ./scripts/conformance.java.sh [avro-tools version]
+#
+# Writes the reference output of every fixture in tests/conformance with the
+# Java avro-tools, the .avsc of a schema file or the .avpr of a protocol, and
+# records the version it reports in tests/conformance/VERSION. The jar is
+# downloaded to the out folder, unless AVRO_TOOLS_JAR points to one.
+# Schema files need avro-tools 1.12 or later.
+
+version="${1:-1.12.0}"
+jar="${AVRO_TOOLS_JAR:-out/avro-tools-$version.jar}"
+if [ ! -f "$jar" ]; then
+    mkdir -p out
+    curl -fsSL -o "$jar" \
+        "https://repo1.maven.org/maven2/org/apache/avro/avro-tools/$version/avro-tools-$version.jar"
+fi
+
+for idl in tests/conformance/*.avdl; do
+    if [ -f "${idl%.avdl}.avsc" ]; then
+        output="${idl%.avdl}.avsc"
+    else
+        output="${idl%.avdl}.avpr"
+    fi
+    java -jar "$jar" idl "$idl" "$output"
+done
+
+# Without arguments avro-tools lists its tools after its version
+java -jar "$jar" 2>&1 | head -n 1 > tests/conformance/VERSION
@@ -0,0 +1,15 @@
+#!/bin/sh -e
+#
+# Usage
+# ./scripts/fuzz_corpus.sh
+#
+# Seeds the corpus of the fuzz target with the IDL files of the tests and
+# benchmarks, and the inputs that used to crash the parser.
+
+corpus=fuzz/corpus/fuzz_parse_protocol
+mkdir -p "$corpus"
+for file in tests/samples/*.avdl tests/conformance/*.avdl \
+    crates/avdl-parser/benches/fixtures/*.avdl fuzz/regressions/fuzz_parse_protocol/*; do
+    # Named after their folder too, samples and fixtures share file names
+    cp "$file" "$corpus/$(dirname "$file" | tr / _)_$(basename "$file")"
+done
@@ -0,0 +1,51 @@
+// Generated from the protocol org.example.Shop, do not edit.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Kind of *item*, see <kinds>
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Kind {
+    #[serde(rename = "BOOK")]
+    Book,
+    #[serde(rename = "FOOD")]
+    Food,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Hash(pub [u8; 16]);
+
+/// A product_name | with pipes & <tags>
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Item {
+    /// The `id` of the item
+    pub id: String,
+    pub kind: Kind,
+    #[serde(default = "Item::default_tags")]
+    pub tags: Vec<String>,
+    #[serde(default = "Item::default_hash")]
+    pub hash: Option<Hash>,
+    #[serde(default = "Item::default_price")]
+    pub price: f64,
+}
+
+impl Item {
+    fn default_tags() -> Vec<String> {
+        vec![]
+    }
+
+    fn default_hash() -> Option<Hash> {
+        None
+    }
+
+    fn default_price() -> f64 {
+        1.5
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Order {
+    pub items: Vec<Item>,
+    pub counts: HashMap<String, i64>,
+}